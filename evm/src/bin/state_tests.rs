@@ -0,0 +1,41 @@
+use std::env;
+use std::path::PathBuf;
+use std::process::exit;
+
+use anyhow::{anyhow, Result};
+use env_logger::{try_init_from_env, Env, DEFAULT_FILTER_ENV};
+use plonky2_evm::state_tests::{run_state_tests, RunnerOptions};
+
+/// Usage: `state_tests <fixtures dir> [--prove] [--fork <name>]... [--filter <substring>]`
+///
+/// Runs the filled `GeneralStateTests` fixtures found in the given directory, and prints a
+/// pass/fail matrix per category and fork. By default, only the `Shanghai` post-states are checked.
+fn main() -> Result<()> {
+    let _ = try_init_from_env(Env::default().filter_or(DEFAULT_FILTER_ENV, "warn"));
+
+    let mut args = env::args().skip(1);
+    let mut root = None;
+    let mut options = RunnerOptions::default();
+    let mut forks = vec![];
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--prove" => options.prove = true,
+            "--fork" => forks.push(args.next().ok_or_else(|| anyhow!("Missing fork name"))?),
+            "--filter" => {
+                options.filter = Some(args.next().ok_or_else(|| anyhow!("Missing filter"))?)
+            }
+            _ => root = Some(PathBuf::from(arg)),
+        }
+    }
+    if !forks.is_empty() {
+        options.forks = forks;
+    }
+    let root = root.ok_or_else(|| anyhow!("Missing fixtures directory"))?;
+
+    let report = run_state_tests(&root, &options)?;
+    print!("{report}");
+    if report.num_failed() > 0 {
+        exit(1);
+    }
+    Ok(())
+}
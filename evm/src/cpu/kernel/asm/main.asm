@@ -38,10 +38,15 @@ global start_txn:
     SWAP2
     // stack: init_gas_used, txn_counter, num_nibbles, txn_nb
 
-    // If the prover has no txn for us to process, halt.
+global txn_loop:
+    // stack: prev_gas_used, txn_counter, num_nibbles, txn_nb
+    // If the prover has no more txns for us to process, execute the withdrawals and halt.
     PROVER_INPUT(no_txn)
     %jumpi(execute_withdrawals)
 
+    // Clear the state left by the previous txn, if any.
+    %reset_txn_state
+
     // Call route_txn. When we return, we will process the txn receipt.
     PUSH txn_after
     // stack: retdest, prev_gas_used, txn_counter, num_nibbles, txn_nb
@@ -54,6 +59,7 @@ global txn_after:
     %process_receipt
     // stack: new_cum_gas, txn_counter, num_nibbles, txn_nb
     SWAP3 %increment SWAP3
+    %jump(txn_loop)
 
 global execute_withdrawals:
     // stack: cum_gas, txn_counter, num_nibbles, txn_nb
@@ -68,3 +74,46 @@ global hash_final_tries:
     %mpt_hash_txn_trie     %mload_global_metadata(@GLOBAL_METADATA_TXN_TRIE_DIGEST_AFTER)       %assert_eq
     %mpt_hash_receipt_trie %mload_global_metadata(@GLOBAL_METADATA_RECEIPT_TRIE_DIGEST_AFTER)   %assert_eq
     %jump(halt)
+
+// Resets the per-transaction metadata and all normalized txn fields, so that several txns can be
+// processed in a single run. The remaining global metadata is deliberately carried over:
+// - LARGEST_CONTEXT, TRIE_DATA_SIZE, RLP_DATA_SIZE and TRIE_ENCODED_CHILD_SIZE are append-only
+//   allocation pointers; resetting them would make the next txn overwrite live data.
+// - The trie roots, digests, block metadata, txn numbers and kernel hash/len are block-level.
+// - MEMORY_SIZE and BLOOM_ENTRY_LEN are not used by the kernel.
+%macro reset_txn_state
+    PUSH 0 %mstore_global_metadata(@GLOBAL_METADATA_REFUND_COUNTER)
+    PUSH 0 %mstore_global_metadata(@GLOBAL_METADATA_ACCESSED_ADDRESSES_LEN)
+    PUSH 0 %mstore_global_metadata(@GLOBAL_METADATA_ACCESSED_STORAGE_KEYS_LEN)
+    PUSH 0 %mstore_global_metadata(@GLOBAL_METADATA_SELFDESTRUCT_LIST_LEN)
+    PUSH 0 %mstore_global_metadata(@GLOBAL_METADATA_JOURNAL_LEN)
+    PUSH 0 %mstore_global_metadata(@GLOBAL_METADATA_JOURNAL_DATA_LEN)
+    PUSH 0 %mstore_global_metadata(@GLOBAL_METADATA_CURRENT_CHECKPOINT)
+    PUSH 0 %mstore_global_metadata(@GLOBAL_METADATA_TOUCHED_ADDRESSES_LEN)
+    PUSH 0 %mstore_global_metadata(@GLOBAL_METADATA_ACCESS_LIST_DATA_COST)
+    PUSH 0 %mstore_global_metadata(@GLOBAL_METADATA_ACCESS_LIST_RLP_START)
+    PUSH 0 %mstore_global_metadata(@GLOBAL_METADATA_ACCESS_LIST_RLP_LEN)
+    PUSH 0 %mstore_global_metadata(@GLOBAL_METADATA_CONTRACT_CREATION)
+    PUSH 0 %mstore_global_metadata(@GLOBAL_METADATA_IS_PRECOMPILE_FROM_EOA)
+    PUSH 0 %mstore_global_metadata(@GLOBAL_METADATA_CALL_STACK_DEPTH)
+    // Logs of a successful txn are only cleared for failed receipts, so clear them here too.
+    PUSH 0 %mstore_global_metadata(@GLOBAL_METADATA_LOGS_LEN)
+    PUSH 0 %mstore_global_metadata(@GLOBAL_METADATA_LOGS_DATA_LEN)
+    PUSH 0 %mstore_global_metadata(@GLOBAL_METADATA_LOGS_PAYLOAD_LEN)
+    PUSH 0 %mstore_txn_field(@TXN_FIELD_CHAIN_ID_PRESENT)
+    PUSH 0 %mstore_txn_field(@TXN_FIELD_CHAIN_ID)
+    PUSH 0 %mstore_txn_field(@TXN_FIELD_NONCE)
+    PUSH 0 %mstore_txn_field(@TXN_FIELD_MAX_PRIORITY_FEE_PER_GAS)
+    PUSH 0 %mstore_txn_field(@TXN_FIELD_MAX_FEE_PER_GAS)
+    PUSH 0 %mstore_txn_field(@TXN_FIELD_GAS_LIMIT)
+    PUSH 0 %mstore_txn_field(@TXN_FIELD_INTRINSIC_GAS)
+    PUSH 0 %mstore_txn_field(@TXN_FIELD_TO)
+    PUSH 0 %mstore_txn_field(@TXN_FIELD_VALUE)
+    PUSH 0 %mstore_txn_field(@TXN_FIELD_DATA_LEN)
+    PUSH 0 %mstore_txn_field(@TXN_FIELD_Y_PARITY)
+    PUSH 0 %mstore_txn_field(@TXN_FIELD_R)
+    PUSH 0 %mstore_txn_field(@TXN_FIELD_S)
+    PUSH 0 %mstore_txn_field(@TXN_FIELD_ORIGIN)
+    PUSH 0 %mstore_txn_field(@TXN_FIELD_COMPUTED_FEE_PER_GAS)
+    PUSH 0 %mstore_txn_field(@TXN_FIELD_COMPUTED_PRIORITY_FEE_PER_GAS)
+%endmacro
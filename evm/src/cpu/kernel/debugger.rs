@@ -0,0 +1,599 @@
+//! A step debugger for kernel code, built on top of the `Interpreter`.
+//!
+//! The `Debugger` can be driven programmatically from tests, or interactively through
+//! `Debugger::repl`, which reads commands from any `BufRead` (e.g. stdin).
+
+use std::collections::{BTreeSet, HashSet};
+use std::io::{BufRead, Write};
+
+use anyhow::{anyhow, bail};
+use ethereum_types::U256;
+
+use crate::cpu::kernel::aggregator::KERNEL;
+use crate::cpu::kernel::constants::context_metadata::ContextMetadata;
+use crate::cpu::kernel::interpreter::Interpreter;
+use crate::memory::segments::Segment;
+use crate::witness::memory::MemoryAddress;
+
+/// Opcode of the `JUMP` instruction, used by the kernel to call and return from functions.
+const JUMP_OPCODE: u8 = 0x56;
+
+/// A memory cell whose modifications interrupt execution.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Watchpoint {
+    pub(crate) context: usize,
+    pub(crate) segment: Segment,
+    pub(crate) offset: usize,
+}
+
+impl Watchpoint {
+    const fn address(&self) -> MemoryAddress {
+        MemoryAddress::new(self.context, self.segment, self.offset)
+    }
+}
+
+/// The reason why the debugger handed control back to the caller.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum StopReason {
+    /// The requested number of instructions has been executed.
+    Step,
+    /// Execution reached a breakpoint at the given offset.
+    Breakpoint(usize),
+    /// A watched memory cell was modified by the last instruction.
+    Watchpoint {
+        watchpoint: Watchpoint,
+        old: U256,
+        new: U256,
+    },
+    /// The interpreter reached one of its halting offsets.
+    Halted,
+}
+
+/// A reconstructed kernel function call.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Frame {
+    /// The offset of the function's entry point.
+    pub(crate) entry: usize,
+    /// The offset of the `JUMP` which entered the function, or `None` for the initial frame.
+    pub(crate) call_site: Option<usize>,
+    /// Stack values at entry which look like return addresses, i.e. which directly follow a
+    /// `JUMP` instruction. Jumping back to any of them is considered a return from this frame.
+    return_candidates: Vec<usize>,
+}
+
+pub(crate) struct Debugger<'a> {
+    pub(crate) interpreter: Interpreter<'a>,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: Vec<Watchpoint>,
+    /// Offsets of the kernel's global labels, which we treat as function entry points.
+    function_entries: HashSet<usize>,
+    call_stack: Vec<Frame>,
+    halted: bool,
+}
+
+impl<'a> Debugger<'a> {
+    pub(crate) fn new(interpreter: Interpreter<'a>) -> Self {
+        let mut debugger = Self {
+            interpreter,
+            breakpoints: BTreeSet::new(),
+            watchpoints: vec![],
+            function_entries: KERNEL.global_labels.values().copied().collect(),
+            call_stack: vec![],
+            halted: false,
+        };
+        let entry = debugger.program_counter();
+        let return_candidates = debugger.return_candidates();
+        debugger.call_stack.push(Frame {
+            entry,
+            call_site: None,
+            return_candidates,
+        });
+        debugger
+    }
+
+    /// Creates a debugger for the kernel, starting at the given global label.
+    pub(crate) fn new_with_kernel(label: &str, initial_stack: Vec<U256>) -> anyhow::Result<Self> {
+        let offset = resolve_label(label)?;
+        Ok(Self::new(Interpreter::new_with_kernel(
+            offset,
+            initial_stack,
+        )))
+    }
+
+    pub(crate) const fn program_counter(&self) -> usize {
+        self.interpreter.generation_state.registers.program_counter
+    }
+
+    pub(crate) const fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    pub(crate) fn add_breakpoint(&mut self, offset: usize) {
+        self.breakpoints.insert(offset);
+    }
+
+    pub(crate) fn add_label_breakpoint(&mut self, label: &str) -> anyhow::Result<()> {
+        self.add_breakpoint(resolve_label(label)?);
+        Ok(())
+    }
+
+    /// Removes the breakpoint at `offset`, returning whether there was one.
+    pub(crate) fn remove_breakpoint(&mut self, offset: usize) -> bool {
+        self.breakpoints.remove(&offset)
+    }
+
+    pub(crate) fn add_watchpoint(&mut self, context: usize, segment: Segment, offset: usize) {
+        let watchpoint = Watchpoint {
+            context,
+            segment,
+            offset,
+        };
+        if !self.watchpoints.contains(&watchpoint) {
+            self.watchpoints.push(watchpoint);
+        }
+    }
+
+    /// Removes the given watchpoint, returning whether there was one.
+    pub(crate) fn remove_watchpoint(
+        &mut self,
+        context: usize,
+        segment: Segment,
+        offset: usize,
+    ) -> bool {
+        let len = self.watchpoints.len();
+        self.watchpoints
+            .retain(|w| (w.context, w.segment, w.offset) != (context, segment, offset));
+        self.watchpoints.len() != len
+    }
+
+    /// The reconstructed kernel call stack, outermost frame first.
+    pub(crate) fn call_stack(&self) -> &[Frame] {
+        &self.call_stack
+    }
+
+    pub(crate) fn read_memory(&self, context: usize, segment: Segment, offset: usize) -> U256 {
+        self.interpreter
+            .generation_state
+            .memory
+            .get(MemoryAddress::new(context, segment, offset))
+    }
+
+    /// Executes a single instruction.
+    pub(crate) fn step(&mut self) -> anyhow::Result<StopReason> {
+        if self.halted {
+            return Ok(StopReason::Halted);
+        }
+
+        let pc = self.program_counter();
+        let jump_target = (self.interpreter.is_kernel()
+            && self.interpreter.opcode_at(pc) == JUMP_OPCODE)
+            .then(|| self.interpreter.stack_top().ok())
+            .flatten()
+            .filter(|target| target.bits() <= 32)
+            .map(|target| target.as_usize());
+        let watched_values = self
+            .watchpoints
+            .iter()
+            .map(|w| self.interpreter.generation_state.memory.get(w.address()))
+            .collect::<Vec<_>>();
+
+        if !self.interpreter.step()? {
+            self.halted = true;
+            return Ok(StopReason::Halted);
+        }
+
+        if let Some(target) = jump_target {
+            self.track_jump(pc, target);
+        }
+
+        for (&watchpoint, &old) in self.watchpoints.iter().zip(&watched_values) {
+            let new = self
+                .interpreter
+                .generation_state
+                .memory
+                .get(watchpoint.address());
+            if new != old {
+                return Ok(StopReason::Watchpoint {
+                    watchpoint,
+                    old,
+                    new,
+                });
+            }
+        }
+
+        Ok(StopReason::Step)
+    }
+
+    /// Executes up to `n` instructions, stopping early on breakpoints, watchpoints or halts.
+    pub(crate) fn step_n(&mut self, n: usize) -> anyhow::Result<StopReason> {
+        for i in 0..n {
+            let reason = self.step()?;
+            if reason != StopReason::Step {
+                return Ok(reason);
+            }
+            let pc = self.program_counter();
+            if i + 1 < n && self.breakpoints.contains(&pc) {
+                return Ok(StopReason::Breakpoint(pc));
+            }
+        }
+        Ok(StopReason::Step)
+    }
+
+    /// Runs until a breakpoint or watchpoint is hit, or until the interpreter halts.
+    /// A breakpoint at the current offset is ignored, so that execution can resume from it.
+    pub(crate) fn resume(&mut self) -> anyhow::Result<StopReason> {
+        loop {
+            let reason = self.step()?;
+            if reason != StopReason::Step {
+                return Ok(reason);
+            }
+            let pc = self.program_counter();
+            if self.breakpoints.contains(&pc) {
+                return Ok(StopReason::Breakpoint(pc));
+            }
+        }
+    }
+
+    /// Updates the call stack after a `JUMP` from `pc` to `target`.
+    fn track_jump(&mut self, pc: usize, target: usize) {
+        // Returning to a frame's return address pops it, along with any frame that was entered
+        // through a tail call from it.
+        if let Some(depth) = self
+            .call_stack
+            .iter()
+            .rposition(|frame| frame.return_candidates.contains(&target))
+        {
+            self.call_stack.truncate(depth);
+            return;
+        }
+
+        if self.function_entries.contains(&target) {
+            let return_candidates = self.return_candidates();
+            self.call_stack.push(Frame {
+                entry: target,
+                call_site: Some(pc),
+                return_candidates,
+            });
+        }
+    }
+
+    fn return_candidates(&self) -> Vec<usize> {
+        self.interpreter
+            .stack()
+            .into_iter()
+            .filter(|value| value.bits() <= 32)
+            .map(|value| value.as_usize())
+            .filter(|&offset| offset > 0 && self.interpreter.opcode_at(offset - 1) == JUMP_OPCODE)
+            .collect()
+    }
+
+    pub(crate) fn backtrace(&self) -> String {
+        let mut lines = vec![format!("#0 {}", KERNEL.offset_name(self.program_counter()))];
+        for (i, frame) in self.call_stack.iter().rev().enumerate() {
+            let entry = KERNEL.offset_name(frame.entry);
+            lines.push(match frame.call_site {
+                Some(call_site) => format!(
+                    "#{} in {entry}, called from {}",
+                    i + 1,
+                    KERNEL.offset_name(call_site)
+                ),
+                None => format!("#{} in {entry}", i + 1),
+            });
+        }
+        lines.join("\n")
+    }
+
+    /// Dumps the stack, top first. Values which correspond to a global label are annotated.
+    pub(crate) fn dump_stack(&self) -> String {
+        let stack = self.interpreter.stack();
+        if stack.is_empty() {
+            return "<empty stack>".to_string();
+        }
+        stack
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, value)| {
+                let label = (value.bits() <= 32)
+                    .then(|| KERNEL.offset_label(value.as_usize()))
+                    .flatten()
+                    .map(|label| format!(" ({label})"))
+                    .unwrap_or_default();
+                format!("{i:>4}: {value:#x}{label}")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Dumps the registers and the metadata of the current context.
+    pub(crate) fn dump_context(&self) -> String {
+        let registers = &self.interpreter.generation_state.registers;
+        let mut lines = vec![
+            format!(
+                "pc: {} ({})",
+                registers.program_counter,
+                KERNEL.offset_name(registers.program_counter)
+            ),
+            format!("is_kernel: {}", registers.is_kernel),
+            format!("context: {}", registers.context),
+            format!("stack_len: {}", registers.stack_len),
+            format!("gas_used: {}", registers.gas_used),
+        ];
+        for field in ContextMetadata::all() {
+            let value =
+                self.read_memory(registers.context, Segment::ContextMetadata, field as usize);
+            lines.push(format!("{}: {value:#x}", field.var_name()));
+        }
+        lines.join("\n")
+    }
+
+    /// Runs an interactive session, reading one command per line from `input`.
+    pub(crate) fn repl<R: BufRead, W: Write>(
+        &mut self,
+        input: R,
+        mut output: W,
+    ) -> anyhow::Result<()> {
+        writeln!(output, "{}", self.location())?;
+        for line in input.lines() {
+            let line = line?;
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            let Some((&command, args)) = words.split_first() else {
+                continue;
+            };
+            if matches!(command, "q" | "quit") {
+                break;
+            }
+            match self.execute_command(command, args) {
+                Ok(message) => writeln!(output, "{message}")?,
+                Err(e) => writeln!(output, "error: {e}")?,
+            }
+        }
+        Ok(())
+    }
+
+    fn execute_command(&mut self, command: &str, args: &[&str]) -> anyhow::Result<String> {
+        match (command, args) {
+            ("b" | "break", [location]) => {
+                let offset = parse_location(location)?;
+                self.add_breakpoint(offset);
+                Ok(format!("Breakpoint at {}", KERNEL.offset_name(offset)))
+            }
+            ("d" | "delete", [location]) => {
+                let offset = parse_location(location)?;
+                if !self.remove_breakpoint(offset) {
+                    bail!("No breakpoint at {}", KERNEL.offset_name(offset));
+                }
+                Ok(format!(
+                    "Deleted breakpoint at {}",
+                    KERNEL.offset_name(offset)
+                ))
+            }
+            ("w" | "watch", [segment, offset, context @ ..]) => {
+                let (context, segment, offset) = parse_address(segment, offset, context)?;
+                self.add_watchpoint(context, segment, offset);
+                Ok(format!(
+                    "Watching {segment:?}[{offset}] in context {context}"
+                ))
+            }
+            ("unwatch", [segment, offset, context @ ..]) => {
+                let (context, segment, offset) = parse_address(segment, offset, context)?;
+                if !self.remove_watchpoint(context, segment, offset) {
+                    bail!("No watchpoint on {segment:?}[{offset}] in context {context}");
+                }
+                Ok(format!("Stopped watching {segment:?}[{offset}]"))
+            }
+            ("s" | "step", []) => self.step().map(|reason| self.describe_stop(reason)),
+            ("s" | "step", [n]) => {
+                let n = parse_usize(n)?;
+                self.step_n(n).map(|reason| self.describe_stop(reason))
+            }
+            ("c" | "continue", []) => self.resume().map(|reason| self.describe_stop(reason)),
+            ("stack", []) => Ok(self.dump_stack()),
+            ("ctx" | "context", []) => Ok(self.dump_context()),
+            ("bt" | "backtrace", []) => Ok(self.backtrace()),
+            ("mem", [segment, offset, rest @ ..]) => {
+                let (len, context) = match rest {
+                    [] => (1, &[][..]),
+                    [len, context @ ..] => (parse_usize(len)?, context),
+                };
+                let (context, segment, offset) = parse_address(segment, offset, context)?;
+                Ok((offset..offset + len)
+                    .map(|i| {
+                        format!(
+                            "{segment:?}[{i}]: {:#x}",
+                            self.read_memory(context, segment, i)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            }
+            ("h" | "help", []) => Ok(HELP.to_string()),
+            _ => bail!(
+                "Unknown command `{}`, type `help` for a list of commands",
+                command
+            ),
+        }
+    }
+
+    fn describe_stop(&self, reason: StopReason) -> String {
+        let reason = match reason {
+            StopReason::Step => "Stepped".to_string(),
+            StopReason::Breakpoint(_) => "Hit breakpoint".to_string(),
+            StopReason::Watchpoint {
+                watchpoint,
+                old,
+                new,
+            } => format!(
+                "{:?}[{}] in context {} changed from {old:#x} to {new:#x}",
+                watchpoint.segment, watchpoint.offset, watchpoint.context
+            ),
+            StopReason::Halted => "Halted".to_string(),
+        };
+        format!("{reason}\n{}", self.location())
+    }
+
+    fn location(&self) -> String {
+        let pc = self.program_counter();
+        format!(
+            "At {} (opcode {:#04x})",
+            KERNEL.offset_name(pc),
+            self.interpreter.opcode_at(pc)
+        )
+    }
+}
+
+const HELP: &str = "\
+break <label|offset>                  set a breakpoint
+delete <label|offset>                 remove a breakpoint
+watch <segment> <offset> [ctx]        stop when a memory cell changes
+unwatch <segment> <offset> [ctx]      remove a watchpoint
+step [n]                              execute one or `n` instructions
+continue                              run until a breakpoint, watchpoint or halt
+stack                                 dump the stack, top first
+context                               dump registers and context metadata
+backtrace                             show the reconstructed call stack
+mem <segment> <offset> [len] [ctx]    read memory
+quit                                  exit the debugger
+Segments can be given by index or by name, e.g. `SEGMENT_TRIE_DATA`.";
+
+fn resolve_label(label: &str) -> anyhow::Result<usize> {
+    KERNEL
+        .global_labels
+        .get(label)
+        .copied()
+        .ok_or_else(|| anyhow!("Unknown label {}", label))
+}
+
+fn parse_usize(s: &str) -> anyhow::Result<usize> {
+    let parsed = match s.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    parsed.map_err(|_| anyhow!("Invalid number {}", s))
+}
+
+/// Parses either a numeric offset or a global label.
+fn parse_location(s: &str) -> anyhow::Result<usize> {
+    parse_usize(s).or_else(|_| resolve_label(s))
+}
+
+fn parse_segment(s: &str) -> anyhow::Result<Segment> {
+    if let Ok(index) = parse_usize(s) {
+        return Segment::all()
+            .get(index)
+            .copied()
+            .ok_or_else(|| anyhow!("Invalid segment index {}", index));
+    }
+    Segment::all()
+        .into_iter()
+        .find(|segment| segment.var_name() == s || format!("{segment:?}") == s)
+        .ok_or_else(|| anyhow!("Unknown segment {}", s))
+}
+
+fn parse_address(
+    segment: &str,
+    offset: &str,
+    context: &[&str],
+) -> anyhow::Result<(usize, Segment, usize)> {
+    let context = match context {
+        [] => 0,
+        [context] => parse_usize(context)?,
+        _ => bail!("Too many arguments"),
+    };
+    Ok((context, parse_segment(segment)?, parse_usize(offset)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use anyhow::Result;
+    use ethereum_types::U256;
+
+    use crate::cpu::kernel::aggregator::KERNEL;
+    use crate::cpu::kernel::debugger::{Debugger, StopReason};
+    use crate::cpu::kernel::interpreter::Interpreter;
+    use crate::memory::segments::Segment;
+
+    #[test]
+    fn test_breakpoints_and_steps() -> Result<()> {
+        let code = vec![
+            0x60, 0x1, 0x60, 0x2, 0x1, 0x63, 0xde, 0xad, 0xbe, 0xef, 0x56,
+        ]; // PUSH1, 1, PUSH1, 2, ADD, PUSH4 deadbeef, JUMP
+        let prover_inputs = HashMap::new();
+        let mut debugger = Debugger::new(Interpreter::new(&code, 0, vec![], &prover_inputs));
+        debugger.add_breakpoint(4);
+
+        assert_eq!(debugger.resume()?, StopReason::Breakpoint(4));
+        assert_eq!(debugger.interpreter.stack(), &[1.into(), 2.into()]);
+
+        assert_eq!(debugger.step()?, StopReason::Step);
+        assert_eq!(debugger.interpreter.stack(), &[3.into()]);
+
+        assert_eq!(debugger.resume()?, StopReason::Halted);
+        assert!(debugger.is_halted());
+        assert_eq!(debugger.interpreter.stack(), &[3.into()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_watchpoint() -> Result<()> {
+        // PUSH1 0, PUSH1 0x0d, PUSH1 5, PUSH1 0x42, MSTORE_GENERAL, PUSH4 deadbeef, JUMP
+        let code = vec![
+            0x60, 0x0, 0x60, 0x0d, 0x60, 0x0, 0x60, 0x42, 0xfc, 0x63, 0xde, 0xad, 0xbe, 0xef, 0x56,
+        ];
+        let prover_inputs = HashMap::new();
+        let mut debugger = Debugger::new(Interpreter::new(&code, 0, vec![], &prover_inputs));
+        debugger.add_watchpoint(0, Segment::TrieData, 0);
+
+        assert_eq!(
+            debugger.resume()?,
+            StopReason::Watchpoint {
+                watchpoint: debugger.watchpoints[0],
+                old: U256::zero(),
+                new: 0x42.into(),
+            }
+        );
+        assert_eq!(debugger.program_counter(), 9);
+        assert_eq!(debugger.resume()?, StopReason::Halted);
+        Ok(())
+    }
+
+    #[test]
+    fn test_call_stack() -> Result<()> {
+        let exp = KERNEL.global_labels["exp"];
+        let initial_stack = vec![0xdeadbeefu32.into(), 8.into(), 3.into()];
+        let mut debugger = Debugger::new_with_kernel("exp", initial_stack)?;
+        debugger.add_label_breakpoint("exp")?;
+
+        // `exp` recurses once per bit of the exponent, plus once for the base case.
+        for depth in 2..=5 {
+            assert_eq!(debugger.resume()?, StopReason::Breakpoint(exp));
+            assert_eq!(debugger.call_stack().len(), depth);
+            assert!(debugger.call_stack().iter().all(|frame| frame.entry == exp));
+        }
+
+        assert!(debugger.remove_breakpoint(exp));
+        assert_eq!(debugger.resume()?, StopReason::Halted);
+        assert_eq!(debugger.call_stack().len(), 1);
+        assert_eq!(debugger.interpreter.stack(), &[6561.into()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_repl() -> Result<()> {
+        let initial_stack = vec![0xdeadbeefu32.into(), 2.into(), 3.into()];
+        let mut debugger = Debugger::new_with_kernel("exp", initial_stack)?;
+        let commands = "break exp\ncontinue\nbacktrace\nstack\nfoo\ndelete exp\ncontinue\nquit\n";
+        let mut output = vec![];
+        debugger.repl(commands.as_bytes(), &mut output)?;
+
+        let output = String::from_utf8(output)?;
+        assert!(output.contains("Hit breakpoint"));
+        assert!(output.contains("called from"));
+        assert!(output.contains("error: Unknown command `foo`"));
+        assert!(output.contains("Halted"));
+        assert_eq!(debugger.interpreter.stack(), &[9.into()]);
+        Ok(())
+    }
+}
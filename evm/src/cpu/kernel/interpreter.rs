@@ -162,12 +162,30 @@ impl<'a> Interpreter<'a> {
         Ok(())
     }
 
+    /// Executes a single instruction, unless a halting point has been reached.
+    /// Returns whether the interpreter can keep running afterwards.
+    pub(crate) fn step(&mut self) -> anyhow::Result<bool> {
+        let pc = self.generation_state.registers.program_counter;
+        if self.is_kernel() && self.halt_offsets.contains(&pc) {
+            self.running = false;
+            return Ok(false);
+        }
+        self.running = true;
+        self.run_opcode()?;
+        Ok(self.running)
+    }
+
     fn code(&self) -> &MemorySegmentState {
         // The context is 0 if we are in kernel mode.
         &self.generation_state.memory.contexts[(1 - self.is_kernel() as usize) * self.context()]
             .segments[Segment::Code as usize]
     }
 
+    /// Returns the opcode located at `offset` in the code of the current context.
+    pub(crate) fn opcode_at(&self, offset: usize) -> u8 {
+        self.code().get(offset).byte(0)
+    }
+
     fn code_slice(&self, n: usize) -> Vec<u8> {
         let pc = self.generation_state.registers.program_counter;
         self.code().content[pc..pc + n]
@@ -358,10 +376,7 @@ impl<'a> Interpreter<'a> {
     }
 
     fn run_opcode(&mut self) -> anyhow::Result<()> {
-        let opcode = self
-            .code()
-            .get(self.generation_state.registers.program_counter)
-            .byte(0);
+        let opcode = self.opcode_at(self.generation_state.registers.program_counter);
         self.opcode_count[opcode as usize] += 1;
         self.incr(1);
         match opcode {
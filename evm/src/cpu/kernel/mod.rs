@@ -10,6 +10,8 @@ mod parser;
 pub mod stack;
 mod utils;
 
+#[cfg(test)]
+mod debugger;
 #[cfg(test)]
 mod interpreter;
 #[cfg(test)]
@@ -69,8 +69,8 @@ fn prepare_interpreter(
     // Set contract code and transaction.
     interpreter.generation_state.inputs.contract_code = contract_code;
 
-    interpreter.generation_state.inputs.signed_txn = Some(transaction.to_vec());
-    let rlp_prover_inputs = all_rlp_prover_inputs_reversed(transaction);
+    interpreter.generation_state.inputs.signed_txns = vec![transaction.to_vec()];
+    let rlp_prover_inputs = all_rlp_prover_inputs_reversed(&[transaction.to_vec()]);
     interpreter.generation_state.rlp_prover_inputs = rlp_prover_inputs;
 }
 
@@ -18,6 +18,7 @@ mod receipt;
 mod rlp;
 mod signed_syscalls;
 mod transaction_parsing;
+mod txn_list;
 
 use std::str::FromStr;
 
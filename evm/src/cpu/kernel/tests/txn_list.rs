@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+
+use eth_trie_utils::nibbles::Nibbles;
+use eth_trie_utils::partial_trie::{HashedPartialTrie, Node, PartialTrie};
+use ethereum_types::{Address, U256};
+use hex_literal::hex;
+use keccak_hash::keccak;
+
+use crate::cpu::kernel::aggregator::KERNEL;
+use crate::cpu::kernel::constants::context_metadata::ContextMetadata;
+use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
+use crate::cpu::kernel::interpreter::Interpreter;
+use crate::generation::mpt::{all_mpt_prover_inputs_reversed, AccountRlp, LegacyReceiptRlp};
+use crate::generation::rlp::all_rlp_prover_inputs_reversed;
+use crate::generation::TrieInputs;
+use crate::memory::segments::Segment;
+use crate::proof::TrieRoots;
+use crate::util::h2u;
+
+const BENEFICIARY: [u8; 20] = hex!("2adc25665018aa1fe0e6bc666dac8fc2697ff9ba");
+
+/// A value transfer of 100 wei from `0x2c75...5c23` (nonce 5) to `0xa0a0...a0a0`, using 21032 gas.
+const TRANSFER_TXN: [u8; 99] = hex!("f861050a8255f094a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0648242421ba02c89eb757d9deeb1f5b3859a9d4d679951ef610ac47ad4608dc142beb1b7e313a05af7e9fbab825455d36c36c7f4cfcafbeafa9a77bdff936b52afb36d4fe4bcdd");
+const TRANSFER_GAS: u64 = 21032;
+
+/// The `add11` transaction from `0xa94f...bf0b` (nonce 0), storing `1 + 1` in the storage of
+/// `0x095e...2d87`, using 43112 gas.
+const ADD11_TXN: [u8; 101] = hex!("f863800a83061a8094095e7baea6a6c7c4c2dfeb977efac326af552d87830186a0801ba0ffb600e63115a7362e7811894a91d8ba4330e526f22121c994c4692035dfdfd5a06198379fcac8de3dbfac48b165df4bf88e2088f294b61efb9a65fe2281c76e16");
+const ADD11_GAS: u64 = 0xa868;
+
+const ADD11_CODE: [u8; 9] = [0x60, 0x01, 0x60, 0x01, 0x01, 0x60, 0x00, 0x55, 0x00];
+
+fn nibbles(address: [u8; 20]) -> Nibbles {
+    Nibbles::from_bytes_be(keccak(address).as_bytes()).unwrap()
+}
+
+fn txn_key(i: usize) -> Nibbles {
+    Nibbles::from_bytes_be(&rlp::encode(&(i as u64))).expect("An RLP-encoded index is short")
+}
+
+/// The state trie after processing the first `num_txns` transactions of
+/// `[TRANSFER_TXN, ADD11_TXN]`.
+fn state_trie(num_txns: usize) -> HashedPartialTrie {
+    let transfer_sender = hex!("2c7536e3605d9c16a7a3d7b1898e529396a65c23");
+    let transfer_to = hex!("a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0");
+    let add11_sender = hex!("a94f5374fce5edbc8e2a8697c15331677e6ebf0b");
+    let add11_to = hex!("095e7baea6a6c7c4c2dfeb977efac326af552d87");
+
+    let beneficiary_account = AccountRlp {
+        nonce: 1.into(),
+        ..AccountRlp::default()
+    };
+    let transfer_sender_initial_balance = U256::from(10).pow(23.into());
+    let (transfer_sender_account, transfer_to_account) = if num_txns >= 1 {
+        let sender = AccountRlp {
+            nonce: 6.into(),
+            balance: transfer_sender_initial_balance - 100 - TRANSFER_GAS * 10,
+            ..AccountRlp::default()
+        };
+        let to = AccountRlp {
+            balance: 100.into(),
+            ..AccountRlp::default()
+        };
+        (sender, Some(to))
+    } else {
+        let sender = AccountRlp {
+            nonce: 5.into(),
+            balance: transfer_sender_initial_balance,
+            ..AccountRlp::default()
+        };
+        (sender, None)
+    };
+    let (add11_sender_account, add11_to_account) = if num_txns >= 2 {
+        let sender = AccountRlp {
+            nonce: 1.into(),
+            balance: 0xde0b6b3a75be550u64.into(),
+            ..AccountRlp::default()
+        };
+        let to = AccountRlp {
+            balance: 0xde0b6b3a76586a0u64.into(),
+            code_hash: keccak(ADD11_CODE),
+            // Storage map: { 0 => 2 }
+            storage_root: HashedPartialTrie::from(Node::Leaf {
+                nibbles: Nibbles::from_h256_be(keccak([0u8; 32])),
+                value: vec![2],
+            })
+            .hash(),
+            ..AccountRlp::default()
+        };
+        (sender, to)
+    } else {
+        let sender = AccountRlp {
+            balance: 0x0de0b6b3a7640000u64.into(),
+            ..AccountRlp::default()
+        };
+        let to = AccountRlp {
+            balance: 0x0de0b6b3a7640000u64.into(),
+            code_hash: keccak(ADD11_CODE),
+            ..AccountRlp::default()
+        };
+        (sender, to)
+    };
+
+    let mut trie = HashedPartialTrie::from(Node::Empty);
+    trie.insert(
+        nibbles(BENEFICIARY),
+        rlp::encode(&beneficiary_account).to_vec(),
+    );
+    trie.insert(
+        nibbles(transfer_sender),
+        rlp::encode(&transfer_sender_account).to_vec(),
+    );
+    if let Some(account) = transfer_to_account {
+        trie.insert(nibbles(transfer_to), rlp::encode(&account).to_vec());
+    }
+    trie.insert(
+        nibbles(add11_sender),
+        rlp::encode(&add11_sender_account).to_vec(),
+    );
+    trie.insert(nibbles(add11_to), rlp::encode(&add11_to_account).to_vec());
+    trie
+}
+
+/// Runs the kernel on the first `num_txns` transactions of `[TRANSFER_TXN, ADD11_TXN]`. The kernel
+/// itself checks the final state, transaction and receipt roots, the block gas used and the number
+/// of processed transactions against the expected values set in the global metadata.
+fn run_txns(num_txns: usize) {
+    let txns = [TRANSFER_TXN.to_vec(), ADD11_TXN.to_vec()][..num_txns].to_vec();
+    let gas_used = [TRANSFER_GAS, TRANSFER_GAS + ADD11_GAS];
+
+    let tries_before = TrieInputs {
+        state_trie: state_trie(0),
+        transactions_trie: Node::Empty.into(),
+        receipts_trie: Node::Empty.into(),
+        storage_tries: vec![(
+            keccak(hex!("095e7baea6a6c7c4c2dfeb977efac326af552d87")),
+            Node::Empty.into(),
+        )],
+    };
+
+    let mut transactions_trie = HashedPartialTrie::from(Node::Empty);
+    let mut receipts_trie = HashedPartialTrie::from(Node::Empty);
+    for (i, txn) in txns.iter().enumerate() {
+        transactions_trie.insert(txn_key(i), txn.clone());
+        let receipt = LegacyReceiptRlp {
+            status: true,
+            cum_gas_used: gas_used[i].into(),
+            bloom: vec![0; 256].into(),
+            logs: vec![],
+        };
+        receipts_trie.insert(txn_key(i), rlp::encode(&receipt).to_vec());
+    }
+    let trie_roots_after = TrieRoots {
+        state_root: state_trie(num_txns).hash(),
+        transactions_root: transactions_trie.hash(),
+        receipts_root: receipts_trie.hash(),
+    };
+
+    let mut interpreter = Interpreter::new_with_kernel(0, vec![]);
+    interpreter.generation_state.registers.program_counter = KERNEL.global_labels["load_all_mpts"];
+    interpreter.push(0xDEADBEEFu32.into());
+    interpreter.generation_state.mpt_prover_inputs =
+        all_mpt_prover_inputs_reversed(&tries_before).expect("Invalid MPT data.");
+    interpreter.run().expect("MPT loading failed.");
+    assert_eq!(interpreter.stack(), vec![]);
+
+    let global_metadata_to_set = [
+        (
+            GlobalMetadata::StateTrieRootDigestBefore,
+            h2u(tries_before.state_trie.hash()),
+        ),
+        (
+            GlobalMetadata::TransactionTrieRootDigestBefore,
+            h2u(tries_before.transactions_trie.hash()),
+        ),
+        (
+            GlobalMetadata::ReceiptTrieRootDigestBefore,
+            h2u(tries_before.receipts_trie.hash()),
+        ),
+        (
+            GlobalMetadata::StateTrieRootDigestAfter,
+            h2u(trie_roots_after.state_root),
+        ),
+        (
+            GlobalMetadata::TransactionTrieRootDigestAfter,
+            h2u(trie_roots_after.transactions_root),
+        ),
+        (
+            GlobalMetadata::ReceiptTrieRootDigestAfter,
+            h2u(trie_roots_after.receipts_root),
+        ),
+        (GlobalMetadata::TxnNumberAfter, num_txns.into()),
+        (
+            GlobalMetadata::BlockGasUsedAfter,
+            gas_used[num_txns - 1].into(),
+        ),
+        (GlobalMetadata::BlockGasLimit, 1_000_000.into()),
+        (GlobalMetadata::BlockBaseFee, 10.into()),
+        (
+            GlobalMetadata::BlockBeneficiary,
+            U256::from_big_endian(&Address::from(BENEFICIARY).0),
+        ),
+    ];
+    interpreter.set_global_metadata_multi_fields(&global_metadata_to_set);
+
+    let mut contract_code = HashMap::new();
+    contract_code.insert(keccak(vec![]), vec![]);
+    contract_code.insert(keccak(ADD11_CODE), ADD11_CODE.to_vec());
+    interpreter.generation_state.inputs.contract_code = contract_code;
+    interpreter.generation_state.inputs.signed_txns = txns.clone();
+    interpreter.generation_state.rlp_prover_inputs = all_rlp_prover_inputs_reversed(&txns);
+
+    interpreter.generation_state.registers.program_counter =
+        KERNEL.global_labels["hash_initial_tries"];
+    interpreter.generation_state.memory.contexts[0].segments[Segment::ContextMetadata as usize]
+        .set(ContextMetadata::GasLimit as usize, 1_000_000.into());
+    interpreter.set_is_kernel(true);
+    interpreter
+        .run()
+        .unwrap_or_else(|e| panic!("Processing {num_txns} txn(s) failed: {e:?}"));
+}
+
+#[test]
+fn test_txn_list() {
+    // Check the receipts, the gas used and the state root after each transaction.
+    run_txns(1);
+    run_txns(2);
+}
@@ -1,10 +1,14 @@
 use core::mem::{self, MaybeUninit};
 use std::collections::BTreeMap;
+use std::fs;
 use std::ops::Range;
+use std::path::PathBuf;
 
+use anyhow::Context;
 use eth_trie_utils::partial_trie::{HashedPartialTrie, Node, PartialTrie};
 use hashbrown::HashMap;
 use itertools::{zip_eq, Itertools};
+use keccak_hash::keccak;
 use plonky2::field::extension::Extendable;
 use plonky2::fri::FriParams;
 use plonky2::gates::constant::ConstantGate;
@@ -19,6 +23,7 @@ use plonky2::plonk::circuit_data::{
 };
 use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
 use plonky2::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
+use plonky2::plonk::prover::ProverOptions;
 use plonky2::recursion::cyclic_recursion::check_cyclic_proof_verifier_data;
 use plonky2::recursion::dummy_circuit::cyclic_base_proof;
 use plonky2::util::serialization::{
@@ -36,8 +41,8 @@ use crate::cross_table_lookup::{
 use crate::generation::GenerationInputs;
 use crate::get_challenges::observe_public_values_target;
 use crate::proof::{
-    BlockHashesTarget, BlockMetadataTarget, ExtraBlockData, ExtraBlockDataTarget, PublicValues,
-    PublicValuesTarget, StarkProofWithMetadata, TrieRootsTarget,
+    AllProof, BlockHashesTarget, BlockMetadataTarget, ExtraBlockData, ExtraBlockDataTarget,
+    PublicValues, PublicValuesTarget, StarkProofWithMetadata, TrieRootsTarget,
 };
 use crate::prover::prove;
 use crate::recursive_verifier::{
@@ -490,7 +495,12 @@ where
             stark_config,
         );
 
-        // Regenerate the upper circuits.
+        self.regenerate_upper_circuits(stark_config);
+    }
+
+    /// Regenerate the root, aggregation and block circuits, after the set of supported table sizes
+    /// has changed.
+    fn regenerate_upper_circuits(&mut self, stark_config: &StarkConfig) {
         self.root = Self::create_root_circuit(&self.by_table, stark_config);
         self.aggregation = Self::create_aggregation_circuit(&self.root);
         self.block = Self::create_block_circuit(&self.aggregation);
@@ -944,7 +954,104 @@ where
         generation_inputs: GenerationInputs,
         timing: &mut TimingTree,
     ) -> anyhow::Result<(ProofWithPublicInputs<F, C, D>, PublicValues)> {
-        let all_proof = prove::<F, C, D>(all_stark, config, generation_inputs, timing)?;
+        let all_proof = prove::<F, C, D>(
+            all_stark,
+            config,
+            generation_inputs,
+            &ProverOptions::default(),
+            timing,
+        )?;
+        self.prove_root_from_all_proof(all_proof, config)
+    }
+
+    /// Like `prove_root`, but instead of failing when a table has a size outside of the supported
+    /// ranges, builds the shrinking circuits for that size on demand, as in `support_table_sizes`.
+    /// Along with the root proof, it returns the table sizes this added support for.
+    ///
+    /// When these aren't empty, the root, aggregation and block circuits were regenerated, so that
+    /// the proofs generated before can't be aggregated with this one, and must be proven again.
+    /// This is thus best suited to warming up a set of circuits, starting from ranges as small as a
+    /// single size per table, before proving blocks with them.
+    pub fn prove_root_on_demand(
+        &mut self,
+        all_stark: &AllStark<F, D>,
+        config: &StarkConfig,
+        generation_inputs: GenerationInputs,
+        timing: &mut TimingTree,
+        cache: Option<&TableCircuitsCache<F, D>>,
+    ) -> anyhow::Result<(
+        ProofWithPublicInputs<F, C, D>,
+        PublicValues,
+        Vec<(Table, usize)>,
+    )> {
+        let all_proof = prove::<F, C, D>(
+            all_stark,
+            config,
+            generation_inputs,
+            &ProverOptions::default(),
+            timing,
+        )?;
+
+        let new_sizes =
+            self.support_table_sizes(all_stark, all_proof.degree_bits(config), config, cache)?;
+
+        let (root_proof, public_values) = self.prove_root_from_all_proof(all_proof, config)?;
+        Ok((root_proof, public_values, new_sizes))
+    }
+
+    /// Makes sure that each table supports the size given in `degree_bits`, building the missing
+    /// shrinking circuits, or loading them from `cache` if they were built before, in which case
+    /// they are stored there. Returns the table sizes this added support for.
+    ///
+    /// When these aren't empty, the root, aggregation and block circuits were regenerated, and
+    /// their verifier data changed, so that proofs generated before can't be used with the new
+    /// circuits.
+    pub fn support_table_sizes(
+        &mut self,
+        all_stark: &AllStark<F, D>,
+        degree_bits: [usize; NUM_TABLES],
+        config: &StarkConfig,
+        cache: Option<&TableCircuitsCache<F, D>>,
+    ) -> anyhow::Result<Vec<(Table, usize)>> {
+        let mut new_sizes = Vec::new();
+        for (table, degree_bits) in zip_eq(Table::all(), degree_bits) {
+            let by_stark_size = &mut self.by_table[table as usize].by_stark_size;
+            if by_stark_size.contains_key(&degree_bits) {
+                continue;
+            }
+            let cached = match cache {
+                Some(cache) => cache.load(table, degree_bits, config)?,
+                None => None,
+            };
+            let circuits = match cached {
+                Some(circuits) => circuits,
+                None => {
+                    let circuits = RecursiveCircuitsForTableSize::for_table(
+                        all_stark,
+                        table,
+                        degree_bits,
+                        config,
+                    );
+                    if let Some(cache) = cache {
+                        cache.store(table, degree_bits, config, &circuits)?;
+                    }
+                    circuits
+                }
+            };
+            by_stark_size.insert(degree_bits, circuits);
+            new_sizes.push((table, degree_bits));
+        }
+        if !new_sizes.is_empty() {
+            self.regenerate_upper_circuits(config);
+        }
+        Ok(new_sizes)
+    }
+
+    fn prove_root_from_all_proof(
+        &self,
+        all_proof: AllProof<F, C, D>,
+        config: &StarkConfig,
+    ) -> anyhow::Result<(ProofWithPublicInputs<F, C, D>, PublicValues)> {
         let mut root_inputs = PartialWitness::new();
 
         for table in 0..NUM_TABLES {
@@ -1362,6 +1469,61 @@ where
         })
     }
 
+    /// Builds the chain of circuits for the given table and size.
+    fn for_table(
+        all_stark: &AllStark<F, D>,
+        table: Table,
+        degree_bits: usize,
+        stark_config: &StarkConfig,
+    ) -> Self {
+        let ctls = &all_stark.cross_table_lookups;
+        match table {
+            Table::Arithmetic => Self::new(
+                table,
+                &all_stark.arithmetic_stark,
+                degree_bits,
+                ctls,
+                stark_config,
+            ),
+            Table::BytePacking => Self::new(
+                table,
+                &all_stark.byte_packing_stark,
+                degree_bits,
+                ctls,
+                stark_config,
+            ),
+            Table::Cpu => Self::new(table, &all_stark.cpu_stark, degree_bits, ctls, stark_config),
+            Table::Keccak => Self::new(
+                table,
+                &all_stark.keccak_stark,
+                degree_bits,
+                ctls,
+                stark_config,
+            ),
+            Table::KeccakSponge => Self::new(
+                table,
+                &all_stark.keccak_sponge_stark,
+                degree_bits,
+                ctls,
+                stark_config,
+            ),
+            Table::Logic => Self::new(
+                table,
+                &all_stark.logic_stark,
+                degree_bits,
+                ctls,
+                stark_config,
+            ),
+            Table::Memory => Self::new(
+                table,
+                &all_stark.memory_stark,
+                degree_bits,
+                ctls,
+                stark_config,
+            ),
+        }
+    }
+
     fn new<S: Stark<F, D>>(
         table: Table,
         stark: &S,
@@ -1433,6 +1595,91 @@ where
     }
 }
 
+/// A directory holding the shrinking circuits built by `AllRecursiveCircuits::support_table_sizes`,
+/// one file per table, size and `StarkConfig`, so that each of them is only built once. The
+/// circuits also depend on the tables' constraints, so a directory shouldn't be shared between
+/// different versions of this crate.
+pub struct TableCircuitsCache<'a, F: RichField + Extendable<D>, const D: usize> {
+    dir: PathBuf,
+    gate_serializer: &'a dyn GateSerializer<F, D>,
+    generator_serializer: &'a dyn WitnessGeneratorSerializer<F, D>,
+}
+
+impl<'a, F: RichField + Extendable<D>, const D: usize> TableCircuitsCache<'a, F, D> {
+    pub fn new<P: Into<PathBuf>>(
+        dir: P,
+        gate_serializer: &'a dyn GateSerializer<F, D>,
+        generator_serializer: &'a dyn WitnessGeneratorSerializer<F, D>,
+    ) -> Self {
+        Self {
+            dir: dir.into(),
+            gate_serializer,
+            generator_serializer,
+        }
+    }
+
+    /// The file holding the circuits of the given table and size. Its name includes a digest of
+    /// `config`, since circuits built for one configuration can't be used with another.
+    fn path(&self, table: Table, degree_bits: usize, config: &StarkConfig) -> PathBuf {
+        let config_digest = keccak(format!(
+            "{}_{}_{:?}",
+            config.security_bits, config.num_challenges, config.fri_config
+        ));
+        self.dir.join(format!(
+            "{table:?}_{degree_bits}_{:016x}.bin",
+            config_digest.to_low_u64_be()
+        ))
+    }
+
+    fn load<C>(
+        &self,
+        table: Table,
+        degree_bits: usize,
+        config: &StarkConfig,
+    ) -> anyhow::Result<Option<RecursiveCircuitsForTableSize<F, C, D>>>
+    where
+        C: GenericConfig<D, F = F>,
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        let path = self.path(table, degree_bits, config);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let bytes =
+            fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let circuits = RecursiveCircuitsForTableSize::from_buffer(
+            &mut Buffer::new(&bytes),
+            self.gate_serializer,
+            self.generator_serializer,
+        )
+        .with_context(|| format!("Failed to deserialize the circuits in {}", path.display()))?;
+        Ok(Some(circuits))
+    }
+
+    fn store<C>(
+        &self,
+        table: Table,
+        degree_bits: usize,
+        config: &StarkConfig,
+        circuits: &RecursiveCircuitsForTableSize<F, C, D>,
+    ) -> anyhow::Result<()>
+    where
+        C: GenericConfig<D, F = F>,
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        let mut buffer = vec![];
+        circuits
+            .to_buffer(&mut buffer, self.gate_serializer, self.generator_serializer)
+            .with_context(|| {
+                format!("Failed to serialize the {table:?} circuits of size {degree_bits}")
+            })?;
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let path = self.path(table, degree_bits, config);
+        fs::write(&path, buffer).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
 /// Our usual recursion threshold is 2^12 gates, but for these shrinking circuits, we use a few more
 /// gates for a constant inner VK and for public inputs. This pushes us over the threshold to 2^13.
 /// As long as we're at 2^13 gates, we might as well use a narrower witness.
@@ -1,11 +1,12 @@
 use std::collections::HashMap;
 
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use eth_trie_utils::partial_trie::{HashedPartialTrie, PartialTrie};
 use ethereum_types::{Address, BigEndianHash, H256, U256};
 use itertools::enumerate;
 use plonky2::field::extension::Extendable;
 use plonky2::field::polynomial::PolynomialValues;
+use plonky2::field::types::Field;
 use plonky2::hash::hash_types::RichField;
 use plonky2::timed;
 use plonky2::util::timing::TimingTree;
@@ -20,12 +21,15 @@ use crate::config::StarkConfig;
 use crate::cpu::columns::CpuColumnsView;
 use crate::cpu::kernel::aggregator::KERNEL;
 use crate::cpu::kernel::constants::global_metadata::GlobalMetadata;
+use crate::generation::mpt::LogRlp;
 use crate::generation::state::GenerationState;
 use crate::memory::segments::Segment;
 use crate::proof::{BlockHashes, BlockMetadata, ExtraBlockData, PublicValues, TrieRoots};
-use crate::util::h2u;
+use crate::util::{h2u, u256_to_usize};
+use crate::witness::errors::ProgramError;
 use crate::witness::memory::{MemoryAddress, MemoryChannel};
 use crate::witness::transition::transition;
+use crate::witness::util::stack_peek;
 
 pub mod mpt;
 pub(crate) mod prover_input;
@@ -42,8 +46,8 @@ pub struct GenerationInputs {
     pub gas_used_before: U256,
     pub gas_used_after: U256,
 
-    // A None would yield an empty proof, otherwise this contains the encoding of a transaction.
-    pub signed_txn: Option<Vec<u8>>,
+    // The encodings of the transactions to process, in order. An empty list yields an empty proof.
+    pub signed_txns: Vec<Vec<u8>>,
     // Withdrawal pairs `(addr, amount)`. At the end of the txs, `amount` is added to `addr`'s balance. See EIP-4895.
     pub withdrawals: Vec<(Address, U256)>,
     pub tries: TrieInputs,
@@ -112,7 +116,7 @@ fn apply_metadata_and_tries_memops<F: RichField + Extendable<D>, const D: usize>
         (GlobalMetadata::TxnNumberBefore, inputs.txn_number_before),
         (
             GlobalMetadata::TxnNumberAfter,
-            inputs.txn_number_before + if inputs.signed_txn.is_some() { 1 } else { 0 },
+            inputs.txn_number_before + U256::from(inputs.signed_txns.len()),
         ),
         (
             GlobalMetadata::StateTrieRootDigestBefore,
@@ -244,6 +248,169 @@ pub fn generate_traces<F: RichField + Extendable<D>, const D: usize>(
     Ok((tables, public_values))
 }
 
+/// The effects of a transaction, as observed by running the kernel without checking the final
+/// tries.
+#[derive(Clone, Debug, Default)]
+pub struct TxnOutcome {
+    /// Whether the transaction succeeded.
+    pub status: bool,
+    /// The cumulative gas used in the block after this transaction, as stored in its receipt.
+    pub cum_gas_used: U256,
+    /// The logs emitted by the transaction.
+    pub logs: Vec<LogRlp>,
+}
+
+/// The effects of all the transactions of some `GenerationInputs`. This is useful to fill in
+/// `gas_used_after` and `trie_roots_after` when they aren't known beforehand, e.g. when the
+/// expected outputs only consist of a post-state root.
+#[derive(Clone, Debug, Default)]
+pub struct SimulationOutcome {
+    /// The outcome of each transaction, in order.
+    pub txns: Vec<TxnOutcome>,
+    pub gas_used_after: U256,
+}
+
+/// The error returned by `simulate_txns` when the kernel rejects a transaction, either because it
+/// fails validation (nonce, balance, gas limits, fees...) or because it can't be decoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxnRejected {
+    /// The index of the rejected transaction in `GenerationInputs::signed_txns`.
+    pub txn_index: usize,
+    /// The kernel routine which rejected the transaction.
+    pub reason: String,
+}
+
+impl std::fmt::Display for TxnRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "transaction {} was rejected at {}",
+            self.txn_index, self.reason
+        )
+    }
+}
+
+impl std::error::Error for TxnRejected {}
+
+/// Executes the kernel on the given inputs until the final tries are about to be hashed and checked,
+/// and returns the outcome of every transaction. No trace is generated, and
+/// `inputs.trie_roots_after`, `inputs.gas_used_after` are ignored.
+///
+/// If a transaction is rejected by the kernel, the returned error wraps a `TxnRejected`.
+pub fn simulate_txns<F: RichField + Extendable<D>, const D: usize>(
+    inputs: &GenerationInputs,
+) -> anyhow::Result<SimulationOutcome> {
+    let mut state = GenerationState::<F>::new(inputs.clone(), &KERNEL.code)
+        .map_err(|err| anyhow!("Failed to parse all the initial prover inputs: {:?}", err))?;
+
+    apply_metadata_and_tries_memops(&mut state, inputs);
+
+    let route_txn_pc = KERNEL.global_labels["route_txn"];
+    let process_normalized_txn_pc = KERNEL.global_labels["process_normalized_txn"];
+    let process_receipt_pc = KERNEL.global_labels["process_receipt"];
+    let hash_final_tries_pc = KERNEL.global_labels["hash_final_tries"];
+    let halt_pc = KERNEL.global_labels["halt"];
+    let invalid_txn_pcs = [
+        "invalid_txn",
+        "invalid_txn_1",
+        "invalid_txn_2",
+        "invalid_txn_3",
+    ]
+    .map(|label| KERNEL.global_labels[label]);
+    let mut outcome = SimulationOutcome::default();
+    // Whether the current transaction is being decoded, i.e. hasn't reached validation yet.
+    let mut decoding = false;
+
+    loop {
+        let pc = state.registers.program_counter;
+        let txn_index = outcome.txns.len();
+        if state.registers.is_kernel {
+            if pc == route_txn_pc {
+                decoding = true;
+            } else if pc == process_normalized_txn_pc {
+                decoding = false;
+            } else if invalid_txn_pcs.contains(&pc) {
+                return Err(TxnRejected {
+                    txn_index,
+                    reason: KERNEL.offset_name(pc),
+                }
+                .into());
+            } else if pc == process_receipt_pc {
+                // stack: status, leftover_gas, prev_cum_gas, txn_nb, num_nibbles, retdest
+                let read = |i, what| {
+                    stack_peek(&state, i)
+                        .map_err(|err| anyhow!("Failed to read the {}: {:?}", what, err))
+                };
+                let status = !read(0, "transaction status")?.is_zero();
+                let prev_cum_gas = read(2, "cumulative gas")?;
+                if let Some(prev_txn) = outcome.txns.last_mut() {
+                    prev_txn.cum_gas_used = prev_cum_gas;
+                }
+                // Failed transactions don't emit any log.
+                let logs = if status {
+                    read_logs(&state)
+                        .map_err(|err| anyhow!("Failed to read the transaction logs: {:?}", err))?
+                } else {
+                    vec![]
+                };
+                outcome.txns.push(TxnOutcome {
+                    status,
+                    cum_gas_used: U256::zero(),
+                    logs,
+                });
+            } else if pc == hash_final_tries_pc {
+                // stack: cum_gas, txn_counter, num_nibbles, txn_nb
+                outcome.gas_used_after = stack_peek(&state, 0)
+                    .map_err(|err| anyhow!("Failed to read the cumulative gas: {:?}", err))?;
+                if let Some(last_txn) = outcome.txns.last_mut() {
+                    last_txn.cum_gas_used = outcome.gas_used_after;
+                }
+                return Ok(outcome);
+            } else if pc == halt_pc {
+                bail!("The kernel halted before hashing the final tries");
+            }
+        }
+
+        if let Err(err) = transition(&mut state) {
+            if decoding {
+                return Err(err.context(TxnRejected {
+                    txn_index,
+                    reason: format!("decoding ({})", KERNEL.offset_name(pc)),
+                }));
+            }
+            return Err(err);
+        }
+    }
+}
+
+/// Reads the logs of the current transaction from the `Logs` and `LogsData` segments.
+/// Each log is stored as `[payload_len, address, num_topics, topics, data_len, data]`.
+fn read_logs<F: Field>(state: &GenerationState<F>) -> Result<Vec<LogRlp>, ProgramError> {
+    let load = |segment, virt| state.memory.get(MemoryAddress::new(0, segment, virt));
+    let num_logs = u256_to_usize(state.memory.read_global_metadata(GlobalMetadata::LogsLen))?;
+
+    (0..num_logs)
+        .map(|i| {
+            let log_ptr = u256_to_usize(load(Segment::Logs, i))?;
+            let address = Address::from(H256::from_uint(&load(Segment::LogsData, log_ptr + 1)));
+            let num_topics = u256_to_usize(load(Segment::LogsData, log_ptr + 2))?;
+            let topics = (0..num_topics)
+                .map(|j| H256::from_uint(&load(Segment::LogsData, log_ptr + 3 + j)))
+                .collect();
+            let data_len_ptr = log_ptr + 3 + num_topics;
+            let data_len = u256_to_usize(load(Segment::LogsData, data_len_ptr))?;
+            let data = (0..data_len)
+                .map(|j| load(Segment::LogsData, data_len_ptr + 1 + j).byte(0))
+                .collect::<Vec<_>>();
+            Ok(LogRlp {
+                address,
+                topics,
+                data: data.into(),
+            })
+        })
+        .collect()
+}
+
 fn simulate_cpu<F: RichField + Extendable<D>, const D: usize>(
     state: &mut GenerationState<F>,
 ) -> anyhow::Result<()> {
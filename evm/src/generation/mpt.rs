@@ -14,7 +14,7 @@ use crate::generation::TrieInputs;
 use crate::witness::errors::{ProgramError, ProverInputError};
 use crate::Node;
 
-#[derive(RlpEncodable, RlpDecodable, Debug)]
+#[derive(RlpEncodable, RlpDecodable, Debug, Clone)]
 pub struct AccountRlp {
     pub nonce: U256,
     pub balance: U256,
@@ -50,8 +50,10 @@ impl<F: Field> GenerationState<F> {
         }
     }
 
+    /// Whether all transactions have been processed. As each transaction is read in full from the
+    /// RLP tape before being executed, this is the case once the tape is empty.
     fn no_txn(&mut self) -> Result<U256, ProgramError> {
-        Ok(U256::from(self.inputs.signed_txn.is_none() as u8))
+        Ok(U256::from(self.rlp_prover_inputs.is_empty() as u8))
     }
 
     /// Finite field operations.
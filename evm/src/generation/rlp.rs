@@ -1,16 +1,19 @@
 use ethereum_types::U256;
 
-pub(crate) fn all_rlp_prover_inputs_reversed(signed_txn: &[u8]) -> Vec<U256> {
-    let mut inputs = all_rlp_prover_inputs(signed_txn);
+pub(crate) fn all_rlp_prover_inputs_reversed(signed_txns: &[Vec<u8>]) -> Vec<U256> {
+    let mut inputs = all_rlp_prover_inputs(signed_txns);
     inputs.reverse();
     inputs
 }
 
-fn all_rlp_prover_inputs(signed_txn: &[u8]) -> Vec<U256> {
+/// The transactions are read one after the other, each one preceded by its length.
+fn all_rlp_prover_inputs(signed_txns: &[Vec<u8>]) -> Vec<U256> {
     let mut prover_inputs = vec![];
-    prover_inputs.push(signed_txn.len().into());
-    for &byte in signed_txn {
-        prover_inputs.push(byte.into());
+    for signed_txn in signed_txns {
+        prover_inputs.push(signed_txn.len().into());
+        for &byte in signed_txn {
+            prover_inputs.push(byte.into());
+        }
     }
     prover_inputs
 }
@@ -764,6 +764,7 @@ mod tests {
                 challenges: vec![ctl_z_data.challenge; config.num_challenges],
             },
             &mut Challenger::new(),
+            None,
             &mut timing,
         )?;
 
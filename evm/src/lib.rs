@@ -28,10 +28,12 @@ pub mod proof;
 pub mod prover;
 pub mod recursive_verifier;
 pub mod stark;
+pub mod state_tests;
 pub mod util;
 pub mod vanishing_poly;
 pub mod verifier;
 pub mod witness;
+pub mod witness_builder;
 
 #[cfg(test)]
 mod stark_testing;
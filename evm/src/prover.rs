@@ -2,6 +2,7 @@ use anyhow::{ensure, Result};
 use itertools::Itertools;
 use once_cell::sync::Lazy;
 use plonky2::field::extension::Extendable;
+use plonky2::field::fft::FftRootTable;
 use plonky2::field::packable::Packable;
 use plonky2::field::packed::PackedField;
 use plonky2::field::polynomial::{PolynomialCoeffs, PolynomialValues};
@@ -11,6 +12,7 @@ use plonky2::fri::oracle::PolynomialBatch;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::challenger::Challenger;
 use plonky2::plonk::config::GenericConfig;
+use plonky2::plonk::prover::ProverOptions;
 use plonky2::timed;
 use plonky2::util::timing::TimingTree;
 use plonky2::util::transpose;
@@ -38,24 +40,41 @@ use crate::{
 };
 
 /// Generate traces, then create all STARK proofs.
+///
+/// Of `options`, the thread pool, which trace generation also runs in, and the FFT root table are
+/// used. The STARKs aren't blinded and their LDEs are always retained, so the other options have
+/// no effect.
 pub fn prove<F, C, const D: usize>(
     all_stark: &AllStark<F, D>,
     config: &StarkConfig,
     inputs: GenerationInputs,
+    options: &ProverOptions<F>,
     timing: &mut TimingTree,
 ) -> Result<AllProof<F, C, D>>
 where
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
 {
-    timed!(timing, "build kernel", Lazy::force(&KERNEL));
-    let (traces, public_values) = timed!(
-        timing,
-        "generate all traces",
-        generate_traces(all_stark, inputs, config, timing)?
-    );
-    let proof = prove_with_traces(all_stark, config, traces, public_values, timing)?;
-    Ok(proof)
+    let run = || {
+        timed!(timing, "build kernel", Lazy::force(&KERNEL));
+        let (traces, public_values) = timed!(
+            timing,
+            "generate all traces",
+            generate_traces(all_stark, inputs, config, timing)?
+        );
+        prove_with_traces(
+            all_stark,
+            config,
+            traces,
+            public_values,
+            options.fft_root_table.as_deref(),
+            timing,
+        )
+    };
+    if let Some(thread_pool) = &options.thread_pool {
+        return thread_pool.install(run);
+    }
+    run()
 }
 
 /// Compute all STARK proofs.
@@ -64,6 +83,7 @@ pub(crate) fn prove_with_traces<F, C, const D: usize>(
     config: &StarkConfig,
     trace_poly_values: [Vec<PolynomialValues<F>>; NUM_TABLES],
     public_values: PublicValues,
+    fft_root_table: Option<&FftRootTable<F>>,
     timing: &mut TimingTree,
 ) -> Result<AllProof<F, C, D>>
 where
@@ -92,7 +112,7 @@ where
                         false,
                         cap_height,
                         timing,
-                        None,
+                        fft_root_table,
                     )
                 )
             })
@@ -136,6 +156,7 @@ where
             ctl_data_per_table,
             &mut challenger,
             &ctl_challenges,
+            fft_root_table,
             timing
         )?
     );
@@ -171,6 +192,7 @@ fn prove_with_commitments<F, C, const D: usize>(
     ctl_data_per_table: [CtlData<F>; NUM_TABLES],
     challenger: &mut Challenger<F, C::Hasher>,
     ctl_challenges: &GrandProductChallengeSet<F>,
+    fft_root_table: Option<&FftRootTable<F>>,
     timing: &mut TimingTree,
 ) -> Result<[StarkProofWithMetadata<F, C, D>; NUM_TABLES]>
 where
@@ -188,6 +210,7 @@ where
             &ctl_data_per_table[Table::Arithmetic as usize],
             ctl_challenges,
             challenger,
+            fft_root_table,
             timing,
         )?
     );
@@ -202,6 +225,7 @@ where
             &ctl_data_per_table[Table::BytePacking as usize],
             ctl_challenges,
             challenger,
+            fft_root_table,
             timing,
         )?
     );
@@ -216,6 +240,7 @@ where
             &ctl_data_per_table[Table::Cpu as usize],
             ctl_challenges,
             challenger,
+            fft_root_table,
             timing,
         )?
     );
@@ -230,6 +255,7 @@ where
             &ctl_data_per_table[Table::Keccak as usize],
             ctl_challenges,
             challenger,
+            fft_root_table,
             timing,
         )?
     );
@@ -244,6 +270,7 @@ where
             &ctl_data_per_table[Table::KeccakSponge as usize],
             ctl_challenges,
             challenger,
+            fft_root_table,
             timing,
         )?
     );
@@ -258,6 +285,7 @@ where
             &ctl_data_per_table[Table::Logic as usize],
             ctl_challenges,
             challenger,
+            fft_root_table,
             timing,
         )?
     );
@@ -272,6 +300,7 @@ where
             &ctl_data_per_table[Table::Memory as usize],
             ctl_challenges,
             challenger,
+            fft_root_table,
             timing,
         )?
     );
@@ -299,6 +328,7 @@ pub(crate) fn prove_single_table<F, C, S, const D: usize>(
     ctl_data: &CtlData<F>,
     ctl_challenges: &GrandProductChallengeSet<F>,
     challenger: &mut Challenger<F, C::Hasher>,
+    fft_root_table: Option<&FftRootTable<F>>,
     timing: &mut TimingTree,
 ) -> Result<StarkProofWithMetadata<F, C, D>>
 where
@@ -368,7 +398,7 @@ where
             false,
             config.fri_config.cap_height,
             timing,
-            None,
+            fft_root_table,
         )
     );
 
@@ -434,7 +464,7 @@ where
             false,
             config.fri_config.cap_height,
             timing,
-            None,
+            fft_root_table,
         )
     );
     // Observe the quotient polynomials Merkle cap.
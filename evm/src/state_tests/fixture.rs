@@ -0,0 +1,229 @@
+//! Deserialization of filled `GeneralStateTests` fixtures, and their conversion into
+//! `GenerationInputs`.
+
+use std::collections::{BTreeMap, HashMap};
+
+use eth_trie_utils::nibbles::Nibbles;
+use eth_trie_utils::partial_trie::{HashedPartialTrie, PartialTrie};
+use ethereum_types::{Address, BigEndianHash, H256, U256};
+use keccak_hash::keccak;
+use serde::Deserialize;
+
+use crate::generation::mpt::{AccountRlp, LegacyReceiptRlp, LogRlp};
+use crate::generation::{GenerationInputs, SimulationOutcome, TrieInputs};
+use crate::proof::{BlockHashes, BlockMetadata, TrieRoots};
+use crate::util::{deserialize_hex_bytes, deserialize_opt_hex_bytes};
+use crate::Node;
+
+/// A single test of a fixture file, i.e. one of its top-level entries.
+#[derive(Clone, Debug, Deserialize)]
+pub struct StateTest {
+    pub env: Env,
+    pub pre: BTreeMap<Address, PreAccount>,
+    /// Expected post-states, for each fork.
+    pub post: BTreeMap<String, Vec<PostState>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Env {
+    pub current_coinbase: Address,
+    pub current_difficulty: U256,
+    pub current_gas_limit: U256,
+    pub current_number: U256,
+    pub current_timestamp: U256,
+    #[serde(default)]
+    pub current_base_fee: Option<U256>,
+    #[serde(default)]
+    pub current_random: Option<H256>,
+    #[serde(default)]
+    pub previous_hash: Option<H256>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PreAccount {
+    pub balance: U256,
+    #[serde(deserialize_with = "deserialize_hex_bytes")]
+    pub code: Vec<u8>,
+    pub nonce: U256,
+    pub storage: BTreeMap<U256, U256>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostState {
+    /// The expected state trie root.
+    pub hash: H256,
+    /// The expected hash of the RLP-encoded list of logs.
+    pub logs: H256,
+    /// The signed transaction. Older fixtures don't include it.
+    #[serde(default, deserialize_with = "deserialize_opt_hex_bytes")]
+    pub txbytes: Option<Vec<u8>>,
+    /// Set when the transaction is expected to be rejected.
+    #[serde(default)]
+    pub expect_exception: Option<String>,
+    pub indexes: PostIndexes,
+}
+
+/// Indices in the `data`, `gasLimit` and `value` arrays of the fixture's transaction.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct PostIndexes {
+    pub data: usize,
+    pub gas: usize,
+    pub value: usize,
+}
+
+impl StateTest {
+    /// The tries and contract code of the pre-state.
+    pub fn tries_before(&self) -> (TrieInputs, HashMap<H256, Vec<u8>>) {
+        let mut state_trie = HashedPartialTrie::from(Node::Empty);
+        let mut storage_tries = vec![];
+        let mut contract_code = HashMap::new();
+        contract_code.insert(keccak(vec![]), vec![]);
+
+        for (address, account) in &self.pre {
+            let mut storage_trie = HashedPartialTrie::from(Node::Empty);
+            for (key, value) in &account.storage {
+                if value.is_zero() {
+                    continue;
+                }
+                let key_hash = keccak(H256::from_uint(key));
+                storage_trie.insert(Nibbles::from_h256_be(key_hash), rlp::encode(value).to_vec());
+            }
+
+            let code_hash = keccak(&account.code);
+            contract_code.insert(code_hash, account.code.clone());
+
+            let account_rlp = AccountRlp {
+                nonce: account.nonce,
+                balance: account.balance,
+                storage_root: storage_trie.hash(),
+                code_hash,
+            };
+            let address_hash = keccak(address);
+            state_trie.insert(
+                Nibbles::from_h256_be(address_hash),
+                rlp::encode(&account_rlp).to_vec(),
+            );
+            storage_tries.push((address_hash, storage_trie));
+        }
+
+        let tries = TrieInputs {
+            state_trie,
+            transactions_trie: Node::Empty.into(),
+            receipts_trie: Node::Empty.into(),
+            storage_tries,
+        };
+        (tries, contract_code)
+    }
+
+    pub fn block_metadata(&self) -> BlockMetadata {
+        let env = &self.env;
+        BlockMetadata {
+            block_beneficiary: env.current_coinbase,
+            block_timestamp: env.current_timestamp,
+            block_number: env.current_number,
+            block_difficulty: env.current_difficulty,
+            block_random: env
+                .current_random
+                .unwrap_or_else(|| H256::from_uint(&env.current_difficulty)),
+            block_gaslimit: env.current_gas_limit,
+            block_chain_id: 1.into(),
+            block_base_fee: env.current_base_fee.unwrap_or_else(|| 0xa.into()),
+            block_gas_used: 0.into(),
+            block_bloom: [0.into(); 8],
+        }
+    }
+
+    /// Inputs for executing the transaction of `post`, with unknown final tries and gas.
+    /// These can be completed with `complete_inputs` once the `SimulationOutcome` is known.
+    pub fn partial_inputs(&self, post: &PostState) -> GenerationInputs {
+        let (tries, contract_code) = self.tries_before();
+        let mut prev_hashes = vec![H256::default(); 256];
+        prev_hashes[255] = self.env.previous_hash.unwrap_or_default();
+
+        GenerationInputs {
+            txn_number_before: 0.into(),
+            gas_used_before: 0.into(),
+            gas_used_after: 0.into(),
+            signed_txns: post.txbytes.iter().cloned().collect(),
+            withdrawals: vec![],
+            tries,
+            trie_roots_after: TrieRoots::default(),
+            genesis_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
+            contract_code,
+            block_metadata: self.block_metadata(),
+            block_hashes: BlockHashes {
+                prev_hashes,
+                cur_hash: H256::default(),
+            },
+        }
+    }
+}
+
+/// Fills in the final tries, gas and bloom of `inputs`, given the outcome of its transactions and
+/// the expected post-state root.
+pub fn complete_inputs(
+    mut inputs: GenerationInputs,
+    outcome: &SimulationOutcome,
+    expected_state_root: H256,
+) -> GenerationInputs {
+    let all_logs: Vec<LogRlp> = outcome
+        .txns
+        .iter()
+        .flat_map(|txn| txn.logs.iter().cloned())
+        .collect();
+    let block_bloom = logs_bloom(&all_logs);
+
+    let mut transactions_trie = HashedPartialTrie::from(Node::Empty);
+    let mut receipts_trie = HashedPartialTrie::from(Node::Empty);
+    for (i, (txn, txn_outcome)) in inputs.signed_txns.iter().zip(&outcome.txns).enumerate() {
+        // Typed transactions are prefixed by their type, while legacy ones are RLP lists.
+        let txn_type = match txn.first() {
+            Some(&byte) if byte < 0xc0 => byte,
+            _ => 0,
+        };
+        let receipt = LegacyReceiptRlp {
+            status: txn_outcome.status,
+            cum_gas_used: txn_outcome.cum_gas_used,
+            bloom: logs_bloom(&txn_outcome.logs).to_vec().into(),
+            logs: txn_outcome.logs.clone(),
+        };
+        let key = Nibbles::from_bytes_be(&rlp::encode(&(i as u64)))
+            .expect("An RLP-encoded index is short");
+        transactions_trie.insert(key, txn.clone());
+        receipts_trie.insert(key, receipt.encode(txn_type));
+    }
+
+    inputs.trie_roots_after = TrieRoots {
+        state_root: expected_state_root,
+        transactions_root: transactions_trie.hash(),
+        receipts_root: receipts_trie.hash(),
+    };
+    inputs.gas_used_after = outcome.gas_used_after;
+    inputs.block_metadata.block_gas_used = outcome.gas_used_after;
+    inputs.block_metadata.block_bloom =
+        core::array::from_fn(|i| U256::from_big_endian(&block_bloom[32 * i..32 * (i + 1)]));
+    inputs
+}
+
+/// The hash of the RLP-encoded list of logs, as found in the `logs` field of post-states.
+pub fn logs_hash(logs: &[LogRlp]) -> H256 {
+    keccak(rlp::encode_list::<LogRlp, _>(logs))
+}
+
+/// Computes the 2048-bit bloom filter of the given logs.
+pub fn logs_bloom(logs: &[LogRlp]) -> [u8; 256] {
+    let mut bloom = [0u8; 256];
+    let entries = logs.iter().flat_map(|log| {
+        core::iter::once(log.address.as_bytes()).chain(log.topics.iter().map(|t| t.as_bytes()))
+    });
+    for entry in entries {
+        let hash = keccak(entry);
+        for i in [0, 2, 4] {
+            let bit = (((hash[i] as usize) << 8) | hash[i + 1] as usize) & 2047;
+            bloom[255 - bit / 8] |= 1 << (bit % 8);
+        }
+    }
+    bloom
+}
@@ -0,0 +1,431 @@
+//! A runner for the `GeneralStateTests` of the official ethereum/tests repository.
+//!
+//! Filled fixtures are read from a local directory. For each post-state of the selected forks, the
+//! transaction is first executed to learn its gas usage and logs, then the completed
+//! `GenerationInputs` are run through trace generation, or optionally fully proven. The kernel
+//! checks the final state root against the fixture's, while logs are compared against the
+//! fixture's logs hash.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, ensure, Result};
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::plonk::config::KeccakGoldilocksConfig;
+use plonky2::plonk::prover::ProverOptions;
+use plonky2::util::timing::TimingTree;
+
+use crate::all_stark::AllStark;
+use crate::config::StarkConfig;
+use crate::generation::{generate_traces, simulate_txns, TxnRejected};
+use crate::prover::prove;
+use crate::state_tests::fixture::{complete_inputs, logs_hash, PostState, StateTest};
+use crate::verifier::verify_proof;
+
+pub mod fixture;
+
+type F = GoldilocksField;
+const D: usize = 2;
+type C = KeccakGoldilocksConfig;
+
+#[derive(Clone, Debug)]
+pub struct RunnerOptions {
+    /// The forks whose post-states should be checked.
+    pub forks: Vec<String>,
+    /// Whether to generate and verify a full proof, instead of only generating traces.
+    pub prove: bool,
+    /// If set, only tests whose name contains this string are run.
+    pub filter: Option<String>,
+}
+
+impl Default for RunnerOptions {
+    fn default() -> Self {
+        Self {
+            forks: vec!["Shanghai".to_string()],
+            prove: false,
+            filter: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TestStatus {
+    Passed,
+    Failed(String),
+    Skipped(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct TestResult {
+    /// The directory of the fixture, relative to the tests root, e.g. `stExample`.
+    pub category: String,
+    pub name: String,
+    pub fork: String,
+    /// The index of the post-state within the fork's list.
+    pub index: usize,
+    pub status: TestStatus,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    pub results: Vec<TestResult>,
+}
+
+impl Report {
+    pub fn num_failed(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|r| matches!(r.status, TestStatus::Failed(_)))
+            .count()
+    }
+}
+
+/// Runs every fixture found under `root`, recursively.
+pub fn run_state_tests(root: &Path, options: &RunnerOptions) -> Result<Report> {
+    let mut files = vec![];
+    collect_fixtures(root, &mut files)?;
+    files.sort();
+
+    let mut report = Report::default();
+    for file in files {
+        let category = file
+            .strip_prefix(root)?
+            .parent()
+            .and_then(|dir| dir.components().next())
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let contents = fs::read_to_string(&file)?;
+        let tests: BTreeMap<String, StateTest> = serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("Failed to parse {}: {}", file.display(), e))?;
+        for (name, test) in tests {
+            if let Some(filter) = &options.filter {
+                if !name.contains(filter.as_str()) {
+                    continue;
+                }
+            }
+            report
+                .results
+                .extend(run_state_test(&category, &name, &test, options));
+        }
+    }
+    Ok(report)
+}
+
+/// Runs the post-states of `test` for every selected fork.
+pub fn run_state_test(
+    category: &str,
+    name: &str,
+    test: &StateTest,
+    options: &RunnerOptions,
+) -> Vec<TestResult> {
+    let mut results = vec![];
+    for fork in &options.forks {
+        let Some(posts) = test.post.get(fork) else {
+            continue;
+        };
+        for (index, post) in posts.iter().enumerate() {
+            log::info!("Running {name} ({fork}, #{index})");
+            // The kernel and trace generation may panic on unexpected inputs. Don't let a single
+            // test bring down the whole run.
+            let status = catch_unwind(AssertUnwindSafe(|| run_post_state(test, post, options)))
+                .unwrap_or_else(|_| TestStatus::Failed("panicked".to_string()));
+            results.push(TestResult {
+                category: category.to_string(),
+                name: name.to_string(),
+                fork: fork.clone(),
+                index,
+                status,
+            });
+        }
+    }
+    results
+}
+
+fn run_post_state(test: &StateTest, post: &PostState, options: &RunnerOptions) -> TestStatus {
+    if post.txbytes.is_none() {
+        return TestStatus::Skipped("no `txbytes` in fixture".to_string());
+    }
+
+    let result = check_post_state(test, post, options);
+    match (&post.expect_exception, result) {
+        (None, Ok(())) => TestStatus::Passed,
+        (None, Err(e)) => TestStatus::Failed(format!("{e:#}")),
+        // Only a rejection by the kernel's transaction validation or decoding counts as the
+        // expected exception; any other error is a failure of the runner or the prover.
+        (Some(exception), Err(e)) => match e.downcast_ref::<TxnRejected>() {
+            Some(rejection) => {
+                log::debug!("Expected {exception}, got {rejection}");
+                TestStatus::Passed
+            }
+            None => TestStatus::Failed(format!("expected exception {exception}, got {e:#}")),
+        },
+        (Some(exception), Ok(())) => TestStatus::Failed(format!(
+            "expected exception {exception}, but the transaction was accepted"
+        )),
+    }
+}
+
+fn check_post_state(test: &StateTest, post: &PostState, options: &RunnerOptions) -> Result<()> {
+    let inputs = test.partial_inputs(post);
+    let outcome = simulate_txns::<F, D>(&inputs)?;
+    for (i, txn) in outcome.txns.iter().enumerate() {
+        log::debug!(
+            "Transaction {i}: status {}, cumulative gas {}, {} logs",
+            txn.status,
+            txn.cum_gas_used,
+            txn.logs.len()
+        );
+    }
+    let logs: Vec<_> = outcome
+        .txns
+        .iter()
+        .flat_map(|txn| txn.logs.iter().cloned())
+        .collect();
+    let actual_logs_hash = logs_hash(&logs);
+    ensure!(
+        actual_logs_hash == post.logs,
+        "logs hash mismatch: expected {:?}, got {:?}",
+        post.logs,
+        actual_logs_hash
+    );
+
+    let inputs = complete_inputs(inputs, &outcome, post.hash);
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+    let mut timing = TimingTree::new("state test", log::Level::Debug);
+    if options.prove {
+        let proof = prove::<F, C, D>(
+            &all_stark,
+            &config,
+            inputs,
+            &ProverOptions::default(),
+            &mut timing,
+        )?;
+        verify_proof(&all_stark, proof, &config)
+    } else {
+        let (_, public_values) = generate_traces(&all_stark, inputs, &config, &mut timing)?;
+        ensure!(
+            public_values.trie_roots_after.state_root == post.hash,
+            "state root mismatch: expected {:?}, got {:?}",
+            post.hash,
+            public_values.trie_roots_after.state_root
+        );
+        Ok(())
+    }
+}
+
+fn collect_fixtures(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_fixtures(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "json") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+impl Display for Report {
+    /// Prints a matrix of passed/total counts, with one row per category and one column per fork,
+    /// followed by the list of failures.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let forks: BTreeSet<&str> = self.results.iter().map(|r| r.fork.as_str()).collect();
+        let mut counts: BTreeMap<(&str, &str), (usize, usize)> = BTreeMap::new();
+        for result in &self.results {
+            let (passed, total) = counts
+                .entry((result.category.as_str(), result.fork.as_str()))
+                .or_default();
+            match result.status {
+                TestStatus::Passed => {
+                    *passed += 1;
+                    *total += 1;
+                }
+                TestStatus::Failed(_) => *total += 1,
+                TestStatus::Skipped(_) => {}
+            }
+        }
+        let categories: BTreeSet<&str> = counts.keys().map(|&(category, _)| category).collect();
+        let width = categories.iter().map(|c| c.len()).max().unwrap_or(0).max(8);
+
+        write!(f, "{:width$}", "category")?;
+        for fork in &forks {
+            write!(f, " | {fork:>12}")?;
+        }
+        writeln!(f)?;
+        for category in &categories {
+            write!(f, "{category:width$}")?;
+            for fork in &forks {
+                let (passed, total) = counts.get(&(*category, *fork)).copied().unwrap_or_default();
+                write!(f, " | {:>12}", format!("{passed}/{total}"))?;
+            }
+            writeln!(f)?;
+        }
+
+        for result in &self.results {
+            if let TestStatus::Failed(reason) = &result.status {
+                writeln!(
+                    f,
+                    "FAILED {}/{} ({}, #{}): {}",
+                    result.category, result.name, result.fork, result.index, reason
+                )?;
+            }
+        }
+        let num_skipped = self
+            .results
+            .iter()
+            .filter(|r| matches!(r.status, TestStatus::Skipped(_)))
+            .count();
+        writeln!(
+            f,
+            "{} passed, {} failed, {} skipped",
+            self.results.len() - self.num_failed() - num_skipped,
+            self.num_failed(),
+            num_skipped
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eth_trie_utils::nibbles::Nibbles;
+    use eth_trie_utils::partial_trie::{HashedPartialTrie, PartialTrie};
+    use ethereum_types::{Address, H256};
+    use hex_literal::hex;
+    use keccak_hash::keccak;
+
+    use crate::generation::mpt::AccountRlp;
+    use crate::state_tests::fixture::StateTest;
+    use crate::state_tests::{run_state_test, RunnerOptions, TestStatus};
+    use crate::Node;
+
+    /// The `add11` test from ethereum/tests, without its expected state root.
+    const ADD11: &str = r#"{
+        "env": {
+            "currentCoinbase": "0x2adc25665018aa1fe0e6bc666dac8fc2697ff9ba",
+            "currentDifficulty": "0x020000",
+            "currentGasLimit": "0xff112233",
+            "currentNumber": "0x01",
+            "currentTimestamp": "0x03e8",
+            "currentBaseFee": "0x0a",
+            "currentRandom": "0x0000000000000000000000000000000000000000000000000000000000020000"
+        },
+        "pre": {
+            "0x095e7baea6a6c7c4c2dfeb977efac326af552d87": {
+                "balance": "0x0de0b6b3a7640000",
+                "code": "0x600160010160005500",
+                "nonce": "0x00",
+                "storage": {}
+            },
+            "0x2adc25665018aa1fe0e6bc666dac8fc2697ff9ba": {
+                "balance": "0x00",
+                "code": "0x",
+                "nonce": "0x01",
+                "storage": {}
+            },
+            "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b": {
+                "balance": "0x0de0b6b3a7640000",
+                "code": "0x",
+                "nonce": "0x00",
+                "storage": {}
+            }
+        },
+        "post": {
+            "Shanghai": [
+                {
+                    "hash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                    "logs": "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+                    "txbytes": "0xf863800a83061a8094095e7baea6a6c7c4c2dfeb977efac326af552d87830186a0801ba0ffb600e63115a7362e7811894a91d8ba4330e526f22121c994c4692035dfdfd5a06198379fcac8de3dbfac48b165df4bf88e2088f294b61efb9a65fe2281c76e16",
+                    "indexes": { "data": 0, "gas": 0, "value": 0 }
+                }
+            ]
+        }
+    }"#;
+
+    fn expected_state_root() -> H256 {
+        let beneficiary = hex!("2adc25665018aa1fe0e6bc666dac8fc2697ff9ba");
+        let sender = hex!("a94f5374fce5edbc8e2a8697c15331677e6ebf0b");
+        let to = hex!("095e7baea6a6c7c4c2dfeb977efac326af552d87");
+        let code = [0x60, 0x01, 0x60, 0x01, 0x01, 0x60, 0x00, 0x55, 0x00];
+
+        let accounts = [
+            (
+                beneficiary,
+                AccountRlp {
+                    nonce: 1.into(),
+                    ..AccountRlp::default()
+                },
+            ),
+            (
+                sender,
+                AccountRlp {
+                    balance: 0xde0b6b3a75be550u64.into(),
+                    nonce: 1.into(),
+                    ..AccountRlp::default()
+                },
+            ),
+            (
+                to,
+                AccountRlp {
+                    balance: 0xde0b6b3a76586a0u64.into(),
+                    code_hash: keccak(code),
+                    // Storage map: { 0 => 2 }
+                    storage_root: HashedPartialTrie::from(Node::Leaf {
+                        nibbles: Nibbles::from_h256_be(keccak([0u8; 32])),
+                        value: vec![2],
+                    })
+                    .hash(),
+                },
+            ),
+        ];
+
+        let mut state_trie = HashedPartialTrie::from(Node::Empty);
+        for (address, account) in accounts {
+            state_trie.insert(
+                Nibbles::from_h256_be(keccak(address)),
+                rlp::encode(&account).to_vec(),
+            );
+        }
+        state_trie.hash()
+    }
+
+    #[test]
+    fn test_add11() -> anyhow::Result<()> {
+        let mut test: StateTest = serde_json::from_str(ADD11)?;
+        let options = RunnerOptions::default();
+
+        let results = run_state_test("stExample", "add11", &test, &options);
+        assert_eq!(results.len(), 1);
+        assert!(matches!(results[0].status, TestStatus::Failed(_)));
+
+        test.post.get_mut("Shanghai").unwrap()[0].hash = expected_state_root();
+        let results = run_state_test("stExample", "add11", &test, &options);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].status, TestStatus::Passed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_expected_exception() -> anyhow::Result<()> {
+        let mut test: StateTest = serde_json::from_str(ADD11)?;
+        let options = RunnerOptions::default();
+        test.post.get_mut("Shanghai").unwrap()[0].expect_exception =
+            Some("TransactionException.NONCE_MISMATCH_TOO_LOW".to_string());
+
+        // A valid transaction doesn't raise the expected exception.
+        let results = run_state_test("stExample", "add11", &test, &options);
+        assert!(matches!(results[0].status, TestStatus::Failed(_)));
+
+        // With a sender nonce ahead of the transaction's, the kernel rejects it.
+        let sender = Address::from(hex!("a94f5374fce5edbc8e2a8697c15331677e6ebf0b"));
+        test.pre.get_mut(&sender).unwrap().nonce = 1.into();
+        let results = run_state_test("stExample", "add11", &test, &options);
+        assert_eq!(results[0].status, TestStatus::Passed);
+
+        Ok(())
+    }
+}
@@ -10,6 +10,8 @@ use plonky2::field::types::Field;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
 use plonky2::util::transpose;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
 
 use crate::witness::errors::ProgramError;
 
@@ -232,3 +234,39 @@ pub(crate) fn get_h256<F: RichField>(slice: &[F]) -> H256 {
             .collect_vec(),
     )
 }
+
+/// Decodes a hex string, with or without a `0x` prefix.
+pub(crate) fn decode_hex(s: &str) -> Result<Vec<u8>, String> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    if s.len() % 2 != 0 {
+        return Err(format!("Odd-length hex string {s}"));
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|e| e.to_string()))
+        .collect()
+}
+
+pub(crate) fn deserialize_hex_bytes<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<u8>, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    decode_hex(&s).map_err(D::Error::custom)
+}
+
+pub(crate) fn deserialize_opt_hex_bytes<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<u8>>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| decode_hex(&s).map_err(D::Error::custom))
+        .transpose()
+}
+
+pub(crate) fn deserialize_hex_bytes_list<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Vec<u8>>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|s| decode_hex(s).map_err(D::Error::custom))
+        .collect()
+}
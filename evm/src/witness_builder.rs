@@ -0,0 +1,964 @@
+//! Builds the `GenerationInputs` of every transaction of a block from data served by an archive
+//! node, so that real blocks can be proven without a bespoke pipeline.
+//!
+//! The input is a JSON object gathering:
+//! - `header`: the block, as returned by `eth_getBlockByNumber`,
+//! - `chainId`,
+//! - `transactions`: the raw signed transactions, in block order,
+//! - `receipts`: the output of `eth_getBlockReceipts`,
+//! - `traces`: the output of `debug_traceBlockByNumber` with the `prestateTracer` in `diffMode`,
+//! - `proofs`: `eth_getProof` responses against the parent block, for every account and storage
+//!   slot touched by the block,
+//! - optionally `withdrawals` (as in the block body), `prevHashes` (the 256 previous block hashes,
+//!   oldest first) and `genesisStateRoot`.
+//!
+//! The proofs are turned into partial state and storage tries, in which every node which isn't on
+//! the path of a proven key is hashed out. The post-state of each transaction is then obtained by
+//! applying the diff reported by the tracer to the partial tries of the previous one. Since the
+//! resulting roots are checked against the block header, incomplete or inconsistent data is
+//! reported as an error rather than producing unprovable inputs.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use anyhow::{anyhow, bail, ensure, Result};
+use eth_trie_utils::nibbles::Nibbles;
+use eth_trie_utils::partial_trie::{HashedPartialTrie, PartialTrie};
+use ethereum_types::{Address, BigEndianHash, H256, U256, U512};
+use keccak_hash::keccak;
+use rlp::Rlp;
+use serde::Deserialize;
+
+use crate::generation::mpt::{AccountRlp, LegacyReceiptRlp, LogRlp};
+use crate::generation::{GenerationInputs, TrieInputs};
+use crate::proof::{BlockHashes, BlockMetadata, TrieRoots};
+use crate::util::{deserialize_hex_bytes, deserialize_hex_bytes_list, deserialize_opt_hex_bytes};
+use crate::Node;
+
+/// Withdrawal amounts are expressed in Gwei, while the kernel expects them in Wei.
+const GWEI: u64 = 1_000_000_000;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockWitness {
+    pub header: Header,
+    pub chain_id: U256,
+    #[serde(deserialize_with = "deserialize_hex_bytes_list")]
+    pub transactions: Vec<Vec<u8>>,
+    pub receipts: Vec<Receipt>,
+    pub traces: Vec<TxnTrace>,
+    pub proofs: Vec<AccountProof>,
+    #[serde(default)]
+    pub withdrawals: Vec<Withdrawal>,
+    #[serde(default)]
+    pub prev_hashes: Option<Vec<H256>>,
+    #[serde(default)]
+    pub genesis_state_root: Option<H256>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Header {
+    pub hash: H256,
+    pub parent_hash: H256,
+    pub number: U256,
+    pub timestamp: U256,
+    pub miner: Address,
+    pub difficulty: U256,
+    pub mix_hash: H256,
+    pub gas_limit: U256,
+    pub gas_used: U256,
+    #[serde(default)]
+    pub base_fee_per_gas: U256,
+    #[serde(deserialize_with = "deserialize_hex_bytes")]
+    pub logs_bloom: Vec<u8>,
+    pub state_root: H256,
+    pub transactions_root: H256,
+    pub receipts_root: H256,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Receipt {
+    pub status: U256,
+    pub cumulative_gas_used: U256,
+    #[serde(deserialize_with = "deserialize_hex_bytes")]
+    pub logs_bloom: Vec<u8>,
+    pub logs: Vec<Log>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Log {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    #[serde(deserialize_with = "deserialize_hex_bytes")]
+    pub data: Vec<u8>,
+}
+
+/// One entry of the output of `debug_traceBlockByNumber`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TxnTrace {
+    pub result: StateDiff,
+}
+
+/// The output of the `prestateTracer` in `diffMode`. `pre` holds the original state of every
+/// account modified by the transaction, and `post` only the modified fields. Accounts which are
+/// only in `pre` have been deleted, and storage slots which are only in `pre` have been cleared.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct StateDiff {
+    #[serde(default)]
+    pub pre: BTreeMap<Address, AccountDiff>,
+    #[serde(default)]
+    pub post: BTreeMap<Address, AccountDiff>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct AccountDiff {
+    #[serde(default)]
+    pub balance: Option<U256>,
+    #[serde(default)]
+    pub nonce: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_opt_hex_bytes")]
+    pub code: Option<Vec<u8>>,
+    #[serde(default)]
+    pub storage: BTreeMap<H256, H256>,
+}
+
+/// An `eth_getProof` response. Only the fields needed to rebuild the partial tries are kept.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountProof {
+    pub address: Address,
+    #[serde(deserialize_with = "deserialize_hex_bytes_list")]
+    pub account_proof: Vec<Vec<u8>>,
+    pub balance: U256,
+    pub nonce: U256,
+    pub code_hash: H256,
+    pub storage_hash: H256,
+    #[serde(default)]
+    pub storage_proof: Vec<StorageProof>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct StorageProof {
+    #[serde(deserialize_with = "deserialize_hex_bytes_list")]
+    pub proof: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Withdrawal {
+    pub address: Address,
+    /// The amount, in Gwei.
+    pub amount: U256,
+}
+
+/// Reads a `BlockWitness` from a JSON file and builds the inputs of each of its transactions.
+pub fn generation_inputs_from_json<P: AsRef<Path>>(path: P) -> Result<Vec<GenerationInputs>> {
+    let file = File::open(path.as_ref())
+        .map_err(|err| anyhow!("Cannot open {}: {err}", path.as_ref().display()))?;
+    BlockWitness::from_reader(BufReader::new(file))?.generation_inputs()
+}
+
+impl BlockWitness {
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Builds one `GenerationInputs` per transaction, each starting from the state left by the
+    /// previous one. The withdrawals are processed with the last transaction. A block without
+    /// transactions yields a single input with no transaction, processing the withdrawals.
+    pub fn generation_inputs(&self) -> Result<Vec<GenerationInputs>> {
+        self.batched_generation_inputs(1)
+    }
+
+    /// Like `generation_inputs`, but groups up to `txns_per_input` consecutive transactions in
+    /// each `GenerationInputs`, so that they are covered by a single root proof.
+    pub fn batched_generation_inputs(
+        &self,
+        txns_per_input: usize,
+    ) -> Result<Vec<GenerationInputs>> {
+        ensure!(
+            txns_per_input > 0,
+            "Each input should hold at least one transaction"
+        );
+        let num_txns = self.transactions.len();
+        ensure!(
+            self.receipts.len() == num_txns,
+            "Expected {num_txns} receipts, got {}",
+            self.receipts.len()
+        );
+        ensure!(
+            self.traces.len() == num_txns,
+            "Expected {num_txns} traces, got {}",
+            self.traces.len()
+        );
+        ensure!(
+            self.header.logs_bloom.len() == 256,
+            "The logs bloom should be 256 bytes long"
+        );
+
+        let mut state = PartialState::from_proofs(&self.proofs)?;
+        let mut contract_code = HashMap::new();
+        contract_code.insert(keccak([]), vec![]);
+        let mut transactions_trie = HashedPartialTrie::from(Node::Empty);
+        let mut receipts_trie = HashedPartialTrie::from(Node::Empty);
+
+        let block_metadata = self.block_metadata();
+        let block_hashes = self.block_hashes()?;
+        let genesis_state_trie_root = self
+            .genesis_state_root
+            .unwrap_or_else(|| HashedPartialTrie::from(Node::Empty).hash());
+
+        let batches = if num_txns == 0 {
+            vec![0..0]
+        } else {
+            (0..num_txns)
+                .step_by(txns_per_input)
+                .map(|start| start..num_txns.min(start + txns_per_input))
+                .collect()
+        };
+        let num_batches = batches.len();
+
+        let mut inputs = vec![];
+        let mut gas_used_before = U256::zero();
+        for (batch_index, batch) in batches.into_iter().enumerate() {
+            let tries = TrieInputs {
+                state_trie: state.state_trie.clone(),
+                transactions_trie: transactions_trie.clone(),
+                receipts_trie: receipts_trie.clone(),
+                storage_tries: state.storage_tries(),
+            };
+
+            let txn_number_before = batch.start;
+            let mut signed_txns = vec![];
+            let mut gas_used_after = gas_used_before;
+            for i in batch {
+                let diff = &self.traces[i].result;
+                for code in diff.pre.values().chain(diff.post.values()) {
+                    if let Some(code) = &code.code {
+                        contract_code.insert(keccak(code), code.clone());
+                    }
+                }
+                state
+                    .apply_diff(diff)
+                    .map_err(|err| anyhow!("Cannot apply the diff of transaction {i}: {err}"))?;
+
+                let txn = &self.transactions[i];
+                let key = txn_key(i);
+                transactions_trie.insert(key, txn.clone());
+                receipts_trie.insert(key, self.receipts[i].encode(txn));
+                signed_txns.push(txn.clone());
+                gas_used_after = self.receipts[i].cumulative_gas_used;
+            }
+
+            let withdrawals = if batch_index + 1 == num_batches {
+                self.withdrawals
+                    .iter()
+                    .map(|w| (w.address, w.amount * U256::from(GWEI)))
+                    .collect()
+            } else {
+                vec![]
+            };
+            for &(address, amount) in &withdrawals {
+                state.add_balance(address, amount)?;
+            }
+
+            inputs.push(GenerationInputs {
+                txn_number_before: txn_number_before.into(),
+                gas_used_before,
+                gas_used_after,
+                signed_txns,
+                withdrawals,
+                tries,
+                trie_roots_after: TrieRoots {
+                    state_root: state.state_trie.hash(),
+                    transactions_root: transactions_trie.hash(),
+                    receipts_root: receipts_trie.hash(),
+                },
+                genesis_state_trie_root,
+                contract_code: contract_code.clone(),
+                block_metadata: block_metadata.clone(),
+                block_hashes: block_hashes.clone(),
+            });
+            gas_used_before = gas_used_after;
+        }
+
+        ensure!(
+            gas_used_before == self.header.gas_used,
+            "The receipts use {gas_used_before} gas, but the header reports {}",
+            self.header.gas_used
+        );
+        let roots = &inputs
+            .last()
+            .expect("There is at least one input")
+            .trie_roots_after;
+        ensure!(
+            roots.state_root == self.header.state_root,
+            "Computed state root {:?}, but the header has {:?}",
+            roots.state_root,
+            self.header.state_root
+        );
+        ensure!(
+            roots.transactions_root == self.header.transactions_root,
+            "Computed transactions root {:?}, but the header has {:?}",
+            roots.transactions_root,
+            self.header.transactions_root
+        );
+        ensure!(
+            roots.receipts_root == self.header.receipts_root,
+            "Computed receipts root {:?}, but the header has {:?}",
+            roots.receipts_root,
+            self.header.receipts_root
+        );
+
+        Ok(inputs)
+    }
+
+    fn block_metadata(&self) -> BlockMetadata {
+        let header = &self.header;
+        let bloom = &header.logs_bloom;
+        BlockMetadata {
+            block_beneficiary: header.miner,
+            block_timestamp: header.timestamp,
+            block_number: header.number,
+            block_difficulty: header.difficulty,
+            block_random: header.mix_hash,
+            block_gaslimit: header.gas_limit,
+            block_chain_id: self.chain_id,
+            block_base_fee: header.base_fee_per_gas,
+            block_gas_used: header.gas_used,
+            block_bloom: core::array::from_fn(|i| {
+                U256::from_big_endian(&bloom[32 * i..32 * (i + 1)])
+            }),
+        }
+    }
+
+    fn block_hashes(&self) -> Result<BlockHashes> {
+        let prev_hashes = match &self.prev_hashes {
+            Some(prev_hashes) => {
+                ensure!(
+                    prev_hashes.len() == 256,
+                    "Expected 256 previous block hashes, got {}",
+                    prev_hashes.len()
+                );
+                ensure!(
+                    prev_hashes[255] == self.header.parent_hash,
+                    "The last previous block hash should be the parent hash"
+                );
+                prev_hashes.clone()
+            }
+            None => {
+                let mut prev_hashes = vec![H256::zero(); 256];
+                prev_hashes[255] = self.header.parent_hash;
+                prev_hashes
+            }
+        };
+        Ok(BlockHashes {
+            prev_hashes,
+            cur_hash: self.header.hash,
+        })
+    }
+}
+
+impl Receipt {
+    /// The receipt, as stored in the receipts trie.
+    fn encode(&self, signed_txn: &[u8]) -> Vec<u8> {
+        // Typed transactions are prefixed by their type, while legacy ones are RLP lists.
+        let txn_type = match signed_txn.first() {
+            Some(&byte) if byte < 0xc0 => byte,
+            _ => 0,
+        };
+        LegacyReceiptRlp {
+            status: !self.status.is_zero(),
+            cum_gas_used: self.cumulative_gas_used,
+            bloom: self.logs_bloom.clone().into(),
+            logs: self
+                .logs
+                .iter()
+                .map(|log| LogRlp {
+                    address: log.address,
+                    topics: log.topics.clone(),
+                    data: log.data.clone().into(),
+                })
+                .collect(),
+        }
+        .encode(txn_type)
+    }
+}
+
+/// The key of the `i`-th transaction in the transactions and receipts tries.
+fn txn_key(i: usize) -> Nibbles {
+    Nibbles::from_bytes_be(&rlp::encode(&(i as u64))).expect("An RLP-encoded index is short")
+}
+
+/// The partial state trie, along with the partial storage tries and the values of the accounts it
+/// contains.
+struct PartialState {
+    state_trie: HashedPartialTrie,
+    accounts: HashMap<Address, AccountRlp>,
+    storage: HashMap<Address, HashedPartialTrie>,
+}
+
+impl PartialState {
+    fn from_proofs(proofs: &[AccountProof]) -> Result<Self> {
+        // The first node of a proof is the root, so all proofs should start with the same one.
+        let proof_root = |proof: &AccountProof| {
+            proof
+                .account_proof
+                .first()
+                .map_or_else(|| HashedPartialTrie::from(Node::Empty).hash(), keccak)
+        };
+        let parent_state_root = proof_root(
+            proofs
+                .first()
+                .ok_or_else(|| anyhow!("No account proofs were given"))?,
+        );
+        ensure!(
+            proofs.iter().all(|p| proof_root(p) == parent_state_root),
+            "The account proofs don't share the same state root"
+        );
+        let state_trie = partial_trie_from_proofs(
+            parent_state_root,
+            proofs.iter().flat_map(|p| &p.account_proof),
+        )?;
+
+        let mut accounts = HashMap::new();
+        let mut storage = HashMap::new();
+        for proof in proofs {
+            let storage_trie = partial_trie_from_proofs(
+                proof.storage_hash,
+                proof.storage_proof.iter().flat_map(|p| &p.proof),
+            )?;
+            let account = AccountRlp {
+                nonce: proof.nonce,
+                balance: proof.balance,
+                storage_root: proof.storage_hash,
+                code_hash: proof.code_hash,
+            };
+            accounts.insert(proof.address, account);
+            storage.insert(proof.address, storage_trie);
+        }
+
+        Ok(Self {
+            state_trie,
+            accounts,
+            storage,
+        })
+    }
+
+    fn storage_tries(&self) -> Vec<(H256, HashedPartialTrie)> {
+        self.storage
+            .iter()
+            .map(|(address, trie)| (keccak(address), trie.clone()))
+            .collect()
+    }
+
+    fn apply_diff(&mut self, diff: &StateDiff) -> Result<()> {
+        for address in diff.pre.keys() {
+            if !diff.post.contains_key(address) {
+                self.delete_account(*address);
+            }
+        }
+
+        for (address, post) in &diff.post {
+            let pre = diff.pre.get(address);
+            let mut storage_trie = match self.storage.remove(address) {
+                Some(trie) => trie,
+                None if pre.is_none() => HashedPartialTrie::from(Node::Empty),
+                None => bail!("No proof was given for account {address:?}"),
+            };
+
+            let cleared_slots = pre
+                .iter()
+                .flat_map(|pre| pre.storage.keys())
+                .filter(|slot| !post.storage.contains_key(slot));
+            for slot in cleared_slots {
+                storage_trie.delete(Nibbles::from_h256_be(keccak(slot)));
+            }
+            for (slot, value) in &post.storage {
+                let key = Nibbles::from_h256_be(keccak(slot));
+                let value = value.into_uint();
+                if value.is_zero() {
+                    storage_trie.delete(key);
+                } else {
+                    storage_trie.insert(key, rlp::encode(&value).to_vec());
+                }
+            }
+
+            let field = |f: fn(&AccountDiff) -> Option<U256>| {
+                f(post).or_else(|| pre.and_then(f)).unwrap_or_default()
+            };
+            let code = post
+                .code
+                .as_ref()
+                .or_else(|| pre.and_then(|pre| pre.code.as_ref()));
+            let account = AccountRlp {
+                nonce: field(|a| a.nonce.map(U256::from)),
+                balance: field(|a| a.balance),
+                storage_root: storage_trie.hash(),
+                code_hash: code.map_or_else(|| keccak([]), keccak),
+            };
+            self.set_account(*address, account, storage_trie);
+        }
+
+        Ok(())
+    }
+
+    fn add_balance(&mut self, address: Address, amount: U256) -> Result<()> {
+        let mut account = self.accounts.get(&address).cloned().unwrap_or_default();
+        let storage_trie = self
+            .storage
+            .remove(&address)
+            .ok_or_else(|| anyhow!("No proof was given for withdrawal address {address:?}"))?;
+        account.balance += amount;
+        self.set_account(address, account, storage_trie);
+        Ok(())
+    }
+
+    fn set_account(
+        &mut self,
+        address: Address,
+        account: AccountRlp,
+        storage_trie: HashedPartialTrie,
+    ) {
+        self.state_trie.insert(
+            Nibbles::from_h256_be(keccak(address)),
+            rlp::encode(&account).to_vec(),
+        );
+        self.accounts.insert(address, account);
+        self.storage.insert(address, storage_trie);
+    }
+
+    fn delete_account(&mut self, address: Address) {
+        self.state_trie
+            .delete(Nibbles::from_h256_be(keccak(address)));
+        self.accounts.remove(&address);
+        self.storage.remove(&address);
+    }
+}
+
+/// Rebuilds the partial trie with the given root from the nodes of Merkle proofs. Subtries whose
+/// root isn't among the given nodes are hashed out.
+pub fn partial_trie_from_proofs<'a, I>(root: H256, nodes: I) -> Result<HashedPartialTrie>
+where
+    I: IntoIterator<Item = &'a Vec<u8>>,
+{
+    if root == HashedPartialTrie::from(Node::Empty).hash() {
+        return Ok(Node::Empty.into());
+    }
+    let nodes = nodes
+        .into_iter()
+        .map(|node| (keccak(node), node.as_slice()))
+        .collect();
+    Ok(node_from_hash(&nodes, root)?.into())
+}
+
+fn node_from_hash(nodes: &HashMap<H256, &[u8]>, hash: H256) -> Result<Node> {
+    match nodes.get(&hash) {
+        Some(bytes) => node_from_rlp(nodes, &Rlp::new(bytes)),
+        None => Ok(Node::Hash(hash)),
+    }
+}
+
+fn node_from_rlp(nodes: &HashMap<H256, &[u8]>, rlp: &Rlp) -> Result<Node> {
+    match rlp.item_count()? {
+        17 => {
+            let mut children = core::array::from_fn(|_| Node::Empty.into());
+            for (i, child) in children.iter_mut().enumerate() {
+                *child = child_from_rlp(nodes, &rlp.at(i)?)?.into();
+            }
+            Ok(Node::Branch {
+                children,
+                value: rlp.at(16)?.data()?.to_vec(),
+            })
+        }
+        2 => {
+            let (nibbles, is_leaf) = decode_hex_prefix(rlp.at(0)?.data()?)?;
+            if is_leaf {
+                Ok(Node::Leaf {
+                    nibbles,
+                    value: rlp.at(1)?.data()?.to_vec(),
+                })
+            } else {
+                Ok(Node::Extension {
+                    nibbles,
+                    child: child_from_rlp(nodes, &rlp.at(1)?)?.into(),
+                })
+            }
+        }
+        n => bail!("Invalid trie node with {n} items"),
+    }
+}
+
+/// Decodes a reference to a child node, which is either inlined or given by its hash.
+fn child_from_rlp(nodes: &HashMap<H256, &[u8]>, rlp: &Rlp) -> Result<Node> {
+    if rlp.is_list() {
+        return node_from_rlp(nodes, rlp);
+    }
+    match rlp.data()? {
+        [] => Ok(Node::Empty),
+        hash if hash.len() == 32 => node_from_hash(nodes, H256::from_slice(hash)),
+        data => bail!("Invalid child reference of {} bytes", data.len()),
+    }
+}
+
+/// Decodes a hex-prefix encoded path, returning the nibbles and whether they belong to a leaf.
+fn decode_hex_prefix(bytes: &[u8]) -> Result<(Nibbles, bool)> {
+    let (&first, rest) = bytes
+        .split_first()
+        .ok_or_else(|| anyhow!("Empty hex-prefix encoded path"))?;
+    let flag = first >> 4;
+    ensure!(flag < 4, "Invalid hex-prefix flag {flag}");
+
+    let mut nibbles = vec![];
+    if flag & 1 == 1 {
+        nibbles.push(first & 0xf);
+    }
+    for byte in rest {
+        nibbles.extend([byte >> 4, byte & 0xf]);
+    }
+    ensure!(nibbles.len() <= 64, "Paths are at most 64 nibbles long");
+
+    let packed = nibbles
+        .iter()
+        .fold(U512::zero(), |acc, &n| (acc << 4) | U512::from(n));
+    let nibbles = Nibbles {
+        count: nibbles.len(),
+        packed,
+    };
+    Ok((nibbles, flag & 2 == 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use rlp::RlpStream;
+
+    use super::*;
+
+    /// Encodes a node as in an Ethereum trie, collecting the encodings of its descendants which are
+    /// too long to be inlined.
+    fn encode_node(trie: &HashedPartialTrie, encodings: &mut Vec<Vec<u8>>) -> Vec<u8> {
+        let mut stream = RlpStream::new();
+        match &**trie {
+            Node::Branch { children, value } => {
+                stream.begin_list(17);
+                for child in children {
+                    append_child(&mut stream, child, encodings);
+                }
+                stream.append(value);
+            }
+            Node::Extension { nibbles, child } => {
+                stream.begin_list(2);
+                stream.append(&encode_hex_prefix(nibbles, false));
+                append_child(&mut stream, child, encodings);
+            }
+            Node::Leaf { nibbles, value } => {
+                stream.begin_list(2);
+                stream.append(&encode_hex_prefix(nibbles, true));
+                stream.append(value);
+            }
+            Node::Empty | Node::Hash(_) => unreachable!(),
+        }
+        stream.out().to_vec()
+    }
+
+    fn append_child(
+        stream: &mut RlpStream,
+        child: &HashedPartialTrie,
+        encodings: &mut Vec<Vec<u8>>,
+    ) {
+        match &**child {
+            Node::Empty => {
+                stream.append_empty_data();
+            }
+            Node::Hash(h) => {
+                stream.append(h);
+            }
+            _ => {
+                let encoding = encode_node(child, encodings);
+                if encoding.len() < 32 {
+                    stream.append_raw(&encoding, 1);
+                } else {
+                    stream.append(&keccak(&encoding));
+                    encodings.push(encoding);
+                }
+            }
+        }
+    }
+
+    fn encode_hex_prefix(nibbles: &Nibbles, is_leaf: bool) -> Vec<u8> {
+        let nibbles = (0..nibbles.count)
+            .map(|i| nibbles.get_nibble(i))
+            .collect::<Vec<_>>();
+        let flag = 2 * is_leaf as u8 + (nibbles.len() % 2) as u8;
+        let mut all = vec![flag];
+        if nibbles.len() % 2 == 0 {
+            all.push(0);
+        }
+        all.extend(nibbles);
+        all.chunks(2).map(|c| (c[0] << 4) | c[1]).collect()
+    }
+
+    /// All the nodes of `trie`, the root first.
+    fn all_nodes(trie: &HashedPartialTrie) -> Vec<Vec<u8>> {
+        let mut encodings = vec![];
+        let root = encode_node(trie, &mut encodings);
+        encodings.insert(0, root);
+        encodings
+    }
+
+    fn account(balance: u64) -> AccountRlp {
+        AccountRlp {
+            balance: balance.into(),
+            ..Default::default()
+        }
+    }
+
+    fn state_trie(accounts: &[(Address, AccountRlp)]) -> HashedPartialTrie {
+        let mut trie = HashedPartialTrie::from(Node::Empty);
+        for (address, account) in accounts {
+            trie.insert(
+                Nibbles::from_h256_be(keccak(address)),
+                rlp::encode(account).to_vec(),
+            );
+        }
+        trie
+    }
+
+    #[test]
+    fn test_hex_prefix() -> Result<()> {
+        let (nibbles, is_leaf) = decode_hex_prefix(&[0x3a, 0xbc])?;
+        assert!(is_leaf);
+        assert_eq!(nibbles.count, 3);
+        assert_eq!(nibbles.packed, U512::from(0xabc));
+
+        let (nibbles, is_leaf) = decode_hex_prefix(&[0x00, 0x12])?;
+        assert!(!is_leaf);
+        assert_eq!(nibbles.count, 2);
+        assert_eq!(nibbles.packed, U512::from(0x12));
+        Ok(())
+    }
+
+    #[test]
+    fn test_partial_trie_from_proofs() -> Result<()> {
+        let accounts = (0..16u64)
+            .map(|i| (Address::from_low_u64_be(i), account(i + 1)))
+            .collect::<Vec<_>>();
+        let full_trie = state_trie(&accounts);
+        let nodes = all_nodes(&full_trie);
+        assert_eq!(keccak(&nodes[0]), full_trie.hash());
+
+        // All nodes give back the full trie.
+        let rebuilt = partial_trie_from_proofs(full_trie.hash(), &nodes)?;
+        assert_eq!(rebuilt.hash(), full_trie.hash());
+
+        // With only the root, all of its children are hashed out, but the root is unchanged.
+        let partial = partial_trie_from_proofs(full_trie.hash(), &nodes[..1])?;
+        assert_eq!(partial.hash(), full_trie.hash());
+        let Node::Branch { children, .. } = &*partial else {
+            panic!("The root should be a branch");
+        };
+        assert!(children.iter().all(|child| {
+            let child: &HashedPartialTrie = child;
+            matches!(**child, Node::Hash(_) | Node::Empty)
+        }));
+        Ok(())
+    }
+
+    #[test]
+    fn test_generation_inputs() -> Result<()> {
+        let sender = Address::from_low_u64_be(1);
+        let recipient = Address::from_low_u64_be(2);
+        let miner = Address::from_low_u64_be(3);
+        let others = (4..20u64)
+            .map(|i| (Address::from_low_u64_be(i), account(i)))
+            .collect::<Vec<_>>();
+
+        let mut accounts = vec![
+            (sender, account(1_000_000)),
+            (recipient, account(0)),
+            (miner, account(0)),
+        ];
+        accounts.extend(others.iter().cloned());
+        let trie_before = state_trie(&accounts);
+        let nodes = all_nodes(&trie_before);
+
+        let proof = |address: Address, balance: u64| AccountProof {
+            address,
+            account_proof: nodes.clone(),
+            balance: balance.into(),
+            nonce: 0.into(),
+            code_hash: keccak([]),
+            storage_hash: HashedPartialTrie::from(Node::Empty).hash(),
+            storage_proof: vec![],
+        };
+        let diff = |pre: &[(Address, u64, u64)], post: &[(Address, u64, u64)]| {
+            let to_map = |accounts: &[(Address, u64, u64)]| {
+                accounts
+                    .iter()
+                    .map(|&(address, balance, nonce)| {
+                        let diff = AccountDiff {
+                            balance: Some(balance.into()),
+                            nonce: Some(nonce),
+                            ..Default::default()
+                        };
+                        (address, diff)
+                    })
+                    .collect()
+            };
+            TxnTrace {
+                result: StateDiff {
+                    pre: to_map(pre),
+                    post: to_map(post),
+                },
+            }
+        };
+        let receipt = |cumulative_gas_used: u64| Receipt {
+            status: 1.into(),
+            cumulative_gas_used: cumulative_gas_used.into(),
+            logs_bloom: vec![0; 256],
+            logs: vec![],
+        };
+
+        // Two transfers from the sender to the recipient, each paying 10 to the miner, and a
+        // withdrawal of 1 Gwei to an untouched account.
+        let traces = vec![
+            diff(
+                &[(sender, 1_000_000, 0), (recipient, 0, 0), (miner, 0, 0)],
+                &[(sender, 999_890, 1), (recipient, 100, 0), (miner, 10, 0)],
+            ),
+            diff(
+                &[(sender, 999_890, 1), (recipient, 100, 0), (miner, 10, 0)],
+                &[(sender, 999_780, 2), (recipient, 200, 0), (miner, 20, 0)],
+            ),
+        ];
+        let transactions = vec![vec![0xc1, 0x01], vec![0xc1, 0x02]];
+        let receipts = vec![receipt(21_000), receipt(42_000)];
+
+        let mut accounts_after = vec![
+            (
+                sender,
+                AccountRlp {
+                    nonce: 2.into(),
+                    ..account(999_780)
+                },
+            ),
+            (recipient, account(200)),
+            (miner, account(20)),
+        ];
+        accounts_after.extend(others[1..].iter().cloned());
+        accounts_after.push((others[0].0, account(4 + GWEI)));
+
+        let mut transactions_trie = HashedPartialTrie::from(Node::Empty);
+        let mut receipts_trie = HashedPartialTrie::from(Node::Empty);
+        for (i, (txn, receipt)) in transactions.iter().zip(&receipts).enumerate() {
+            transactions_trie.insert(txn_key(i), txn.clone());
+            receipts_trie.insert(txn_key(i), receipt.encode(txn));
+        }
+
+        let witness = BlockWitness {
+            header: Header {
+                hash: H256::from_low_u64_be(2),
+                parent_hash: H256::from_low_u64_be(1),
+                number: 2.into(),
+                timestamp: 0x1234.into(),
+                miner,
+                difficulty: 0.into(),
+                mix_hash: H256::zero(),
+                gas_limit: 30_000_000.into(),
+                gas_used: 42_000.into(),
+                base_fee_per_gas: 7.into(),
+                logs_bloom: vec![0; 256],
+                state_root: state_trie(&accounts_after).hash(),
+                transactions_root: transactions_trie.hash(),
+                receipts_root: receipts_trie.hash(),
+            },
+            chain_id: 1.into(),
+            transactions,
+            receipts,
+            traces,
+            proofs: vec![
+                proof(sender, 1_000_000),
+                proof(recipient, 0),
+                proof(miner, 0),
+                proof(others[0].0, 4),
+            ],
+            withdrawals: vec![Withdrawal {
+                address: others[0].0,
+                amount: 1.into(),
+            }],
+            prev_hashes: None,
+            genesis_state_root: None,
+        };
+
+        let inputs = witness.generation_inputs()?;
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(inputs[0].tries.state_trie.hash(), trie_before.hash());
+        assert_eq!(inputs[0].txn_number_before, 0.into());
+        assert_eq!(inputs[0].gas_used_before, 0.into());
+        assert_eq!(inputs[0].gas_used_after, 21_000.into());
+        assert!(inputs[0].withdrawals.is_empty());
+        assert_eq!(inputs[1].txn_number_before, 1.into());
+        assert_eq!(inputs[1].gas_used_before, 21_000.into());
+        assert_eq!(inputs[1].gas_used_after, 42_000.into());
+        assert_eq!(inputs[1].withdrawals, vec![(others[0].0, GWEI.into())]);
+        assert_eq!(
+            inputs[1].tries.state_trie.hash(),
+            inputs[0].trie_roots_after.state_root
+        );
+        assert_eq!(
+            inputs[1].tries.transactions_trie.hash(),
+            inputs[0].trie_roots_after.transactions_root
+        );
+
+        // Both transactions can also be processed at once.
+        let batched = witness.batched_generation_inputs(2)?;
+        assert_eq!(batched.len(), 1);
+        assert_eq!(batched[0].signed_txns.len(), 2);
+        assert_eq!(batched[0].gas_used_before, 0.into());
+        assert_eq!(batched[0].gas_used_after, 42_000.into());
+        assert_eq!(batched[0].tries.state_trie.hash(), trie_before.hash());
+        assert_eq!(batched[0].trie_roots_after, inputs[1].trie_roots_after);
+
+        // A wrong state root in the header is reported.
+        let mut bad_witness = witness;
+        bad_witness.header.state_root = H256::zero();
+        assert!(bad_witness.generation_inputs().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_trace() -> Result<()> {
+        let json = r#"{
+            "txHash": "0x01",
+            "result": {
+                "pre": {
+                    "0x0000000000000000000000000000000000000001": {
+                        "balance": "0x10",
+                        "nonce": 3,
+                        "code": "0x6001",
+                        "storage": {
+                            "0x0000000000000000000000000000000000000000000000000000000000000001":
+                            "0x0000000000000000000000000000000000000000000000000000000000000002"
+                        }
+                    }
+                },
+                "post": {
+                    "0x0000000000000000000000000000000000000001": { "balance": "0x08" }
+                }
+            }
+        }"#;
+        let trace: TxnTrace = serde_json::from_str(json)?;
+        let pre = &trace.result.pre[&Address::from_low_u64_be(1)];
+        assert_eq!(pre.balance, Some(0x10.into()));
+        assert_eq!(pre.nonce, Some(3));
+        assert_eq!(pre.code, Some(vec![0x60, 0x01]));
+        assert_eq!(pre.storage.len(), 1);
+        let post = &trace.result.post[&Address::from_low_u64_be(1)];
+        assert_eq!(post.balance, Some(0x08.into()));
+        assert!(post.storage.is_empty());
+        Ok(())
+    }
+}
@@ -1,7 +1,8 @@
 use std::collections::HashMap;
 use std::marker::PhantomData;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use anyhow::Context;
 use env_logger::{try_init_from_env, Env, DEFAULT_FILTER_ENV};
 use eth_trie_utils::partial_trie::{HashedPartialTrie, PartialTrie};
 use ethereum_types::{BigEndianHash, H256};
@@ -11,9 +12,9 @@ use plonky2::field::goldilocks_field::GoldilocksField;
 use plonky2::plonk::config::PoseidonGoldilocksConfig;
 use plonky2::util::serialization::{DefaultGateSerializer, DefaultGeneratorSerializer};
 use plonky2::util::timing::TimingTree;
-use plonky2_evm::all_stark::AllStark;
+use plonky2_evm::all_stark::{AllStark, Table};
 use plonky2_evm::config::StarkConfig;
-use plonky2_evm::fixed_recursive_verifier::AllRecursiveCircuits;
+use plonky2_evm::fixed_recursive_verifier::{AllRecursiveCircuits, TableCircuitsCache};
 use plonky2_evm::generation::{GenerationInputs, TrieInputs};
 use plonky2_evm::proof::{BlockHashes, BlockMetadata, PublicValues, TrieRoots};
 use plonky2_evm::Node;
@@ -31,48 +32,7 @@ fn test_empty_txn_list() -> anyhow::Result<()> {
     let all_stark = AllStark::<F, D>::default();
     let config = StarkConfig::standard_fast_config();
 
-    let block_metadata = BlockMetadata {
-        block_number: 1.into(),
-        ..Default::default()
-    };
-
-    let state_trie = HashedPartialTrie::from(Node::Empty);
-    let transactions_trie = HashedPartialTrie::from(Node::Empty);
-    let receipts_trie = HashedPartialTrie::from(Node::Empty);
-    let storage_tries = vec![];
-
-    let mut contract_code = HashMap::new();
-    contract_code.insert(keccak(vec![]), vec![]);
-
-    // No transactions, so no trie roots change.
-    let trie_roots_after = TrieRoots {
-        state_root: state_trie.hash(),
-        transactions_root: transactions_trie.hash(),
-        receipts_root: receipts_trie.hash(),
-    };
-    let mut initial_block_hashes = vec![H256::default(); 256];
-    initial_block_hashes[255] = H256::from_uint(&0x200.into());
-    let inputs = GenerationInputs {
-        signed_txn: None,
-        withdrawals: vec![],
-        tries: TrieInputs {
-            state_trie,
-            transactions_trie,
-            receipts_trie,
-            storage_tries,
-        },
-        trie_roots_after,
-        contract_code,
-        genesis_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
-        block_metadata,
-        txn_number_before: 0.into(),
-        gas_used_before: 0.into(),
-        gas_used_after: 0.into(),
-        block_hashes: BlockHashes {
-            prev_hashes: initial_block_hashes,
-            cur_hash: H256::default(),
-        },
-    };
+    let inputs = empty_txn_list_inputs();
 
     // Initialize the preprocessed circuits for the zkEVM.
     // The provided ranges are the minimal ones to prove an empty list, except the one of the CPU
@@ -83,33 +43,31 @@ fn test_empty_txn_list() -> anyhow::Result<()> {
         &config,
     );
 
-    {
-        let gate_serializer = DefaultGateSerializer;
-        let generator_serializer = DefaultGeneratorSerializer {
-            _phantom: PhantomData::<C>,
-        };
-
-        let timing = TimingTree::new("serialize AllRecursiveCircuits", log::Level::Info);
-        let all_circuits_bytes = all_circuits
-            .to_bytes(&gate_serializer, &generator_serializer)
-            .map_err(|_| anyhow::Error::msg("AllRecursiveCircuits serialization failed."))?;
-        timing.filter(Duration::from_millis(100)).print();
-        info!(
-            "AllRecursiveCircuits length: {} bytes",
-            all_circuits_bytes.len()
-        );
+    let gate_serializer = DefaultGateSerializer;
+    let generator_serializer = DefaultGeneratorSerializer {
+        _phantom: PhantomData::<C>,
+    };
 
-        let timing = TimingTree::new("deserialize AllRecursiveCircuits", log::Level::Info);
-        let all_circuits_from_bytes = AllRecursiveCircuits::<F, C, D>::from_bytes(
-            &all_circuits_bytes,
-            &gate_serializer,
-            &generator_serializer,
-        )
-        .map_err(|_| anyhow::Error::msg("AllRecursiveCircuits deserialization failed."))?;
-        timing.filter(Duration::from_millis(100)).print();
+    let timing = TimingTree::new("serialize AllRecursiveCircuits", log::Level::Info);
+    let all_circuits_bytes = all_circuits
+        .to_bytes(&gate_serializer, &generator_serializer)
+        .map_err(|_| anyhow::Error::msg("AllRecursiveCircuits serialization failed."))?;
+    timing.filter(Duration::from_millis(100)).print();
+    info!(
+        "AllRecursiveCircuits length: {} bytes",
+        all_circuits_bytes.len()
+    );
 
-        assert_eq!(all_circuits, all_circuits_from_bytes);
-    }
+    let timing = TimingTree::new("deserialize AllRecursiveCircuits", log::Level::Info);
+    let all_circuits_from_bytes = AllRecursiveCircuits::<F, C, D>::from_bytes(
+        &all_circuits_bytes,
+        &gate_serializer,
+        &generator_serializer,
+    )
+    .map_err(|_| anyhow::Error::msg("AllRecursiveCircuits deserialization failed."))?;
+    timing.filter(Duration::from_millis(100)).print();
+
+    assert_eq!(all_circuits, all_circuits_from_bytes);
 
     let mut timing = TimingTree::new("prove", log::Level::Info);
     // We're missing some preprocessed circuits.
@@ -125,6 +83,48 @@ fn test_empty_txn_list() -> anyhow::Result<()> {
         &StarkConfig::standard_fast_config(),
     );
 
+    // Alternatively, the missing circuits can be built on demand, and reused from disk later on.
+    {
+        let cache_dir = std::env::temp_dir().join(format!(
+            "plonky2_evm_empty_txn_list_{}_{}",
+            std::process::id(),
+            SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos()
+        ));
+        let cache = TableCircuitsCache::new(&cache_dir, &gate_serializer, &generator_serializer);
+
+        let mut on_demand_circuits = all_circuits_from_bytes;
+        let mut timing = TimingTree::new("prove on demand", log::Level::Info);
+        let (root_proof, _, new_sizes) = on_demand_circuits.prove_root_on_demand(
+            &all_stark,
+            &config,
+            inputs.clone(),
+            &mut timing,
+            Some(&cache),
+        )?;
+        timing.filter(Duration::from_millis(100)).print();
+        assert_eq!(new_sizes, vec![(Table::Cpu, 12)]);
+        on_demand_circuits.verify_root(root_proof)?;
+        assert_eq!(all_circuits, on_demand_circuits);
+        drop(on_demand_circuits);
+
+        // Circuits missing the same sizes load them back from the cache instead of building them.
+        let mut cached_circuits = AllRecursiveCircuits::<F, C, D>::from_bytes(
+            &all_circuits_bytes,
+            &gate_serializer,
+            &generator_serializer,
+        )
+        .context("AllRecursiveCircuits deserialization failed.")?;
+        let new_sizes = cached_circuits.support_table_sizes(
+            &all_stark,
+            [16, 10, 12, 14, 9, 12, 17],
+            &config,
+            Some(&cache),
+        )?;
+        assert_eq!(new_sizes, vec![(Table::Cpu, 12)]);
+        std::fs::remove_dir_all(cache_dir)?;
+        assert_eq!(all_circuits, cached_circuits);
+    }
+
     let mut timing = TimingTree::new("prove", log::Level::Info);
     let (root_proof, public_values) =
         all_circuits.prove_root(&all_stark, &config, inputs, &mut timing)?;
@@ -163,6 +163,104 @@ fn test_empty_txn_list() -> anyhow::Result<()> {
     verifier.verify(block_proof)
 }
 
+/// Prove the empty list of transactions with circuits missing the CPU size it needs, building it
+/// through `prove_root_on_demand`.
+#[test]
+#[ignore] // Too slow to run on CI.
+fn test_empty_txn_list_on_demand() -> anyhow::Result<()> {
+    init_logger();
+
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+    let inputs = empty_txn_list_inputs();
+
+    // The CPU table needs a size of `2^12` to prove an empty list.
+    let mut all_circuits = AllRecursiveCircuits::<F, C, D>::new(
+        &all_stark,
+        &[16..17, 10..11, 11..12, 14..15, 9..11, 12..13, 17..18],
+        &config,
+    );
+    let root_verifier_data = all_circuits.root.circuit.verifier_only.clone();
+
+    let mut timing = TimingTree::new("prove on demand", log::Level::Info);
+    let (first_proof, first_public_values, new_sizes) = all_circuits.prove_root_on_demand(
+        &all_stark,
+        &config,
+        inputs.clone(),
+        &mut timing,
+        None,
+    )?;
+    timing.filter(Duration::from_millis(100)).print();
+    // Supporting the new size regenerated the root circuit.
+    assert_eq!(new_sizes, vec![(Table::Cpu, 12)]);
+    assert_ne!(all_circuits.root.circuit.verifier_only, root_verifier_data);
+    all_circuits.verify_root(first_proof.clone())?;
+
+    // Once warmed up, the circuits are left untouched, so the proofs can be aggregated.
+    let root_verifier_data = all_circuits.root.circuit.verifier_only.clone();
+    let mut timing = TimingTree::new("prove on demand", log::Level::Info);
+    let (second_proof, second_public_values, new_sizes) =
+        all_circuits.prove_root_on_demand(&all_stark, &config, inputs, &mut timing, None)?;
+    timing.filter(Duration::from_millis(100)).print();
+    assert!(new_sizes.is_empty());
+    assert_eq!(all_circuits.root.circuit.verifier_only, root_verifier_data);
+
+    let (agg_proof, _) = all_circuits.prove_aggregation(
+        false,
+        &first_proof,
+        first_public_values,
+        false,
+        &second_proof,
+        second_public_values,
+    )?;
+    all_circuits.verify_aggregation(&agg_proof)
+}
+
+fn empty_txn_list_inputs() -> GenerationInputs {
+    let block_metadata = BlockMetadata {
+        block_number: 1.into(),
+        ..Default::default()
+    };
+
+    let state_trie = HashedPartialTrie::from(Node::Empty);
+    let transactions_trie = HashedPartialTrie::from(Node::Empty);
+    let receipts_trie = HashedPartialTrie::from(Node::Empty);
+    let storage_tries = vec![];
+
+    let mut contract_code = HashMap::new();
+    contract_code.insert(keccak(vec![]), vec![]);
+
+    // No transactions, so no trie roots change.
+    let trie_roots_after = TrieRoots {
+        state_root: state_trie.hash(),
+        transactions_root: transactions_trie.hash(),
+        receipts_root: receipts_trie.hash(),
+    };
+    let mut initial_block_hashes = vec![H256::default(); 256];
+    initial_block_hashes[255] = H256::from_uint(&0x200.into());
+    GenerationInputs {
+        signed_txns: vec![],
+        withdrawals: vec![],
+        tries: TrieInputs {
+            state_trie,
+            transactions_trie,
+            receipts_trie,
+            storage_tries,
+        },
+        trie_roots_after,
+        contract_code,
+        genesis_state_trie_root: HashedPartialTrie::from(Node::Empty).hash(),
+        block_metadata,
+        txn_number_before: 0.into(),
+        gas_used_before: 0.into(),
+        gas_used_after: 0.into(),
+        block_hashes: BlockHashes {
+            prev_hashes: initial_block_hashes,
+            cur_hash: H256::default(),
+        },
+    }
+}
+
 fn init_logger() {
     let _ = try_init_from_env(Env::default().filter_or(DEFAULT_FILTER_ENV, "info"));
 }
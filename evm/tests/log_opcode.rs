@@ -13,6 +13,7 @@ use hex_literal::hex;
 use keccak_hash::keccak;
 use plonky2::field::goldilocks_field::GoldilocksField;
 use plonky2::plonk::config::PoseidonGoldilocksConfig;
+use plonky2::plonk::prover::ProverOptions;
 use plonky2::util::timing::TimingTree;
 use plonky2_evm::all_stark::AllStark;
 use plonky2_evm::config::StarkConfig;
@@ -216,7 +217,7 @@ fn test_log_opcodes() -> anyhow::Result<()> {
     };
 
     let inputs = GenerationInputs {
-        signed_txn: Some(txn.to_vec()),
+        signed_txns: vec![txn.to_vec()],
         withdrawals: vec![],
         tries: tries_before,
         trie_roots_after,
@@ -234,7 +235,13 @@ fn test_log_opcodes() -> anyhow::Result<()> {
     };
 
     let mut timing = TimingTree::new("prove", log::Level::Debug);
-    let proof = prove::<F, C, D>(&all_stark, &config, inputs, &mut timing)?;
+    let proof = prove::<F, C, D>(
+        &all_stark,
+        &config,
+        inputs,
+        &ProverOptions::default(),
+        &mut timing,
+    )?;
     timing.filter(Duration::from_millis(100)).print();
 
     // Assert that the proof leads to the correct state and receipt roots.
@@ -421,7 +428,7 @@ fn test_log_with_aggreg() -> anyhow::Result<()> {
     };
 
     let inputs_first = GenerationInputs {
-        signed_txn: Some(txn.to_vec()),
+        signed_txns: vec![txn.to_vec()],
         withdrawals: vec![],
         tries: tries_before,
         trie_roots_after: tries_after,
@@ -546,7 +553,7 @@ fn test_log_with_aggreg() -> anyhow::Result<()> {
     };
 
     let inputs = GenerationInputs {
-        signed_txn: Some(txn_2.to_vec()),
+        signed_txns: vec![txn_2.to_vec()],
         withdrawals: vec![],
         tries: tries_before,
         trie_roots_after,
@@ -1,2 +1,5 @@
 #[cfg(target_arch = "x86_64")]
 pub mod x86_64;
+
+#[cfg(not(all(target_arch = "x86_64", target_feature = "avx2")))]
+pub mod portable;
@@ -0,0 +1,268 @@
+//! Goldilocks arithmetic on vectors of four `u64` lanes, written against a handful of lane-wise
+//! operations (see `Lanes`) so that it can be backed by whichever vector instructions a target
+//! offers. Comparisons return masks, i.e. all ones in the lanes where they hold and zero
+//! elsewhere, so that the arithmetic selects with `and` instead of branching.
+//!
+//! The only backend used in production is `wasm32` `simd128`. Tests also run the arithmetic on a
+//! plain `[u64; 4]` backend, which is how it gets checked on other hosts.
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+use core::arch::wasm32::*;
+
+use crate::goldilocks_field::GoldilocksField;
+use crate::types::Field64;
+
+pub(crate) trait Lanes: Copy {
+    fn splat(x: u64) -> Self;
+    fn from_array(x: [u64; 4]) -> Self;
+    fn to_array(self) -> [u64; 4];
+
+    fn wrapping_add(self, rhs: Self) -> Self;
+    fn wrapping_sub(self, rhs: Self) -> Self;
+    fn and(self, rhs: Self) -> Self;
+    fn or(self, rhs: Self) -> Self;
+    fn shl32(self) -> Self;
+    fn shr32(self) -> Self;
+
+    /// Unsigned `self < rhs`, as a mask.
+    fn lt_mask(self, rhs: Self) -> Self;
+
+    /// The low 64 bits of the products of the lanes.
+    fn wrapping_mul(self, rhs: Self) -> Self;
+
+    /// The full 128-bit products of the lanes, as `(lo, hi)`.
+    #[inline]
+    fn mul_wide(self, rhs: Self) -> (Self, Self) {
+        // There is no widening 64-bit multiplication to rely on, so we build it from four
+        // 32 x 32 -> 64 products, as in schoolbook multiplication.
+        let lo_mask = Self::splat(0xffffffff);
+        let (x_lo, x_hi) = (self.and(lo_mask), self.shr32());
+        let (y_lo, y_hi) = (rhs.and(lo_mask), rhs.shr32());
+
+        let lo_lo = x_lo.wrapping_mul(y_lo);
+        let lo_hi = x_lo.wrapping_mul(y_hi);
+        let hi_lo = x_hi.wrapping_mul(y_lo);
+        let hi_hi = x_hi.wrapping_mul(y_hi);
+
+        // None of these sums can overflow: each product is at most (2^32 - 1)^2.
+        let mid0 = lo_hi.wrapping_add(lo_lo.shr32());
+        let mid1 = hi_lo.wrapping_add(mid0.and(lo_mask));
+        let lo = mid1.shl32().or(lo_lo.and(lo_mask));
+        let hi = hi_hi.wrapping_add(mid0.shr32()).wrapping_add(mid1.shr32());
+        (lo, hi)
+    }
+}
+
+/// Two `v128`s, each holding two lanes.
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+#[derive(Copy, Clone)]
+pub(crate) struct Simd128x2([v128; 2]);
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+impl Simd128x2 {
+    #[inline]
+    fn map(self, f: impl Fn(v128) -> v128) -> Self {
+        Self([f(self.0[0]), f(self.0[1])])
+    }
+    #[inline]
+    fn zip(self, rhs: Self, f: impl Fn(v128, v128) -> v128) -> Self {
+        Self([f(self.0[0], rhs.0[0]), f(self.0[1], rhs.0[1])])
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+impl Lanes for Simd128x2 {
+    #[inline]
+    fn splat(x: u64) -> Self {
+        Self([u64x2_splat(x); 2])
+    }
+    #[inline]
+    fn from_array(x: [u64; 4]) -> Self {
+        Self([u64x2(x[0], x[1]), u64x2(x[2], x[3])])
+    }
+    #[inline]
+    fn to_array(self) -> [u64; 4] {
+        let [lo, hi] = self.0;
+        [
+            u64x2_extract_lane::<0>(lo),
+            u64x2_extract_lane::<1>(lo),
+            u64x2_extract_lane::<0>(hi),
+            u64x2_extract_lane::<1>(hi),
+        ]
+    }
+
+    #[inline]
+    fn wrapping_add(self, rhs: Self) -> Self {
+        self.zip(rhs, i64x2_add)
+    }
+    #[inline]
+    fn wrapping_sub(self, rhs: Self) -> Self {
+        self.zip(rhs, i64x2_sub)
+    }
+    #[inline]
+    fn and(self, rhs: Self) -> Self {
+        self.zip(rhs, v128_and)
+    }
+    #[inline]
+    fn or(self, rhs: Self) -> Self {
+        self.zip(rhs, v128_or)
+    }
+    #[inline]
+    fn shl32(self) -> Self {
+        self.map(|x| i64x2_shl(x, 32))
+    }
+    #[inline]
+    fn shr32(self) -> Self {
+        self.map(|x| u64x2_shr(x, 32))
+    }
+
+    #[inline]
+    fn lt_mask(self, rhs: Self) -> Self {
+        // simd128 only has signed 64-bit comparisons. Flipping the sign bit of both sides turns
+        // them into unsigned ones.
+        let sign_bit = u64x2_splat(1 << 63);
+        self.zip(rhs, |x, y| {
+            i64x2_lt(v128_xor(x, sign_bit), v128_xor(y, sign_bit))
+        })
+    }
+
+    #[inline]
+    fn wrapping_mul(self, rhs: Self) -> Self {
+        self.zip(rhs, i64x2_mul)
+    }
+}
+
+#[cfg(test)]
+impl Lanes for [u64; 4] {
+    fn splat(x: u64) -> Self {
+        [x; 4]
+    }
+    fn from_array(x: [u64; 4]) -> Self {
+        x
+    }
+    fn to_array(self) -> [u64; 4] {
+        self
+    }
+
+    fn wrapping_add(self, rhs: Self) -> Self {
+        core::array::from_fn(|i| self[i].wrapping_add(rhs[i]))
+    }
+    fn wrapping_sub(self, rhs: Self) -> Self {
+        core::array::from_fn(|i| self[i].wrapping_sub(rhs[i]))
+    }
+    fn and(self, rhs: Self) -> Self {
+        core::array::from_fn(|i| self[i] & rhs[i])
+    }
+    fn or(self, rhs: Self) -> Self {
+        core::array::from_fn(|i| self[i] | rhs[i])
+    }
+    fn shl32(self) -> Self {
+        self.map(|x| x << 32)
+    }
+    fn shr32(self) -> Self {
+        self.map(|x| x >> 32)
+    }
+
+    fn lt_mask(self, rhs: Self) -> Self {
+        core::array::from_fn(|i| ((self[i] < rhs[i]) as u64).wrapping_neg())
+    }
+    fn wrapping_mul(self, rhs: Self) -> Self {
+        core::array::from_fn(|i| self[i].wrapping_mul(rhs[i]))
+    }
+}
+
+const EPSILON: u64 = GoldilocksField::ORDER.wrapping_neg();
+
+/// Convert to canonical representation.
+#[inline]
+fn canonicalize<V: Lanes>(x: V) -> V {
+    // x >= ORDER iff ORDER - 1 < x. Subtracting ORDER is the same as adding EPSILON.
+    let mask = V::splat(GoldilocksField::ORDER - 1).lt_mask(x);
+    x.wrapping_add(mask.and(V::splat(EPSILON)))
+}
+
+#[inline]
+pub(crate) fn add<V: Lanes>(x: V, y: V) -> V {
+    // With y canonical, x + y < 2^64 + ORDER, so adding EPSILON once after an overflow is enough.
+    let y = canonicalize(y);
+    let res_wrapped = x.wrapping_add(y);
+    let carry = res_wrapped.lt_mask(y);
+    res_wrapped.wrapping_add(carry.and(V::splat(EPSILON)))
+}
+
+#[inline]
+pub(crate) fn sub<V: Lanes>(x: V, y: V) -> V {
+    // With y canonical, a borrow leaves the wrapped difference above EPSILON, so subtracting it
+    // cannot underflow again.
+    let y = canonicalize(y);
+    let res_wrapped = x.wrapping_sub(y);
+    let borrow = x.lt_mask(y);
+    res_wrapped.wrapping_sub(borrow.and(V::splat(EPSILON)))
+}
+
+#[inline]
+pub(crate) fn neg<V: Lanes>(y: V) -> V {
+    sub(V::splat(0), y)
+}
+
+#[inline]
+pub(crate) fn mul<V: Lanes>(x: V, y: V) -> V {
+    let (lo, hi) = x.mul_wide(y);
+    reduce128(lo, hi)
+}
+
+/// Reduce `lo + 2^64 hi` to a 64-bit value, following the scalar `reduce128`. The result might
+/// not be in canonical form.
+#[inline]
+fn reduce128<V: Lanes>(lo: V, hi: V) -> V {
+    let epsilon = V::splat(EPSILON);
+    let hi_hi = hi.shr32();
+    let hi_lo = hi.and(epsilon);
+
+    // 2^96 = -1, so subtract hi_hi.
+    let t0 = lo.wrapping_sub(hi_hi);
+    let borrow = lo.lt_mask(hi_hi);
+    let t0 = t0.wrapping_sub(borrow.and(epsilon));
+
+    // 2^64 = EPSILON, so add hi_lo * EPSILON = (hi_lo << 32) - hi_lo.
+    let t1 = hi_lo.shl32().wrapping_sub(hi_lo);
+    let t2 = t0.wrapping_add(t1);
+    let carry = t2.lt_mask(t1);
+    t2.wrapping_add(carry.and(epsilon))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arch::portable::goldilocks_lanes::{add, mul, neg, sub, Lanes};
+    use crate::goldilocks_field::GoldilocksField;
+    use crate::ops::Square;
+    use crate::prime_field_testing::test_inputs;
+    use crate::types::Field64;
+
+    /// Compares the vector arithmetic, run on `[u64; 4]` lanes, against the scalar field on all
+    /// pairs of edge-case inputs, including non-canonical ones.
+    #[test]
+    fn test_matches_scalar_on_edge_cases() {
+        let mut inputs = test_inputs(GoldilocksField::ORDER);
+        // Non-canonical representations in [ORDER, 2^64).
+        inputs.extend((0..10).map(|i| GoldilocksField::ORDER + i));
+        inputs.extend((0..10).map(|i| u64::MAX - i));
+
+        let to_field = |x: [u64; 4]| x.to_array().map(GoldilocksField);
+        for a_chunk in inputs.chunks_exact(4) {
+            let a = <[u64; 4]>::from_array(a_chunk.try_into().unwrap());
+            let a_field = to_field(a);
+            for &b_scalar in &inputs {
+                let b = <[u64; 4]>::splat(b_scalar);
+                let b_field = GoldilocksField(b_scalar);
+
+                assert_eq!(to_field(add(a, b)), a_field.map(|x| x + b_field));
+                assert_eq!(to_field(sub(a, b)), a_field.map(|x| x - b_field));
+                assert_eq!(to_field(sub(b, a)), a_field.map(|x| b_field - x));
+                assert_eq!(to_field(mul(a, b)), a_field.map(|x| x * b_field));
+                assert_eq!(to_field(neg(a)), a_field.map(|x| -x));
+                assert_eq!(to_field(mul(a, a)), a_field.map(|x| x.square()));
+            }
+        }
+    }
+}
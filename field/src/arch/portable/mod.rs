@@ -0,0 +1,3 @@
+#[cfg(any(test, all(target_arch = "wasm32", target_feature = "simd128")))]
+mod goldilocks_lanes;
+pub mod packed_goldilocks_field;
@@ -0,0 +1,361 @@
+use core::fmt;
+use core::fmt::{Debug, Formatter};
+use core::iter::{Product, Sum};
+use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+use crate::arch::portable::goldilocks_lanes::{Lanes, Simd128x2};
+use crate::goldilocks_field::GoldilocksField;
+use crate::ops::Square;
+use crate::packed::PackedField;
+use crate::types::Field;
+
+/// Portable packed Goldilocks field, available on targets without AVX2. It is the default packing
+/// on `wasm32` with `simd128`; elsewhere the scalar field stays the default until this type is
+/// shown to be faster.
+///
+/// On `wasm32` with the `simd128` target feature, the arithmetic runs on 128-bit vectors (see
+/// `goldilocks_lanes`). Elsewhere we emulate a `u64x4` by running the scalar arithmetic on each
+/// lane; the lanes are independent, so the CPU can overlap them. Like `Avx2GoldilocksField`, we wrap
+/// `[GoldilocksField; 4]` so that slices of `GoldilocksField` can be cast to this type.
+#[derive(Copy, Clone)]
+#[repr(transparent)]
+pub struct PackedGoldilocksPortable(pub [GoldilocksField; 4]);
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+impl PackedGoldilocksPortable {
+    #[inline]
+    fn new(x: Simd128x2) -> Self {
+        Self(x.to_array().map(GoldilocksField))
+    }
+    #[inline]
+    fn get(&self) -> Simd128x2 {
+        Simd128x2::from_array(self.0.map(|x| x.0))
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+mod backend {
+    use super::PackedGoldilocksPortable as P;
+    use crate::arch::portable::goldilocks_lanes;
+
+    #[inline]
+    pub(super) fn add(x: P, y: P) -> P {
+        P::new(goldilocks_lanes::add(x.get(), y.get()))
+    }
+    #[inline]
+    pub(super) fn sub(x: P, y: P) -> P {
+        P::new(goldilocks_lanes::sub(x.get(), y.get()))
+    }
+    #[inline]
+    pub(super) fn neg(x: P) -> P {
+        P::new(goldilocks_lanes::neg(x.get()))
+    }
+    #[inline]
+    pub(super) fn mul(x: P, y: P) -> P {
+        P::new(goldilocks_lanes::mul(x.get(), y.get()))
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_feature = "simd128")))]
+mod backend {
+    use core::array;
+
+    use super::PackedGoldilocksPortable as P;
+
+    #[inline]
+    pub(super) fn add(x: P, y: P) -> P {
+        P(array::from_fn(|i| x.0[i] + y.0[i]))
+    }
+    #[inline]
+    pub(super) fn sub(x: P, y: P) -> P {
+        P(array::from_fn(|i| x.0[i] - y.0[i]))
+    }
+    #[inline]
+    pub(super) fn neg(x: P) -> P {
+        P(x.0.map(|x| -x))
+    }
+    #[inline]
+    pub(super) fn mul(x: P, y: P) -> P {
+        P(array::from_fn(|i| x.0[i] * y.0[i]))
+    }
+}
+
+impl Add<Self> for PackedGoldilocksPortable {
+    type Output = Self;
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        backend::add(self, rhs)
+    }
+}
+impl Add<GoldilocksField> for PackedGoldilocksPortable {
+    type Output = Self;
+    #[inline]
+    fn add(self, rhs: GoldilocksField) -> Self {
+        self + Self::from(rhs)
+    }
+}
+impl Add<PackedGoldilocksPortable> for GoldilocksField {
+    type Output = PackedGoldilocksPortable;
+    #[inline]
+    fn add(self, rhs: Self::Output) -> Self::Output {
+        Self::Output::from(self) + rhs
+    }
+}
+impl AddAssign<Self> for PackedGoldilocksPortable {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+impl AddAssign<GoldilocksField> for PackedGoldilocksPortable {
+    #[inline]
+    fn add_assign(&mut self, rhs: GoldilocksField) {
+        *self = *self + rhs;
+    }
+}
+
+impl Debug for PackedGoldilocksPortable {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "({:?})", self.0)
+    }
+}
+
+impl Default for PackedGoldilocksPortable {
+    #[inline]
+    fn default() -> Self {
+        Self::ZEROS
+    }
+}
+
+impl Div<GoldilocksField> for PackedGoldilocksPortable {
+    type Output = Self;
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    #[inline]
+    fn div(self, rhs: GoldilocksField) -> Self {
+        self * rhs.inverse()
+    }
+}
+impl DivAssign<GoldilocksField> for PackedGoldilocksPortable {
+    #[allow(clippy::suspicious_op_assign_impl)]
+    #[inline]
+    fn div_assign(&mut self, rhs: GoldilocksField) {
+        *self *= rhs.inverse();
+    }
+}
+
+impl From<GoldilocksField> for PackedGoldilocksPortable {
+    fn from(x: GoldilocksField) -> Self {
+        Self([x; 4])
+    }
+}
+
+impl Mul<Self> for PackedGoldilocksPortable {
+    type Output = Self;
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        backend::mul(self, rhs)
+    }
+}
+impl Mul<GoldilocksField> for PackedGoldilocksPortable {
+    type Output = Self;
+    #[inline]
+    fn mul(self, rhs: GoldilocksField) -> Self {
+        self * Self::from(rhs)
+    }
+}
+impl Mul<PackedGoldilocksPortable> for GoldilocksField {
+    type Output = PackedGoldilocksPortable;
+    #[inline]
+    fn mul(self, rhs: PackedGoldilocksPortable) -> Self::Output {
+        Self::Output::from(self) * rhs
+    }
+}
+impl MulAssign<Self> for PackedGoldilocksPortable {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+impl MulAssign<GoldilocksField> for PackedGoldilocksPortable {
+    #[inline]
+    fn mul_assign(&mut self, rhs: GoldilocksField) {
+        *self = *self * rhs;
+    }
+}
+
+impl Neg for PackedGoldilocksPortable {
+    type Output = Self;
+    #[inline]
+    fn neg(self) -> Self {
+        backend::neg(self)
+    }
+}
+
+impl Product for PackedGoldilocksPortable {
+    #[inline]
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.reduce(|x, y| x * y).unwrap_or(Self::ONES)
+    }
+}
+
+unsafe impl PackedField for PackedGoldilocksPortable {
+    const WIDTH: usize = 4;
+
+    type Scalar = GoldilocksField;
+
+    const ZEROS: Self = Self([GoldilocksField::ZERO; 4]);
+    const ONES: Self = Self([GoldilocksField::ONE; 4]);
+
+    #[inline]
+    fn from_slice(slice: &[Self::Scalar]) -> &Self {
+        assert_eq!(slice.len(), Self::WIDTH);
+        unsafe { &*slice.as_ptr().cast() }
+    }
+    #[inline]
+    fn from_slice_mut(slice: &mut [Self::Scalar]) -> &mut Self {
+        assert_eq!(slice.len(), Self::WIDTH);
+        unsafe { &mut *slice.as_mut_ptr().cast() }
+    }
+    #[inline]
+    fn as_slice(&self) -> &[Self::Scalar] {
+        &self.0[..]
+    }
+    #[inline]
+    fn as_slice_mut(&mut self) -> &mut [Self::Scalar] {
+        &mut self.0[..]
+    }
+
+    #[inline]
+    fn interleave(&self, other: Self, block_len: usize) -> (Self, Self) {
+        let ([a0, a1, a2, a3], [b0, b1, b2, b3]) = (self.0, other.0);
+        match block_len {
+            1 => (Self([a0, b0, a2, b2]), Self([a1, b1, a3, b3])),
+            2 => (Self([a0, a1, b0, b1]), Self([a2, a3, b2, b3])),
+            4 => (*self, other),
+            _ => panic!("unsupported block_len"),
+        }
+    }
+}
+
+impl Square for PackedGoldilocksPortable {
+    #[inline]
+    fn square(&self) -> Self {
+        backend::mul(*self, *self)
+    }
+}
+
+impl Sub<Self> for PackedGoldilocksPortable {
+    type Output = Self;
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        backend::sub(self, rhs)
+    }
+}
+impl Sub<GoldilocksField> for PackedGoldilocksPortable {
+    type Output = Self;
+    #[inline]
+    fn sub(self, rhs: GoldilocksField) -> Self {
+        self - Self::from(rhs)
+    }
+}
+impl Sub<PackedGoldilocksPortable> for GoldilocksField {
+    type Output = PackedGoldilocksPortable;
+    #[inline]
+    fn sub(self, rhs: PackedGoldilocksPortable) -> Self::Output {
+        Self::Output::from(self) - rhs
+    }
+}
+impl SubAssign<Self> for PackedGoldilocksPortable {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+impl SubAssign<GoldilocksField> for PackedGoldilocksPortable {
+    #[inline]
+    fn sub_assign(&mut self, rhs: GoldilocksField) {
+        *self = *self - rhs;
+    }
+}
+
+impl Sum for PackedGoldilocksPortable {
+    #[inline]
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.reduce(|x, y| x + y).unwrap_or(Self::ZEROS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arch::portable::packed_goldilocks_field::PackedGoldilocksPortable;
+    use crate::goldilocks_field::GoldilocksField;
+    use crate::ops::Square;
+    use crate::packed::PackedField;
+    use crate::prime_field_testing::test_inputs;
+    use crate::test_packed_field_arithmetic;
+    use crate::types::Field64;
+
+    test_packed_field_arithmetic!(
+        crate::arch::portable::packed_goldilocks_field::PackedGoldilocksPortable
+    );
+
+    /// Like the packed field tests, but also covering non-canonical inputs.
+    #[test]
+    fn test_matches_scalar_on_edge_cases() {
+        let mut inputs: Vec<_> = test_inputs(GoldilocksField::ORDER)
+            .into_iter()
+            .map(GoldilocksField)
+            .collect();
+        // Non-canonical representations in [ORDER, 2^64).
+        inputs.extend((0..10).map(|i| GoldilocksField(GoldilocksField::ORDER + i)));
+        inputs.extend((0..10).map(|i| GoldilocksField(u64::MAX - i)));
+
+        for a_arr in inputs.chunks_exact(4) {
+            for b in &inputs {
+                let packed_a = *PackedGoldilocksPortable::from_slice(a_arr);
+                let packed_b = PackedGoldilocksPortable::from(*b);
+                let ops = [
+                    (
+                        packed_a + packed_b,
+                        a_arr.iter().map(|&a| a + *b).collect::<Vec<_>>(),
+                    ),
+                    (packed_a - packed_b, a_arr.iter().map(|&a| a - *b).collect()),
+                    (packed_a * packed_b, a_arr.iter().map(|&a| a * *b).collect()),
+                    (packed_b - packed_a, a_arr.iter().map(|&a| *b - a).collect()),
+                    (-packed_a, a_arr.iter().map(|&a| -a).collect()),
+                    (
+                        packed_a.square(),
+                        a_arr.iter().map(|&a| a.square()).collect(),
+                    ),
+                ];
+                for (res, expected) in ops {
+                    assert_eq!(res.as_slice(), expected, "a = {a_arr:?}, b = {b:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_interleave() {
+        let vals = |xs: [u64; 4]| PackedGoldilocksPortable(xs.map(GoldilocksField));
+        let packed_a = vals([0, 1, 2, 3]);
+        let packed_b = vals([10, 11, 12, 13]);
+        {
+            let (x1, y1) = packed_a.interleave(packed_b, 1);
+            assert_eq!(x1.as_slice(), vals([0, 10, 2, 12]).as_slice());
+            assert_eq!(y1.as_slice(), vals([1, 11, 3, 13]).as_slice());
+        }
+        {
+            let (x2, y2) = packed_a.interleave(packed_b, 2);
+            assert_eq!(x2.as_slice(), vals([0, 1, 10, 11]).as_slice());
+            assert_eq!(y2.as_slice(), vals([2, 3, 12, 13]).as_slice());
+        }
+        {
+            let (x4, y4) = packed_a.interleave(packed_b, 4);
+            assert_eq!(x4.as_slice(), packed_a.as_slice());
+            assert_eq!(y4.as_slice(), packed_b.as_slice());
+        }
+    }
+}
@@ -456,8 +456,9 @@ fn exp_acc<const N: usize>(base: GoldilocksField, tail: GoldilocksField) -> Gold
 
 #[cfg(test)]
 mod tests {
-    use crate::{test_field_arithmetic, test_prime_field_arithmetic};
+    use crate::{test_field_arithmetic, test_packed_field_arithmetic, test_prime_field_arithmetic};
 
     test_prime_field_arithmetic!(crate::goldilocks_field::GoldilocksField);
     test_field_arithmetic!(crate::goldilocks_field::GoldilocksField);
+    test_packed_field_arithmetic!(crate::goldilocks_field::GoldilocksField);
 }
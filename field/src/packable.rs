@@ -38,3 +38,8 @@ impl Packable for crate::goldilocks_field::GoldilocksField {
 impl Packable for crate::goldilocks_field::GoldilocksField {
     type Packing = crate::arch::x86_64::avx512_goldilocks_field::Avx512GoldilocksField;
 }
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+impl Packable for crate::goldilocks_field::GoldilocksField {
+    type Packing = crate::arch::portable::packed_goldilocks_field::PackedGoldilocksPortable;
+}
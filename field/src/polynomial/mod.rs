@@ -12,7 +12,7 @@ use plonky2_util::log2_strict;
 use serde::{Deserialize, Serialize};
 
 use crate::extension::{Extendable, FieldExtension};
-use crate::fft::{fft, fft_with_options, ifft, FftRootTable};
+use crate::fft::{fft, fft_with_options, ifft, FftRootTable, FftStrategy};
 use crate::types::Field;
 
 /// A polynomial in point-value form.
@@ -78,13 +78,18 @@ impl<F: Field> PolynomialValues<F> {
 
     pub fn lde(self, rate_bits: usize) -> Self {
         let coeffs = ifft(self).lde(rate_bits);
-        fft_with_options(coeffs, Some(rate_bits), None)
+        fft_with_options(coeffs, Some(rate_bits), None, FftStrategy::default())
     }
 
     /// Low-degree extend `Self` (seen as evaluations over the subgroup) onto a coset.
     pub fn lde_onto_coset(self, rate_bits: usize) -> Self {
         let coeffs = ifft(self).lde(rate_bits);
-        coeffs.coset_fft_with_options(F::coset_shift(), Some(rate_bits), None)
+        coeffs.coset_fft_with_options(
+            F::coset_shift(),
+            Some(rate_bits),
+            None,
+            FftStrategy::default(),
+        )
     }
 
     pub fn degree(&self) -> usize {
@@ -269,13 +274,14 @@ impl<F: Field> PolynomialCoeffs<F> {
         self,
         zero_factor: Option<usize>,
         root_table: Option<&FftRootTable<F>>,
+        strategy: FftStrategy,
     ) -> PolynomialValues<F> {
-        fft_with_options(self, zero_factor, root_table)
+        fft_with_options(self, zero_factor, root_table, strategy)
     }
 
     /// Returns the evaluation of the polynomial on the coset `shift*H`.
     pub fn coset_fft(&self, shift: F) -> PolynomialValues<F> {
-        self.coset_fft_with_options(shift, None, None)
+        self.coset_fft_with_options(shift, None, None, FftStrategy::default())
     }
 
     /// Returns the evaluation of the polynomial on the coset `shift*H`.
@@ -284,6 +290,7 @@ impl<F: Field> PolynomialCoeffs<F> {
         shift: F,
         zero_factor: Option<usize>,
         root_table: Option<&FftRootTable<F>>,
+        strategy: FftStrategy,
     ) -> PolynomialValues<F> {
         let modified_poly: Self = shift
             .powers()
@@ -291,7 +298,7 @@ impl<F: Field> PolynomialCoeffs<F> {
             .map(|(r, &c)| r * c)
             .collect::<Vec<_>>()
             .into();
-        modified_poly.fft_with_options(zero_factor, root_table)
+        modified_poly.fft_with_options(zero_factor, root_table, strategy)
     }
 
     pub fn to_extension<const D: usize>(&self) -> PolynomialCoeffs<F::Extension>
@@ -181,3 +181,108 @@ macro_rules! test_prime_field_arithmetic {
         }
     };
 }
+
+/// Checks a `PackedField` over a 64-bit prime field against its scalar field, lane by lane, on
+/// the inputs of `test_inputs`.
+#[macro_export]
+macro_rules! test_packed_field_arithmetic {
+    ($packed:ty) => {
+        mod packed_field_arithmetic {
+            use alloc::vec::Vec;
+
+            use $crate::ops::Square;
+            use $crate::packed::PackedField;
+            use $crate::types::{Field, Field64};
+
+            type P = $packed;
+            type F = <$packed as PackedField>::Scalar;
+
+            /// Packs `WIDTH` consecutive test inputs, starting from each of them in turn, so that
+            /// every input appears in every lane.
+            fn packed_inputs() -> Vec<P> {
+                let inputs = $crate::prime_field_testing::test_inputs(F::ORDER);
+                (0..inputs.len())
+                    .map(|i| {
+                        let mut packed = P::default();
+                        for (j, x) in packed.as_slice_mut().iter_mut().enumerate() {
+                            *x = F::from_canonical_u64(inputs[(i + j) % inputs.len()]);
+                        }
+                        packed
+                    })
+                    .collect()
+            }
+
+            fn check_unary_op(op: impl Fn(P) -> P, expected_op: impl Fn(F) -> F) {
+                for a in packed_inputs() {
+                    let expected = a.as_slice().iter().map(|&x| expected_op(x));
+                    assert!(op(a).as_slice().iter().copied().eq(expected), "a = {a:?}");
+                }
+            }
+
+            fn check_binary_op(op: impl Fn(P, P) -> P, expected_op: impl Fn(F, F) -> F) {
+                let inputs = packed_inputs();
+                for &a in &inputs {
+                    for &b in &inputs {
+                        let expected = a
+                            .as_slice()
+                            .iter()
+                            .zip(b.as_slice())
+                            .map(|(&x, &y)| expected_op(x, y));
+                        assert!(
+                            op(a, b).as_slice().iter().copied().eq(expected),
+                            "a = {a:?}, b = {b:?}"
+                        );
+                    }
+                }
+            }
+
+            #[test]
+            fn packed_addition() {
+                check_binary_op(|a, b| a + b, |x, y| x + y);
+            }
+
+            #[test]
+            fn packed_subtraction() {
+                check_binary_op(|a, b| a - b, |x, y| x - y);
+            }
+
+            #[test]
+            fn packed_multiplication() {
+                check_binary_op(|a, b| a * b, |x, y| x * y);
+            }
+
+            #[test]
+            fn packed_negation() {
+                check_unary_op(|a| -a, |x| -x);
+            }
+
+            #[test]
+            fn packed_square() {
+                check_unary_op(|a| a.square(), |x| x.square());
+            }
+
+            #[test]
+            fn packed_broadcast() {
+                for x in $crate::prime_field_testing::test_inputs(F::ORDER) {
+                    let x = F::from_canonical_u64(x);
+                    assert!(P::from(x).as_slice().iter().all(|&y| y == x));
+                }
+            }
+
+            #[test]
+            fn packed_interleave_is_involution() {
+                let inputs = packed_inputs();
+                for (&a, &b) in inputs.iter().zip(inputs.iter().skip(1)) {
+                    let mut block_len = 1;
+                    while block_len <= P::WIDTH {
+                        let (x, y) = a.interleave(b, block_len);
+                        let (res_a, res_b) = x.interleave(y, block_len);
+                        assert_eq!(res_a.as_slice(), a.as_slice());
+                        assert_eq!(res_b.as_slice(), b.as_slice());
+                        block_len *= 2;
+                    }
+                }
+            }
+        }
+    };
+}
@@ -233,6 +233,15 @@ pub trait ParallelIteratorMock {
     where
         P: Fn(&Self::Item) -> bool + Sync + Send;
 
+    fn find_first<P>(self, predicate: P) -> Option<Self::Item>
+    where
+        P: Fn(&Self::Item) -> bool + Sync + Send;
+
+    fn find_map_first<P, R>(self, predicate: P) -> Option<R>
+    where
+        P: Fn(Self::Item) -> Option<R> + Sync + Send,
+        R: Send;
+
     fn flat_map_iter<U, F>(self, map_op: F) -> FlatMap<Self, U, F>
     where
         Self: Sized,
@@ -251,6 +260,21 @@ impl<T: Iterator> ParallelIteratorMock for T {
         self.find(predicate)
     }
 
+    fn find_first<P>(mut self, predicate: P) -> Option<Self::Item>
+    where
+        P: Fn(&Self::Item) -> bool + Sync + Send,
+    {
+        self.find(predicate)
+    }
+
+    fn find_map_first<P, R>(mut self, predicate: P) -> Option<R>
+    where
+        P: Fn(Self::Item) -> Option<R> + Sync + Send,
+        R: Send,
+    {
+        self.find_map(predicate)
+    }
+
     fn flat_map_iter<U, F>(self, map_op: F) -> FlatMap<Self, U, F>
     where
         Self: Sized,
@@ -280,3 +304,32 @@ where
 {
     (oper_a(), oper_b())
 }
+
+#[cfg(feature = "parallel")]
+pub fn current_num_threads() -> usize {
+    rayon::current_num_threads()
+}
+
+#[cfg(not(feature = "parallel"))]
+pub const fn current_num_threads() -> usize {
+    1
+}
+
+#[cfg(feature = "parallel")]
+pub use rayon::ThreadPool;
+
+/// Without the `parallel` feature, a thread pool that runs everything on the current thread, so
+/// that code taking a pool builds either way.
+#[cfg(not(feature = "parallel"))]
+#[derive(Debug, Default)]
+pub struct ThreadPool;
+
+#[cfg(not(feature = "parallel"))]
+impl ThreadPool {
+    pub fn install<OP, R>(&self, op: OP) -> R
+    where
+        OP: FnOnce() -> R,
+    {
+        op()
+    }
+}
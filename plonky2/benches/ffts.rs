@@ -1,9 +1,11 @@
 mod allocator;
 
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use plonky2::field::fft::{fft_with_options, FftStrategy};
 use plonky2::field::goldilocks_field::GoldilocksField;
 use plonky2::field::polynomial::PolynomialCoeffs;
 use plonky2::field::types::Field;
+use plonky2_maybe_rayon::rayon;
 use tynm::type_name;
 
 pub(crate) fn bench_ffts<F: Field>(c: &mut Criterion) {
@@ -13,11 +15,31 @@ pub(crate) fn bench_ffts<F: Field>(c: &mut Criterion) {
         let size = 1 << size_log;
         group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, _| {
             let coeffs = PolynomialCoeffs::new(F::rand_vec(size));
-            b.iter(|| coeffs.clone().fft_with_options(None, None));
+            b.iter(|| {
+                coeffs
+                    .clone()
+                    .fft_with_options(None, None, FftStrategy::default())
+            });
         });
     }
 }
 
+pub(crate) fn bench_fft_strategies<F: Field>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("fft-strategies<{}>", type_name::<F>()));
+    group.sample_size(10);
+
+    for size_log in [16, 18, 20, 21, 22, 23] {
+        let size = 1 << size_log;
+        let coeffs = PolynomialCoeffs::new(F::rand_vec(size));
+        for strategy in [FftStrategy::Classic, FftStrategy::FourStep] {
+            let id = BenchmarkId::new(format!("{strategy:?}"), size);
+            group.bench_with_input(id, &size, |b, _| {
+                b.iter(|| fft_with_options(coeffs.clone(), None, None, strategy));
+            });
+        }
+    }
+}
+
 pub(crate) fn bench_ldes<F: Field>(c: &mut Criterion) {
     const RATE_BITS: usize = 3;
 
@@ -31,15 +53,51 @@ pub(crate) fn bench_ldes<F: Field>(c: &mut Criterion) {
             let coeffs = PolynomialCoeffs::new(F::rand_vec(orig_size));
             b.iter(|| {
                 let padded_coeffs = coeffs.lde(RATE_BITS);
-                padded_coeffs.fft_with_options(Some(RATE_BITS), None)
+                padded_coeffs.fft_with_options(Some(RATE_BITS), None, FftStrategy::default())
             });
         });
     }
 }
 
+/// Low-degree extensions at the sizes where they dominate proving time, with their zero factor, on
+/// 1 thread and on all of them.
+pub(crate) fn bench_lde_strategies<F: Field>(c: &mut Criterion) {
+    const RATE_BITS: usize = 3;
+
+    let mut group = c.benchmark_group(format!("lde-strategies<{}>", type_name::<F>()));
+    group.sample_size(10);
+
+    let mut thread_counts = vec![1, num_cpus::get()];
+    thread_counts.dedup();
+
+    for size_log in [20, 21, 22, 23] {
+        let lde_size = 1 << size_log;
+        let padded_coeffs =
+            PolynomialCoeffs::new(F::rand_vec(lde_size >> RATE_BITS)).lde(RATE_BITS);
+        for &num_threads in &thread_counts {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .build()
+                .unwrap();
+            for strategy in [FftStrategy::Classic, FftStrategy::FourStep] {
+                let id = BenchmarkId::new(format!("{strategy:?}/{num_threads}"), lde_size);
+                group.bench_with_input(id, &lde_size, |b, _| {
+                    b.iter(|| {
+                        pool.install(|| {
+                            fft_with_options(padded_coeffs.clone(), Some(RATE_BITS), None, strategy)
+                        })
+                    });
+                });
+            }
+        }
+    }
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     bench_ffts::<GoldilocksField>(c);
+    bench_fft_strategies::<GoldilocksField>(c);
     bench_ldes::<GoldilocksField>(c);
+    bench_lde_strategies::<GoldilocksField>(c);
 }
 
 criterion_group!(benches, criterion_benchmark);
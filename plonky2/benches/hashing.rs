@@ -1,12 +1,17 @@
 mod allocator;
 
-use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
 use plonky2::field::goldilocks_field::GoldilocksField;
 use plonky2::field::types::Sample;
+use plonky2::fri::prover::fri_proof_of_work;
+use plonky2::fri::FriConfig;
 use plonky2::hash::hash_types::{BytesHash, RichField};
 use plonky2::hash::keccak::KeccakHash;
 use plonky2::hash::poseidon::{Poseidon, SPONGE_WIDTH};
-use plonky2::plonk::config::Hasher;
+use plonky2::iop::challenger::Challenger;
+use plonky2::plonk::circuit_data::CircuitConfig;
+use plonky2::plonk::config::{GenericConfig, Hasher, PoseidonGoldilocksConfig};
+use plonky2_maybe_rayon::rayon;
 use tynm::type_name;
 
 pub(crate) fn bench_keccak<F: RichField>(c: &mut Criterion) {
@@ -32,9 +37,73 @@ pub(crate) fn bench_poseidon<F: Poseidon>(c: &mut Criterion) {
     );
 }
 
+/// Permutes a batch of 16 states, as in proof-of-work grinding.
+pub(crate) fn bench_poseidon_batch<F: Poseidon>(c: &mut Criterion) {
+    c.bench_function(
+        &format!("poseidon_batch<{}, {SPONGE_WIDTH}>/16", type_name::<F>()),
+        |b| {
+            b.iter_batched(
+                || {
+                    (0..16)
+                        .map(|_| F::rand_array::<SPONGE_WIDTH>())
+                        .collect::<Vec<_>>()
+                },
+                |mut states| F::poseidon_batch(&mut states),
+                BatchSize::SmallInput,
+            )
+        },
+    );
+}
+
+/// Grinds a FRI proof of work on 1 thread and on all of them.
+fn bench_proof_of_work(c: &mut Criterion) {
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    let mut group = c.benchmark_group("proof-of-work");
+    group.sample_size(10);
+
+    let mut thread_counts = vec![1, num_cpus::get()];
+    thread_counts.dedup();
+
+    for proof_of_work_bits in [20, 22] {
+        let config = FriConfig {
+            proof_of_work_bits,
+            ..CircuitConfig::standard_recursion_config().fri_config
+        };
+        for &num_threads in &thread_counts {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .build()
+                .unwrap();
+            group.bench_with_input(
+                BenchmarkId::new(format!("{proof_of_work_bits}-bits"), num_threads),
+                &num_threads,
+                |b, _| {
+                    b.iter_batched(
+                        || {
+                            let mut challenger =
+                                Challenger::<F, <C as GenericConfig<D>>::Hasher>::new();
+                            challenger.observe_elements(&F::rand_vec(4));
+                            challenger
+                        },
+                        |mut challenger| {
+                            pool.install(|| fri_proof_of_work::<F, C, D>(&mut challenger, &config))
+                        },
+                        BatchSize::SmallInput,
+                    )
+                },
+            );
+        }
+    }
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     bench_poseidon::<GoldilocksField>(c);
+    bench_poseidon_batch::<GoldilocksField>(c);
     bench_keccak::<GoldilocksField>(c);
+    bench_proof_of_work(c);
 }
 
 criterion_group!(benches, criterion_benchmark);
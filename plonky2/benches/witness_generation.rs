@@ -0,0 +1,140 @@
+mod allocator;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use plonky2::gates::noop::NoopGate;
+use plonky2::iop::generator::{generate_partial_witness, GeneratedValues};
+use plonky2::iop::witness::{PartialWitness, PartitionWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{
+    CircuitConfig, CircuitData, CommonCircuitData, ProverOnlyCircuitData,
+};
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use plonky2_maybe_rayon::rayon;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// Builds a circuit verifying a proof of `2^log2_size` rows, as in the `bench_recursion` example,
+/// along with the inputs of its witness.
+fn recursive_circuit(log2_size: usize) -> (CircuitData<F, C, D>, PartialWitness<F>) {
+    let config = CircuitConfig::standard_recursion_config();
+
+    let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+    for _ in 0..(1 << (log2_size - 1)) + 1 {
+        builder.add_gate(NoopGate, vec![]);
+    }
+    let inner_data = builder.build::<C>();
+    let inner_proof = inner_data.prove(PartialWitness::new()).unwrap();
+
+    let mut builder = CircuitBuilder::<F, D>::new(config);
+    let proof_target = builder.add_virtual_proof_with_pis(&inner_data.common);
+    let verifier_target =
+        builder.add_virtual_verifier_data(inner_data.common.config.fri_config.cap_height);
+    builder.verify_proof::<C>(&proof_target, &verifier_target, &inner_data.common);
+    let data = builder.build::<C>();
+
+    let mut inputs = PartialWitness::new();
+    inputs.set_proof_with_pis_target(&proof_target, &inner_proof);
+    inputs.set_verifier_data_target(&verifier_target, &inner_data.verifier_only);
+    (data, inputs)
+}
+
+/// The scheduler which `generate_partial_witness` replaced, as a baseline: it runs the pending
+/// generators sequentially, in passes, and each newly populated target queues its watchers for the
+/// next pass.
+fn generate_partial_witness_in_passes<'a>(
+    inputs: PartialWitness<F>,
+    prover_data: &'a ProverOnlyCircuitData<F, C, D>,
+    common_data: &'a CommonCircuitData<F, D>,
+) -> PartitionWitness<'a, F> {
+    let generators = &prover_data.generators;
+    let generator_indices_by_watches = &prover_data.generator_indices_by_watches;
+
+    let mut witness = PartitionWitness::new(
+        common_data.config.num_wires,
+        common_data.degree(),
+        &prover_data.representative_map,
+    );
+    for (t, v) in inputs.target_values.into_iter() {
+        witness.set_target(t, v);
+    }
+
+    let mut pending_generator_indices: Vec<_> = (0..generators.len()).collect();
+    let mut generator_is_expired = vec![false; generators.len()];
+    let mut buffer = GeneratedValues::empty();
+
+    while !pending_generator_indices.is_empty() {
+        let mut next_pending_generator_indices = Vec::new();
+        for &generator_idx in &pending_generator_indices {
+            if generator_is_expired[generator_idx] {
+                continue;
+            }
+            if generators[generator_idx].0.run(&witness, &mut buffer) {
+                generator_is_expired[generator_idx] = true;
+            }
+            let new_target_reps = buffer
+                .target_values
+                .drain(..)
+                .flat_map(|(t, v)| witness.set_target_returning_rep(t, v));
+            for watch in new_target_reps {
+                if let Some(watchers) = generator_indices_by_watches.get(&watch) {
+                    next_pending_generator_indices
+                        .extend(watchers.iter().filter(|&&idx| !generator_is_expired[idx]));
+                }
+            }
+        }
+        pending_generator_indices = next_pending_generator_indices;
+    }
+
+    assert!(generator_is_expired.into_iter().all(|expired| expired));
+    witness
+}
+
+fn bench_witness_generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("witness-generation");
+    group.sample_size(10);
+
+    let mut thread_counts = vec![1, num_cpus::get()];
+    thread_counts.dedup();
+
+    for log2_size in [12, 14] {
+        let (data, inputs) = recursive_circuit(log2_size);
+        group.bench_function(
+            BenchmarkId::new(format!("recursion-2^{log2_size}"), "in-passes"),
+            |b| {
+                b.iter(|| {
+                    generate_partial_witness_in_passes(
+                        inputs.clone(),
+                        &data.prover_only,
+                        &data.common,
+                    )
+                })
+            },
+        );
+        for &num_threads in &thread_counts {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .build()
+                .unwrap();
+            group.bench_with_input(
+                BenchmarkId::new(format!("recursion-2^{log2_size}"), num_threads),
+                &num_threads,
+                |b, _| {
+                    b.iter(|| {
+                        pool.install(|| {
+                            generate_partial_witness(
+                                inputs.clone(),
+                                &data.prover_only,
+                                &data.common,
+                            )
+                        })
+                    })
+                },
+            );
+        }
+    }
+}
+
+criterion_group!(benches, bench_witness_generation);
+criterion_main!(benches);
@@ -3,8 +3,6 @@
 // put it in `src/bin/`, but then we wouldn't have access to
 // `[dev-dependencies]`.
 
-extern crate alloc;
-use alloc::sync::Arc;
 use core::num::ParseIntError;
 use core::ops::RangeInclusive;
 use core::str::FromStr;
@@ -106,8 +104,8 @@ fn dummy_lookup_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>,
     let mut builder = CircuitBuilder::<F, D>::new(config.clone());
     let tip5_table = TIP5_TABLE.to_vec();
     let inps = 0..256;
-    let table = Arc::new(inps.zip_eq(tip5_table).collect());
-    let tip5_idx = builder.add_lookup_table_from_pairs(table);
+    let table: Vec<(u16, u16)> = inps.zip_eq(tip5_table).collect();
+    let tip5_idx = builder.add_lookup_table_from_pairs(&table);
     let initial_a = builder.add_virtual_target();
     builder.add_lookup_from_index(initial_a, tip5_idx);
     builder.register_public_input(initial_a);
@@ -1,3 +1,5 @@
+use std::time::Instant;
+
 use anyhow::Result;
 use plonky2::field::types::Field;
 use plonky2::iop::witness::{PartialWitness, WitnessWrite};
@@ -5,12 +7,17 @@ use plonky2::plonk::circuit_builder::CircuitBuilder;
 use plonky2::plonk::circuit_data::CircuitConfig;
 use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// The number of values checked in the strategy comparison.
+const NUM_CHECKS: usize = 4096;
+/// The number of bits each of those values is checked against.
+const NUM_BITS: usize = 32;
+
 /// An example of using Plonky2 to prove that a given value lies in a given range.
 fn main() -> Result<()> {
-    const D: usize = 2;
-    type C = PoseidonGoldilocksConfig;
-    type F = <C as GenericConfig<D>>::F;
-
     let config = CircuitConfig::standard_recursion_config();
     let mut builder = CircuitBuilder::<F, D>::new(config);
 
@@ -32,5 +39,44 @@ fn main() -> Result<()> {
         proof.public_inputs[0], log_max,
     );
 
+    data.verify(proof)?;
+
+    compare_strategies(false)?;
+    compare_strategies(true)
+}
+
+/// Proves `NUM_CHECKS` range checks of `NUM_BITS` bits each, and reports the resulting circuit
+/// size, using either `BaseSumGate` decompositions or the shared limb lookup table.
+fn compare_strategies(use_lookup_range_checks: bool) -> Result<()> {
+    let config = CircuitConfig {
+        use_lookup_range_checks,
+        ..CircuitConfig::standard_recursion_config()
+    };
+    let mut builder = CircuitBuilder::<F, D>::new(config);
+    let mut pw = PartialWitness::new();
+    for i in 0..NUM_CHECKS {
+        let x = builder.add_virtual_target();
+        builder.range_check(x, NUM_BITS);
+        pw.set_target(x, F::from_canonical_usize(i * 1_000_003));
+    }
+
+    let data = builder.build::<C>();
+    let start = Instant::now();
+    let proof = data.prove(pw)?;
+    let elapsed = start.elapsed();
+
+    println!(
+        "{} range checks of {} bits with {}: {} rows, proved in {:?}",
+        NUM_CHECKS,
+        NUM_BITS,
+        if use_lookup_range_checks {
+            "lookups"
+        } else {
+            "BaseSumGate"
+        },
+        data.common.degree(),
+        elapsed,
+    );
+
     data.verify(proof)
 }
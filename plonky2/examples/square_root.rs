@@ -78,13 +78,13 @@ where
 {
     impl_generator_serializer! {
         CustomGeneratorSerializer,
-        DummyProofGenerator<F, C, D>,
-        ArithmeticBaseGenerator<F, D>,
-        ConstantGenerator<F>,
-        PoseidonGenerator<F, D>,
-        PoseidonMdsGenerator<D>,
-        RandomValueGenerator,
-        SquareRootGenerator<F, D>
+        "DummyProofGenerator" => DummyProofGenerator<F, C, D>,
+        "ArithmeticBaseGenerator" => ArithmeticBaseGenerator<F, D>,
+        "ConstantGenerator" => ConstantGenerator<F>,
+        "PoseidonGenerator" => PoseidonGenerator<F, D>,
+        "PoseidonMdsGenerator" => PoseidonMdsGenerator<D>,
+        "RandomValueGenerator" => RandomValueGenerator,
+        "SquareRootGenerator" => SquareRootGenerator<F, D>
     }
 }
 
@@ -3,6 +3,7 @@ use alloc::vec::Vec;
 use serde::Serialize;
 
 use crate::fri::reduction_strategies::FriReductionStrategy;
+use crate::util::ceil_div_usize;
 
 mod challenges;
 pub mod oracle;
@@ -10,6 +11,8 @@ pub mod proof;
 pub mod prover;
 pub mod recursive_verifier;
 pub mod reduction_strategies;
+#[cfg(feature = "std")]
+pub mod security;
 pub mod structure;
 mod validate_shape;
 pub mod verifier;
@@ -54,6 +57,33 @@ impl FriConfig {
     pub const fn num_cap_elements(&self) -> usize {
         1 << self.cap_height
     }
+
+    /// The number of query rounds needed for `security_bits` bits of conjectured security, given
+    /// `proof_of_work_bits` bits of grinding. Each query round contributes `rate_bits` bits.
+    pub fn num_query_rounds_for_security(
+        &self,
+        security_bits: usize,
+        proof_of_work_bits: u32,
+    ) -> usize {
+        assert!(
+            self.rate_bits > 0,
+            "Query rounds add no security with rate 1."
+        );
+        let query_bits = security_bits.saturating_sub(proof_of_work_bits as usize);
+        ceil_div_usize(query_bits, self.rate_bits)
+    }
+
+    /// Returns this config with `proof_of_work_bits` bits of grinding and the fewest query rounds
+    /// that keep `security_bits` bits of conjectured security. Each extra bit of proof of work
+    /// doubles the prover's expected grinding time, while each query round saved makes proofs
+    /// smaller and cheaper to verify, including recursively.
+    pub fn with_proof_of_work_bits(&self, proof_of_work_bits: u32, security_bits: usize) -> Self {
+        Self {
+            proof_of_work_bits,
+            num_query_rounds: self.num_query_rounds_for_security(security_bits, proof_of_work_bits),
+            ..self.clone()
+        }
+    }
 }
 
 /// FRI parameters, including generated parameters which are specific to an instance size, in
@@ -101,3 +131,29 @@ impl FriParams {
         1 << self.final_poly_bits()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::plonk::circuit_data::CircuitConfig;
+
+    #[test]
+    fn test_with_proof_of_work_bits() {
+        let config = CircuitConfig::standard_recursion_config();
+        let fri_config = &config.fri_config;
+        let security_bits = config.security_bits;
+        assert_eq!(
+            fri_config.with_proof_of_work_bits(fri_config.proof_of_work_bits, security_bits),
+            *fri_config
+        );
+
+        // With rate 1/8, each query round is worth 3 bits.
+        let ground = fri_config.with_proof_of_work_bits(22, security_bits);
+        assert_eq!(ground.proof_of_work_bits, 22);
+        assert_eq!(ground.num_query_rounds, 26);
+        assert_eq!(ground.rate_bits, fri_config.rate_bits);
+        assert_eq!(
+            fri_config.num_query_rounds_for_security(security_bits, 100),
+            0
+        );
+    }
+}
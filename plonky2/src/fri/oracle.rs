@@ -1,22 +1,25 @@
-use alloc::format;
+use alloc::borrow::Cow;
 use alloc::vec::Vec;
+use alloc::{format, vec};
 
 use itertools::Itertools;
 use plonky2_field::types::Field;
 use plonky2_maybe_rayon::*;
+use rand::rngs::OsRng;
+use rand::RngCore;
 
 use crate::field::extension::Extendable;
-use crate::field::fft::FftRootTable;
+use crate::field::fft::{FftRootTable, FftStrategy};
 use crate::field::packed::PackedField;
 use crate::field::polynomial::{PolynomialCoeffs, PolynomialValues};
 use crate::fri::proof::FriProof;
-use crate::fri::prover::fri_proof;
+use crate::fri::prover::fri_proof_from_oracles;
 use crate::fri::structure::{FriBatchInfo, FriInstanceInfo};
 use crate::fri::FriParams;
 use crate::hash::hash_types::RichField;
 use crate::hash::merkle_tree::MerkleTree;
 use crate::iop::challenger::Challenger;
-use crate::plonk::config::GenericConfig;
+use crate::plonk::config::{GenericConfig, Hasher};
 use crate::timed;
 use crate::util::reducing::ReducingFactor;
 use crate::util::timing::TimingTree;
@@ -34,6 +37,10 @@ pub struct PolynomialBatch<F: RichField + Extendable<D>, C: GenericConfig<D, F =
     pub degree_log: usize,
     pub rate_bits: usize,
     pub blinding: bool,
+    /// The salts of all leaves, `SALT_SIZE` per leaf in leaf order, if blinding and the LDE is not
+    /// retained (see `from_coeffs_chunked`). Otherwise the salts are part of the leaves, and this
+    /// is empty.
+    pub salts: Vec<F>,
 }
 
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> Default
@@ -46,6 +53,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> D
             degree_log: 0,
             rate_bits: 0,
             blinding: false,
+            salts: Vec::new(),
         }
     }
 }
@@ -61,6 +69,27 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         cap_height: usize,
         timing: &mut TimingTree,
         fft_root_table: Option<&FftRootTable<F>>,
+    ) -> Self {
+        Self::from_values_with_rng(
+            values,
+            rate_bits,
+            blinding,
+            cap_height,
+            &mut OsRng,
+            timing,
+            fft_root_table,
+        )
+    }
+
+    /// Like `from_values`, but with the salts drawn from `rng`.
+    pub fn from_values_with_rng<R: RngCore + ?Sized>(
+        values: Vec<PolynomialValues<F>>,
+        rate_bits: usize,
+        blinding: bool,
+        cap_height: usize,
+        rng: &mut R,
+        timing: &mut TimingTree,
+        fft_root_table: Option<&FftRootTable<F>>,
     ) -> Self {
         let coeffs = timed!(
             timing,
@@ -68,11 +97,12 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             values.into_par_iter().map(|v| v.ifft()).collect::<Vec<_>>()
         );
 
-        Self::from_coeffs(
+        Self::from_coeffs_with_rng(
             coeffs,
             rate_bits,
             blinding,
             cap_height,
+            rng,
             timing,
             fft_root_table,
         )
@@ -86,12 +116,38 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         cap_height: usize,
         timing: &mut TimingTree,
         fft_root_table: Option<&FftRootTable<F>>,
+    ) -> Self {
+        Self::from_coeffs_with_rng(
+            polynomials,
+            rate_bits,
+            blinding,
+            cap_height,
+            &mut OsRng,
+            timing,
+            fft_root_table,
+        )
+    }
+
+    /// Like `from_coeffs`, but draws the salts from `rng` rather than from the OS. The salts are
+    /// drawn in leaf order, so the same RNG state gives the same leaves as `from_coeffs_chunked`.
+    ///
+    /// `fft_root_table` may be the table for any domain at least as large as the LDE.
+    pub fn from_coeffs_with_rng<R: RngCore + ?Sized>(
+        polynomials: Vec<PolynomialCoeffs<F>>,
+        rate_bits: usize,
+        blinding: bool,
+        cap_height: usize,
+        rng: &mut R,
+        timing: &mut TimingTree,
+        fft_root_table: Option<&FftRootTable<F>>,
     ) -> Self {
         let degree = polynomials[0].len();
+        let lde_bits = log2_strict(degree) + rate_bits;
+        let salts = Self::sample_salts(blinding, lde_bits, rng);
         let lde_values = timed!(
             timing,
             "FFT + blinding",
-            Self::lde_values(&polynomials, rate_bits, blinding, fft_root_table)
+            Self::lde_values(&polynomials, rate_bits, &salts, fft_root_table)
         );
 
         let mut leaves = timed!(timing, "transpose LDEs", transpose(&lde_values));
@@ -108,38 +164,268 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             degree_log: log2_strict(degree),
             rate_bits,
             blinding,
+            salts: Vec::new(),
         }
     }
 
+    /// Like `from_values`, but doesn't retain the LDE; see `from_coeffs_chunked`.
+    pub fn from_values_chunked<R: RngCore + ?Sized>(
+        values: Vec<PolynomialValues<F>>,
+        rate_bits: usize,
+        blinding: bool,
+        cap_height: usize,
+        lde_chunk_bits: usize,
+        rng: &mut R,
+        timing: &mut TimingTree,
+        fft_root_table: Option<&FftRootTable<F>>,
+    ) -> Self {
+        let coeffs = timed!(
+            timing,
+            "IFFT",
+            values.into_par_iter().map(|v| v.ifft()).collect::<Vec<_>>()
+        );
+
+        Self::from_coeffs_chunked(
+            coeffs,
+            rate_bits,
+            blinding,
+            cap_height,
+            lde_chunk_bits,
+            rng,
+            timing,
+            fft_root_table,
+        )
+    }
+
+    /// Creates a list polynomial commitment for the polynomials `polynomials` without ever holding
+    /// their whole LDE in memory. The leaves are computed in `2^lde_chunk_bits` chunks, each of
+    /// which is hashed and dropped before the next one is computed, and only the Merkle digests
+    /// are kept. LDE values needed later are recomputed from the coefficients, by
+    /// `lde_values_on_coset` and `get_leaf`.
+    ///
+    /// The commitment is identical to the one computed by `from_coeffs_with_rng` with an RNG in the
+    /// same state. The salts are drawn from `rng`.
+    pub fn from_coeffs_chunked<R: RngCore + ?Sized>(
+        polynomials: Vec<PolynomialCoeffs<F>>,
+        rate_bits: usize,
+        blinding: bool,
+        cap_height: usize,
+        lde_chunk_bits: usize,
+        rng: &mut R,
+        timing: &mut TimingTree,
+        fft_root_table: Option<&FftRootTable<F>>,
+    ) -> Self {
+        let degree = polynomials[0].len();
+        let degree_log = log2_strict(degree);
+        let lde_bits = degree_log + rate_bits;
+        assert!(
+            lde_chunk_bits <= lde_bits,
+            "Cannot split an LDE of size 2^{lde_bits} into 2^{lde_chunk_bits} chunks"
+        );
+        let chunk_len = 1 << (lde_bits - lde_chunk_bits);
+
+        let salt_size = if blinding { SALT_SIZE } else { 0 };
+        let salts = Self::sample_salts(blinding, lde_bits, rng);
+
+        let mut leaf_hashes = Vec::with_capacity(1 << lde_bits);
+        timed!(
+            timing,
+            &format!("FFT + hash leaves in {} chunks", 1 << lde_chunk_bits),
+            for chunk in 0..1 << lde_chunk_bits {
+                // Leaves are in bit-reversed order, so each contiguous chunk of them holds the
+                // points of one coset of the LDE domain.
+                let coset = reverse_bits(chunk, lde_chunk_bits);
+                let values = Self::coset_values(
+                    &polynomials,
+                    lde_bits,
+                    lde_chunk_bits,
+                    coset,
+                    fft_root_table,
+                );
+                let mut leaves = transpose(&values);
+                drop(values);
+                reverse_index_bits_in_place(&mut leaves);
+
+                let chunk_salts = &salts[chunk * chunk_len * salt_size..];
+                let chunk_hashes: Vec<_> = leaves
+                    .par_iter_mut()
+                    .enumerate()
+                    .map(|(i, leaf)| {
+                        leaf.extend_from_slice(&chunk_salts[i * salt_size..(i + 1) * salt_size]);
+                        C::Hasher::hash_or_noop(leaf)
+                    })
+                    .collect();
+                leaf_hashes.extend(chunk_hashes);
+            }
+        );
+
+        let merkle_tree = timed!(
+            timing,
+            "build Merkle tree",
+            MerkleTree::from_leaf_hashes(leaf_hashes, cap_height)
+        );
+
+        Self {
+            polynomials,
+            merkle_tree,
+            degree_log,
+            rate_bits,
+            blinding,
+            salts,
+        }
+    }
+
+    /// Whether the LDE is held in memory, or recomputed when needed.
+    pub fn retains_lde(&self) -> bool {
+        !self.merkle_tree.leaves.is_empty()
+    }
+
+    /// Evaluates `polynomials` on the points of the LDE domain, of size `2^lde_bits`, whose index
+    /// in natural order is congruent to `coset` modulo `2^log_num_cosets`. Returns one vector per
+    /// polynomial, in natural order.
+    fn coset_values(
+        polynomials: &[PolynomialCoeffs<F>],
+        lde_bits: usize,
+        log_num_cosets: usize,
+        coset: usize,
+        fft_root_table: Option<&FftRootTable<F>>,
+    ) -> Vec<Vec<F>> {
+        let lg_size = lde_bits - log_num_cosets;
+        let size = 1 << lg_size;
+        let shift = F::coset_shift() * F::primitive_root_of_unity(lde_bits).exp_u64(coset as u64);
+        let fft_root_table = Self::root_table_prefix(fft_root_table, lg_size);
+        let fft_root_table = fft_root_table.as_deref();
+        polynomials
+            .par_iter()
+            .map(|p| {
+                let degree = p.len();
+                if degree <= size {
+                    p.padded(size)
+                        .coset_fft_with_options(
+                            shift,
+                            Some(lg_size - log2_strict(degree)),
+                            fft_root_table,
+                            FftStrategy::default(),
+                        )
+                        .values
+                } else {
+                    // `X^size` is constant on a coset of the subgroup of order `size`, so we can
+                    // reduce `p(shift * X)` modulo `X^size - 1` before the FFT.
+                    let mut folded = vec![F::ZERO; size];
+                    for (i, (&c, s)) in p.coeffs.iter().zip(shift.powers()).enumerate() {
+                        folded[i % size] += c * s;
+                    }
+                    PolynomialCoeffs::new(folded)
+                        .fft_with_options(None, fft_root_table, FftStrategy::default())
+                        .values
+                }
+            })
+            .collect()
+    }
+
+    /// Like `get_lde_values`, but for all the LDE points whose index `i` in natural order is
+    /// congruent to `coset` modulo `2^log_num_cosets`, and computed from the coefficients rather
+    /// than read from the Merkle tree. Returns one vector per polynomial, with the value at `i` in
+    /// position `i >> log_num_cosets`.
+    pub fn lde_values_on_coset(
+        &self,
+        log_num_cosets: usize,
+        coset: usize,
+        fft_root_table: Option<&FftRootTable<F>>,
+    ) -> Vec<Vec<F>> {
+        Self::coset_values(
+            &self.polynomials,
+            self.degree_log + self.rate_bits,
+            log_num_cosets,
+            coset,
+            fft_root_table,
+        )
+    }
+
+    /// The `index`th leaf of the Merkle tree, salt included. If the LDE isn't retained, the leaf
+    /// is recomputed by evaluating each polynomial at the corresponding point.
+    pub fn get_leaf(&self, index: usize) -> Vec<F> {
+        if self.retains_lde() {
+            return self.merkle_tree.get(index).to_vec();
+        }
+
+        let lde_bits = self.degree_log + self.rate_bits;
+        let x = F::coset_shift()
+            * F::primitive_root_of_unity(lde_bits).exp_u64(reverse_bits(index, lde_bits) as u64);
+        let mut leaf: Vec<F> = self.polynomials.par_iter().map(|p| p.eval(x)).collect();
+        if self.blinding {
+            leaf.extend_from_slice(&self.salts[index * SALT_SIZE..(index + 1) * SALT_SIZE]);
+        }
+        leaf
+    }
+
+    /// The LDEs of `polynomials`, followed by one column per salt element. `salts` holds the salts
+    /// of all leaves, `SALT_SIZE` per leaf in leaf order, or is empty if not blinding.
     fn lde_values(
         polynomials: &[PolynomialCoeffs<F>],
         rate_bits: usize,
-        blinding: bool,
+        salts: &[F],
         fft_root_table: Option<&FftRootTable<F>>,
     ) -> Vec<Vec<F>> {
         let degree = polynomials[0].len();
+        let lde_bits = log2_strict(degree) + rate_bits;
+        let fft_root_table = Self::root_table_prefix(fft_root_table, lde_bits);
+        let fft_root_table = fft_root_table.as_deref();
 
-        // If blinding, salt with two random elements to each leaf vector.
-        let salt_size = if blinding { SALT_SIZE } else { 0 };
+        // If blinding, salt each leaf vector with `SALT_SIZE` random elements.
+        let salt_size = if salts.is_empty() { 0 } else { SALT_SIZE };
 
         polynomials
             .par_iter()
             .map(|p| {
                 assert_eq!(p.len(), degree, "Polynomial degrees inconsistent");
                 p.lde(rate_bits)
-                    .coset_fft_with_options(F::coset_shift(), Some(rate_bits), fft_root_table)
+                    .coset_fft_with_options(
+                        F::coset_shift(),
+                        Some(rate_bits),
+                        fft_root_table,
+                        FftStrategy::default(),
+                    )
                     .values
             })
-            .chain(
-                (0..salt_size)
-                    .into_par_iter()
-                    .map(|_| F::rand_vec(degree << rate_bits)),
-            )
+            .chain((0..salt_size).into_par_iter().map(|j| {
+                // Leaves are in bit-reversed order.
+                (0..1 << lde_bits)
+                    .map(|i| salts[reverse_bits(i, lde_bits) * SALT_SIZE + j])
+                    .collect()
+            }))
             .collect()
     }
 
-    /// Fetches LDE values at the `index * step`th point.
+    /// Draws the salts of the `2^lde_bits` leaves, `SALT_SIZE` per leaf in leaf order, if blinding.
+    fn sample_salts<R: RngCore + ?Sized>(blinding: bool, lde_bits: usize, rng: &mut R) -> Vec<F> {
+        let salt_size = if blinding { SALT_SIZE } else { 0 };
+        (0..salt_size << lde_bits).map(|_| F::sample(rng)).collect()
+    }
+
+    /// The root table for FFTs over `2^lg_n` points, given one for a domain at least as large.
+    /// Row `k` of a root table only depends on `k`, so the table for a smaller domain is a prefix
+    /// of the one for a larger domain.
+    fn root_table_prefix(
+        fft_root_table: Option<&FftRootTable<F>>,
+        lg_n: usize,
+    ) -> Option<Cow<'_, FftRootTable<F>>> {
+        let fft_root_table = fft_root_table?;
+        assert!(
+            fft_root_table.len() >= lg_n,
+            "Root table of length {} is too small for FFTs of size 2^{lg_n}",
+            fft_root_table.len()
+        );
+        Some(if fft_root_table.len() == lg_n {
+            Cow::Borrowed(fft_root_table)
+        } else {
+            Cow::Owned(fft_root_table[..lg_n].to_vec())
+        })
+    }
+
+    /// Fetches LDE values at the `index * step`th point. The LDE must be retained.
     pub fn get_lde_values(&self, index: usize, step: usize) -> &[F] {
+        debug_assert!(self.retains_lde(), "LDE values were not retained");
         let index = index * step;
         let index = reverse_bits(index, self.degree_log + self.rate_bits);
         let slice = &self.merkle_tree.leaves[index];
@@ -217,18 +503,152 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             lde_final_poly.coset_fft(F::coset_shift().into())
         );
 
-        let fri_proof = fri_proof::<F, C, D>(
-            &oracles
-                .par_iter()
-                .map(|c| &c.merkle_tree)
-                .collect::<Vec<_>>(),
+        fri_proof_from_oracles::<F, C, D>(
+            oracles,
             lde_final_poly,
             lde_final_values,
             challenger,
             fri_params,
             timing,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::field::fft::fft_root_table;
+    use crate::field::types::Sample;
+    use crate::hash::merkle_proofs::verify_merkle_proof_to_cap;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::util::serialization::{Buffer, Read, Write};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn random_polys(num_polys: usize, degree_log: usize) -> Vec<PolynomialCoeffs<F>> {
+        (0..num_polys)
+            .map(|_| PolynomialCoeffs::new(F::rand_vec(1 << degree_log)))
+            .collect()
+    }
+
+    #[test]
+    fn test_chunked_commitment_matches_full() {
+        let (degree_log, rate_bits, cap_height) = (5, 3, 2);
+        let polys = random_polys(13, degree_log);
+        for blinding in [false, true] {
+            let full = PolynomialBatch::<F, C, D>::from_coeffs_with_rng(
+                polys.clone(),
+                rate_bits,
+                blinding,
+                cap_height,
+                &mut ChaCha8Rng::seed_from_u64(0),
+                &mut TimingTree::default(),
+                None,
+            );
+
+            for lde_chunk_bits in [0, 1, rate_bits, degree_log + rate_bits] {
+                let chunked = PolynomialBatch::<F, C, D>::from_coeffs_chunked(
+                    polys.clone(),
+                    rate_bits,
+                    blinding,
+                    cap_height,
+                    lde_chunk_bits,
+                    &mut ChaCha8Rng::seed_from_u64(0),
+                    &mut TimingTree::default(),
+                    None,
+                );
+                assert!(!chunked.retains_lde());
+                assert_eq!(chunked.merkle_tree.cap, full.merkle_tree.cap);
+                assert_eq!(chunked.merkle_tree.digests, full.merkle_tree.digests);
+            }
+        }
+    }
+
+    #[test]
+    fn test_recomputed_lde_values() -> Result<()> {
+        let (degree_log, rate_bits, cap_height) = (4, 2, 1);
+        let lde_bits = degree_log + rate_bits;
+        let polys = random_polys(5, degree_log);
+        // A root table for a larger domain works too.
+        let fft_root_table = fft_root_table(1 << (lde_bits + 1));
+        let full = PolynomialBatch::<F, C, D>::from_coeffs_with_rng(
+            polys.clone(),
+            rate_bits,
+            true,
+            cap_height,
+            &mut ChaCha8Rng::seed_from_u64(0),
+            &mut TimingTree::default(),
+            Some(&fft_root_table),
+        );
+        let chunked = PolynomialBatch::<F, C, D>::from_coeffs_chunked(
+            polys,
+            rate_bits,
+            true,
+            cap_height,
+            2,
+            &mut ChaCha8Rng::seed_from_u64(0),
+            &mut TimingTree::default(),
+            Some(&fft_root_table),
+        );
+
+        for index in 0..1 << lde_bits {
+            assert_eq!(full.get_leaf(index), full.merkle_tree.get(index));
+            let leaf = chunked.get_leaf(index);
+            assert_eq!(leaf, full.merkle_tree.get(index));
+            let proof = chunked.merkle_tree.prove(index);
+            verify_merkle_proof_to_cap(leaf, index, &chunked.merkle_tree.cap, &proof)?;
+        }
+
+        for log_num_cosets in [0, 1, lde_bits] {
+            for coset in 0..1 << log_num_cosets {
+                let columns =
+                    chunked.lde_values_on_coset(log_num_cosets, coset, Some(&fft_root_table));
+                for (j, row) in transpose(&columns).into_iter().enumerate() {
+                    let index = coset + (j << log_num_cosets);
+                    assert_eq!(row, full.get_lde_values(index, 1));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chunked_batch_serialization() -> Result<()> {
+        let (degree_log, rate_bits, cap_height) = (4, 2, 1);
+        let batch = PolynomialBatch::<F, C, D>::from_coeffs_chunked(
+            random_polys(3, degree_log),
+            rate_bits,
+            true,
+            cap_height,
+            1,
+            &mut ChaCha8Rng::seed_from_u64(0),
+            &mut TimingTree::default(),
+            None,
         );
 
-        fri_proof
+        let mut bytes = Vec::new();
+        bytes.write_polynomial_batch(&batch)?;
+        let batch_from_bytes = Buffer::new(&bytes).read_polynomial_batch::<F, C, D>()?;
+        assert_eq!(batch, batch_from_bytes);
+
+        // The salts are needed to recompute the leaves.
+        for index in 0..1 << (degree_log + rate_bits) {
+            let proof = batch_from_bytes.merkle_tree.prove(index);
+            verify_merkle_proof_to_cap(
+                batch_from_bytes.get_leaf(index),
+                index,
+                &batch_from_bytes.merkle_tree.cap,
+                &proof,
+            )?;
+        }
+
+        Ok(())
     }
 }
@@ -1,9 +1,11 @@
 use alloc::vec::Vec;
+use core::ops::Range;
 
 use plonky2_maybe_rayon::*;
 
 use crate::field::extension::{flatten, unflatten, Extendable};
 use crate::field::polynomial::{PolynomialCoeffs, PolynomialValues};
+use crate::fri::oracle::PolynomialBatch;
 use crate::fri::proof::{FriInitialTreeProof, FriProof, FriQueryRound, FriQueryStep};
 use crate::fri::{FriConfig, FriParams};
 use crate::hash::hash_types::RichField;
@@ -26,6 +28,62 @@ pub fn fri_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const
     challenger: &mut Challenger<F, C::Hasher>,
     fri_params: &FriParams,
     timing: &mut TimingTree,
+) -> FriProof<F, C::Hasher, D> {
+    fri_proof_with_initial_leaves::<F, C, D>(
+        initial_merkle_trees,
+        &|i, x_index| initial_merkle_trees[i].get(x_index).to_vec(),
+        lde_polynomial_coeffs,
+        lde_polynomial_values,
+        challenger,
+        fri_params,
+        timing,
+    )
+}
+
+/// Like `fri_proof`, but reads the initial leaves from the oracles, which recompute them when
+/// their LDE isn't retained.
+pub fn fri_proof_from_oracles<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    initial_oracles: &[&PolynomialBatch<F, C, D>],
+    lde_polynomial_coeffs: PolynomialCoeffs<F::Extension>,
+    lde_polynomial_values: PolynomialValues<F::Extension>,
+    challenger: &mut Challenger<F, C::Hasher>,
+    fri_params: &FriParams,
+    timing: &mut TimingTree,
+) -> FriProof<F, C::Hasher, D> {
+    let initial_merkle_trees = initial_oracles
+        .iter()
+        .map(|o| &o.merkle_tree)
+        .collect::<Vec<_>>();
+    fri_proof_with_initial_leaves::<F, C, D>(
+        &initial_merkle_trees,
+        &|i, x_index| initial_oracles[i].get_leaf(x_index),
+        lde_polynomial_coeffs,
+        lde_polynomial_values,
+        challenger,
+        fri_params,
+        timing,
+    )
+}
+
+/// The `x_index`th leaf of the `i`th initial tree.
+type InitialLeafFn<'a, F> = dyn Fn(usize, usize) -> Vec<F> + Sync + 'a;
+
+fn fri_proof_with_initial_leaves<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    initial_merkle_trees: &[&MerkleTree<F, C::Hasher>],
+    initial_leaf: &InitialLeafFn<F>,
+    lde_polynomial_coeffs: PolynomialCoeffs<F::Extension>,
+    lde_polynomial_values: PolynomialValues<F::Extension>,
+    challenger: &mut Challenger<F, C::Hasher>,
+    fri_params: &FriParams,
+    timing: &mut TimingTree,
 ) -> FriProof<F, C::Hasher, D> {
     let n = lde_polynomial_values.len();
     assert_eq!(lde_polynomial_coeffs.len(), n);
@@ -50,8 +108,14 @@ pub fn fri_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const
     );
 
     // Query phase
-    let query_round_proofs =
-        fri_prover_query_rounds::<F, C, D>(initial_merkle_trees, &trees, challenger, n, fri_params);
+    let query_round_proofs = fri_prover_query_rounds::<F, C, D>(
+        initial_merkle_trees,
+        initial_leaf,
+        &trees,
+        challenger,
+        n,
+        fri_params,
+    );
 
     FriProof {
         commit_phase_merkle_caps: trees.iter().map(|t| t.cap.clone()).collect(),
@@ -111,8 +175,14 @@ fn fri_committed_trees<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>,
     (trees, coeffs)
 }
 
-/// Performs the proof-of-work (a.k.a. grinding) step of the FRI protocol. Returns the PoW witness.
-fn fri_proof_of_work<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+/// Performs the proof-of-work (a.k.a. grinding) step of the FRI protocol. Returns the PoW witness,
+/// which is the smallest valid one so that it doesn't depend on how the search is split among
+/// threads.
+pub fn fri_proof_of_work<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
     challenger: &mut Challenger<F, C::Hasher>,
     config: &FriConfig,
 ) -> F {
@@ -138,18 +208,11 @@ fn fri_proof_of_work<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, c
     let witness_input_pos = challenger.input_buffer.len();
     duplex_intermediate_state.set_from_iter(challenger.input_buffer.clone(), 0);
 
-    let pow_witness = (0..=F::NEG_ONE.to_canonical_u64())
-        .into_par_iter()
-        .find_any(|&candidate| {
-            let mut duplex_state = duplex_intermediate_state;
-            duplex_state.set_elt(F::from_canonical_u64(candidate), witness_input_pos);
-            duplex_state.permute();
-            let pow_response = duplex_state.squeeze().iter().last().unwrap();
-            let leading_zeros = pow_response.to_canonical_u64().leading_zeros();
-            leading_zeros >= min_leading_zeros
-        })
-        .map(F::from_canonical_u64)
-        .expect("Proof of work failed. This is highly unlikely!");
+    let pow_witness = F::from_canonical_u64(grind(
+        duplex_intermediate_state,
+        witness_input_pos,
+        min_leading_zeros,
+    ));
 
     // Recompute pow_response using our normal Challenger code, and make sure it matches.
     challenger.observe_element(pow_witness);
@@ -159,12 +222,88 @@ fn fri_proof_of_work<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, c
     pow_witness
 }
 
+/// The number of PoW witness candidates a thread tries at a time, in increasing order.
+const POW_CHUNK_SIZE: u64 = 1 << 12;
+
+/// The number of chunks per thread in each window of chunks searched in parallel.
+const POW_CHUNKS_PER_THREAD: u64 = 4;
+
+/// The number of PoW witness candidates whose duplex states are permuted together, so that the
+/// permutation can be computed with vector instructions.
+const POW_BATCH_SIZE: u64 = 16;
+
+/// Returns the smallest PoW witness for which the duplex response has at least
+/// `min_leading_zeros` leading zeros. The candidates are split into chunks, which are each searched
+/// in batches. Windows of a few chunks per thread are searched in parallel, one after the other.
+fn grind<F: RichField, P: PlonkyPermutation<F>>(
+    duplex_intermediate_state: P,
+    witness_input_pos: usize,
+    min_leading_zeros: u32,
+) -> u64 {
+    let num_candidates = F::ORDER;
+    let num_chunks = num_candidates.div_ceil(POW_CHUNK_SIZE);
+    // A single parallel search over all the chunks would split them into one huge range per
+    // thread, and the witness, which is expected among the first `2^min_leading_zeros` candidates,
+    // would always be found by the thread holding the first range, alone.
+    let window_size = current_num_threads() as u64 * POW_CHUNKS_PER_THREAD;
+    (0..num_chunks)
+        .step_by(window_size as usize)
+        .find_map(|window_start| {
+            let window_end = num_chunks.min(window_start + window_size);
+            (window_start..window_end)
+                .into_par_iter()
+                .find_map_first(|chunk| {
+                    let start = chunk * POW_CHUNK_SIZE;
+                    let end = num_candidates.min(start + POW_CHUNK_SIZE);
+                    grind_chunk(
+                        duplex_intermediate_state,
+                        witness_input_pos,
+                        min_leading_zeros,
+                        start..end,
+                    )
+                })
+        })
+        .expect("Proof of work failed. This is highly unlikely!")
+}
+
+/// Returns the smallest of `candidates` that is a valid PoW witness, if any.
+fn grind_chunk<F: RichField, P: PlonkyPermutation<F>>(
+    duplex_intermediate_state: P,
+    witness_input_pos: usize,
+    min_leading_zeros: u32,
+    candidates: Range<u64>,
+) -> Option<u64> {
+    let mut duplex_states = Vec::with_capacity(POW_BATCH_SIZE as usize);
+    let mut batch_start = candidates.start;
+    while batch_start < candidates.end {
+        let batch_end = candidates.end.min(batch_start + POW_BATCH_SIZE);
+        duplex_states.clear();
+        duplex_states.extend((batch_start..batch_end).map(|candidate| {
+            let mut duplex_state = duplex_intermediate_state;
+            duplex_state.set_elt(F::from_canonical_u64(candidate), witness_input_pos);
+            duplex_state
+        }));
+        P::permute_batch(&mut duplex_states);
+
+        let found = duplex_states.iter().position(|duplex_state| {
+            let pow_response = duplex_state.squeeze().iter().last().unwrap();
+            pow_response.to_canonical_u64().leading_zeros() >= min_leading_zeros
+        });
+        if let Some(i) = found {
+            return Some(batch_start + i as u64);
+        }
+        batch_start = batch_end;
+    }
+    None
+}
+
 fn fri_prover_query_rounds<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
     const D: usize,
 >(
     initial_merkle_trees: &[&MerkleTree<F, C::Hasher>],
+    initial_leaf: &InitialLeafFn<F>,
     trees: &[MerkleTree<F, C::Hasher>],
     challenger: &mut Challenger<F, C::Hasher>,
     n: usize,
@@ -175,7 +314,13 @@ fn fri_prover_query_rounds<
         .into_par_iter()
         .map(|rand| {
             let x_index = rand.to_canonical_u64() as usize % n;
-            fri_prover_query_round::<F, C, D>(initial_merkle_trees, trees, x_index, fri_params)
+            fri_prover_query_round::<F, C, D>(
+                initial_merkle_trees,
+                initial_leaf,
+                trees,
+                x_index,
+                fri_params,
+            )
         })
         .collect()
 }
@@ -186,6 +331,7 @@ fn fri_prover_query_round<
     const D: usize,
 >(
     initial_merkle_trees: &[&MerkleTree<F, C::Hasher>],
+    initial_leaf: &InitialLeafFn<F>,
     trees: &[MerkleTree<F, C::Hasher>],
     mut x_index: usize,
     fri_params: &FriParams,
@@ -193,7 +339,8 @@ fn fri_prover_query_round<
     let mut query_steps = Vec::new();
     let initial_proof = initial_merkle_trees
         .iter()
-        .map(|t| (t.get(x_index).to_vec(), t.prove(x_index)))
+        .enumerate()
+        .map(|(i, t)| (initial_leaf(i, x_index), t.prove(x_index)))
         .collect::<Vec<_>>();
     for (i, tree) in trees.iter().enumerate() {
         let arity_bits = fri_params.reduction_arity_bits[i];
@@ -214,3 +361,39 @@ fn fri_prover_query_round<
         steps: query_steps,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::{Field, PrimeField64, Sample};
+    use crate::fri::prover::grind;
+    use crate::hash::hashing::PlonkyPermutation;
+    use crate::hash::poseidon::PoseidonPermutation;
+
+    type F = GoldilocksField;
+
+    #[test]
+    fn test_grind_finds_smallest_witness() {
+        let witness_input_pos = 3;
+        let min_leading_zeros = 10;
+        let duplex_intermediate_state = PoseidonPermutation::new(F::rand_vec(witness_input_pos));
+
+        let is_valid = |candidate: u64| {
+            let mut duplex_state = duplex_intermediate_state;
+            duplex_state.set_elt(F::from_canonical_u64(candidate), witness_input_pos);
+            duplex_state.permute();
+            let pow_response = duplex_state.squeeze().iter().last().unwrap();
+            pow_response.to_canonical_u64().leading_zeros() >= min_leading_zeros
+        };
+        let expected = (0..).find(|&candidate| is_valid(candidate)).unwrap();
+
+        assert_eq!(
+            grind(
+                duplex_intermediate_state,
+                witness_input_pos,
+                min_leading_zeros
+            ),
+            expected
+        );
+    }
+}
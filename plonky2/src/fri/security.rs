@@ -0,0 +1,192 @@
+//! Estimates of the soundness of FRI-based proofs, for checking that a config achieves its
+//! `security_bits`.
+//!
+//! We consider three sources of soundness error:
+//! - The FRI query phase. Each query round catches a function far from the code with some
+//!   probability depending on the proximity parameter, and the proof of work adds its bits on top.
+//! - The FRI commit phase, i.e. the folding challenges, and the out-of-domain point, which are
+//!   sampled from the extension field.
+//! - The other challenges (e.g. for the permutation argument or for combining constraints), which
+//!   are sampled from the base field `num_challenges` times, each with an error of roughly
+//!   `degree / |F|`.
+//!
+//! The conjectured security follows the usual conjecture that FRI is sound up to the list-decoding
+//! capacity, so that each query round contributes `rate_bits` bits. The proven figures use the
+//! bounds of [BCIKS20](https://eprint.iacr.org/2020/654), as stated in the ethSTARK documentation,
+//! in the list-decoding regime up to the Johnson bound and in the unique-decoding regime.
+
+use alloc::format;
+use alloc::string::String;
+
+use crate::fri::FriParams;
+
+/// Which security estimate a config must achieve.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SecurityAssumption {
+    Conjectured,
+    Proven,
+}
+
+/// Estimates of the security of a config for an instance size, in bits.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SecurityReport {
+    /// The conjectured security, with FRI sound up to the list-decoding capacity.
+    pub conjectured_bits: f64,
+    /// The proven security, with FRI in the list-decoding regime, up to the Johnson bound.
+    pub proven_bits: f64,
+    /// The proven security, with FRI in the unique-decoding regime. This is weaker than
+    /// `proven_bits` for all but the smallest rates, but relies on older results.
+    pub proven_unique_decoding_bits: f64,
+}
+
+impl SecurityReport {
+    /// Computes the security of a proof with the given FRI parameters, over a base field of
+    /// `field_bits` bits and its extension of degree `extension_degree`, with `num_challenges`
+    /// repetitions of the base field challenges.
+    pub fn new(
+        fri_params: &FriParams,
+        field_bits: f64,
+        extension_degree: usize,
+        num_challenges: usize,
+    ) -> Self {
+        let degree_bits = fri_params.degree_bits as f64;
+        let challenge_bits = num_challenges as f64 * (field_bits - degree_bits);
+        let extension_bits = extension_degree as f64 * field_bits;
+
+        let config = &fri_params.config;
+        let rate_bits = config.rate_bits as f64;
+        let lde_bits = fri_params.lde_bits() as f64;
+        let num_queries = config.num_query_rounds as f64;
+        let pow_bits = config.proof_of_work_bits as f64;
+        let sum_arities: f64 = fri_params
+            .reduction_arity_bits
+            .iter()
+            .map(|&arity_bits| (1u64 << arity_bits) as f64)
+            .sum();
+
+        let conjectured_fri_bits =
+            (rate_bits * num_queries + pow_bits).min(extension_bits - lde_bits);
+
+        let rate = 2f64.powf(-rate_bits);
+        let domain_size = 2f64.powf(lde_bits);
+        let extension_size = 2f64.powf(extension_bits);
+
+        // The list-decoding bound holds for any m >= 3, so we take the best one.
+        let proven_fri_bits = (3..=256)
+            .map(|m| {
+                let m = m as f64;
+                let commit_error = (m + 0.5).powi(7) / (3.0 * rate.powf(1.5)) * domain_size.powi(2)
+                    / extension_size
+                    + (2.0 * m + 1.0) * (domain_size + 1.0) / rate.sqrt() * sum_arities
+                        / extension_size;
+                let alpha = rate.sqrt() * (1.0 + 1.0 / (2.0 * m));
+                let query_error = alpha.powf(num_queries) * 2f64.powf(-pow_bits);
+                -(commit_error + query_error).log2()
+            })
+            .fold(f64::NEG_INFINITY, f64::max);
+
+        let unique_decoding_fri_bits = {
+            let commit_error = domain_size * sum_arities / extension_size;
+            let query_error = ((1.0 + rate) / 2.0).powf(num_queries) * 2f64.powf(-pow_bits);
+            -(commit_error + query_error).log2()
+        };
+
+        Self {
+            conjectured_bits: conjectured_fri_bits.min(challenge_bits),
+            proven_bits: proven_fri_bits.min(challenge_bits),
+            proven_unique_decoding_bits: unique_decoding_fri_bits.min(challenge_bits),
+        }
+    }
+
+    /// The security under `assumption`, taking the better of the two proven regimes.
+    pub fn bits(&self, assumption: SecurityAssumption) -> f64 {
+        match assumption {
+            SecurityAssumption::Conjectured => self.conjectured_bits,
+            SecurityAssumption::Proven => self.proven_bits.max(self.proven_unique_decoding_bits),
+        }
+    }
+
+    /// Checks that the security under `assumption` is at least `security_bits`.
+    pub fn check(
+        &self,
+        assumption: SecurityAssumption,
+        security_bits: usize,
+    ) -> Result<(), String> {
+        let bits = self.bits(assumption);
+        if bits >= security_bits as f64 {
+            Ok(())
+        } else {
+            Err(format!(
+                "{assumption:?} security is {bits:.1} bits, below the required {security_bits}"
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::Field;
+    use crate::fri::security::{SecurityAssumption, SecurityReport};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    fn build_with_requirement(assumption: SecurityAssumption) {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = GoldilocksField;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        builder.require_security(assumption);
+        let x = builder.add_virtual_target();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        let _ = builder.constant(F::ONE);
+        builder.build::<C>();
+    }
+
+    #[test]
+    fn test_standard_recursion_config() {
+        let config = CircuitConfig::standard_recursion_config();
+        let report = config.security::<GoldilocksField, 2>(12);
+
+        // 28 queries of 3 bits each, and 16 bits of proof of work.
+        assert!((report.conjectured_bits - 100.0).abs() < 1e-6);
+        assert!(report.check(SecurityAssumption::Conjectured, 100).is_ok());
+
+        // Proven bounds lose about half the bits of each query.
+        assert!(report.proven_bits < report.conjectured_bits);
+        assert!(report.proven_bits > report.proven_unique_decoding_bits);
+        assert!(report.check(SecurityAssumption::Proven, 100).is_err());
+
+        // The base field challenges weaken as the degree grows.
+        let large = config.security::<GoldilocksField, 2>(20);
+        assert!(large.conjectured_bits < 100.0);
+    }
+
+    #[test]
+    fn test_more_queries_are_more_secure() {
+        let config = CircuitConfig::standard_recursion_config();
+        let fri_params = config.fri_config.fri_params(12, false);
+        let mut more_queries = fri_params.clone();
+        more_queries.config.num_query_rounds *= 2;
+
+        let report = SecurityReport::new(&fri_params, 64.0, 2, 4);
+        let more = SecurityReport::new(&more_queries, 64.0, 2, 4);
+        assert!(more.conjectured_bits > report.conjectured_bits);
+        assert!(more.proven_bits > report.proven_bits);
+        assert!(more.proven_unique_decoding_bits > report.proven_unique_decoding_bits);
+    }
+
+    #[test]
+    fn test_require_conjectured_security() {
+        build_with_requirement(SecurityAssumption::Conjectured);
+    }
+
+    #[test]
+    #[should_panic(expected = "Insecure circuit config")]
+    fn test_require_proven_security() {
+        build_with_requirement(SecurityAssumption::Proven);
+    }
+}
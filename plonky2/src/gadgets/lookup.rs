@@ -1,8 +1,9 @@
 use alloc::borrow::ToOwned;
 use alloc::vec;
+use alloc::vec::Vec;
 
 use crate::field::extension::Extendable;
-use crate::gates::lookup::LookupGate;
+use crate::gates::lookup::{LookupGate, LookupGenerator};
 use crate::gates::lookup_table::{LookupTable, LookupTableGate};
 use crate::gates::noop::NoopGate;
 use crate::hash::hash_types::RichField;
@@ -47,8 +48,13 @@ pub const OTHER_TABLE: [u16; 256] = [
 pub const SMALLER_TABLE: [u16; 8] = [2, 24, 56, 100, 128, 16, 20, 49];
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Adds a lookup table to the list of stored lookup tables `self.luts`. It returns the index of the LUT within `self.luts`.
+    pub fn add_lookup_table(&mut self, table: LookupTable<F>) -> usize {
+        self.update_luts(table)
+    }
+
     /// Adds a lookup table to the list of stored lookup tables `self.luts` based on a table of (input, output) pairs. It returns the index of the LUT within `self.luts`.
-    pub fn add_lookup_table_from_pairs(&mut self, table: LookupTable) -> usize {
+    pub fn add_lookup_table_from_pairs(&mut self, table: &[(u16, u16)]) -> usize {
         self.update_luts_from_pairs(table)
     }
 
@@ -62,22 +68,60 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.update_luts_from_fn(f, inputs)
     }
 
-    /// Adds a lookup (input, output) pair to the stored lookups. Takes a `Target` input and returns a `Target` output.
-    pub fn add_lookup_from_index(&mut self, looking_in: Target, lut_index: usize) -> Target {
+    /// Adds a dynamic lookup table, whose rows are tuples of targets rather than constants, to the list of stored lookup tables `self.luts`. It returns the index of the LUT within `self.luts`.
+    pub fn add_dynamic_lookup_table(&mut self, rows: Vec<Vec<Target>>) -> usize {
+        self.update_dynamic_luts(rows)
+    }
+
+    /// Adds a lookup to the stored lookups, which checks that the tuple `values` is a row of the LUT.
+    pub fn add_lookup(&mut self, values: &[Target], lut_index: usize) {
         assert!(
             lut_index < self.get_luts_length(),
             "lut number {} not in luts (length = {})",
             lut_index,
             self.get_luts_length()
         );
+        let width = self.get_lut(lut_index).width();
+        assert_eq!(
+            values.len(),
+            width,
+            "lut number {} has {} columns, but {} values were looked up",
+            lut_index,
+            width,
+            values.len()
+        );
+        self.update_lookups(values, lut_index);
+    }
+
+    /// Adds a lookup to the stored lookups, whose output is the last column of the row of the LUT
+    /// starting with `inputs`. Takes the `Target` inputs and returns a `Target` output.
+    ///
+    /// The LUT must be constant: the outputs of dynamic tables can't be computed from the circuit.
+    pub fn add_lookup_from_inputs(&mut self, inputs: &[Target], lut_index: usize) -> Target {
+        assert!(
+            self.get_dynamic_lut_rows(lut_index).is_none(),
+            "lut number {} is dynamic, its outputs must be computed by the caller",
+            lut_index
+        );
         let looking_out = self.add_virtual_target();
-        self.update_lookups(looking_in, looking_out, lut_index);
+        self.add_lookup(&[inputs, &[looking_out]].concat(), lut_index);
+        self.add_simple_generator(LookupGenerator {
+            inputs: inputs.to_vec(),
+            output: looking_out,
+            lut: self.get_lut(lut_index),
+        });
         looking_out
     }
 
+    /// Adds a lookup (input, output) pair to the stored lookups. Takes a `Target` input and returns a `Target` output.
+    pub fn add_lookup_from_index(&mut self, looking_in: Target, lut_index: usize) -> Target {
+        self.add_lookup_from_inputs(&[looking_in], lut_index)
+    }
+
     /// We call this function at the end of circuit building right before the PI gate to add all `LookupTableGate` and `LookupGate`.
     /// It also updates `self.lookup_rows` accordingly.
     pub fn add_all_lookups(&mut self) {
+        let width = self.lookup_width();
         for lut_index in 0..self.num_luts() {
             assert!(
                 !self.get_lut_lookups(lut_index).is_empty(),
@@ -92,8 +136,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
                 let lookups = self.get_lut_lookups(lut_index).to_owned();
 
-                let gate = LookupGate::new_from_table(&self.config, lut.clone());
-                let num_slots = LookupGate::num_slots(&self.config);
+                let gate = LookupGate::new_from_table(&self.config, width, lut.clone());
+                let num_slots = LookupGate::<F>::num_slots(&self.config, width);
 
                 // Given the number of lookups and the number of slots for each gate, it is possible
                 // to compute the number of gates that will employ all their slots; such gates can
@@ -104,34 +148,32 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
                 let lookup_iter = lookups.chunks_exact(num_slots);
                 // `last_chunk` will contain the remainder of lookups, which cannot fill all the
                 // slots of a `LookupGate`; this last chunk will be processed by incrementally
-                // filling slots, to avoid that the `LookupGenerator` is run on unused slots
+                // filling slots, so that the prover can pad the unused ones
                 let last_chunk = lookup_iter.remainder();
                 // handle chunks that can fill all the slots of a `LookupGate`
                 lookup_iter.for_each(|chunk| {
                     let row = self.add_gate(gate.clone(), vec![]);
-                    for (i, (looking_in, looking_out)) in chunk.iter().enumerate() {
-                        let gate_in = Target::wire(row, LookupGate::wire_ith_looking_inp(i));
-                        let gate_out = Target::wire(row, LookupGate::wire_ith_looking_out(i));
-                        self.connect(gate_in, *looking_in);
-                        self.connect(gate_out, *looking_out);
+                    for (i, lookup) in chunk.iter().enumerate() {
+                        self.connect_lookup(row, i, width, lookup);
                     }
                 });
                 // deal with the last chunk
-                for (looking_in, looking_out) in last_chunk.iter() {
-                    let (gate, i) =
+                for lookup in last_chunk.iter() {
+                    let (row, i) =
                         self.find_slot(gate.clone(), &[F::from_canonical_usize(lut_index)], &[]);
-                    let gate_in = Target::wire(gate, LookupGate::wire_ith_looking_inp(i));
-                    let gate_out = Target::wire(gate, LookupGate::wire_ith_looking_out(i));
-                    self.connect(gate_in, *looking_in);
-                    self.connect(gate_out, *looking_out);
+                    self.connect_lookup(row, i, width, lookup);
                 }
 
-                // Create LUT gates. Nothing is connected to them.
+                // Create LUT gates. Only the rows of dynamic LUTs are connected to them.
                 let last_lut_gate = self.num_gates();
-                let num_lut_entries = LookupTableGate::num_slots(&self.config);
+                let num_lut_entries = LookupTableGate::<F>::num_slots(&self.config, width);
                 let num_lut_rows = (self.get_luts_idx_length(lut_index) - 1) / num_lut_entries + 1;
-                let gate =
-                    LookupTableGate::new_from_table(&self.config, lut.clone(), last_lut_gate);
+                let gate = LookupTableGate::new_from_table(
+                    &self.config,
+                    width,
+                    lut.clone(),
+                    last_lut_gate,
+                );
                 // Also instances of `LookupTableGate` can be placed with the `add_gate` function
                 // rather than being instantiated slot by slot; note that in this case there is no
                 // need to separately handle the last chunk of LUT entries that cannot fill all the
@@ -142,6 +184,11 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
                 let first_lut_gate = self.num_gates() - 1;
 
+                if let Some(rows) = self.get_dynamic_lut_rows(lut_index) {
+                    let rows = rows.to_owned();
+                    self.connect_dynamic_lut_rows(first_lut_gate, width, &rows, num_lut_rows);
+                }
+
                 // Will ensure the next row's wires will be all zeros. With this, there is no distinction between the transition constraints on the first row
                 // and on the other rows. Additionally, initial constraints become a simple zero check.
                 self.add_gate(NoopGate, vec![]);
@@ -153,4 +200,49 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             }
         }
     }
+
+    /// Connects the rows of a dynamic LUT to the slots of its `LookupTableGate`s, starting from
+    /// `first_lut_gate`. Since nothing else fixes the contents of the gates, the columns past the
+    /// width of the table are connected to zero, and the unused slots are given a zero multiplicity.
+    fn connect_dynamic_lut_rows(
+        &mut self,
+        first_lut_gate: usize,
+        width: usize,
+        rows: &[Vec<Target>],
+        num_lut_rows: usize,
+    ) {
+        let zero = self.zero();
+        let num_lut_entries = LookupTableGate::<F>::num_slots(&self.config, width);
+        for lut_entry in 0..num_lut_rows * num_lut_entries {
+            let row = first_lut_gate - lut_entry / num_lut_entries;
+            let slot = lut_entry % num_lut_entries;
+            match rows.get(lut_entry) {
+                Some(values) => {
+                    for col in 0..width {
+                        let gate_wire = Target::wire(
+                            row,
+                            LookupTableGate::<F>::wire_ith_looked(width, slot, col),
+                        );
+                        self.connect(gate_wire, values.get(col).copied().unwrap_or(zero));
+                    }
+                }
+                None => {
+                    let multiplicity_wire = Target::wire(
+                        row,
+                        LookupTableGate::<F>::wire_ith_multiplicity(width, slot),
+                    );
+                    self.connect(multiplicity_wire, zero);
+                }
+            }
+        }
+    }
+
+    /// Connects a looked up tuple to the `i`-th slot of the `LookupGate` at `row`. The wires past
+    /// the width of the tuple are left unset, hence zero, like the padding of the LUT's rows.
+    fn connect_lookup(&mut self, row: usize, i: usize, width: usize, lookup: &[Target]) {
+        for (col, &target) in lookup.iter().enumerate() {
+            let gate_wire = Target::wire(row, LookupGate::<F>::wire_ith_looking(width, i, col));
+            self.connect(gate_wire, target);
+        }
+    }
 }
@@ -0,0 +1,620 @@
+//! Read/write memories, checked with offline memory checking.
+//!
+//! The operations on a memory are recorded while building the circuit, and turned into lookups
+//! when it is built. Each operation, as well as the initialization of each cell, is described by a
+//! tuple `(address, time, old value, new value)`, where the old value is the content of the cell
+//! before the operation, and the new value its content afterwards. Reads leave the cell unchanged.
+//!
+//! The prover sorts these tuples by address, then by time, and the sorted list forms a dynamic
+//! lookup table. Since the times of the operations are distinct, looking all the tuples up in that
+//! table, which has as many rows, proves that it is a permutation of them. It then suffices to
+//! check that, in the sorted list:
+//! - addresses start at 0, increase by 0 or 1 between consecutive rows, and end at the last cell,
+//! - times increase between consecutive rows of the same address, which is checked with a lookup
+//!   in a range table,
+//! - the old value of each row is the new value of the previous one, if they share their address.
+//!
+//! The initialization of each cell happens before any operation, so it's the first row of its
+//! address. Each operation hence costs a constant number of gates, whatever the size of the memory.
+//! Memories which are never written to only need one lookup per read, in a table of
+//! `(address, value)` rows.
+
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use itertools::Itertools;
+
+use crate::field::extension::Extendable;
+use crate::field::types::{Field, PrimeField64};
+use crate::gates::lookup_table::LookupTable;
+use crate::hash::hash_types::RichField;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
+
+/// An operation on a memory.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct MemoryOp {
+    address: Target,
+    /// The content of the cell before the operation. For reads, this is the value read.
+    old_value: Target,
+    /// The content of the cell after the operation. For reads, this is `old_value`.
+    new_value: Target,
+    is_write: bool,
+}
+
+/// A memory, along with the operations performed on it so far.
+#[derive(Clone, Debug)]
+pub(crate) struct Memory {
+    initial_values: Vec<Target>,
+    ops: Vec<MemoryOp>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Adds a read/write memory whose cells initially hold `initial_values`, and returns its index.
+    ///
+    /// Unlike `random_access`, the cost of accessing a memory doesn't depend on its size: it is
+    /// one lookup per read if the memory is never written to, and a few gates per access otherwise,
+    /// plus a constant number of gates per cell.
+    ///
+    /// A memory which is written to is checked with a lookup table of width 4. All the lookups of
+    /// a circuit are padded to the width of its widest table, so this also raises the width of the
+    /// lookups in other tables, leaving fewer slots per lookup gate, e.g. half as many as with
+    /// tables of `(input, output)` pairs.
+    pub fn add_memory(&mut self, initial_values: &[Target]) -> usize {
+        assert!(
+            !initial_values.is_empty(),
+            "A memory must have at least one cell"
+        );
+        self.memories.push(Memory {
+            initial_values: initial_values.to_vec(),
+            ops: Vec::new(),
+        });
+        self.memories.len() - 1
+    }
+
+    /// Reads the cell at `address` of the memory at index `memory_index`. The address must be less
+    /// than the size of the memory.
+    pub fn memory_read(&mut self, memory_index: usize, address: Target) -> Target {
+        let value = self.add_virtual_target();
+        self.add_memory_op(
+            memory_index,
+            MemoryOp {
+                address,
+                old_value: value,
+                new_value: value,
+                is_write: false,
+            },
+        );
+        value
+    }
+
+    /// Writes `value` to the cell at `address` of the memory at index `memory_index`. The address
+    /// must be less than the size of the memory.
+    pub fn memory_write(&mut self, memory_index: usize, address: Target, value: Target) {
+        let old_value = self.add_virtual_target();
+        self.add_memory_op(
+            memory_index,
+            MemoryOp {
+                address,
+                old_value,
+                new_value: value,
+                is_write: true,
+            },
+        );
+    }
+
+    fn add_memory_op(&mut self, memory_index: usize, op: MemoryOp) {
+        assert!(
+            memory_index < self.memories.len(),
+            "memory number {} not in memories (length = {})",
+            memory_index,
+            self.memories.len()
+        );
+        self.memories[memory_index].ops.push(op);
+    }
+
+    /// We call this function at the beginning of circuit building to check the operations on all
+    /// the memories, before the lookups are placed.
+    pub(crate) fn add_all_memories(&mut self) {
+        let memories = core::mem::take(&mut self.memories);
+        for memory in memories.iter().filter(|memory| !memory.ops.is_empty()) {
+            self.add_memory_checks(memory);
+        }
+    }
+
+    fn add_memory_checks(&mut self, memory: &Memory) {
+        let num_cells = memory.initial_values.len();
+        let num_ops = memory.ops.len();
+        let num_rows = num_cells + num_ops;
+        let read_only = memory.ops.iter().all(|op| !op.is_write);
+
+        // Copy the memory to contiguous virtual targets, so that generators can find it.
+        let layout = MemoryLayout::new(self, num_cells, num_ops, read_only);
+        for (i, &value) in memory.initial_values.iter().enumerate() {
+            self.connect(layout.initial_value(i), value);
+        }
+        for (k, op) in memory.ops.iter().enumerate() {
+            self.connect(layout.address(k), op.address);
+            self.connect(layout.old_value(k), op.old_value);
+            self.connect(layout.new_value(k), op.new_value);
+        }
+
+        self.add_simple_generator(MemoryGenerator {
+            layout,
+            is_write: memory.ops.iter().map(|op| op.is_write).collect(),
+        });
+
+        // Addresses of the cells for a read-only memory, times of the operations otherwise.
+        let zero = self.zero();
+        let one = self.one();
+        let num_counters = if read_only { num_cells } else { num_rows };
+        let counters = (1..num_counters).fold(vec![zero], |mut counters, _| {
+            let next = self.add(*counters.last().unwrap(), one);
+            counters.push(next);
+            counters
+        });
+
+        if read_only {
+            let rows = (0..num_cells)
+                .map(|i| vec![counters[i], memory.initial_values[i]])
+                .collect();
+            let lut_index = self.add_dynamic_lookup_table(rows);
+            for op in &memory.ops {
+                self.add_lookup(&[op.address, op.new_value], lut_index);
+            }
+            return;
+        }
+
+        self.add_simple_generator(MemorySortGenerator { layout });
+
+        let sorted_rows = (0..num_rows)
+            .map(|row| (0..4).map(|col| layout.sorted(row, col)).collect_vec())
+            .collect_vec();
+        let lut_index = self.add_dynamic_lookup_table(sorted_rows.clone());
+        for (i, &value) in memory.initial_values.iter().enumerate() {
+            self.add_lookup(&[counters[i], counters[i], zero, value], lut_index);
+        }
+        for (k, op) in memory.ops.iter().enumerate() {
+            self.add_lookup(
+                &[
+                    op.address,
+                    counters[num_cells + k],
+                    op.old_value,
+                    op.new_value,
+                ],
+                lut_index,
+            );
+        }
+
+        let range_table = (0..num_rows)
+            .map(|i| vec![F::from_canonical_usize(i)])
+            .collect();
+        let range_lut_index = self.add_lookup_table(LookupTable::new(range_table));
+
+        self.connect(sorted_rows[0][0], zero);
+        self.connect(sorted_rows[num_rows - 1][0], counters[num_cells - 1]);
+        for (row, (prev, cur)) in sorted_rows.iter().tuple_windows().enumerate() {
+            let (prev_address, prev_time, prev_new) = (prev[0], prev[1], prev[3]);
+            let (address, time, old) = (cur[0], cur[1], cur[2]);
+            let same_address = BoolTarget::new_unsafe(layout.same_address(row + 1));
+            self.assert_bool(same_address);
+
+            // The address increases by one, unless it is the same.
+            let address_diff = self.sub(address, prev_address);
+            let new_address = self.not(same_address);
+            self.connect(address_diff, new_address.target);
+
+            // The cell keeps its content between operations.
+            let value_diff = self.sub(old, prev_new);
+            let value_check = self.mul(same_address.target, value_diff);
+            self.assert_zero(value_check);
+
+            // Operations on a cell are sorted by time.
+            let time_diff = self.sub(time, prev_time);
+            let time_gap = self.mul_sub(same_address.target, time_diff, same_address.target);
+            self.add_lookup(&[time_gap], range_lut_index);
+        }
+    }
+}
+
+/// Positions of the contiguous virtual targets holding a copy of a memory: the initial values,
+/// then the address, old value and new value of each operation, along with a target set once the
+/// operation is generated, then, unless the memory is read-only, the rows of the sorted list of
+/// operations and whether each row shares its address with the previous one.
+#[derive(Copy, Clone, Debug, Default)]
+struct MemoryLayout {
+    start: usize,
+    num_cells: usize,
+    num_ops: usize,
+}
+
+impl MemoryLayout {
+    fn new<F: RichField + Extendable<D>, const D: usize>(
+        builder: &mut CircuitBuilder<F, D>,
+        num_cells: usize,
+        num_ops: usize,
+        read_only: bool,
+    ) -> Self {
+        let num_rows = num_cells + num_ops;
+        let num_sorted_targets = if read_only { 0 } else { 5 * num_rows - 1 };
+        let targets = builder.add_virtual_targets(num_cells + 4 * num_ops + num_sorted_targets);
+        let start = match targets[0] {
+            Target::VirtualTarget { index } => index,
+            Target::Wire(_) => unreachable!(),
+        };
+
+        Self {
+            start,
+            num_cells,
+            num_ops,
+        }
+    }
+
+    const fn num_rows(&self) -> usize {
+        self.num_cells + self.num_ops
+    }
+
+    const fn target(&self, offset: usize) -> Target {
+        Target::VirtualTarget {
+            index: self.start + offset,
+        }
+    }
+
+    const fn initial_value(&self, address: usize) -> Target {
+        self.target(address)
+    }
+
+    const fn address(&self, op: usize) -> Target {
+        self.target(self.num_cells + 4 * op)
+    }
+
+    const fn old_value(&self, op: usize) -> Target {
+        self.target(self.num_cells + 4 * op + 1)
+    }
+
+    const fn new_value(&self, op: usize) -> Target {
+        self.target(self.num_cells + 4 * op + 2)
+    }
+
+    /// Set by `MemoryGenerator` once the operation is generated. Unlike the values read, which may
+    /// also be set elsewhere, it tells where the generation of the operations stopped.
+    const fn generated(&self, op: usize) -> Target {
+        self.target(self.num_cells + 4 * op + 3)
+    }
+
+    /// The `col`-th column of the `row`-th sorted tuple `(address, time, old value, new value)`.
+    const fn sorted(&self, row: usize, col: usize) -> Target {
+        self.target(self.num_cells + 4 * self.num_ops + 4 * row + col)
+    }
+
+    /// Whether the `row`-th sorted tuple has the same address as the previous one. `row` must be
+    /// positive.
+    const fn same_address(&self, row: usize) -> Target {
+        self.target(self.num_cells + 4 * self.num_ops + 4 * self.num_rows() + row - 1)
+    }
+
+    fn read(src: &mut Buffer) -> IoResult<Self> {
+        Ok(Self {
+            start: src.read_usize()?,
+            num_cells: src.read_usize()?,
+            num_ops: src.read_usize()?,
+        })
+    }
+
+    fn write(&self, dst: &mut Vec<u8>) -> IoResult<()> {
+        dst.write_usize(self.start)?;
+        dst.write_usize(self.num_cells)?;
+        dst.write_usize(self.num_ops)
+    }
+}
+
+fn address_value<F: PrimeField64>(address: F, num_cells: usize) -> usize {
+    usize::try_from(address.to_canonical_u64())
+        .ok()
+        .filter(|&address| address < num_cells)
+        .unwrap_or_else(|| panic!("Address {address} is out of a memory of {num_cells} cells"))
+}
+
+/// Computes the content of the cell before each operation, going through the operations in order
+/// while keeping track of the content of the cells. The address or value of an operation may
+/// depend on earlier reads, so each run generates the operations whose inputs are known, and the
+/// next one resumes from the first operation left.
+#[derive(Debug, Default)]
+pub struct MemoryGenerator {
+    layout: MemoryLayout,
+    is_write: Vec<bool>,
+}
+
+impl MemoryGenerator {
+    /// Whether the address of the `k`-th operation, and its value for a write, are known.
+    fn is_ready<F: Field>(&self, witness: &PartitionWitness<F>, k: usize) -> bool {
+        witness.contains(self.layout.address(k))
+            && (!self.is_write[k] || witness.contains(self.layout.new_value(k)))
+    }
+
+    /// The number of operations generated in earlier runs.
+    fn num_generated<F: Field>(&self, witness: &PartitionWitness<F>) -> usize {
+        let (mut low, mut high) = (0, self.layout.num_ops);
+        while low < high {
+            let mid = (low + high) / 2;
+            if witness.contains(self.layout.generated(mid)) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for MemoryGenerator {
+    fn id(&self) -> String {
+        "MemoryGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        let layout = &self.layout;
+        let mut deps = (0..layout.num_cells)
+            .map(|i| layout.initial_value(i))
+            .collect_vec();
+        for (k, &is_write) in self.is_write.iter().enumerate() {
+            deps.push(layout.address(k));
+            if is_write {
+                deps.push(layout.new_value(k));
+            }
+        }
+        deps
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let finished = self.try_run(witness, out_buffer);
+        debug_assert!(finished);
+    }
+
+    fn try_run(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) -> bool {
+        let layout = &self.layout;
+        let first = self.num_generated(witness);
+        if first == layout.num_ops {
+            return true;
+        }
+        let initial_values = (0..layout.num_cells)
+            .map(|i| layout.initial_value(i))
+            .collect_vec();
+        if !witness.contains_all(&initial_values) || !self.is_ready(witness, first) {
+            return false;
+        }
+
+        // Replay the writes generated in earlier runs.
+        let mut cells = witness.get_targets(&initial_values);
+        for k in (0..first).filter(|&k| self.is_write[k]) {
+            let cell = address_value(witness.get_target(layout.address(k)), layout.num_cells);
+            cells[cell] = witness.get_target(layout.new_value(k));
+        }
+
+        for k in first..layout.num_ops {
+            if !self.is_ready(witness, k) {
+                return false;
+            }
+            let cell = address_value(witness.get_target(layout.address(k)), layout.num_cells);
+            out_buffer.set_target(layout.old_value(k), cells[cell]);
+            out_buffer.set_target(layout.generated(k), F::ZERO);
+            if self.is_write[k] {
+                cells[cell] = witness.get_target(layout.new_value(k));
+            }
+        }
+        true
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        self.layout.write(dst)?;
+        for &is_write in &self.is_write {
+            dst.write_bool(is_write)?;
+        }
+        Ok(())
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let layout = MemoryLayout::read(src)?;
+        let is_write = (0..layout.num_ops)
+            .map(|_| src.read_bool())
+            .collect::<IoResult<_>>()?;
+        Ok(Self { layout, is_write })
+    }
+}
+
+/// Sorts the initializations and operations of a memory by address, then by time.
+#[derive(Debug, Default)]
+pub struct MemorySortGenerator {
+    layout: MemoryLayout,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for MemorySortGenerator {
+    fn id(&self) -> String {
+        "MemorySortGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        let layout = &self.layout;
+        (0..layout.num_cells)
+            .map(|i| layout.initial_value(i))
+            .chain(
+                (0..layout.num_ops)
+                    .flat_map(|k| [layout.address(k), layout.old_value(k), layout.new_value(k)]),
+            )
+            .collect()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let layout = &self.layout;
+        let initializations = (0..layout.num_cells).map(|i| {
+            let value = witness.get_target(layout.initial_value(i));
+            (i, i, F::ZERO, value)
+        });
+        let ops = (0..layout.num_ops).map(|k| {
+            let address = witness.get_target(layout.address(k));
+            (
+                address_value(address, layout.num_cells),
+                layout.num_cells + k,
+                witness.get_target(layout.old_value(k)),
+                witness.get_target(layout.new_value(k)),
+            )
+        });
+        let sorted = initializations
+            .chain(ops)
+            .sorted_by_key(|&(address, time, _, _)| (address, time))
+            .collect_vec();
+
+        for (row, &(address, time, old_value, new_value)) in sorted.iter().enumerate() {
+            let values = [
+                F::from_canonical_usize(address),
+                F::from_canonical_usize(time),
+                old_value,
+                new_value,
+            ];
+            for (col, value) in values.into_iter().enumerate() {
+                out_buffer.set_target(layout.sorted(row, col), value);
+            }
+            if row > 0 {
+                let same_address = address == sorted[row - 1].0;
+                out_buffer.set_bool_target(
+                    BoolTarget::new_unsafe(layout.same_address(row)),
+                    same_address,
+                );
+            }
+        }
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        self.layout.write(dst)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        Ok(Self {
+            layout: MemoryLayout::read(src)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::marker::PhantomData;
+
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::{Field, Sample};
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::util::serialization::{DefaultGateSerializer, DefaultGeneratorSerializer};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_read_only_memory() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let values = F::rand_vec(1000);
+        let value_targets = builder.add_virtual_targets(values.len());
+        let memory = builder.add_memory(&value_targets);
+        let addresses = [0, 999, 500, 500, 17];
+        for address in addresses {
+            let address_target = builder.constant(F::from_canonical_usize(address));
+            let value = builder.memory_read(memory, address_target);
+            let expected = builder.constant(values[address]);
+            builder.connect(value, expected);
+        }
+        // A read can depend on a previous one.
+        let address = builder.constant(F::from_canonical_usize(3));
+        let value = builder.memory_read(memory, address);
+        let one = builder.one();
+        let one_hundred = builder.constant(F::from_canonical_usize(100));
+        let address = builder.select(BoolTarget::new_unsafe(one), one_hundred, value);
+        let value = builder.memory_read(memory, address);
+        let expected = builder.constant(values[100]);
+        builder.connect(value, expected);
+
+        let mut pw = PartialWitness::new();
+        pw.set_target_arr(&value_targets, &values);
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    /// Builds a circuit incrementing the cells at `addresses` of a memory of `num_cells` zeros,
+    /// then reading them back.
+    fn counters_circuit(
+        num_cells: usize,
+        addresses: &[usize],
+    ) -> (CircuitBuilder<F, D>, Vec<Target>, Vec<Target>) {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let zero = builder.zero();
+        let memory = builder.add_memory(&vec![zero; num_cells]);
+        let address_targets = builder.add_virtual_targets(addresses.len());
+        for &address in &address_targets {
+            let count = builder.memory_read(memory, address);
+            let incremented = builder.add_const(count, F::ONE);
+            builder.memory_write(memory, address, incremented);
+        }
+        let counts = (0..num_cells)
+            .map(|i| {
+                let address = builder.constant(F::from_canonical_usize(i));
+                builder.memory_read(memory, address)
+            })
+            .collect();
+
+        (builder, address_targets, counts)
+    }
+
+    #[test]
+    fn test_read_write_memory() -> Result<()> {
+        let addresses = [3, 1, 3, 3, 0, 7, 1];
+        let (mut builder, address_targets, counts) = counters_circuit(8, &addresses);
+        for (i, &count) in counts.iter().enumerate() {
+            let expected = addresses.iter().filter(|&&address| address == i).count();
+            let expected = builder.constant(F::from_canonical_usize(expected));
+            builder.connect(count, expected);
+        }
+
+        let data = builder.build::<C>();
+
+        // The memory generators can be serialized.
+        let generator_serializer = DefaultGeneratorSerializer::<C, D> {
+            _phantom: PhantomData,
+        };
+        let bytes = data.to_bytes(&DefaultGateSerializer, &generator_serializer)?;
+        let data_from_bytes = CircuitData::<F, C, D>::from_bytes(
+            &bytes,
+            &DefaultGateSerializer,
+            &generator_serializer,
+        )?;
+
+        let mut pw = PartialWitness::new();
+        for (&target, &address) in address_targets.iter().zip(&addresses) {
+            pw.set_target(target, F::from_canonical_usize(address));
+        }
+        let proof = data_from_bytes.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_memory_address_out_of_bounds() {
+        let (builder, address_targets, _) = counters_circuit(4, &[4]);
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(address_targets[0], F::from_canonical_usize(4));
+        let data = builder.build::<C>();
+        data.prove(pw).unwrap();
+    }
+}
@@ -11,6 +11,9 @@ use crate::util::log2_strict;
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// Checks that a `Target` matches a vector at a particular index.
+    ///
+    /// The size of the vector must be a power of two, and the cost grows with it. To access large
+    /// vectors, or to access the same vector many times, see `add_memory`.
     pub fn random_access(&mut self, access_index: Target, v: Vec<Target>) -> Target {
         let vec_size = v.len();
         let bits = log2_strict(vec_size);
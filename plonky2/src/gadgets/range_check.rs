@@ -3,6 +3,7 @@ use alloc::vec;
 use alloc::vec::Vec;
 
 use crate::field::extension::Extendable;
+use crate::gates::lookup_table::LookupTable;
 use crate::hash::hash_types::RichField;
 use crate::iop::generator::{GeneratedValues, SimpleGenerator};
 use crate::iop::target::{BoolTarget, Target};
@@ -11,10 +12,53 @@ use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::CommonCircuitData;
 use crate::util::serialization::{Buffer, IoResult, Read, Write};
 
+/// The number of bits in each limb of a lookup-based range check.
+pub const RANGE_CHECK_LIMB_BITS: usize = 16;
+
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
-    /// Checks that `x < 2^n_log` using a `BaseSumGate`.
+    /// Checks that `x < 2^n_log`, using either a `BaseSumGate` or, if
+    /// `CircuitConfig::use_lookup_range_checks` is set, lookups into a shared table of limbs.
     pub fn range_check(&mut self, x: Target, n_log: usize) {
-        self.split_le(x, n_log);
+        if self.config.use_lookup_range_checks {
+            self.range_check_with_lookups(x, n_log);
+        } else {
+            self.split_le(x, n_log);
+        }
+    }
+
+    /// Checks that `x < 2^n_log` by splitting it into `RANGE_CHECK_LIMB_BITS`-bit limbs, each of
+    /// which is looked up in the table `[0, 2^RANGE_CHECK_LIMB_BITS)`.
+    fn range_check_with_lookups(&mut self, x: Target, n_log: usize) {
+        if n_log > RANGE_CHECK_LIMB_BITS {
+            // The low limb is checked against the table, and the high part recursively.
+            self.split_low_high(x, RANGE_CHECK_LIMB_BITS, n_log);
+            return;
+        }
+
+        let lut_index = self.range_check_lut();
+        self.add_lookup(&[x], lut_index);
+        if n_log < RANGE_CHECK_LIMB_BITS {
+            // Since `x < 2^16`, the shifted value cannot wrap around, and it fits in the table
+            // exactly when `x < 2^n_log`.
+            let shift = F::from_canonical_u64(1 << (RANGE_CHECK_LIMB_BITS - n_log));
+            let shifted = self.mul_const(shift, x);
+            self.add_lookup(&[shifted], lut_index);
+        }
+    }
+
+    /// Returns the index of the table `[0, 2^RANGE_CHECK_LIMB_BITS)`, adding it on first use.
+    fn range_check_lut(&mut self) -> usize {
+        if let Some(lut_index) = self.range_check_lut {
+            return lut_index;
+        }
+        let table = LookupTable::new(
+            (0..1u64 << RANGE_CHECK_LIMB_BITS)
+                .map(|i| vec![F::from_canonical_u64(i)])
+                .collect(),
+        );
+        let lut_index = self.add_lookup_table(table);
+        self.range_check_lut = Some(lut_index);
+        lut_index
     }
 
     /// Returns the first `num_low_bits` little-endian bits of `x`.
@@ -100,3 +144,62 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for Low
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn lookup_range_check_config() -> CircuitConfig {
+        CircuitConfig {
+            use_lookup_range_checks: true,
+            ..CircuitConfig::standard_recursion_config()
+        }
+    }
+
+    #[test]
+    fn test_lookup_range_check() -> Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(lookup_range_check_config());
+        let mut pw = PartialWitness::new();
+        let mut rng = OsRng;
+        for n_log in [0, 1, 7, 16, 17, 32, 40, 63] {
+            let max = (1u64 << n_log) - 1;
+            for value in [0, max, rng.gen_range(0..=max)] {
+                let x = builder.add_virtual_target();
+                builder.range_check(x, n_log);
+                pw.set_target(x, F::from_canonical_u64(value));
+            }
+        }
+        // Every check shares a single table.
+        assert_eq!(builder.num_luts(), 1);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_lookup_range_check_out_of_range() {
+        let mut builder = CircuitBuilder::<F, D>::new(lookup_range_check_config());
+        let x = builder.add_virtual_target();
+        builder.range_check(x, 10);
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(1 << 10));
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw).unwrap();
+        data.verify(proof).unwrap();
+    }
+}
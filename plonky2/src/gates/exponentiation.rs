@@ -2,26 +2,19 @@ use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use alloc::{format, vec};
 use core::marker::PhantomData;
+use core::ops::Index;
 
 use crate::field::extension::Extendable;
-use crate::field::ops::Square;
-use crate::field::packed::PackedField;
-use crate::field::types::Field;
+use crate::gates::expression_gate::{ExpressionGate, ExpressionVars};
 use crate::gates::gate::Gate;
-use crate::gates::packed_util::PackedEvaluableBase;
-use crate::gates::util::StridedConstraintConsumer;
 use crate::hash::hash_types::RichField;
-use crate::iop::ext_target::ExtensionTarget;
+use crate::impl_expression_gate;
 use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGeneratorRef};
 use crate::iop::target::Target;
 use crate::iop::wire::Wire;
 use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
-use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::{CircuitConfig, CommonCircuitData};
-use crate::plonk::vars::{
-    EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
-    EvaluationVarsBasePacked,
-};
+use crate::plonk::expression::ExpressionBuilder;
 use crate::util::serialization::{Buffer, IoResult, Read, Write};
 
 /// A gate for raising a value to a power.
@@ -85,93 +78,7 @@ impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for Exponentiation
         Ok(Self::new(num_power_bits))
     }
 
-    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
-        let base = vars.local_wires[self.wire_base()];
-
-        let power_bits: Vec<_> = (0..self.num_power_bits)
-            .map(|i| vars.local_wires[self.wire_power_bit(i)])
-            .collect();
-        let intermediate_values: Vec<_> = (0..self.num_power_bits)
-            .map(|i| vars.local_wires[self.wire_intermediate_value(i)])
-            .collect();
-
-        let output = vars.local_wires[self.wire_output()];
-
-        let mut constraints = Vec::with_capacity(self.num_constraints());
-
-        for i in 0..self.num_power_bits {
-            let prev_intermediate_value = if i == 0 {
-                F::Extension::ONE
-            } else {
-                intermediate_values[i - 1].square()
-            };
-
-            // power_bits is in LE order, but we accumulate in BE order.
-            let cur_bit = power_bits[self.num_power_bits - i - 1];
-
-            let not_cur_bit = F::Extension::ONE - cur_bit;
-            let computed_intermediate_value =
-                prev_intermediate_value * (cur_bit * base + not_cur_bit);
-            constraints.push(computed_intermediate_value - intermediate_values[i]);
-        }
-
-        constraints.push(output - intermediate_values[self.num_power_bits - 1]);
-
-        constraints
-    }
-
-    fn eval_unfiltered_base_one(
-        &self,
-        _vars: EvaluationVarsBase<F>,
-        _yield_constr: StridedConstraintConsumer<F>,
-    ) {
-        panic!("use eval_unfiltered_base_packed instead");
-    }
-
-    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
-        self.eval_unfiltered_base_batch_packed(vars_base)
-    }
-
-    fn eval_unfiltered_circuit(
-        &self,
-        builder: &mut CircuitBuilder<F, D>,
-        vars: EvaluationTargets<D>,
-    ) -> Vec<ExtensionTarget<D>> {
-        let base = vars.local_wires[self.wire_base()];
-
-        let power_bits: Vec<_> = (0..self.num_power_bits)
-            .map(|i| vars.local_wires[self.wire_power_bit(i)])
-            .collect();
-        let intermediate_values: Vec<_> = (0..self.num_power_bits)
-            .map(|i| vars.local_wires[self.wire_intermediate_value(i)])
-            .collect();
-
-        let output = vars.local_wires[self.wire_output()];
-
-        let mut constraints = Vec::with_capacity(self.num_constraints());
-
-        let one = builder.one_extension();
-        for i in 0..self.num_power_bits {
-            let prev_intermediate_value = if i == 0 {
-                one
-            } else {
-                builder.square_extension(intermediate_values[i - 1])
-            };
-
-            // power_bits is in LE order, but we accumulate in BE order.
-            let cur_bit = power_bits[self.num_power_bits - i - 1];
-            let mul_by = builder.select_ext_generalized(cur_bit, base, one);
-            let intermediate_value_diff =
-                builder.mul_sub_extension(prev_intermediate_value, mul_by, intermediate_values[i]);
-            constraints.push(intermediate_value_diff);
-        }
-
-        let output_diff =
-            builder.sub_extension(output, intermediate_values[self.num_power_bits - 1]);
-        constraints.push(output_diff);
-
-        constraints
-    }
+    impl_expression_gate!();
 
     fn generators(&self, row: usize, _local_constants: &[F]) -> Vec<WitnessGeneratorRef<F, D>> {
         let gen = ExponentiationGenerator::<F, D> {
@@ -198,14 +105,18 @@ impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for Exponentiation
     }
 }
 
-impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D>
+impl<F: RichField + Extendable<D>, const D: usize> ExpressionGate<F, D>
     for ExponentiationGate<F, D>
 {
-    fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
+    fn eval_constraints<B, V>(
         &self,
-        vars: EvaluationVarsBasePacked<P>,
-        mut yield_constr: StridedConstraintConsumer<P>,
-    ) {
+        builder: &mut B,
+        vars: ExpressionVars<V, B::Expr>,
+        yield_constr: &mut impl FnMut(B::Expr),
+    ) where
+        B: ExpressionBuilder<F, D>,
+        V: Index<usize, Output = B::Expr> + ?Sized,
+    {
         let base = vars.local_wires[self.wire_base()];
 
         let power_bits: Vec<_> = (0..self.num_power_bits)
@@ -217,23 +128,21 @@ impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D>
 
         let output = vars.local_wires[self.wire_output()];
 
+        let one = builder.one();
         for i in 0..self.num_power_bits {
             let prev_intermediate_value = if i == 0 {
-                P::ONES
+                one
             } else {
-                intermediate_values[i - 1].square()
+                builder.square(intermediate_values[i - 1])
             };
 
             // power_bits is in LE order, but we accumulate in BE order.
             let cur_bit = power_bits[self.num_power_bits - i - 1];
-
-            let not_cur_bit = P::ONES - cur_bit;
-            let computed_intermediate_value =
-                prev_intermediate_value * (cur_bit * base + not_cur_bit);
-            yield_constr.one(computed_intermediate_value - intermediate_values[i]);
+            let mul_by = builder.select(cur_bit, base, one);
+            yield_constr(builder.mul_sub(prev_intermediate_value, mul_by, intermediate_values[i]));
         }
 
-        yield_constr.one(output - intermediate_values[self.num_power_bits - 1]);
+        yield_constr(builder.sub(output, intermediate_values[self.num_power_bits - 1]));
     }
 }
 
@@ -315,10 +224,11 @@ mod tests {
 
     use super::*;
     use crate::field::goldilocks_field::GoldilocksField;
-    use crate::field::types::Sample;
+    use crate::field::types::{Field, Sample};
     use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
     use crate::hash::hash_types::HashOut;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::vars::EvaluationVars;
     use crate::util::log2_ceil;
 
     const MAX_POWER_BITS: usize = 17;
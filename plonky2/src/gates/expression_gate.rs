@@ -0,0 +1,145 @@
+use alloc::vec::Vec;
+use core::ops::Index;
+
+use crate::field::extension::Extendable;
+use crate::field::packed::PackedField;
+use crate::gates::gate::Gate;
+use crate::gates::packed_util::eval_base_batch_packed;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::{RichField, NUM_HASH_OUT_ELTS};
+use crate::iop::ext_target::ExtensionTarget;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::expression::{ExpressionBuilder, PackedExpressionBuilder};
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBaseBatch, EvaluationVarsBasePacked,
+};
+
+/// The constants and wires of a gate, as expressions of an [`ExpressionBuilder`]. `V` is the
+/// container the constants and wires are read from, e.g. a slice.
+pub struct ExpressionVars<'a, V: ?Sized, T> {
+    pub local_constants: &'a V,
+    pub local_wires: &'a V,
+    pub public_inputs_hash: [T; NUM_HASH_OUT_ELTS],
+}
+
+/// A gate whose constraints are defined once, over an [`ExpressionBuilder`].
+///
+/// The evaluators of [`Gate`] are then derived from `eval_constraints` by invoking
+/// [`impl_expression_gate!`](crate::impl_expression_gate) in the gate's `Gate` impl.
+pub trait ExpressionGate<F: RichField + Extendable<D>, const D: usize>: Gate<F, D> {
+    /// Evaluates the constraints of this gate, passing each of them to `yield_constr`.
+    fn eval_constraints<B, V>(
+        &self,
+        builder: &mut B,
+        vars: ExpressionVars<V, B::Expr>,
+        yield_constr: &mut impl FnMut(B::Expr),
+    ) where
+        B: ExpressionBuilder<F, D>,
+        V: Index<usize, Output = B::Expr> + ?Sized;
+
+    fn eval_unfiltered_from_constraints(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        let vars = ExpressionVars {
+            local_constants: vars.local_constants,
+            local_wires: vars.local_wires,
+            public_inputs_hash: vars.public_inputs_hash.elements.map(F::Extension::from),
+        };
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+        self.eval_constraints(
+            &mut PackedExpressionBuilder::<F::Extension, D>::new(),
+            vars,
+            &mut |constraint| constraints.push(constraint),
+        );
+        constraints
+    }
+
+    fn eval_unfiltered_circuit_from_constraints(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let vars = ExpressionVars {
+            local_constants: vars.local_constants,
+            local_wires: vars.local_wires,
+            public_inputs_hash: vars
+                .public_inputs_hash
+                .elements
+                .map(|t| builder.convert_to_ext(t)),
+        };
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+        self.eval_constraints(builder, vars, &mut |constraint| {
+            constraints.push(constraint)
+        });
+        constraints
+    }
+
+    fn eval_unfiltered_base_packed_from_constraints<P: PackedField<Scalar = F>>(
+        &self,
+        vars_base: EvaluationVarsBasePacked<P>,
+        mut yield_constr: StridedConstraintConsumer<P>,
+    ) {
+        let vars = ExpressionVars {
+            local_constants: &vars_base.local_constants,
+            local_wires: &vars_base.local_wires,
+            public_inputs_hash: vars_base.public_inputs_hash.elements.map(P::from),
+        };
+        self.eval_constraints(
+            &mut PackedExpressionBuilder::<P, 1>::new(),
+            vars,
+            &mut |constraint| yield_constr.one(constraint),
+        );
+    }
+
+    /// Evaluates an entire batch of points, packing them as
+    /// [`PackedEvaluableBase`](crate::gates::packed_util::PackedEvaluableBase) does.
+    fn eval_unfiltered_base_batch_from_constraints(
+        &self,
+        vars_batch: EvaluationVarsBaseBatch<F>,
+    ) -> Vec<F> {
+        eval_base_batch_packed(
+            self.num_constraints(),
+            vars_batch,
+            |vars_packed, yield_constr| {
+                self.eval_unfiltered_base_packed_from_constraints(vars_packed, yield_constr)
+            },
+            |vars_leftovers, yield_constr| {
+                self.eval_unfiltered_base_packed_from_constraints(vars_leftovers, yield_constr)
+            },
+        )
+    }
+}
+
+/// Implements the evaluators of [`Gate`] for an [`ExpressionGate`], from its `eval_constraints`.
+/// This should be invoked in the gate's `Gate` impl, where `F` and `D` are in scope, which then
+/// only defines the other methods of `Gate`.
+#[macro_export]
+macro_rules! impl_expression_gate {
+    () => {
+        fn eval_unfiltered(
+            &self,
+            vars: $crate::plonk::vars::EvaluationVars<F, D>,
+        ) -> $crate::alloc::vec::Vec<F::Extension> {
+            $crate::gates::expression_gate::ExpressionGate::eval_unfiltered_from_constraints(
+                self, vars,
+            )
+        }
+
+        fn eval_unfiltered_base_batch(
+            &self,
+            vars_base: $crate::plonk::vars::EvaluationVarsBaseBatch<F>,
+        ) -> $crate::alloc::vec::Vec<F> {
+            $crate::gates::expression_gate::ExpressionGate::eval_unfiltered_base_batch_from_constraints(
+                self, vars_base,
+            )
+        }
+
+        fn eval_unfiltered_circuit(
+            &self,
+            builder: &mut $crate::plonk::circuit_builder::CircuitBuilder<F, D>,
+            vars: $crate::plonk::vars::EvaluationTargets<D>,
+        ) -> $crate::alloc::vec::Vec<$crate::iop::ext_target::ExtensionTarget<D>> {
+            $crate::gates::expression_gate::ExpressionGate::eval_unfiltered_circuit_from_constraints(
+                self, builder, vars,
+            )
+        }
+    };
+}
@@ -3,9 +3,6 @@ use alloc::vec::Vec;
 use alloc::{format, vec};
 use core::usize;
 
-use itertools::Itertools;
-use keccak_hash::keccak;
-
 use super::lookup_table::LookupTable;
 use crate::field::extension::Extendable;
 use crate::field::packed::PackedField;
@@ -25,57 +22,60 @@ use crate::plonk::vars::{
 };
 use crate::util::serialization::{Buffer, IoResult, Read, Write};
 
-pub type Lookup = Vec<(Target, Target)>;
+/// The looked up tuples of a lookup table.
+pub type Lookup = Vec<Vec<Target>>;
 
-/// A gate which stores (input, output) lookup pairs made elsewhere in the trace. It doesn't check any constraints itself.
+/// A gate which stores tuples looked up elsewhere in the trace. It doesn't check any constraints itself.
+///
+/// Each slot holds one tuple, padded with zeros to the width of the widest table of the circuit.
 #[derive(Debug, Clone)]
-pub struct LookupGate {
+pub struct LookupGate<F> {
     /// Number of lookups per gate.
     pub num_slots: usize,
+    /// Number of columns of the lookup argument, i.e. the width of the widest table of the circuit.
+    pub width: usize,
     /// LUT associated to the gate.
-    lut: LookupTable,
+    lut: LookupTable<F>,
     /// The Keccak hash of the lookup table.
     lut_hash: [u8; 32],
 }
 
-impl LookupGate {
-    pub fn new_from_table(config: &CircuitConfig, lut: LookupTable) -> Self {
-        let table_bytes = lut
-            .iter()
-            .flat_map(|(input, output)| [input.to_le_bytes(), output.to_le_bytes()].concat())
-            .collect_vec();
-
+impl<F: RichField> LookupGate<F> {
+    pub fn new_from_table(config: &CircuitConfig, width: usize, lut: LookupTable<F>) -> Self {
         Self {
-            num_slots: Self::num_slots(config),
+            num_slots: Self::num_slots(config, width),
+            width,
+            lut_hash: lut.keccak_hash(),
             lut,
-            lut_hash: keccak(table_bytes).0,
         }
     }
-    pub(crate) const fn num_slots(config: &CircuitConfig) -> usize {
-        let wires_per_lookup = 2;
-        config.num_routed_wires / wires_per_lookup
-    }
+}
 
-    pub const fn wire_ith_looking_inp(i: usize) -> usize {
-        2 * i
+impl<F> LookupGate<F> {
+    pub(crate) const fn num_slots(config: &CircuitConfig, width: usize) -> usize {
+        let wires_per_lookup = width;
+        config.num_routed_wires / wires_per_lookup
     }
 
-    pub const fn wire_ith_looking_out(i: usize) -> usize {
-        2 * i + 1
+    /// Wire for the `col`-th column of the looking tuple in the `i`-th slot.
+    pub const fn wire_ith_looking(width: usize, i: usize, col: usize) -> usize {
+        debug_assert!(col < width);
+        width * i + col
     }
 }
 
-impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for LookupGate {
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for LookupGate<F> {
     fn id(&self) -> String {
         // Custom implementation to not have the entire lookup table
         format!(
-            "LookupGate {{num_slots: {}, lut_hash: {:?}}}",
-            self.num_slots, self.lut_hash
+            "LookupGate {{num_slots: {}, width: {}, lut_hash: {:?}}}",
+            self.num_slots, self.width, self.lut_hash
         )
     }
 
     fn serialize(&self, dst: &mut Vec<u8>, common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
         dst.write_usize(self.num_slots)?;
+        dst.write_usize(self.width)?;
         for (i, lut) in common_data.luts.iter().enumerate() {
             if lut == &self.lut {
                 dst.write_usize(i)?;
@@ -88,12 +88,14 @@ impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for LookupGate {
 
     fn deserialize(src: &mut Buffer, common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
         let num_slots = src.read_usize()?;
+        let width = src.read_usize()?;
         let lut_index = src.read_usize()?;
         let mut lut_hash = [0u8; 32];
         src.read_exact(&mut lut_hash)?;
 
         Ok(Self {
             num_slots,
+            width,
             lut: common_data.luts[lut_index].clone(),
             lut_hash,
         })
@@ -125,23 +127,14 @@ impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for LookupGate {
         vec![]
     }
 
-    fn generators(&self, row: usize, _local_constants: &[F]) -> Vec<WitnessGeneratorRef<F, D>> {
-        (0..self.num_slots)
-            .map(|i| {
-                WitnessGeneratorRef::new(
-                    LookupGenerator {
-                        row,
-                        lut: self.lut.clone(),
-                        slot_nb: i,
-                    }
-                    .adapter(),
-                )
-            })
-            .collect()
+    fn generators(&self, _row: usize, _local_constants: &[F]) -> Vec<WitnessGeneratorRef<F, D>> {
+        // The wires are copies of the looked up targets, or padding set by the prover. Outputs of
+        // lookups are computed by `LookupGenerator`s, added along with the lookups.
+        vec![]
     }
 
     fn num_wires(&self) -> usize {
-        self.num_slots * 2
+        self.num_slots * self.width
     }
 
     fn num_constants(&self) -> usize {
@@ -157,7 +150,7 @@ impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for LookupGate {
     }
 }
 
-impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D> for LookupGate {
+impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D> for LookupGate<F> {
     fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
         &self,
         _vars: EvaluationVarsBasePacked<P>,
@@ -166,53 +159,37 @@ impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D> for
     }
 }
 
+/// Computes the output of a lookup, i.e. the last column of the row of `lut` whose other columns
+/// are equal to `inputs`.
 #[derive(Clone, Debug, Default)]
-pub struct LookupGenerator {
-    row: usize,
-    lut: LookupTable,
-    slot_nb: usize,
+pub struct LookupGenerator<F> {
+    pub(crate) inputs: Vec<Target>,
+    pub(crate) output: Target,
+    pub(crate) lut: LookupTable<F>,
 }
 
-impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for LookupGenerator {
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for LookupGenerator<F> {
     fn id(&self) -> String {
         "LookupGenerator".to_string()
     }
 
     fn dependencies(&self) -> Vec<Target> {
-        vec![Target::wire(
-            self.row,
-            LookupGate::wire_ith_looking_inp(self.slot_nb),
-        )]
+        self.inputs.clone()
     }
 
     fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
-        let get_wire = |wire: usize| -> F { witness.get_target(Target::wire(self.row, wire)) };
-
-        let input_val = get_wire(LookupGate::wire_ith_looking_inp(self.slot_nb));
-        let (input, output) = self.lut[input_val.to_canonical_u64() as usize];
-        if input_val == F::from_canonical_u16(input) {
-            let output_val = F::from_canonical_u16(output);
-
-            let out_wire = Target::wire(self.row, LookupGate::wire_ith_looking_out(self.slot_nb));
-            out_buffer.set_target(out_wire, output_val);
-        } else {
-            for (input, output) in self.lut.iter() {
-                if input_val == F::from_canonical_u16(*input) {
-                    let output_val = F::from_canonical_u16(*output);
-
-                    let out_wire =
-                        Target::wire(self.row, LookupGate::wire_ith_looking_out(self.slot_nb));
-                    out_buffer.set_target(out_wire, output_val);
-                    return;
-                }
-            }
-            panic!("Incorrect input value provided");
-        };
+        let input_vals = witness.get_targets(&self.inputs);
+        let row = self
+            .lut
+            .find_row(&input_vals)
+            .expect("Incorrect input value provided");
+
+        out_buffer.set_target(self.output, row[input_vals.len()]);
     }
 
     fn serialize(&self, dst: &mut Vec<u8>, common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
-        dst.write_usize(self.row)?;
-        dst.write_usize(self.slot_nb)?;
+        dst.write_target_vec(&self.inputs)?;
+        dst.write_target(self.output)?;
         for (i, lut) in common_data.luts.iter().enumerate() {
             if lut == &self.lut {
                 return dst.write_usize(i);
@@ -223,14 +200,14 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for Loo
     }
 
     fn deserialize(src: &mut Buffer, common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
-        let row = src.read_usize()?;
-        let slot_nb = src.read_usize()?;
+        let inputs = src.read_target_vec()?;
+        let output = src.read_target()?;
         let lut_index = src.read_usize()?;
 
         Ok(Self {
-            row,
+            inputs,
+            output,
             lut: common_data.luts[lut_index].clone(),
-            slot_nb,
         })
     }
 }
@@ -2,14 +2,18 @@ use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use alloc::{format, vec};
+use core::slice::ChunksExact;
 use core::usize;
 
+use hashbrown::HashMap;
 use itertools::Itertools;
 use keccak_hash::keccak;
 use plonky2_util::ceil_div_usize;
+use serde::Serialize;
 
 use crate::field::extension::Extendable;
 use crate::field::packed::PackedField;
+use crate::field::types::Field;
 use crate::gates::gate::Gate;
 use crate::gates::packed_util::PackedEvaluableBase;
 use crate::gates::util::StridedConstraintConsumer;
@@ -26,68 +30,215 @@ use crate::plonk::vars::{
 };
 use crate::util::serialization::{Buffer, IoResult, Read, Write};
 
-pub type LookupTable = Arc<Vec<(u16, u16)>>;
+/// A lookup table: a list of rows, all made of the same number of field elements. A lookup checks
+/// that a tuple of targets is equal to one of the rows.
+///
+/// Functions, like the Tip5 S-box, are stored as (input, output) rows; wider tables can encode
+/// binary operations, e.g. (x, y, x ^ y), or read-only memories, e.g. (address, value).
+///
+/// The rows of a dynamic table aren't fixed when building the circuit: they are targets of the
+/// witness, and only the shape of the table is known.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct LookupTable<F> {
+    /// Number of columns of the table.
+    width: usize,
+    /// Number of rows of the table.
+    len: usize,
+    /// The entries of the table, row by row, or `None` if the table is dynamic.
+    entries: Option<Arc<Vec<F>>>,
+    /// The index of the first row starting with each tuple of inputs, i.e. of all the columns but
+    /// the last one, shared by the clones of the table. `None` if the table is dynamic.
+    #[serde(skip)]
+    row_indices: Option<Arc<HashMap<Vec<F>, usize>>>,
+}
+
+// The row indices are derived from the entries.
+impl<F: PartialEq> PartialEq for LookupTable<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.width == other.width && self.len == other.len && self.entries == other.entries
+    }
+}
+
+impl<F: Eq> Eq for LookupTable<F> {}
+
+impl<F: Field> LookupTable<F> {
+    /// Creates a lookup table from its rows, which must all have the same width.
+    pub fn new(rows: Vec<Vec<F>>) -> Self {
+        assert!(
+            !rows.is_empty(),
+            "A lookup table must have at least one row"
+        );
+        let width = rows[0].len();
+        assert!(width > 0, "A lookup table must have at least one column");
+        assert!(
+            rows.iter().all(|row| row.len() == width),
+            "All the rows of a lookup table must have the same width"
+        );
+
+        let mut row_indices = HashMap::with_capacity(rows.len());
+        for (i, row) in rows.iter().enumerate() {
+            row_indices.entry(row[..width - 1].to_vec()).or_insert(i);
+        }
 
-/// A gate which stores the set of (input, output) value pairs of a lookup table, and their multiplicities.
+        Self {
+            width,
+            len: rows.len(),
+            entries: Some(Arc::new(rows.concat())),
+            row_indices: Some(Arc::new(row_indices)),
+        }
+    }
+
+    /// Creates a two-column lookup table from (input, output) pairs.
+    pub fn from_pairs(pairs: &[(u16, u16)]) -> Self {
+        Self::new(
+            pairs
+                .iter()
+                .map(|&(input, output)| {
+                    vec![F::from_canonical_u16(input), F::from_canonical_u16(output)]
+                })
+                .collect(),
+        )
+    }
+
+    /// Creates a dynamic lookup table with `len` rows of `width` columns, whose entries are
+    /// provided by the witness.
+    pub fn dynamic(width: usize, len: usize) -> Self {
+        assert!(len > 0, "A lookup table must have at least one row");
+        assert!(width > 0, "A lookup table must have at least one column");
+
+        Self {
+            width,
+            len,
+            entries: None,
+            row_indices: None,
+        }
+    }
+
+    /// Whether the entries of the table are witness values rather than constants.
+    pub const fn is_dynamic(&self) -> bool {
+        self.entries.is_none()
+    }
+
+    /// Number of columns of the table.
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of rows of the table.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the `i`-th row of the table. Panics if the table is dynamic.
+    pub fn row(&self, i: usize) -> &[F] {
+        &self.entries()[i * self.width..(i + 1) * self.width]
+    }
+
+    /// Iterates over the rows of the table. Panics if the table is dynamic.
+    pub fn rows(&self) -> ChunksExact<'_, F> {
+        self.entries().chunks_exact(self.width)
+    }
+
+    /// Returns the first row of the table starting with `inputs`, whose length must be one less
+    /// than the width of the table. Panics if the table is dynamic.
+    pub fn find_row(&self, inputs: &[F]) -> Option<&[F]> {
+        let row_indices = self
+            .row_indices
+            .as_deref()
+            .expect("The entries of a dynamic lookup table are only known to the prover");
+        row_indices.get(inputs).map(|&i| self.row(i))
+    }
+
+    fn entries(&self) -> &[F] {
+        self.entries
+            .as_deref()
+            .expect("The entries of a dynamic lookup table are only known to the prover")
+    }
+}
+
+impl<F: RichField> LookupTable<F> {
+    /// The Keccak hash of the table, used to identify it in gate IDs.
+    pub(crate) fn keccak_hash(&self) -> [u8; 32] {
+        let table_bytes = match &self.entries {
+            Some(entries) => entries
+                .iter()
+                .flat_map(|x| x.to_canonical_u64().to_le_bytes())
+                .collect_vec(),
+            None => [b"dynamic".as_slice(), &self.len.to_le_bytes()].concat(),
+        };
+        keccak([self.width.to_le_bytes().as_slice(), &table_bytes].concat()).0
+    }
+}
+
+/// A gate which stores the rows of a lookup table, and their multiplicities.
+///
+/// Each slot holds one row, padded with zeros to the width of the widest table of the circuit,
+/// followed by its multiplicity.
 #[derive(Debug, Clone)]
-pub struct LookupTableGate {
+pub struct LookupTableGate<F> {
     /// Number of lookup entries per gate.
     pub num_slots: usize,
+    /// Number of columns of the lookup argument, i.e. the width of the widest table of the circuit.
+    pub width: usize,
     /// Lookup table associated to the gate.
-    pub lut: LookupTable,
+    pub lut: LookupTable<F>,
     /// The Keccak hash of the lookup table.
     lut_hash: [u8; 32],
     /// First row of the lookup table.
     last_lut_row: usize,
 }
 
-impl LookupTableGate {
-    pub fn new_from_table(config: &CircuitConfig, lut: LookupTable, last_lut_row: usize) -> Self {
-        let table_bytes = lut
-            .iter()
-            .flat_map(|(input, output)| [input.to_le_bytes(), output.to_le_bytes()].concat())
-            .collect_vec();
-
+impl<F: RichField> LookupTableGate<F> {
+    pub fn new_from_table(
+        config: &CircuitConfig,
+        width: usize,
+        lut: LookupTable<F>,
+        last_lut_row: usize,
+    ) -> Self {
         Self {
-            num_slots: Self::num_slots(config),
+            num_slots: Self::num_slots(config, width),
+            width,
+            lut_hash: lut.keccak_hash(),
             lut,
-            lut_hash: keccak(table_bytes).0,
             last_lut_row,
         }
     }
+}
 
-    pub(crate) const fn num_slots(config: &CircuitConfig) -> usize {
-        let wires_per_entry = 3;
+impl<F> LookupTableGate<F> {
+    pub(crate) const fn num_slots(config: &CircuitConfig, width: usize) -> usize {
+        let wires_per_entry = width + 1;
         config.num_routed_wires / wires_per_entry
     }
 
-    /// Wire for the looked input.
-    pub const fn wire_ith_looked_inp(i: usize) -> usize {
-        3 * i
-    }
-
-    // Wire for the looked output.
-    pub const fn wire_ith_looked_out(i: usize) -> usize {
-        3 * i + 1
+    /// Wire for the `col`-th column of the looked row in the `i`-th slot.
+    pub const fn wire_ith_looked(width: usize, i: usize, col: usize) -> usize {
+        debug_assert!(col < width);
+        (width + 1) * i + col
     }
 
     /// Wire for the multiplicity. Set after the trace has been generated.
-    pub const fn wire_ith_multiplicity(i: usize) -> usize {
-        3 * i + 2
+    pub const fn wire_ith_multiplicity(width: usize, i: usize) -> usize {
+        (width + 1) * i + width
     }
 }
 
-impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for LookupTableGate {
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for LookupTableGate<F> {
     fn id(&self) -> String {
         // Custom implementation to not have the entire lookup table
         format!(
-            "LookupTableGate {{num_slots: {}, lut_hash: {:?}, last_lut_row: {}}}",
-            self.num_slots, self.lut_hash, self.last_lut_row
+            "LookupTableGate {{num_slots: {}, width: {}, lut_hash: {:?}, last_lut_row: {}}}",
+            self.num_slots, self.width, self.lut_hash, self.last_lut_row
         )
     }
 
     fn serialize(&self, dst: &mut Vec<u8>, common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
         dst.write_usize(self.num_slots)?;
+        dst.write_usize(self.width)?;
         dst.write_usize(self.last_lut_row)?;
         for (i, lut) in common_data.luts.iter().enumerate() {
             if lut == &self.lut {
@@ -101,6 +252,7 @@ impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for LookupTableGat
 
     fn deserialize(src: &mut Buffer, common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
         let num_slots = src.read_usize()?;
+        let width = src.read_usize()?;
         let last_lut_row = src.read_usize()?;
         let lut_index = src.read_usize()?;
         let mut lut_hash = [0u8; 32];
@@ -108,6 +260,7 @@ impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for LookupTableGat
 
         Ok(Self {
             num_slots,
+            width,
             lut: common_data.luts[lut_index].clone(),
             lut_hash,
             last_lut_row,
@@ -141,6 +294,11 @@ impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for LookupTableGat
     }
 
     fn generators(&self, row: usize, _local_constants: &[F]) -> Vec<WitnessGeneratorRef<F, D>> {
+        if self.lut.is_dynamic() {
+            // The rows of dynamic tables are copies of targets of the witness.
+            return vec![];
+        }
+
         (0..self.num_slots)
             .map(|i| {
                 WitnessGeneratorRef::new(
@@ -149,6 +307,7 @@ impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for LookupTableGat
                         lut: self.lut.clone(),
                         slot_nb: i,
                         num_slots: self.num_slots,
+                        width: self.width,
                         last_lut_row: self.last_lut_row,
                     }
                     .adapter(),
@@ -158,7 +317,7 @@ impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for LookupTableGat
     }
 
     fn num_wires(&self) -> usize {
-        self.num_slots * 3
+        self.num_slots * (self.width + 1)
     }
 
     fn num_constants(&self) -> usize {
@@ -174,7 +333,9 @@ impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for LookupTableGat
     }
 }
 
-impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D> for LookupTableGate {
+impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D>
+    for LookupTableGate<F>
+{
     fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
         &self,
         _vars: EvaluationVarsBasePacked<P>,
@@ -184,15 +345,18 @@ impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D> for
 }
 
 #[derive(Clone, Debug, Default)]
-pub struct LookupTableGenerator {
+pub struct LookupTableGenerator<F> {
     row: usize,
-    lut: LookupTable,
+    lut: LookupTable<F>,
     slot_nb: usize,
     num_slots: usize,
+    width: usize,
     last_lut_row: usize,
 }
 
-impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for LookupTableGenerator {
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D>
+    for LookupTableGenerator<F>
+{
     fn id(&self) -> String {
         "LookupTableGenerator".to_string()
     }
@@ -205,19 +369,18 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for Loo
         let first_row = self.last_lut_row + ceil_div_usize(self.lut.len(), self.num_slots) - 1;
         let slot = (first_row - self.row) * self.num_slots + self.slot_nb;
 
-        let slot_input_target =
-            Target::wire(self.row, LookupTableGate::wire_ith_looked_inp(self.slot_nb));
-        let slot_output_target =
-            Target::wire(self.row, LookupTableGate::wire_ith_looked_out(self.slot_nb));
-
-        if slot < self.lut.len() {
-            let (input, output) = self.lut[slot];
-            out_buffer.set_target(slot_input_target, F::from_canonical_usize(input as usize));
-            out_buffer.set_target(slot_output_target, F::from_canonical_usize(output as usize));
+        // Rows narrower than `width`, and slots past the end of the table, are padded with zeros.
+        let entries = if slot < self.lut.len() {
+            self.lut.row(slot)
         } else {
-            // Pad with zeros.
-            out_buffer.set_target(slot_input_target, F::ZERO);
-            out_buffer.set_target(slot_output_target, F::ZERO);
+            &[]
+        };
+        for col in 0..self.width {
+            let target = Target::wire(
+                self.row,
+                LookupTableGate::<F>::wire_ith_looked(self.width, self.slot_nb, col),
+            );
+            out_buffer.set_target(target, entries.get(col).copied().unwrap_or(F::ZERO));
         }
     }
 
@@ -225,6 +388,7 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for Loo
         dst.write_usize(self.row)?;
         dst.write_usize(self.slot_nb)?;
         dst.write_usize(self.num_slots)?;
+        dst.write_usize(self.width)?;
         dst.write_usize(self.last_lut_row)?;
         for (i, lut) in common_data.luts.iter().enumerate() {
             if lut == &self.lut {
@@ -239,6 +403,7 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for Loo
         let row = src.read_usize()?;
         let slot_nb = src.read_usize()?;
         let num_slots = src.read_usize()?;
+        let width = src.read_usize()?;
         let last_lut_row = src.read_usize()?;
         let lut_index = src.read_usize()?;
 
@@ -247,6 +412,7 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for Loo
             lut: common_data.luts[lut_index].clone(),
             slot_nb,
             num_slots,
+            width,
             last_lut_row,
         })
     }
@@ -6,6 +6,7 @@ pub mod base_sum;
 pub mod constant;
 pub mod coset_interpolation;
 pub mod exponentiation;
+pub mod expression_gate;
 pub mod gate;
 pub mod lookup;
 pub mod lookup_table;
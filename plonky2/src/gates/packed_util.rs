@@ -4,6 +4,7 @@ use alloc::vec::Vec;
 use crate::field::extension::Extendable;
 use crate::field::packable::Packable;
 use crate::field::packed::PackedField;
+use crate::field::types::Field;
 use crate::gates::gate::Gate;
 use crate::gates::util::StridedConstraintConsumer;
 use crate::hash::hash_types::RichField;
@@ -19,25 +20,47 @@ pub trait PackedEvaluableBase<F: RichField + Extendable<D>, const D: usize>: Gat
     /// Evaluates entire batch of points. Returns a matrix of constraints. Constraint `j` for point
     /// `i` is at `index j * batch_size + i`.
     fn eval_unfiltered_base_batch_packed(&self, vars_batch: EvaluationVarsBaseBatch<F>) -> Vec<F> {
-        let mut res = vec![F::ZERO; vars_batch.len() * self.num_constraints()];
-        let (vars_packed_iter, vars_leftovers_iter) = vars_batch.pack::<<F as Packable>::Packing>();
-        let leftovers_start = vars_batch.len() - vars_leftovers_iter.len();
-        for (i, vars_packed) in vars_packed_iter.enumerate() {
-            self.eval_unfiltered_base_packed(
-                vars_packed,
-                StridedConstraintConsumer::new(
-                    &mut res[..],
-                    vars_batch.len(),
-                    <F as Packable>::Packing::WIDTH * i,
-                ),
-            );
-        }
-        for (i, vars_leftovers) in vars_leftovers_iter.enumerate() {
-            self.eval_unfiltered_base_packed(
-                vars_leftovers,
-                StridedConstraintConsumer::new(&mut res[..], vars_batch.len(), leftovers_start + i),
-            );
-        }
-        res
+        eval_base_batch_packed(
+            self.num_constraints(),
+            vars_batch,
+            |vars_packed, yield_constr| self.eval_unfiltered_base_packed(vars_packed, yield_constr),
+            |vars_leftovers, yield_constr| {
+                self.eval_unfiltered_base_packed(vars_leftovers, yield_constr)
+            },
+        )
     }
 }
+
+/// Evaluates `num_constraints` constraints on an entire batch of points, with `eval_packed` on full
+/// packs of points and `eval_leftovers` on the remaining ones. Returns a matrix of constraints laid
+/// out as in [`PackedEvaluableBase::eval_unfiltered_base_batch_packed`].
+pub(crate) fn eval_base_batch_packed<F: Field>(
+    num_constraints: usize,
+    vars_batch: EvaluationVarsBaseBatch<F>,
+    mut eval_packed: impl FnMut(
+        EvaluationVarsBasePacked<<F as Packable>::Packing>,
+        StridedConstraintConsumer<<F as Packable>::Packing>,
+    ),
+    mut eval_leftovers: impl FnMut(EvaluationVarsBasePacked<F>, StridedConstraintConsumer<F>),
+) -> Vec<F> {
+    let mut res = vec![F::ZERO; vars_batch.len() * num_constraints];
+    let (vars_packed_iter, vars_leftovers_iter) = vars_batch.pack::<<F as Packable>::Packing>();
+    let leftovers_start = vars_batch.len() - vars_leftovers_iter.len();
+    for (i, vars_packed) in vars_packed_iter.enumerate() {
+        eval_packed(
+            vars_packed,
+            StridedConstraintConsumer::new(
+                &mut res[..],
+                vars_batch.len(),
+                <F as Packable>::Packing::WIDTH * i,
+            ),
+        );
+    }
+    for (i, vars_leftovers) in vars_leftovers_iter.enumerate() {
+        eval_leftovers(
+            vars_leftovers,
+            StridedConstraintConsumer::new(&mut res[..], vars_batch.len(), leftovers_start + i),
+        );
+    }
+    res
+}
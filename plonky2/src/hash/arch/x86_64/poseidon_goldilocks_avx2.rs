@@ -0,0 +1,225 @@
+//! Batched Poseidon permutations over Goldilocks with AVX2. Four states are permuted together,
+//! transposed so that each 64-bit lane of a vector holds an element of a different state; the
+//! arithmetic mirrors the scalar implementation, including its fast partial rounds.
+
+use core::arch::x86_64::*;
+
+use crate::field::goldilocks_field::GoldilocksField;
+use crate::field::types::Field64;
+use crate::hash::poseidon::{
+    Poseidon, ALL_ROUND_CONSTANTS, HALF_N_FULL_ROUNDS, N_PARTIAL_ROUNDS, SPONGE_WIDTH,
+};
+
+const LANES: usize = 4;
+
+/// `2^64 mod ORDER`.
+const EPSILON: u64 = GoldilocksField::ORDER.wrapping_neg();
+
+type State = [__m256i; SPONGE_WIDTH];
+
+#[inline]
+pub fn poseidon_batch(states: &mut [[GoldilocksField; SPONGE_WIDTH]]) {
+    let mut chunks = states.chunks_exact_mut(LANES);
+    for chunk in &mut chunks {
+        // Safety: this module is only compiled with AVX2 enabled.
+        unsafe {
+            let state = core::array::from_fn(|i| {
+                _mm256_set_epi64x(
+                    chunk[3][i].0 as i64,
+                    chunk[2][i].0 as i64,
+                    chunk[1][i].0 as i64,
+                    chunk[0][i].0 as i64,
+                )
+            });
+            let state = poseidon(state);
+            for (i, x) in state.into_iter().enumerate() {
+                let mut lanes = [0u64; LANES];
+                _mm256_storeu_si256(lanes.as_mut_ptr().cast::<__m256i>(), x);
+                for (lane, s) in lanes.into_iter().zip(chunk.iter_mut()) {
+                    s[i] = GoldilocksField(lane);
+                }
+            }
+        }
+    }
+
+    for state in chunks.into_remainder() {
+        *state = GoldilocksField::poseidon(*state);
+    }
+}
+
+#[inline(always)]
+unsafe fn poseidon(mut state: State) -> State {
+    let mut round_ctr = 0;
+    full_rounds(&mut state, &mut round_ctr);
+    partial_rounds(&mut state, &mut round_ctr);
+    full_rounds(&mut state, &mut round_ctr);
+    state
+}
+
+#[inline(always)]
+unsafe fn full_rounds(state: &mut State, round_ctr: &mut usize) {
+    for _ in 0..HALF_N_FULL_ROUNDS {
+        for i in 0..SPONGE_WIDTH {
+            let round_constant = ALL_ROUND_CONSTANTS[i + SPONGE_WIDTH * *round_ctr];
+            state[i] = sbox(add_canonical(state[i], splat(round_constant)));
+        }
+        *state = mds_layer(state);
+        *round_ctr += 1;
+    }
+}
+
+#[inline(always)]
+unsafe fn partial_rounds(state: &mut State, round_ctr: &mut usize) {
+    type F = GoldilocksField;
+
+    for i in 0..SPONGE_WIDTH {
+        state[i] = add_canonical(state[i], splat(F::FAST_PARTIAL_FIRST_ROUND_CONSTANT[i]));
+    }
+
+    // The initial matrix has first row and column [1, 0, ..., 0].
+    let mut result = [splat(0); SPONGE_WIDTH];
+    result[0] = state[0];
+    for r in 1..SPONGE_WIDTH {
+        for c in 1..SPONGE_WIDTH {
+            let t = splat(F::FAST_PARTIAL_ROUND_INITIAL_MATRIX[r - 1][c - 1]);
+            result[c] = add(result[c], mul(state[r], t));
+        }
+    }
+    *state = result;
+
+    for r in 0..N_PARTIAL_ROUNDS {
+        let s0 = add_canonical(sbox(state[0]), splat(F::FAST_PARTIAL_ROUND_CONSTANTS[r]));
+
+        // result = [d] concat [s0 * v + state[shift up by 1]], where d = [M_00 | w^] dot state.
+        let mds0to0 = F::MDS_MATRIX_CIRC[0] + F::MDS_MATRIX_DIAG[0];
+        let mut d = mul(s0, splat(mds0to0));
+        for i in 1..SPONGE_WIDTH {
+            let w_hat = splat(F::FAST_PARTIAL_ROUND_W_HATS[r][i - 1]);
+            d = add(d, mul(state[i], w_hat));
+            let v = splat(F::FAST_PARTIAL_ROUND_VS[r][i - 1]);
+            state[i] = add(state[i], mul(s0, v));
+        }
+        state[0] = d;
+    }
+    *round_ctr += N_PARTIAL_ROUNDS;
+}
+
+/// The MDS layer. Its entries are small, so as in the scalar `mds_row_shf`, we accumulate the
+/// products and reduce once per row. Here the products are split at 32 bits to fit in the lanes.
+#[inline(always)]
+unsafe fn mds_layer(state: &State) -> State {
+    type F = GoldilocksField;
+
+    let hi: State = core::array::from_fn(|i| _mm256_srli_epi64::<32>(state[i]));
+    core::array::from_fn(|r| {
+        let mut acc_lo = splat(0);
+        let mut acc_hi = splat(0);
+        for i in 0..SPONGE_WIDTH {
+            let j = (i + r) % SPONGE_WIDTH;
+            let mut coeff = F::MDS_MATRIX_CIRC[i];
+            if i == 0 {
+                coeff += F::MDS_MATRIX_DIAG[r];
+            }
+            // `_mm256_mul_epu32` multiplies the low 32 bits of each lane. Each product is below
+            // 2^38, so the 12 sums fit easily.
+            let coeff = splat(coeff);
+            acc_lo = _mm256_add_epi64(acc_lo, _mm256_mul_epu32(state[j], coeff));
+            acc_hi = _mm256_add_epi64(acc_hi, _mm256_mul_epu32(hi[j], coeff));
+        }
+
+        // The row is acc_lo + 2^32 acc_hi = lo + 2^64 hi.
+        let lo = _mm256_add_epi64(acc_lo, _mm256_slli_epi64::<32>(acc_hi));
+        let carry = _mm256_srli_epi64::<63>(lt(lo, acc_lo));
+        let hi = _mm256_add_epi64(_mm256_srli_epi64::<32>(acc_hi), carry);
+        // hi is tiny, so hi * EPSILON = (hi << 32) - hi doesn't overflow.
+        let hi_eps = _mm256_sub_epi64(_mm256_slli_epi64::<32>(hi), hi);
+        add_no_double_overflow(lo, hi_eps)
+    })
+}
+
+/// x |--> x^7
+#[inline(always)]
+unsafe fn sbox(x: __m256i) -> __m256i {
+    let x2 = mul(x, x);
+    let x4 = mul(x2, x2);
+    let x3 = mul(x, x2);
+    mul(x3, x4)
+}
+
+#[inline(always)]
+unsafe fn splat(x: u64) -> __m256i {
+    _mm256_set1_epi64x(x as i64)
+}
+
+/// Unsigned `x < y`, as a mask.
+#[inline(always)]
+unsafe fn lt(x: __m256i, y: __m256i) -> __m256i {
+    // AVX2 only has signed comparisons. Flipping the sign bits turns them into unsigned ones.
+    let sign_bit = splat(1 << 63);
+    _mm256_cmpgt_epi64(_mm256_xor_si256(y, sign_bit), _mm256_xor_si256(x, sign_bit))
+}
+
+/// `x + y` for any `x` and `y` such that the sum doesn't overflow twice after wrapping around,
+/// which holds if either is canonical.
+#[inline(always)]
+unsafe fn add_no_double_overflow(x: __m256i, y: __m256i) -> __m256i {
+    let sum = _mm256_add_epi64(x, y);
+    let carry = lt(sum, y);
+    _mm256_add_epi64(sum, _mm256_and_si256(carry, splat(EPSILON)))
+}
+
+/// `x + y` for canonical `y`.
+#[inline(always)]
+unsafe fn add_canonical(x: __m256i, y: __m256i) -> __m256i {
+    add_no_double_overflow(x, y)
+}
+
+#[inline(always)]
+unsafe fn add(x: __m256i, y: __m256i) -> __m256i {
+    // Subtracting ORDER from a non-canonical y is the same as adding EPSILON.
+    let y_is_canonical = lt(y, splat(GoldilocksField::ORDER));
+    let y = _mm256_add_epi64(y, _mm256_andnot_si256(y_is_canonical, splat(EPSILON)));
+    add_no_double_overflow(x, y)
+}
+
+#[inline(always)]
+unsafe fn mul(x: __m256i, y: __m256i) -> __m256i {
+    // Schoolbook multiplication from four 32 x 32 -> 64 products. None of the sums below can
+    // overflow, as each product is at most (2^32 - 1)^2.
+    let lo_mask = splat(0xffffffff);
+    let x_hi = _mm256_srli_epi64::<32>(x);
+    let y_hi = _mm256_srli_epi64::<32>(y);
+    let lo_lo = _mm256_mul_epu32(x, y);
+    let lo_hi = _mm256_mul_epu32(x, y_hi);
+    let hi_lo = _mm256_mul_epu32(x_hi, y);
+    let hi_hi = _mm256_mul_epu32(x_hi, y_hi);
+
+    let mid0 = _mm256_add_epi64(lo_hi, _mm256_srli_epi64::<32>(lo_lo));
+    let mid1 = _mm256_add_epi64(hi_lo, _mm256_and_si256(mid0, lo_mask));
+    let lo = _mm256_or_si256(
+        _mm256_slli_epi64::<32>(mid1),
+        _mm256_and_si256(lo_lo, lo_mask),
+    );
+    let hi = _mm256_add_epi64(
+        _mm256_add_epi64(hi_hi, _mm256_srli_epi64::<32>(mid0)),
+        _mm256_srli_epi64::<32>(mid1),
+    );
+    reduce128(lo, hi)
+}
+
+/// Reduce `lo + 2^64 hi` as in the scalar `reduce128`. The result might not be canonical.
+#[inline(always)]
+unsafe fn reduce128(lo: __m256i, hi: __m256i) -> __m256i {
+    let epsilon = splat(EPSILON);
+    let hi_hi = _mm256_srli_epi64::<32>(hi);
+    let hi_lo = _mm256_and_si256(hi, epsilon);
+
+    // 2^96 = -1, so subtract hi_hi.
+    let t0 = _mm256_sub_epi64(lo, hi_hi);
+    let borrow = lt(lo, hi_hi);
+    let t0 = _mm256_sub_epi64(t0, _mm256_and_si256(borrow, epsilon));
+
+    // 2^64 = EPSILON, so add hi_lo * EPSILON = (hi_lo << 32) - hi_lo.
+    let t1 = _mm256_sub_epi64(_mm256_slli_epi64::<32>(hi_lo), hi_lo);
+    add_no_double_overflow(t0, t1)
+}
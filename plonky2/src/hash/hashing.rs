@@ -89,6 +89,14 @@ pub trait PlonkyPermutation<T: Copy + Default>:
     /// Apply permutation to internal state
     fn permute(&mut self);
 
+    /// Apply permutation to the internal state of each of `perms`. Implementations may permute
+    /// several states at once, e.g. with vector instructions.
+    fn permute_batch(perms: &mut [Self]) {
+        for perm in perms {
+            perm.permute();
+        }
+    }
+
     /// Return a slice of `RATE` elements
     fn squeeze(&self) -> &[T];
 }
@@ -82,13 +82,14 @@ fn capacity_up_to_mut<T>(v: &mut Vec<T>, len: usize) -> &mut [MaybeUninit<T>] {
     }
 }
 
-fn fill_subtree<F: RichField, H: Hasher<F>>(
+fn fill_subtree<F: RichField, H: Hasher<F>, L: Sync>(
     digests_buf: &mut [MaybeUninit<H::Hash>],
-    leaves: &[Vec<F>],
+    leaves: &[L],
+    hash_leaf: fn(&L) -> H::Hash,
 ) -> H::Hash {
     assert_eq!(leaves.len(), digests_buf.len() / 2 + 1);
     if digests_buf.is_empty() {
-        H::hash_or_noop(&leaves[0])
+        hash_leaf(&leaves[0])
     } else {
         // Layout is: left recursive output || left child digest
         //             || right child digest || right recursive output.
@@ -101,8 +102,8 @@ fn fill_subtree<F: RichField, H: Hasher<F>>(
         let (left_leaves, right_leaves) = leaves.split_at(leaves.len() / 2);
 
         let (left_digest, right_digest) = plonky2_maybe_rayon::join(
-            || fill_subtree::<F, H>(left_digests_buf, left_leaves),
-            || fill_subtree::<F, H>(right_digests_buf, right_leaves),
+            || fill_subtree::<F, H, L>(left_digests_buf, left_leaves, hash_leaf),
+            || fill_subtree::<F, H, L>(right_digests_buf, right_leaves, hash_leaf),
         );
 
         left_digest_mem.write(left_digest);
@@ -111,10 +112,11 @@ fn fill_subtree<F: RichField, H: Hasher<F>>(
     }
 }
 
-fn fill_digests_buf<F: RichField, H: Hasher<F>>(
+fn fill_digests_buf<F: RichField, H: Hasher<F>, L: Sync>(
     digests_buf: &mut [MaybeUninit<H::Hash>],
     cap_buf: &mut [MaybeUninit<H::Hash>],
-    leaves: &[Vec<F>],
+    leaves: &[L],
+    hash_leaf: fn(&L) -> H::Hash,
     cap_height: usize,
 ) {
     // Special case of a tree that's all cap. The usual case will panic because we'll try to split
@@ -126,7 +128,7 @@ fn fill_digests_buf<F: RichField, H: Hasher<F>>(
             .par_iter_mut()
             .zip(leaves)
             .for_each(|(cap_buf, leaf)| {
-                cap_buf.write(H::hash_or_noop(leaf));
+                cap_buf.write(hash_leaf(leaf));
             });
         return;
     }
@@ -142,13 +144,42 @@ fn fill_digests_buf<F: RichField, H: Hasher<F>>(
             // We have `1 << cap_height` sub-trees, one for each entry in `cap`. They are totally
             // independent, so we schedule one task for each. `digests_buf` and `leaves` are split
             // into `1 << cap_height` slices, one for each sub-tree.
-            subtree_cap.write(fill_subtree::<F, H>(subtree_digests, subtree_leaves));
+            subtree_cap.write(fill_subtree::<F, H, L>(
+                subtree_digests,
+                subtree_leaves,
+                hash_leaf,
+            ));
         },
     );
 }
 
 impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
     pub fn new(leaves: Vec<Vec<F>>, cap_height: usize) -> Self {
+        let (digests, cap) = Self::build(&leaves, |leaf| H::hash_or_noop(leaf), cap_height);
+        Self {
+            leaves,
+            digests,
+            cap,
+        }
+    }
+
+    /// Builds a tree over leaves that are only given by their hashes, i.e. `H::hash_or_noop` of
+    /// each leaf. The leaves are not stored, so `get` can't be used on the result, but it can still
+    /// `prove` membership of any leaf.
+    pub fn from_leaf_hashes(leaf_hashes: Vec<H::Hash>, cap_height: usize) -> Self {
+        let (digests, cap) = Self::build(&leaf_hashes, |&hash| hash, cap_height);
+        Self {
+            leaves: Vec::new(),
+            digests,
+            cap,
+        }
+    }
+
+    fn build<L: Sync>(
+        leaves: &[L],
+        hash_leaf: fn(&L) -> H::Hash,
+        cap_height: usize,
+    ) -> (Vec<H::Hash>, MerkleCap<F, H>) {
         let log2_leaves_len = log2_strict(leaves.len());
         assert!(
             cap_height <= log2_leaves_len,
@@ -165,7 +196,7 @@ impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
 
         let digests_buf = capacity_up_to_mut(&mut digests, num_digests);
         let cap_buf = capacity_up_to_mut(&mut cap, len_cap);
-        fill_digests_buf::<F, H>(digests_buf, cap_buf, &leaves[..], cap_height);
+        fill_digests_buf::<F, H, L>(digests_buf, cap_buf, leaves, hash_leaf, cap_height);
 
         unsafe {
             // SAFETY: `fill_digests_buf` and `cap` initialized the spare capacity up to
@@ -174,11 +205,12 @@ impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
             cap.set_len(len_cap);
         }
 
-        Self {
-            leaves,
-            digests,
-            cap: MerkleCap(cap),
-        }
+        (digests, MerkleCap(cap))
+    }
+
+    /// The number of leaves, which is also known for trees built with `from_leaf_hashes`.
+    pub fn num_leaves(&self) -> usize {
+        self.digests.len() / 2 + self.cap.len()
     }
 
     pub fn get(&self, i: usize) -> &[F] {
@@ -188,7 +220,7 @@ impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
     /// Create a Merkle proof from a leaf index.
     pub fn prove(&self, leaf_index: usize) -> MerkleProof<F, H> {
         let cap_height = log2_strict(self.cap.len());
-        let num_layers = log2_strict(self.leaves.len()) - cap_height;
+        let num_layers = log2_strict(self.num_leaves()) - cap_height;
         debug_assert_eq!(leaf_index >> (cap_height + num_layers), 0);
 
         let digest_tree = {
@@ -295,4 +327,32 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_from_leaf_hashes() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let log_n = 8;
+        let n = 1 << log_n;
+        // Leaves of 3 elements are hashed with the no-op path, so cover both paths.
+        for leaf_len in [3, 7] {
+            let leaves = random_data::<F>(n, leaf_len);
+            let leaf_hashes = leaves.iter().map(|leaf| H::hash_or_noop(leaf)).collect();
+            let tree = MerkleTree::<F, H>::new(leaves.clone(), 2);
+            let hashed_tree = MerkleTree::<F, H>::from_leaf_hashes(leaf_hashes, 2);
+
+            assert_eq!(hashed_tree.cap, tree.cap);
+            assert_eq!(hashed_tree.digests, tree.digests);
+            assert_eq!(hashed_tree.num_leaves(), n);
+            for (i, leaf) in leaves.into_iter().enumerate() {
+                let proof = hashed_tree.prove(i);
+                verify_merkle_proof_to_cap(leaf, i, &hashed_tree.cap, &proof)?;
+            }
+        }
+
+        Ok(())
+    }
 }
@@ -16,8 +16,38 @@ pub(crate) fn compress_merkle_proofs<F: RichField, H: Hasher<F>>(
 ) -> Vec<MerkleProof<F, H>> {
     assert!(!proofs.is_empty());
     let height = cap_height + proofs[0].siblings.len();
+    let masks = compression_masks(height, cap_height, indices);
+    // For each proof collect all the unknown proof elements.
+    masks
+        .iter()
+        .zip(proofs)
+        .map(|(mask, p)| MerkleProof {
+            siblings: p
+                .siblings
+                .iter()
+                .zip(mask)
+                .filter_map(|(&sibling, &is_unknown)| is_unknown.then_some(sibling))
+                .collect(),
+        })
+        .collect()
+}
+
+/// The number of siblings in each proof returned by `compress_merkle_proofs`. This only depends on
+/// the indices, so serialized compressed proofs don't need to store their lengths.
+pub(crate) fn compressed_merkle_proof_lengths(
+    height: usize,
+    cap_height: usize,
+    indices: &[usize],
+) -> Vec<usize> {
+    compression_masks(height, cap_height, indices)
+        .iter()
+        .map(|mask| mask.iter().filter(|&&is_unknown| is_unknown).count())
+        .collect()
+}
+
+/// For each index, which siblings along its Merkle path are kept by `compress_merkle_proofs`.
+fn compression_masks(height: usize, cap_height: usize, indices: &[usize]) -> Vec<Vec<bool>> {
     let num_leaves = 1 << height;
-    let mut compressed_proofs = Vec::with_capacity(proofs.len());
     // Holds the known nodes in the tree at a given time. The root is at index 1.
     // Valid indices are 1 through n, and each element at index `i` has
     // children at indices `2i` and `2i +1` its parent at index `floor(i ∕ 2)`.
@@ -28,27 +58,24 @@ pub(crate) fn compress_merkle_proofs<F: RichField, H: Hasher<F>>(
             known[(i + num_leaves) >> j] = true;
         }
     }
-    // For each proof collect all the unknown proof elements.
-    for (&i, p) in indices.iter().zip(proofs) {
-        let mut compressed_proof = MerkleProof {
-            siblings: Vec::new(),
-        };
-        let mut index = i + num_leaves;
-        for &sibling in &p.siblings {
-            let sibling_index = index ^ 1;
-            if !known[sibling_index] {
-                // If the sibling is not yet known, add it to the proof and set it to known.
-                compressed_proof.siblings.push(sibling);
-                known[sibling_index] = true;
-            }
-            // Go up the tree and set the parent to known.
-            index >>= 1;
-            known[index] = true;
-        }
-        compressed_proofs.push(compressed_proof);
-    }
-
-    compressed_proofs
+    indices
+        .iter()
+        .map(|&i| {
+            let mut index = i + num_leaves;
+            (0..height - cap_height)
+                .map(|_| {
+                    let sibling_index = index ^ 1;
+                    // If the sibling is not yet known, it is kept in the proof and becomes known.
+                    let is_unknown = !known[sibling_index];
+                    known[sibling_index] = true;
+                    // Go up the tree and set the parent to known.
+                    index >>= 1;
+                    known[index] = true;
+                    is_unknown
+                })
+                .collect()
+        })
+        .collect()
 }
 
 /// Decompress compressed Merkle proofs.
@@ -147,6 +174,13 @@ mod tests {
         );
 
         assert_eq!(proofs, decompressed_proofs);
+        assert_eq!(
+            compressed_proofs
+                .iter()
+                .map(|p| p.siblings.len())
+                .collect::<Vec<_>>(),
+            compressed_merkle_proof_lengths(h, cap_height, &indices)
+        );
 
         let compressed_proof_bytes = serde_cbor::to_vec(&compressed_proofs).unwrap();
         println!(
@@ -631,6 +631,15 @@ pub trait Poseidon: PrimeField64 {
 
         state
     }
+
+    /// Applies the permutation to each of `states`. Implementations may override this to permute
+    /// several states at once with vector instructions.
+    #[inline]
+    fn poseidon_batch(states: &mut [[Self; SPONGE_WIDTH]]) {
+        for state in states {
+            *state = Self::poseidon(*state);
+        }
+    }
 }
 
 #[derive(Copy, Clone, Default, Debug, PartialEq)]
@@ -648,18 +657,28 @@ impl<T> AsRef<[T]> for PoseidonPermutation<T> {
 
 trait Permuter: Sized {
     fn permute(input: [Self; SPONGE_WIDTH]) -> [Self; SPONGE_WIDTH];
+
+    fn permute_batch(states: &mut [[Self; SPONGE_WIDTH]]);
 }
 
 impl<F: Poseidon> Permuter for F {
     fn permute(input: [Self; SPONGE_WIDTH]) -> [Self; SPONGE_WIDTH] {
         <F as Poseidon>::poseidon(input)
     }
+
+    fn permute_batch(states: &mut [[Self; SPONGE_WIDTH]]) {
+        <F as Poseidon>::poseidon_batch(states)
+    }
 }
 
 impl Permuter for Target {
     fn permute(_input: [Self; SPONGE_WIDTH]) -> [Self; SPONGE_WIDTH] {
         panic!("Call `permute_swapped()` instead of `permute()`");
     }
+
+    fn permute_batch(_states: &mut [[Self; SPONGE_WIDTH]]) {
+        panic!("Call `permute_swapped()` instead of `permute_batch()`");
+    }
 }
 
 impl<T: Copy + Debug + Default + Eq + Permuter + Send + Sync> PlonkyPermutation<T>
@@ -696,6 +715,14 @@ impl<T: Copy + Debug + Default + Eq + Permuter + Send + Sync> PlonkyPermutation<
         self.state = T::permute(self.state);
     }
 
+    fn permute_batch(perms: &mut [Self]) {
+        let mut states: Vec<_> = perms.iter().map(|perm| perm.state).collect();
+        T::permute_batch(&mut states);
+        for (perm, state) in perms.iter_mut().zip(states) {
+            perm.state = state;
+        }
+    }
+
     fn squeeze(&self) -> &[T] {
         &self.state[..Self::RATE]
     }
@@ -753,6 +780,8 @@ impl<F: RichField> AlgebraicHasher<F> for PoseidonHash {
 
 #[cfg(test)]
 pub(crate) mod test_helpers {
+    use alloc::vec::Vec;
+
     use crate::field::types::Field;
     use crate::hash::poseidon::{Poseidon, SPONGE_WIDTH};
 
@@ -788,4 +817,15 @@ pub(crate) mod test_helpers {
             assert_eq!(output[i], output_naive[i]);
         }
     }
+
+    /// Checks `poseidon_batch` against `poseidon`, on a number of states that isn't a multiple of
+    /// any vector width.
+    pub(crate) fn check_batch_consistency<F: Poseidon>() {
+        let inputs: Vec<[F; SPONGE_WIDTH]> = (0..11).map(|_| F::rand_array()).collect();
+        let mut outputs = inputs.clone();
+        F::poseidon_batch(&mut outputs);
+        for (input, output) in inputs.into_iter().zip(outputs) {
+            assert_eq!(output, F::poseidon(input));
+        }
+    }
 }
@@ -279,6 +279,12 @@ impl Poseidon for GoldilocksField {
     //     }
     // }
 
+    #[cfg(all(target_arch="x86_64", target_feature="avx2"))]
+    #[inline]
+    fn poseidon_batch(states: &mut [[Self; 12]]) {
+        crate::hash::arch::x86_64::poseidon_goldilocks_avx2::poseidon_batch(states);
+    }
+
     // #[cfg(all(target_arch="aarch64", target_feature="neon"))]
     // #[inline]
     // fn poseidon(input: [Self; 12]) -> [Self; 12] {
@@ -446,7 +452,9 @@ mod poseidon12_mds {
 mod tests {
     use crate::field::goldilocks_field::GoldilocksField as F;
     use crate::field::types::{Field, PrimeField64};
-    use crate::hash::poseidon::test_helpers::{check_consistency, check_test_vectors};
+    use crate::hash::poseidon::test_helpers::{
+        check_batch_consistency, check_consistency, check_test_vectors,
+    };
 
     #[test]
     fn test_vectors() {
@@ -490,4 +498,9 @@ mod tests {
     fn consistency() {
         check_consistency::<F>();
     }
+
+    #[test]
+    fn batch_consistency() {
+        check_batch_consistency::<F>();
+    }
 }
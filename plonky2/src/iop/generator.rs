@@ -1,9 +1,18 @@
 use alloc::boxed::Box;
+use alloc::collections::{BTreeSet, VecDeque};
 use alloc::string::{String, ToString};
-use alloc::vec;
 use alloc::vec::Vec;
-use core::fmt::Debug;
+use alloc::{format, vec};
+use core::fmt::{Debug, Display, Formatter};
 use core::marker::PhantomData;
+use core::{fmt, mem};
+
+use itertools::Itertools;
+use plonky2_maybe_rayon::*;
+#[cfg(feature = "rand_chacha")]
+use rand::SeedableRng;
+#[cfg(feature = "rand_chacha")]
+use rand_chacha::ChaCha20Rng;
 
 use crate::field::extension::Extendable;
 use crate::field::types::Field;
@@ -18,6 +27,9 @@ use crate::util::serialization::{Buffer, IoResult, Read, Write};
 
 /// Given a `PartitionWitness` that has only inputs set, populates the rest of the witness using the
 /// given set of generators.
+///
+/// Panics if some generators can't run because of missing inputs, see
+/// `try_generate_partial_witness`.
 pub fn generate_partial_witness<
     'a,
     F: RichField + Extendable<D>,
@@ -28,75 +40,432 @@ pub fn generate_partial_witness<
     prover_data: &'a ProverOnlyCircuitData<F, C, D>,
     common_data: &'a CommonCircuitData<F, D>,
 ) -> PartitionWitness<'a, F> {
-    let config = &common_data.config;
-    let generators = &prover_data.generators;
-    let generator_indices_by_watches = &prover_data.generator_indices_by_watches;
+    try_generate_partial_witness(inputs, prover_data, common_data).unwrap_or_else(|e| panic!("{e}"))
+}
 
+/// Like `generate_partial_witness`, but reports the generators which couldn't run instead of
+/// panicking.
+pub fn try_generate_partial_witness<
+    'a,
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    inputs: PartialWitness<F>,
+    prover_data: &'a ProverOnlyCircuitData<F, C, D>,
+    common_data: &'a CommonCircuitData<F, D>,
+) -> Result<PartitionWitness<'a, F>, UnsatisfiedGenerators> {
+    let witness = PartitionWitness::new(
+        common_data.config.num_wires,
+        common_data.degree(),
+        &prover_data.representative_map,
+    );
+    run_generators(witness, inputs, prover_data)
+}
+
+/// Like `generate_partial_witness`, but with the random values of the witness, such as those of
+/// unused public input wires, drawn from a ChaCha20 RNG seeded with `rng_seed`, if set. Each of them
+/// depends only on the seed and its target, so the witness can be reproduced.
+#[cfg(feature = "rand_chacha")]
+pub fn generate_partial_witness_with_seed<
+    'a,
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    inputs: PartialWitness<F>,
+    prover_data: &'a ProverOnlyCircuitData<F, C, D>,
+    common_data: &'a CommonCircuitData<F, D>,
+    rng_seed: Option<u64>,
+) -> PartitionWitness<'a, F> {
     let mut witness = PartitionWitness::new(
-        config.num_wires,
+        common_data.config.num_wires,
         common_data.degree(),
         &prover_data.representative_map,
     );
+    witness.rng_seed = rng_seed;
+    run_generators(witness, inputs, prover_data).unwrap_or_else(|e| panic!("{e}"))
+}
+
+fn run_generators<'a, F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    mut witness: PartitionWitness<'a, F>,
+    inputs: PartialWitness<F>,
+    prover_data: &'a ProverOnlyCircuitData<F, C, D>,
+) -> Result<PartitionWitness<'a, F>, UnsatisfiedGenerators> {
+    let generator_is_expired = run_generators_recording(&mut witness, inputs, prover_data, None);
+    if generator_is_expired.iter().all(|&expired| expired) {
+        Ok(witness)
+    } else {
+        Err(UnsatisfiedGenerators::new(
+            &witness,
+            prover_data,
+            &generator_is_expired,
+        ))
+    }
+}
+
+/// Pending generators are run one at a time while fewer than this many are queued, since a
+/// parallel batch wouldn't pay for its overhead.
+const MIN_PARALLEL_BATCH: usize = 64;
+
+/// Runs the generators until no more progress can be made, and returns which of them finished. If
+/// `producers` is set, it records the generator which set each target representative.
+///
+/// Generators are queued when a target of their watch list is populated, and we keep track of how
+/// many of their watched targets are still missing. While the queue is short, generators are run
+/// one at a time, each seeing the values set by the previous ones, and the generators they enable
+/// are appended to the queue. Once it grows past `MIN_PARALLEL_BATCH`, the queued generators whose
+/// watch lists are complete are run as a parallel batch against the current witness, and their
+/// outputs are merged in queue order, so the resulting witness doesn't depend on the scheduling of
+/// the batch. The other queued generators are then run one at a time.
+fn run_generators_recording<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    witness: &mut PartitionWitness<F>,
+    inputs: PartialWitness<F>,
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    producers: Option<&mut [Option<usize>]>,
+) -> Vec<bool> {
+    let generators = &prover_data.generators;
 
     for (t, v) in inputs.target_values.into_iter() {
         witness.set_target(t, v);
     }
 
-    // Build a list of "pending" generators which are queued to be run. Initially, all generators
-    // are queued.
-    let mut pending_generator_indices: Vec<_> = (0..generators.len()).collect();
+    let mut scheduler = GeneratorScheduler::new(prover_data, producers);
+    while !scheduler.queue.is_empty() {
+        if scheduler.queue.len() < MIN_PARALLEL_BATCH || current_num_threads() == 1 {
+            let generator_idx = scheduler.pop();
+            scheduler.run(witness, generator_idx);
+            continue;
+        }
 
-    // We also track a list of "expired" generators which have already returned false.
-    let mut generator_is_expired = vec![false; generators.len()];
-    let mut remaining_generators = generators.len();
+        let mut batch = Vec::with_capacity(scheduler.queue.len());
+        while !scheduler.queue.is_empty() {
+            let generator_idx = scheduler.pop();
+            if !scheduler.generator_is_expired[generator_idx] {
+                batch.push(generator_idx);
+            }
+        }
+        let num_missing_watches = scheduler.num_missing_watches(witness);
+        let (ready, waiting): (Vec<usize>, Vec<usize>) = batch
+            .into_iter()
+            .partition(|&idx| num_missing_watches[idx] == 0);
+
+        let outputs: Vec<_> = ready
+            .par_iter()
+            .map(|&generator_idx| {
+                let mut buffer = GeneratedValues::empty();
+                let finished = generators[generator_idx].0.run(witness, &mut buffer);
+                (generator_idx, finished, buffer)
+            })
+            .collect();
+        for (generator_idx, finished, mut buffer) in outputs {
+            scheduler.merge(witness, generator_idx, finished, &mut buffer);
+        }
 
-    let mut buffer = GeneratedValues::empty();
+        // Generators with missing watches usually can't make progress yet, but a generator may
+        // produce some of its outputs from a partial watch list.
+        for generator_idx in waiting {
+            scheduler.run(witness, generator_idx);
+        }
+    }
 
-    // Keep running generators until we fail to make progress.
-    while !pending_generator_indices.is_empty() {
-        let mut next_pending_generator_indices = Vec::new();
+    scheduler.generator_is_expired
+}
 
-        for &generator_idx in &pending_generator_indices {
-            if generator_is_expired[generator_idx] {
-                continue;
-            }
+/// The state of `run_generators_recording`.
+struct GeneratorScheduler<
+    'a,
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+> {
+    prover_data: &'a ProverOnlyCircuitData<F, C, D>,
+    producers: Option<&'a mut [Option<usize>]>,
+    /// The generators which are queued to be run.
+    queue: VecDeque<usize>,
+    is_queued: Vec<bool>,
+    /// The number of watched target representatives of each generator which are still unset. It's
+    /// only counted once a parallel batch needs it, since sequential runs don't.
+    num_missing_watches: Option<Vec<usize>>,
+    /// The "expired" generators, which have already returned true.
+    generator_is_expired: Vec<bool>,
+    buffer: GeneratedValues<F>,
+}
 
-            let finished = generators[generator_idx].0.run(&witness, &mut buffer);
-            if finished {
-                generator_is_expired[generator_idx] = true;
-                remaining_generators -= 1;
-            }
+impl<'a, F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    GeneratorScheduler<'a, F, C, D>
+{
+    /// Initially, all generators are queued.
+    fn new(
+        prover_data: &'a ProverOnlyCircuitData<F, C, D>,
+        producers: Option<&'a mut [Option<usize>]>,
+    ) -> Self {
+        let num_generators = prover_data.generators.len();
+        Self {
+            prover_data,
+            producers,
+            queue: (0..num_generators).collect(),
+            is_queued: vec![true; num_generators],
+            num_missing_watches: None,
+            generator_is_expired: vec![false; num_generators],
+            buffer: GeneratedValues::empty(),
+        }
+    }
 
-            // Merge any generated values into our witness, and get a list of newly-populated
-            // targets' representatives.
-            let new_target_reps = buffer
-                .target_values
-                .drain(..)
-                .flat_map(|(t, v)| witness.set_target_returning_rep(t, v));
-
-            // Enqueue unfinished generators that were watching one of the newly populated targets.
-            for watch in new_target_reps {
-                let opt_watchers = generator_indices_by_watches.get(&watch);
-                if let Some(watchers) = opt_watchers {
-                    for &watching_generator_idx in watchers {
-                        if !generator_is_expired[watching_generator_idx] {
-                            next_pending_generator_indices.push(watching_generator_idx);
-                        }
+    fn pop(&mut self) -> usize {
+        let generator_idx = self.queue.pop_front().unwrap();
+        self.is_queued[generator_idx] = false;
+        generator_idx
+    }
+
+    fn num_missing_watches(&mut self, witness: &PartitionWitness<F>) -> &[usize] {
+        let generator_indices_by_watches = &self.prover_data.generator_indices_by_watches;
+        let num_generators = self.generator_is_expired.len();
+        self.num_missing_watches.get_or_insert_with(|| {
+            let mut num_missing_watches = vec![0; num_generators];
+            for (&watch, watchers) in generator_indices_by_watches {
+                if witness.values[watch].is_none() {
+                    for &idx in watchers {
+                        num_missing_watches[idx] += 1;
                     }
                 }
             }
+            num_missing_watches
+        })
+    }
+
+    /// Runs a generator against the current witness, unless it has expired, and merges its outputs.
+    fn run(&mut self, witness: &mut PartitionWitness<F>, generator_idx: usize) {
+        if self.generator_is_expired[generator_idx] {
+            return;
+        }
+        let mut buffer = mem::replace(&mut self.buffer, GeneratedValues::empty());
+        let finished = self.prover_data.generators[generator_idx]
+            .0
+            .run(witness, &mut buffer);
+        self.merge(witness, generator_idx, finished, &mut buffer);
+        self.buffer = buffer;
+    }
+
+    /// Merges the values generated by a run into the witness, leaving `buffer` empty, and enqueues
+    /// the generators that were watching one of the newly populated targets' representatives.
+    fn merge(
+        &mut self,
+        witness: &mut PartitionWitness<F>,
+        generator_idx: usize,
+        finished: bool,
+        buffer: &mut GeneratedValues<F>,
+    ) {
+        if finished {
+            self.generator_is_expired[generator_idx] = true;
         }
+        for (t, v) in buffer.target_values.drain(..) {
+            let Some(watch) = witness.set_target_returning_rep(t, v) else {
+                continue;
+            };
+            if let Some(producers) = self.producers.as_deref_mut() {
+                producers[watch] = Some(generator_idx);
+            }
+            let Some(watchers) = self.prover_data.generator_indices_by_watches.get(&watch) else {
+                continue;
+            };
+            for &idx in watchers {
+                if let Some(num_missing_watches) = &mut self.num_missing_watches {
+                    num_missing_watches[idx] -= 1;
+                }
+                if !self.generator_is_expired[idx] && !self.is_queued[idx] {
+                    self.is_queued[idx] = true;
+                    self.queue.push_back(idx);
+                }
+            }
+        }
+    }
+}
+
+/// Where a generator was added to a circuit.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GeneratorOrigin {
+    /// For the generators of gates, the id of the gate and its row.
+    pub gate: Option<(String, usize)>,
+    /// The contexts which were open when the generator, or its gate, was added.
+    pub context: String,
+}
 
-        pending_generator_indices = next_pending_generator_indices;
+impl Display for GeneratorOrigin {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some((gate, row)) = &self.gate {
+            write!(f, "gate {gate} at row {row}, ")?;
+        }
+        write!(f, "context {}", self.context)
     }
+}
 
-    assert_eq!(
-        remaining_generators, 0,
-        "{} generators weren't run",
-        remaining_generators,
-    );
+/// Where the generator at `index` was added, if the circuit recorded it.
+#[cfg(feature = "generator_origins")]
+fn generator_origin<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    index: usize,
+) -> Option<&GeneratorOrigin> {
+    prover_data.generator_origins.get(index)
+}
 
-    witness
+#[cfg(not(feature = "generator_origins"))]
+const fn generator_origin<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    _prover_data: &ProverOnlyCircuitData<F, C, D>,
+    _index: usize,
+) -> Option<&GeneratorOrigin> {
+    None
+}
+
+/// A generator which didn't finish during witness generation.
+#[derive(Clone, Debug)]
+pub struct StuckGenerator {
+    /// The index of the generator in `ProverOnlyCircuitData::generators`.
+    pub index: usize,
+    pub id: String,
+    pub watch_list: Vec<Target>,
+    /// The targets of the watch list which were never set.
+    pub missing_targets: Vec<Target>,
+    /// Where the generator was added, if the circuit was built with the `generator_origins`
+    /// feature. Deserialized circuits don't keep this information.
+    pub origin: Option<GeneratorOrigin>,
+}
+
+/// The error returned when witness generation stalls because some generators are missing inputs.
+#[derive(Clone, Debug)]
+pub struct UnsatisfiedGenerators {
+    pub generators: Vec<StuckGenerator>,
+}
+
+impl UnsatisfiedGenerators {
+    /// The number of stuck generators described by `Display`.
+    const MAX_DISPLAYED: usize = 10;
+
+    fn new<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+        witness: &PartitionWitness<F>,
+        prover_data: &ProverOnlyCircuitData<F, C, D>,
+        generator_is_expired: &[bool],
+    ) -> Self {
+        let generators = generator_is_expired
+            .iter()
+            .positions(|&expired| !expired)
+            .map(|index| {
+                let generator = &prover_data.generators[index].0;
+                let watch_list = generator.watch_list();
+                let missing_targets = watch_list
+                    .iter()
+                    .copied()
+                    .filter(|&t| witness.try_get_target(t).is_none())
+                    .collect();
+                StuckGenerator {
+                    index,
+                    id: generator.id(),
+                    watch_list,
+                    missing_targets,
+                    origin: generator_origin(prover_data, index).cloned(),
+                }
+            })
+            .collect();
+        Self { generators }
+    }
+}
+
+impl Display for UnsatisfiedGenerators {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} generators weren't run", self.generators.len())?;
+        for generator in self.generators.iter().take(Self::MAX_DISPLAYED) {
+            write!(
+                f,
+                "\n  generator {} ({}) is missing {:?}",
+                generator.index, generator.id, generator.missing_targets
+            )?;
+            if let Some(origin) = &generator.origin {
+                write!(f, ", from {origin}")?;
+            }
+        }
+        if self.generators.len() > Self::MAX_DISPLAYED {
+            write!(
+                f,
+                "\n  and {} more",
+                self.generators.len() - Self::MAX_DISPLAYED
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnsatisfiedGenerators {}
+
+/// Generates the witness from `inputs`, and exports the dependencies between the generators as a
+/// graph in the DOT format.
+///
+/// Each generator is linked to the generators which set the targets it watches. The targets which
+/// were set by `inputs` come from an `inputs` node, and the ones which were never set from a
+/// `missing` node. The generators which didn't finish are highlighted.
+pub fn generator_graph_dot<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    inputs: PartialWitness<F>,
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> String {
+    let num_wires = common_data.config.num_wires;
+    let degree = common_data.degree();
+    let representative_map = &prover_data.representative_map;
+    let mut witness = PartitionWitness::new(num_wires, degree, representative_map);
+    let mut producers = vec![None; representative_map.len()];
+    let generator_is_expired =
+        run_generators_recording(&mut witness, inputs, prover_data, Some(&mut producers));
+
+    let escape = |s: &str| {
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    };
+    let mut dot = String::from("digraph generators {\n");
+    dot.push_str("  inputs [shape=box];\n");
+    dot.push_str("  missing [shape=box, color=red];\n");
+    for (i, generator) in prover_data.generators.iter().enumerate() {
+        let mut label = generator.0.id();
+        if let Some(origin) = generator_origin(prover_data, i) {
+            label = format!("{label}\n{origin}");
+        }
+        let style = if generator_is_expired[i] {
+            ""
+        } else {
+            ", color=red"
+        };
+        dot.push_str(&format!("  g{i} [label=\"{}\"{style}];\n", escape(&label)));
+
+        let sources: BTreeSet<_> = generator
+            .0
+            .watch_list()
+            .into_iter()
+            .map(
+                |t| match producers[representative_map[t.index(num_wires, degree)]] {
+                    Some(producer) => format!("g{producer}"),
+                    None if witness.try_get_target(t).is_some() => "inputs".to_string(),
+                    None => "missing".to_string(),
+                },
+            )
+            .collect();
+        for source in sources {
+            dot.push_str(&format!("  {source} -> g{i};\n"));
+        }
+    }
+    dot.push_str("}\n");
+    dot
 }
 
 /// A generator participates in the generation of the witness.
@@ -205,6 +574,18 @@ pub trait SimpleGenerator<F: RichField + Extendable<D>, const D: usize>:
 
     fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>);
 
+    /// Runs the generator if it can make progress, returning whether it is finished. By default,
+    /// it calls `run_once` once all the dependencies are set, but a generator able to produce some
+    /// of its outputs from part of its dependencies may override it to be run several times.
+    fn try_run(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) -> bool {
+        if witness.contains_all(&self.dependencies()) {
+            self.run_once(witness, out_buffer);
+            true
+        } else {
+            false
+        }
+    }
+
     fn adapter(self) -> SimpleGeneratorAdapter<F, Self, D>
     where
         Self: Sized,
@@ -244,12 +625,7 @@ impl<F: RichField + Extendable<D>, SG: SimpleGenerator<F, D>, const D: usize> Wi
     }
 
     fn run(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) -> bool {
-        if witness.contains_all(&self.inner.dependencies()) {
-            self.inner.run_once(witness, out_buffer);
-            true
-        } else {
-            false
-        }
+        self.inner.try_run(witness, out_buffer)
     }
 
     fn serialize(&self, dst: &mut Vec<u8>, common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
@@ -312,7 +688,18 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for Ran
         Vec::new()
     }
 
-    fn run_once(&self, _witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+    #[cfg_attr(not(feature = "rand_chacha"), allow(unused_variables))]
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        #[cfg(feature = "rand_chacha")]
+        if let Some(seed) = witness.rng_seed {
+            // Each target gets its own stream, so that the value doesn't depend on the order in
+            // which generators are run. Stream 0 is left to the prover's blinding.
+            let mut rng = ChaCha20Rng::seed_from_u64(seed);
+            rng.set_stream(1 + witness.target_index(self.target) as u64);
+            out_buffer.set_target(self.target, F::sample(&mut rng));
+            return;
+        }
+
         let random_value = F::rand();
         out_buffer.set_target(self.target, random_value);
     }
@@ -415,3 +802,102 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for Con
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iop::witness::WitnessWrite;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::with_context;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_unsatisfied_generators() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let x_cubed = with_context!(builder, "cube", builder.exp_u64(x, 3));
+        let z = builder.add(x_cubed, y);
+        builder.register_public_input(z);
+        let data = builder.build::<C>();
+
+        let mut inputs = PartialWitness::new();
+        inputs.set_target(y, F::ONE);
+        let Err(err) =
+            try_generate_partial_witness(inputs.clone(), &data.prover_only, &data.common)
+        else {
+            panic!("Witness generation should fail without `x`");
+        };
+        // The generator computing `x^3` waits for the wire `x` is copied to, and the generators
+        // depending on `x^3` wait for it in turn.
+        let stuck = err
+            .generators
+            .iter()
+            .find(|g| g.id == "ExponentiationGenerator")
+            .unwrap();
+        assert_eq!(stuck.missing_targets, [Target::wire(0, 0)]);
+        #[cfg(feature = "generator_origins")]
+        {
+            let origin = stuck.origin.as_ref().unwrap();
+            assert!(origin.gate.is_some());
+            assert_eq!(origin.context, "root > cube");
+        }
+        assert!(err.to_string().contains("generators weren't run"));
+
+        let dot = generator_graph_dot(inputs.clone(), &data.prover_only, &data.common);
+        assert!(dot.starts_with("digraph generators {"));
+        assert!(dot.contains(&format!("missing -> g{}", stuck.index)));
+        assert!(dot.contains("inputs -> "));
+        // Labels spanning several lines are escaped, so each statement stays on one line.
+        assert!(dot
+            .lines()
+            .all(|line| line.ends_with(';') || line.ends_with('{') || line == "}"));
+
+        inputs.set_target(x, F::TWO);
+        let witness =
+            try_generate_partial_witness(inputs, &data.prover_only, &data.common).unwrap();
+        assert_eq!(witness.get_target(z), F::from_canonical_u64(9));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_batches_match_sequential_runs() {
+        use plonky2_maybe_rayon::rayon::ThreadPoolBuilder;
+
+        // Enough independent chains that the queue exceeds `MIN_PARALLEL_BATCH`.
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let xs = builder.add_virtual_targets(2 * MIN_PARALLEL_BATCH);
+        let ys: Vec<_> = xs
+            .iter()
+            .map(|&x| {
+                let x_cubed = builder.exp_u64(x, 3);
+                builder.mul_add(x_cubed, x, x)
+            })
+            .collect();
+        builder.register_public_inputs(&ys);
+        let data = builder.build::<C>();
+
+        let mut inputs = PartialWitness::new();
+        for (i, &x) in xs.iter().enumerate() {
+            inputs.set_target(x, F::from_canonical_usize(i));
+        }
+        let generate = |num_threads| {
+            let pool = ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .build()
+                .unwrap();
+            pool.install(|| {
+                let witness =
+                    try_generate_partial_witness(inputs.clone(), &data.prover_only, &data.common)
+                        .unwrap();
+                witness.get_targets(&ys)
+            })
+        };
+        assert_eq!(generate(1), generate(4));
+    }
+}
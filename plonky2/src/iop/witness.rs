@@ -303,6 +303,9 @@ pub struct PartitionWitness<'a, F: Field> {
     pub representative_map: &'a [usize],
     pub num_wires: usize,
     pub degree: usize,
+    /// Seeds the values of `RandomValueGenerator`s. With `None`, they are drawn from the thread RNG.
+    #[cfg(feature = "rand_chacha")]
+    pub rng_seed: Option<u64>,
 }
 
 impl<'a, F: Field> PartitionWitness<'a, F> {
@@ -312,6 +315,8 @@ impl<'a, F: Field> PartitionWitness<'a, F> {
             representative_map,
             num_wires,
             degree,
+            #[cfg(feature = "rand_chacha")]
+            rng_seed: None,
         }
     }
 
@@ -1,13 +1,11 @@
 static LOGGER_INITIALIZED: Once = Once::new();
 
-use alloc::sync::Arc;
 use std::sync::Once;
 
 use itertools::Itertools;
 use log::{Level, LevelFilter};
 
 use crate::gadgets::lookup::{OTHER_TABLE, SMALLER_TABLE, TIP5_TABLE};
-use crate::gates::lookup_table::LookupTable;
 use crate::gates::noop::NoopGate;
 use crate::plonk::prover::prove;
 use crate::util::timing::TimingTree;
@@ -54,8 +52,8 @@ fn test_lookup_table_not_used() {
     let mut builder = CircuitBuilder::<F, D>::new(config);
 
     let tip5_table = TIP5_TABLE.to_vec();
-    let table: LookupTable = Arc::new((0..256).zip_eq(tip5_table).collect());
-    builder.add_lookup_table_from_pairs(table);
+    let table: Vec<(u16, u16)> = (0..256).zip_eq(tip5_table).collect();
+    builder.add_lookup_table_from_pairs(&table);
 
     builder.build::<C>();
 }
@@ -96,7 +94,7 @@ fn test_one_lookup() -> anyhow::Result<()> {
 
     LOGGER_INITIALIZED.call_once(|| init_logger().unwrap());
     let tip5_table = TIP5_TABLE.to_vec();
-    let table: LookupTable = Arc::new((0..256).zip_eq(tip5_table).collect());
+    let table: Vec<(u16, u16)> = (0..256).zip_eq(tip5_table).collect();
     let config = CircuitConfig::standard_recursion_config();
     let mut builder = CircuitBuilder::<F, D>::new(config);
 
@@ -108,7 +106,7 @@ fn test_one_lookup() -> anyhow::Result<()> {
 
     let out_a = table[look_val_a].1;
     let out_b = table[look_val_b].1;
-    let table_index = builder.add_lookup_table_from_pairs(table);
+    let table_index = builder.add_lookup_table_from_pairs(&table);
     let output_a = builder.add_lookup_from_index(initial_a, table_index);
 
     let output_b = builder.add_lookup_from_index(initial_b, table_index);
@@ -171,11 +169,11 @@ pub fn test_two_luts() -> anyhow::Result<()> {
     let first_out = tip5_table[look_val_a];
     let second_out = tip5_table[look_val_b];
 
-    let table: LookupTable = Arc::new((0..256).zip_eq(tip5_table).collect());
+    let table: Vec<(u16, u16)> = (0..256).zip_eq(tip5_table).collect();
 
     let other_table = OTHER_TABLE.to_vec();
 
-    let table_index = builder.add_lookup_table_from_pairs(table);
+    let table_index = builder.add_lookup_table_from_pairs(&table);
     let output_a = builder.add_lookup_from_index(initial_a, table_index);
 
     let output_b = builder.add_lookup_from_index(initial_b, table_index);
@@ -184,8 +182,8 @@ pub fn test_two_luts() -> anyhow::Result<()> {
     let s = first_out + second_out;
     let final_out = other_table[s as usize];
 
-    let table2: LookupTable = Arc::new((0..256).zip_eq(other_table).collect());
-    let table2_index = builder.add_lookup_table_from_pairs(table2);
+    let table2: Vec<(u16, u16)> = (0..256).zip_eq(other_table).collect();
+    let table2_index = builder.add_lookup_table_from_pairs(&table2);
 
     let output_final = builder.add_lookup_from_index(sum, table2_index);
 
@@ -250,18 +248,18 @@ pub fn test_different_inputs() -> anyhow::Result<()> {
     let init_b = 2;
 
     let tab: Vec<u16> = SMALLER_TABLE.to_vec();
-    let table: LookupTable = Arc::new((2..10).zip_eq(tab).collect());
+    let table: Vec<(u16, u16)> = (2..10).zip_eq(tab).collect();
 
     let other_table = OTHER_TABLE.to_vec();
 
-    let table2: LookupTable = Arc::new((0..256).zip_eq(other_table).collect());
-    let small_index = builder.add_lookup_table_from_pairs(table.clone());
+    let table2: Vec<(u16, u16)> = (0..256).zip_eq(other_table).collect();
+    let small_index = builder.add_lookup_table_from_pairs(&table);
     let output_a = builder.add_lookup_from_index(initial_a, small_index);
 
     let output_b = builder.add_lookup_from_index(initial_b, small_index);
     let sum = builder.add(output_a, output_b);
 
-    let other_index = builder.add_lookup_table_from_pairs(table2.clone());
+    let other_index = builder.add_lookup_table_from_pairs(&table2);
     let output_final = builder.add_lookup_from_index(sum, other_index);
 
     builder.register_public_input(initial_a);
@@ -335,12 +333,12 @@ pub fn test_many_lookups() -> anyhow::Result<()> {
     let look_val_b = 2;
 
     let tip5_table = TIP5_TABLE.to_vec();
-    let table: LookupTable = Arc::new((0..256).zip_eq(tip5_table).collect());
+    let table: Vec<(u16, u16)> = (0..256).zip_eq(tip5_table).collect();
 
     let out_a = table[look_val_a].1;
     let out_b = table[look_val_b].1;
 
-    let tip5_index = builder.add_lookup_table_from_pairs(table);
+    let tip5_index = builder.add_lookup_table_from_pairs(&table);
     let output_a = builder.add_lookup_from_index(initial_a, tip5_index);
 
     let output_b = builder.add_lookup_from_index(initial_b, tip5_index);
@@ -352,12 +350,12 @@ pub fn test_many_lookups() -> anyhow::Result<()> {
 
     let other_table = OTHER_TABLE.to_vec();
 
-    let table2: LookupTable = Arc::new((0..256).zip_eq(other_table).collect());
+    let table2: Vec<(u16, u16)> = (0..256).zip_eq(other_table).collect();
 
     let s = out_a + out_b;
     let out_final = table2[s as usize].1;
 
-    let other_index = builder.add_lookup_table_from_pairs(table2);
+    let other_index = builder.add_lookup_table_from_pairs(&table2);
     let output_final = builder.add_lookup_from_index(sum, other_index);
 
     builder.register_public_input(initial_a);
@@ -426,15 +424,15 @@ pub fn test_same_luts() -> anyhow::Result<()> {
     let look_val_b = 2;
 
     let tip5_table = TIP5_TABLE.to_vec();
-    let table: LookupTable = Arc::new((0..256).zip_eq(tip5_table).collect());
+    let table: Vec<(u16, u16)> = (0..256).zip_eq(tip5_table).collect();
 
-    let table_index = builder.add_lookup_table_from_pairs(table.clone());
+    let table_index = builder.add_lookup_table_from_pairs(&table);
     let output_a = builder.add_lookup_from_index(initial_a, table_index);
 
     let output_b = builder.add_lookup_from_index(initial_b, table_index);
     let sum = builder.add(output_a, output_b);
 
-    let table2_index = builder.add_lookup_table_from_pairs(table);
+    let table2_index = builder.add_lookup_table_from_pairs(&table);
 
     let output_final = builder.add_lookup_from_index(sum, table2_index);
 
@@ -467,6 +465,208 @@ pub fn test_same_luts() -> anyhow::Result<()> {
     Ok(())
 }
 
+// Tests LUTs of different widths in the same circuit: a 3-column XOR table, a read-only memory
+// with arbitrary field elements and the 2-column Tip5 table.
+#[test]
+fn test_multi_column_luts() -> anyhow::Result<()> {
+    use crate::field::types::{Field, Sample};
+    use crate::gates::lookup_table::LookupTable;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    LOGGER_INITIALIZED.call_once(|| init_logger().unwrap());
+    let config = CircuitConfig::standard_recursion_config();
+    let mut builder = CircuitBuilder::<F, D>::new(config);
+
+    let xor_table = LookupTable::new(
+        (0..16u64)
+            .cartesian_product(0..16u64)
+            .map(|(x, y)| [x, y, x ^ y].map(F::from_canonical_u64).to_vec())
+            .collect(),
+    );
+    let rom = F::rand_vec(10);
+    let rom_table = LookupTable::new(
+        rom.iter()
+            .enumerate()
+            .map(|(i, &value)| vec![F::from_canonical_usize(i), value])
+            .collect(),
+    );
+    let tip5_table: Vec<(u16, u16)> = (0..256).zip_eq(TIP5_TABLE).collect();
+
+    let xor_index = builder.add_lookup_table(xor_table);
+    let rom_index = builder.add_lookup_table(rom_table);
+    let tip5_index = builder.add_lookup_table_from_pairs(&tip5_table);
+    assert_eq!(builder.lookup_width(), 3);
+
+    let x = builder.add_virtual_target();
+    let y = builder.add_virtual_target();
+    let x_xor_y = builder.add_lookup_from_inputs(&[x, y], xor_index);
+    let tip5_x = builder.add_lookup_from_index(x, tip5_index);
+
+    let address = builder.add_virtual_target();
+    let value = builder.add_virtual_target();
+    builder.add_lookup(&[address, value], rom_index);
+    let read_value = builder.add_lookup_from_index(address, rom_index);
+    builder.connect(value, read_value);
+
+    builder.register_public_input(x_xor_y);
+    builder.register_public_input(tip5_x);
+
+    let mut pw = PartialWitness::new();
+    pw.set_target(x, F::from_canonical_u64(12));
+    pw.set_target(y, F::from_canonical_u64(10));
+    pw.set_target(address, F::from_canonical_u64(7));
+    pw.set_target(value, rom[7]);
+
+    let data = builder.build::<C>();
+    let mut timing = TimingTree::new("prove multi-column luts", Level::Debug);
+    let proof = prove(&data.prover_only, &data.common, pw, &mut timing)?;
+    timing.print();
+
+    assert_eq!(proof.public_inputs[0], F::from_canonical_u64(12 ^ 10));
+    assert_eq!(
+        proof.public_inputs[1],
+        F::from_canonical_u16(TIP5_TABLE[12])
+    );
+
+    data.verify(proof)
+}
+
+// Tests that the prover can't look up a tuple which is not a row of the LUT.
+#[should_panic]
+#[test]
+fn test_lookup_not_in_table() {
+    use crate::field::types::Field;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    LOGGER_INITIALIZED.call_once(|| init_logger().unwrap());
+    let config = CircuitConfig::standard_recursion_config();
+    let mut builder = CircuitBuilder::<F, D>::new(config);
+
+    let table: Vec<(u16, u16)> = (0..8).zip_eq(SMALLER_TABLE).collect();
+    let table_index = builder.add_lookup_table_from_pairs(&table);
+
+    let input = builder.add_virtual_target();
+    let output = builder.add_virtual_target();
+    builder.add_lookup(&[input, output], table_index);
+
+    let mut pw = PartialWitness::new();
+    pw.set_target(input, F::from_canonical_u16(3));
+    pw.set_target(output, F::from_canonical_u16(SMALLER_TABLE[4]));
+
+    let data = builder.build::<C>();
+    data.prove(pw).unwrap();
+}
+
+// Tests a dynamic LUT, whose rows are witness values, next to a constant one.
+#[test]
+fn test_dynamic_lut() -> anyhow::Result<()> {
+    use crate::field::types::{Field, Sample};
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    LOGGER_INITIALIZED.call_once(|| init_logger().unwrap());
+    let config = CircuitConfig::standard_recursion_config();
+    let mut builder = CircuitBuilder::<F, D>::new(config);
+
+    let tip5_table: Vec<(u16, u16)> = (0..256).zip_eq(TIP5_TABLE).collect();
+    let tip5_index = builder.add_lookup_table_from_pairs(&tip5_table);
+    let x = builder.add_virtual_target();
+    let tip5_x = builder.add_lookup_from_index(x, tip5_index);
+
+    let rows = (0..100)
+        .map(|_| builder.add_virtual_targets(3))
+        .collect::<Vec<_>>();
+    let dynamic_index = builder.add_dynamic_lookup_table(rows.clone());
+    assert_eq!(builder.lookup_width(), 3);
+    let lookups = [5, 99, 5, 0].map(|i| {
+        let tuple = builder.add_virtual_targets(3);
+        builder.add_lookup(&tuple, dynamic_index);
+        (i, tuple)
+    });
+    builder.register_public_input(tip5_x);
+
+    let mut pw = PartialWitness::new();
+    pw.set_target(x, F::from_canonical_u64(12));
+    let values = rows
+        .iter()
+        .map(|row| {
+            let values = F::rand_vec(3);
+            pw.set_target_arr(row, &values);
+            values
+        })
+        .collect::<Vec<_>>();
+    for (i, tuple) in &lookups {
+        pw.set_target_arr(tuple, &values[*i]);
+    }
+
+    let data = builder.build::<C>();
+    let mut timing = TimingTree::new("prove dynamic lut", Level::Debug);
+    let proof = prove(&data.prover_only, &data.common, pw, &mut timing)?;
+    timing.print();
+
+    assert_eq!(
+        proof.public_inputs[0],
+        F::from_canonical_u16(TIP5_TABLE[12])
+    );
+
+    data.verify(proof)
+}
+
+// Tests that the prover can't look up a tuple which is not a row of a dynamic LUT.
+#[should_panic]
+#[test]
+fn test_lookup_not_in_dynamic_table() {
+    use crate::field::types::Field;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    LOGGER_INITIALIZED.call_once(|| init_logger().unwrap());
+    let config = CircuitConfig::standard_recursion_config();
+    let mut builder = CircuitBuilder::<F, D>::new(config);
+
+    let rows = (0..4)
+        .map(|_| vec![builder.add_virtual_target()])
+        .collect::<Vec<_>>();
+    let dynamic_index = builder.add_dynamic_lookup_table(rows.clone());
+    let looked_up = builder.add_virtual_target();
+    builder.add_lookup(&[looked_up], dynamic_index);
+
+    let mut pw = PartialWitness::new();
+    for (i, row) in rows.iter().enumerate() {
+        pw.set_target(row[0], F::from_canonical_usize(i));
+    }
+    pw.set_target(looked_up, F::from_canonical_usize(4));
+
+    let data = builder.build::<C>();
+    data.prove(pw).unwrap();
+}
+
 #[test]
 fn test_big_lut() -> anyhow::Result<()> {
     use crate::field::types::Field;
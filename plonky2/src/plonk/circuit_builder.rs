@@ -1,8 +1,9 @@
 use alloc::collections::BTreeMap;
-use alloc::sync::Arc;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::cmp::max;
+#[cfg(feature = "generator_origins")]
+use core::iter;
 #[cfg(feature = "std")]
 use std::time::Instant;
 
@@ -17,9 +18,12 @@ use crate::field::fft::fft_root_table;
 use crate::field::polynomial::PolynomialValues;
 use crate::field::types::Field;
 use crate::fri::oracle::PolynomialBatch;
+#[cfg(feature = "std")]
+use crate::fri::security::SecurityAssumption;
 use crate::fri::{FriConfig, FriParams};
 use crate::gadgets::arithmetic::BaseArithmeticOperation;
 use crate::gadgets::arithmetic_extension::ExtensionArithmeticOperation;
+use crate::gadgets::memory::Memory;
 use crate::gadgets::polynomial::PolynomialCoeffsExtTarget;
 use crate::gates::arithmetic_base::ArithmeticGate;
 use crate::gates::arithmetic_extension::ArithmeticExtensionGate;
@@ -34,6 +38,8 @@ use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField
 use crate::hash::merkle_proofs::MerkleProofTarget;
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::ext_target::ExtensionTarget;
+#[cfg(feature = "generator_origins")]
+use crate::iop::generator::GeneratorOrigin;
 use crate::iop::generator::{
     ConstantGenerator, CopyGenerator, RandomValueGenerator, SimpleGenerator, WitnessGeneratorRef,
 };
@@ -111,6 +117,10 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     /// Generators used to generate the witness.
     generators: Vec<WitnessGeneratorRef<F, D>>,
 
+    /// Where each of the generators above was added, for diagnostics.
+    #[cfg(feature = "generator_origins")]
+    generator_origins: Vec<GeneratorOrigin>,
+
     constants_to_targets: HashMap<F, Target>,
     targets_to_constants: HashMap<Target, F>,
 
@@ -129,11 +139,20 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     /// Rows for each LUT: LookupWire contains: first `LookupGate`, first `LookupTableGate`, last `LookupTableGate`.
     lookup_rows: Vec<LookupWire>,
 
-    /// For each LUT index, vector of `(looking_in, looking_out)` pairs.
+    /// For each LUT index, the looked up tuples.
     lut_to_lookups: Vec<Lookup>,
 
-    // Lookup tables in the form of `Vec<(input_value, output_value)>`.
-    luts: Vec<LookupTable>,
+    /// Lookup tables.
+    luts: Vec<LookupTable<F>>,
+
+    /// For each dynamic LUT index, the rows of the table.
+    dynamic_lut_rows: HashMap<usize, Vec<Vec<Target>>>,
+
+    /// Read/write memories, turned into lookups when the circuit is built.
+    pub(crate) memories: Vec<Memory>,
+
+    /// The index of the shared table used by lookup-based range checks, once it has been added.
+    pub(crate) range_check_lut: Option<usize>,
 
     /// Optional common data. When it is `Some(goal_data)`, the `build` function panics if the resulting
     /// common data doesn't equal `goal_data`.
@@ -143,6 +162,11 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     /// Optional verifier data that is registered as public inputs.
     /// This is used in cyclic recursion to hold the circuit's own verifier key.
     pub(crate) verifier_data_public_input: Option<VerifierCircuitTarget>,
+
+    /// Optional security requirement. When it is `Some(assumption)`, the `build` function panics
+    /// if the circuit, at its final degree, falls short of `security_bits` under `assumption`.
+    #[cfg(feature = "std")]
+    required_security: Option<SecurityAssumption>,
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
@@ -157,6 +181,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             copy_constraints: Vec::new(),
             context_log: ContextTree::new(),
             generators: Vec::new(),
+            #[cfg(feature = "generator_origins")]
+            generator_origins: Vec::new(),
             constants_to_targets: HashMap::new(),
             targets_to_constants: HashMap::new(),
             base_arithmetic_results: HashMap::new(),
@@ -166,8 +192,13 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             lookup_rows: Vec::new(),
             lut_to_lookups: Vec::new(),
             luts: Vec::new(),
+            dynamic_lut_rows: HashMap::new(),
+            memories: Vec::new(),
+            range_check_lut: None,
             goal_common_data: None,
             verifier_data_public_input: None,
+            #[cfg(feature = "std")]
+            required_security: None,
         };
         builder.check_config();
         builder
@@ -196,6 +227,14 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         );
     }
 
+    /// Requires the built circuit to achieve `security_bits` under `assumption`. Unlike the check
+    /// in `new`, which only covers the FRI query phase, this accounts for the circuit's final
+    /// degree, so it is done by `build`.
+    #[cfg(feature = "std")]
+    pub fn require_security(&mut self, assumption: SecurityAssumption) {
+        self.required_security = Some(assumption);
+    }
+
     pub fn set_domain_separator(&mut self, separator: Vec<F>) {
         assert!(self.domain_separator.is_none());
         self.domain_separator = Some(separator);
@@ -233,22 +272,22 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         });
     }
 
-    /// Adds a looking (input, output) pair to the corresponding LUT.
-    pub fn update_lookups(&mut self, looking_in: Target, looking_out: Target, lut_index: usize) {
+    /// Adds a looked up tuple to the corresponding LUT.
+    pub fn update_lookups(&mut self, lookup: &[Target], lut_index: usize) {
         assert!(
             lut_index < self.lut_to_lookups.len(),
             "The LUT with index {} has not been created. The last LUT is at index {}",
             lut_index,
             self.lut_to_lookups.len() - 1
         );
-        self.lut_to_lookups[lut_index].push((looking_in, looking_out));
+        self.lut_to_lookups[lut_index].push(lookup.to_vec());
     }
 
     pub fn num_luts(&self) -> usize {
         self.lut_to_lookups.len()
     }
 
-    pub fn get_lut_lookups(&self, lut_index: usize) -> &[(Target, Target)] {
+    pub fn get_lut_lookups(&self, lut_index: usize) -> &[Vec<Target>] {
         &self.lut_to_lookups[lut_index]
     }
 
@@ -451,14 +490,33 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 
     pub fn add_generators(&mut self, generators: Vec<WitnessGeneratorRef<F, D>>) {
+        #[cfg(feature = "generator_origins")]
+        {
+            let origin = self.current_generator_origin();
+            self.generator_origins
+                .extend(iter::repeat_n(origin, generators.len()));
+        }
         self.generators.extend(generators);
     }
 
     pub fn add_simple_generator<G: SimpleGenerator<F, D>>(&mut self, generator: G) {
+        #[cfg(feature = "generator_origins")]
+        {
+            let origin = self.current_generator_origin();
+            self.generator_origins.push(origin);
+        }
         self.generators
             .push(WitnessGeneratorRef::new(generator.adapter()));
     }
 
+    #[cfg(feature = "generator_origins")]
+    fn current_generator_origin(&self) -> GeneratorOrigin {
+        GeneratorOrigin {
+            gate: None,
+            context: self.context_log.open_stack(),
+        }
+    }
+
     /// Returns a routable target with a value of 0.
     pub fn zero(&mut self) -> Target {
         self.constant(F::ZERO)
@@ -588,12 +646,12 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 
     /// Checks whether a LUT is already stored in `self.luts`
-    pub fn is_stored(&self, lut: LookupTable) -> Option<usize> {
+    pub fn is_stored(&self, lut: LookupTable<F>) -> Option<usize> {
         self.luts.iter().position(|elt| *elt == lut)
     }
 
     /// Returns the LUT at index `idx`.
-    pub fn get_lut(&self, idx: usize) -> LookupTable {
+    pub fn get_lut(&self, idx: usize) -> LookupTable<F> {
         assert!(
             idx < self.luts.len(),
             "index idx: {} greater than the total number of created LUTS: {}",
@@ -603,6 +661,11 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.luts[idx].clone()
     }
 
+    /// Returns the number of columns of the lookup argument, i.e. the width of the widest LUT.
+    pub fn lookup_width(&self) -> usize {
+        self.luts.iter().map(|lut| lut.width()).max().unwrap_or(0)
+    }
+
     /// Generates a LUT from a function.
     pub fn get_lut_from_fn<T>(f: fn(T) -> T, inputs: &[T]) -> Vec<(T, T)>
     where
@@ -613,17 +676,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
     /// Given a function `f: fn(u16) -> u16`, adds a LUT to the circuit builder.
     pub fn update_luts_from_fn(&mut self, f: fn(u16) -> u16, inputs: &[u16]) -> usize {
-        let lut = Arc::new(Self::get_lut_from_fn::<u16>(f, inputs));
-
-        // If the LUT `lut` is already stored in `self.luts`, return its index. Otherwise, append `table` to `self.luts` and return its index.
-        if let Some(idx) = self.is_stored(lut.clone()) {
-            idx
-        } else {
-            self.luts.push(lut);
-            self.lut_to_lookups.push(vec![]);
-            assert!(self.luts.len() == self.lut_to_lookups.len());
-            self.luts.len() - 1
-        }
+        self.update_luts_from_pairs(&Self::get_lut_from_fn::<u16>(f, inputs))
     }
 
     /// Adds a table to the vector of LUTs in the circuit builder, given a list of inputs and table values.
@@ -638,22 +691,17 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             .iter()
             .copied()
             .zip_eq(table.iter().copied())
-            .collect();
-        let lut: LookupTable = Arc::new(pairs);
+            .collect::<Vec<_>>();
+        self.update_luts_from_pairs(&pairs)
+    }
 
-        // If the LUT `lut` is already stored in `self.luts`, return its index. Otherwise, append `table` to `self.luts` and return its index.
-        if let Some(idx) = self.is_stored(lut.clone()) {
-            idx
-        } else {
-            self.luts.push(lut);
-            self.lut_to_lookups.push(vec![]);
-            assert!(self.luts.len() == self.lut_to_lookups.len());
-            self.luts.len() - 1
-        }
+    /// Adds a table of (input, output) pairs to the vector of LUTs in the circuit builder.
+    pub fn update_luts_from_pairs(&mut self, pairs: &[(u16, u16)]) -> usize {
+        self.update_luts(LookupTable::from_pairs(pairs))
     }
 
     /// Adds a table to the vector of LUTs in the circuit builder.
-    pub fn update_luts_from_pairs(&mut self, table: LookupTable) -> usize {
+    pub fn update_luts(&mut self, table: LookupTable<F>) -> usize {
         // If the LUT `table` is already stored in `self.luts`, return its index. Otherwise, append `table` to `self.luts` and return its index.
         if let Some(idx) = self.is_stored(table.clone()) {
             idx
@@ -665,6 +713,26 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
     }
 
+    /// Adds a dynamic table, whose rows are tuples of targets, to the vector of LUTs in the circuit
+    /// builder. Unlike constant tables, dynamic tables are never shared.
+    pub fn update_dynamic_luts(&mut self, rows: Vec<Vec<Target>>) -> usize {
+        let width = rows.first().map_or(0, Vec::len);
+        assert!(
+            rows.iter().all(|row| row.len() == width),
+            "All the rows of a lookup table must have the same width"
+        );
+        self.luts.push(LookupTable::dynamic(width, rows.len()));
+        self.lut_to_lookups.push(vec![]);
+        let lut_index = self.luts.len() - 1;
+        self.dynamic_lut_rows.insert(lut_index, rows);
+        lut_index
+    }
+
+    /// Returns the rows of the LUT at index `idx` if it is dynamic, and `None` otherwise.
+    pub fn get_dynamic_lut_rows(&self, idx: usize) -> Option<&[Vec<Target>]> {
+        self.dynamic_lut_rows.get(&idx).map(Vec::as_slice)
+    }
+
     /// Find an available slot, of the form `(row, op)` for gate `G` using parameters `params`
     /// and constants `constants`. Parameters are any data used to differentiate which gate should be
     /// used for the given operation.
@@ -938,6 +1006,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
         let rate_bits = self.config.fri_config.rate_bits;
         let cap_height = self.config.fri_config.cap_height;
+        // Memories are checked with lookups, so they must be placed first.
+        self.add_all_memories();
         // Total number of LUTs.
         let num_luts = self.get_luts_length();
         // Hash the public inputs, and route them to a `PublicInputGate` which will enforce that
@@ -1000,6 +1070,13 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             fri_params.total_arities() <= degree_bits + rate_bits - cap_height,
             "FRI total reduction arity is too large.",
         );
+        #[cfg(feature = "std")]
+        if let Some(assumption) = self.required_security {
+            let report = self.config.security::<F, D>(degree_bits);
+            if let Err(e) = report.check(assumption, self.config.security_bits) {
+                panic!("Insecure circuit config for degree 2^{degree_bits}: {e}");
+            }
+        }
 
         let quotient_degree_factor = self.config.max_quotient_degree_factor;
         let mut gates = self.gates.iter().cloned().collect::<Vec<_>>();
@@ -1012,7 +1089,15 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let num_lookup_selectors = if num_luts != 0 {
             let selector_lookups =
                 selectors_lookup(&gates, &self.gate_instances, &self.lookup_rows);
-            let selector_ends = selector_ends_lookups(&self.lookup_rows, &self.gate_instances);
+            // Only constant LUTs are checked against their expected contents.
+            let static_lookup_rows = self
+                .lookup_rows
+                .iter()
+                .zip_eq(&self.luts)
+                .filter(|(_, lut)| !lut.is_dynamic())
+                .map(|(lookup_wire, _)| lookup_wire.clone())
+                .collect::<Vec<_>>();
+            let selector_ends = selector_ends_lookups(&static_lookup_rows, &self.gate_instances);
             let all_lookup_selectors = [selector_lookups, selector_ends].concat();
             let num_lookup_selectors = all_lookup_selectors.len();
             constant_vecs.extend(all_lookup_selectors);
@@ -1059,20 +1144,26 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             .collect::<HashMap<_, _>>();
 
         // Add gate generators.
-        self.add_generators(
-            self.gate_instances
-                .iter()
-                .enumerate()
-                .flat_map(|(index, gate)| {
-                    let mut gens = gate.gate_ref.0.generators(index, &gate.constants);
-                    // Remove unused generators, if any.
-                    if let Some(&op) = incomplete_gates.get(&index) {
-                        gens.drain(op..);
-                    }
-                    gens
-                })
-                .collect(),
-        );
+        for (index, gate) in self.gate_instances.iter().enumerate() {
+            let mut gens = gate.gate_ref.0.generators(index, &gate.constants);
+            // Remove unused generators, if any.
+            if let Some(&op) = incomplete_gates.get(&index) {
+                gens.truncate(op);
+            }
+            if gens.is_empty() {
+                continue;
+            }
+            #[cfg(feature = "generator_origins")]
+            {
+                let origin = GeneratorOrigin {
+                    gate: Some((gate.gate_ref.0.id(), index)),
+                    context: self.context_log.stack_at_gate(index),
+                };
+                self.generator_origins
+                    .extend(iter::repeat_n(origin, gens.len()));
+            }
+            self.generators.extend(gens);
+        }
 
         // Index generator indices by their watched targets.
         let mut generator_indices_by_watches = BTreeMap::new();
@@ -1105,7 +1196,10 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             0
         } else {
             // There is 1 RE polynomial and multiple Sum/LDC polynomials.
-            ceil_div_usize(LookupGate::num_slots(&self.config), lookup_degree) + 1
+            ceil_div_usize(
+                LookupGate::<F>::num_slots(&self.config, self.lookup_width()),
+                lookup_degree,
+            ) + 1
         };
         let constants_sigmas_cap = constants_sigmas_commitment.merkle_tree.cap.clone();
         let domain_separator = self.domain_separator.unwrap_or_default();
@@ -1148,6 +1242,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
         let prover_only = ProverOnlyCircuitData::<F, C, D> {
             generators: self.generators,
+            #[cfg(feature = "generator_origins")]
+            generator_origins: self.generator_origins,
             generator_indices_by_watches,
             constants_sigmas_commitment,
             sigmas: transpose_poly_values(sigma_vecs),
@@ -12,6 +12,8 @@ use crate::field::fft::FftRootTable;
 use crate::field::types::Field;
 use crate::fri::oracle::PolynomialBatch;
 use crate::fri::reduction_strategies::FriReductionStrategy;
+#[cfg(feature = "std")]
+use crate::fri::security::SecurityReport;
 use crate::fri::structure::{
     FriBatchInfo, FriBatchInfoTarget, FriInstanceInfo, FriInstanceInfoTarget, FriOracleInfo,
     FriPolynomialInfo,
@@ -21,9 +23,12 @@ use crate::gates::gate::GateRef;
 use crate::gates::lookup::Lookup;
 use crate::gates::lookup_table::LookupTable;
 use crate::gates::selectors::SelectorsInfo;
-use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
+use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
 use crate::hash::merkle_tree::MerkleCap;
+use crate::hash::poseidon::PoseidonHash;
 use crate::iop::ext_target::ExtensionTarget;
+#[cfg(feature = "generator_origins")]
+use crate::iop::generator::GeneratorOrigin;
 use crate::iop::generator::{generate_partial_witness, WitnessGeneratorRef};
 use crate::iop::target::Target;
 use crate::iop::witness::{PartialWitness, PartitionWitness};
@@ -31,10 +36,11 @@ use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::config::{GenericConfig, Hasher};
 use crate::plonk::plonk_common::PlonkOracle;
 use crate::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
-use crate::plonk::prover::prove;
+use crate::plonk::prover::{prove, prove_with_options, ProverOptions};
 use crate::plonk::verifier::verify;
 use crate::util::serialization::{
-    Buffer, GateSerializer, IoResult, Read, WitnessGeneratorSerializer, Write,
+    ArtifactKind, Buffer, DefaultGateSerializer, GateSerializer, IoError, IoResult, Read,
+    WitnessGeneratorSerializer, Write,
 };
 use crate::util::timing::TimingTree;
 
@@ -46,6 +52,10 @@ pub struct CircuitConfig {
     /// Whether to use a dedicated gate for base field arithmetic, rather than using a single gate
     /// for both base field and extension field arithmetic.
     pub use_base_arithmetic_gate: bool,
+    /// Whether `range_check` should decompose values into 16-bit limbs checked against a shared
+    /// lookup table, rather than into bits with a `BaseSumGate`. The table costs a fixed number of
+    /// rows, so this only pays off in circuits with many range checks.
+    pub use_lookup_range_checks: bool,
     pub security_bits: usize,
     /// The number of challenge points to generate, for IOPs that have soundness errors of (roughly)
     /// `degree / |F|`.
@@ -75,6 +85,7 @@ impl CircuitConfig {
             num_routed_wires: 80,
             num_constants: 2,
             use_base_arithmetic_gate: true,
+            use_lookup_range_checks: false,
             security_bits: 100,
             num_challenges: 2,
             zero_knowledge: false,
@@ -109,6 +120,37 @@ impl CircuitConfig {
             ..Self::standard_recursion_config()
         }
     }
+
+    /// A config minimizing proof size, at the cost of a slower prover, targeting ~100 bit
+    /// conjectured security. It suits proofs that aren't verified recursively, and is best
+    /// combined with `CompressedProofWithPublicInputs::to_compact_bytes`.
+    pub fn size_optimized_config() -> Self {
+        Self {
+            fri_config: FriConfig {
+                rate_bits: 4,
+                cap_height: 2,
+                proof_of_work_bits: 20,
+                reduction_strategy: FriReductionStrategy::MinSize(None),
+                num_query_rounds: 20,
+            },
+            ..Self::standard_recursion_config()
+        }
+    }
+
+    /// Estimates the security of proofs for circuits with `2^degree_bits` rows.
+    #[cfg(feature = "std")]
+    pub fn security<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        degree_bits: usize,
+    ) -> SecurityReport {
+        let fri_params = self.fri_config.fri_params(degree_bits, self.zero_knowledge);
+        SecurityReport::new(
+            &fri_params,
+            (F::ORDER as f64).log2(),
+            D,
+            self.num_challenges,
+        )
+    }
 }
 
 /// Mock circuit data to only do witness generation without generating a proof.
@@ -144,6 +186,8 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
     ) -> IoResult<Vec<u8>> {
         let mut buffer = Vec::new();
+        buffer.write_header(ArtifactKind::CircuitData)?;
+        buffer.write_hash::<F, PoseidonHash>(self.common.digest())?;
         buffer.write_circuit_data(self, gate_serializer, generator_serializer)?;
         Ok(buffer)
     }
@@ -154,7 +198,11 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
     ) -> IoResult<Self> {
         let mut buffer = Buffer::new(bytes);
-        buffer.read_circuit_data(gate_serializer, generator_serializer)
+        buffer.read_header(ArtifactKind::CircuitData)?;
+        let digest = buffer.read_hash::<F, PoseidonHash>()?;
+        let data = buffer.read_circuit_data(gate_serializer, generator_serializer)?;
+        data.common.check_digest(digest)?;
+        Ok(data)
     }
 
     pub fn prove(&self, inputs: PartialWitness<F>) -> Result<ProofWithPublicInputs<F, C, D>> {
@@ -166,6 +214,20 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         )
     }
 
+    pub fn prove_with_options(
+        &self,
+        inputs: PartialWitness<F>,
+        options: &ProverOptions<F>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_with_options::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            options,
+            &mut TimingTree::default(),
+        )
+    }
+
     pub fn verify(&self, proof_with_pis: ProofWithPublicInputs<F, C, D>) -> Result<()> {
         verify::<F, C, D>(proof_with_pis, &self.verifier_only, &self.common)
     }
@@ -241,6 +303,8 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
     ) -> IoResult<Vec<u8>> {
         let mut buffer = Vec::new();
+        buffer.write_header(ArtifactKind::ProverCircuitData)?;
+        buffer.write_hash::<F, PoseidonHash>(self.common.digest())?;
         buffer.write_prover_circuit_data(self, gate_serializer, generator_serializer)?;
         Ok(buffer)
     }
@@ -251,7 +315,11 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
     ) -> IoResult<Self> {
         let mut buffer = Buffer::new(bytes);
-        buffer.read_prover_circuit_data(gate_serializer, generator_serializer)
+        buffer.read_header(ArtifactKind::ProverCircuitData)?;
+        let digest = buffer.read_hash::<F, PoseidonHash>()?;
+        let data = buffer.read_prover_circuit_data(gate_serializer, generator_serializer)?;
+        data.common.check_digest(digest)?;
+        Ok(data)
     }
 
     pub fn prove(&self, inputs: PartialWitness<F>) -> Result<ProofWithPublicInputs<F, C, D>> {
@@ -262,6 +330,20 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             &mut TimingTree::default(),
         )
     }
+
+    pub fn prove_with_options(
+        &self,
+        inputs: PartialWitness<F>,
+        options: &ProverOptions<F>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_with_options::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            options,
+            &mut TimingTree::default(),
+        )
+    }
 }
 
 /// Circuit data required by the prover.
@@ -280,6 +362,8 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
 {
     pub fn to_bytes(&self, gate_serializer: &dyn GateSerializer<F, D>) -> IoResult<Vec<u8>> {
         let mut buffer = Vec::new();
+        buffer.write_header(ArtifactKind::VerifierCircuitData)?;
+        buffer.write_hash::<F, PoseidonHash>(self.common.digest())?;
         buffer.write_verifier_circuit_data(self, gate_serializer)?;
         Ok(buffer)
     }
@@ -289,7 +373,11 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         gate_serializer: &dyn GateSerializer<F, D>,
     ) -> IoResult<Self> {
         let mut buffer = Buffer::new(&bytes);
-        buffer.read_verifier_circuit_data(gate_serializer)
+        buffer.read_header(ArtifactKind::VerifierCircuitData)?;
+        let digest = buffer.read_hash::<F, PoseidonHash>()?;
+        let data = buffer.read_verifier_circuit_data(gate_serializer)?;
+        data.common.check_digest(digest)?;
+        Ok(data)
     }
 
     pub fn verify(&self, proof_with_pis: ProofWithPublicInputs<F, C, D>) -> Result<()> {
@@ -312,6 +400,11 @@ pub struct ProverOnlyCircuitData<
     const D: usize,
 > {
     pub generators: Vec<WitnessGeneratorRef<F, D>>,
+    /// Where each generator was added to the circuit, used to diagnose stalled witness generation.
+    /// This costs a `String` per generator, so it's only recorded with the `generator_origins`
+    /// feature. It isn't serialized, so it's empty for deserialized circuits.
+    #[cfg(feature = "generator_origins")]
+    pub generator_origins: Vec<GeneratorOrigin>,
     /// Generator indices (within the `Vec` above), indexed by the representative of each target
     /// they watch.
     pub generator_indices_by_watches: BTreeMap<usize, Vec<usize>>,
@@ -346,16 +439,22 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         common_data: &CommonCircuitData<F, D>,
     ) -> IoResult<Vec<u8>> {
         let mut buffer = Vec::new();
+        buffer.write_header(ArtifactKind::ProverOnlyCircuitData)?;
+        buffer.write_hash::<F, PoseidonHash>(common_data.digest())?;
         buffer.write_prover_only_circuit_data(self, generator_serializer, common_data)?;
         Ok(buffer)
     }
 
+    /// Reads prover data written by `to_bytes`, failing if it was written with other
+    /// `common_data`.
     pub fn from_bytes(
         bytes: &[u8],
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
         common_data: &CommonCircuitData<F, D>,
     ) -> IoResult<Self> {
         let mut buffer = Buffer::new(bytes);
+        buffer.read_header(ArtifactKind::ProverOnlyCircuitData)?;
+        common_data.check_digest(buffer.read_hash::<F, PoseidonHash>()?)?;
         buffer.read_prover_only_circuit_data(generator_serializer, common_data)
     }
 }
@@ -373,12 +472,14 @@ pub struct VerifierOnlyCircuitData<C: GenericConfig<D>, const D: usize> {
 impl<C: GenericConfig<D>, const D: usize> VerifierOnlyCircuitData<C, D> {
     pub fn to_bytes(&self) -> IoResult<Vec<u8>> {
         let mut buffer = Vec::new();
+        buffer.write_header(ArtifactKind::VerifierOnlyCircuitData)?;
         buffer.write_verifier_only_circuit_data(self)?;
         Ok(buffer)
     }
 
     pub fn from_bytes(bytes: Vec<u8>) -> IoResult<Self> {
         let mut buffer = Buffer::new(&bytes);
+        buffer.read_header(ArtifactKind::VerifierOnlyCircuitData)?;
         buffer.read_verifier_only_circuit_data()
     }
 }
@@ -420,12 +521,14 @@ pub struct CommonCircuitData<F: RichField + Extendable<D>, const D: usize> {
     pub num_lookup_selectors: usize,
 
     /// The stored lookup tables.
-    pub luts: Vec<LookupTable>,
+    pub luts: Vec<LookupTable<F>>,
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
     pub fn to_bytes(&self, gate_serializer: &dyn GateSerializer<F, D>) -> IoResult<Vec<u8>> {
         let mut buffer = Vec::new();
+        buffer.write_header(ArtifactKind::CommonCircuitData)?;
+        buffer.write_hash::<F, PoseidonHash>(self.digest())?;
         buffer.write_common_circuit_data(self, gate_serializer)?;
         Ok(buffer)
     }
@@ -435,7 +538,47 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
         gate_serializer: &dyn GateSerializer<F, D>,
     ) -> IoResult<Self> {
         let mut buffer = Buffer::new(&bytes);
-        buffer.read_common_circuit_data(gate_serializer)
+        buffer.read_header(ArtifactKind::CommonCircuitData)?;
+        let digest = buffer.read_hash::<F, PoseidonHash>()?;
+        let common_data = buffer.read_common_circuit_data(gate_serializer)?;
+        common_data.check_digest(digest)?;
+        Ok(common_data)
+    }
+
+    /// A digest of this data, stored in serialized circuit data to check that it is read back
+    /// along with the same common data. Gates are identified by their IDs, so that the digest
+    /// doesn't depend on the `GateSerializer`.
+    pub fn digest(&self) -> HashOut<F> {
+        let without_gates = CommonCircuitData {
+            gates: Vec::new(),
+            ..self.clone()
+        };
+        let mut bytes = Vec::new();
+        bytes
+            .write_common_circuit_data(&without_gates, &DefaultGateSerializer)
+            .expect("Writing to a byte-vector cannot fail.");
+        for gate in &self.gates {
+            bytes
+                .write_str(&gate.0.id())
+                .expect("Writing to a byte-vector cannot fail.");
+        }
+        let elements = bytes
+            .chunks(4)
+            .map(|chunk| {
+                let mut limb = [0; 4];
+                limb[..chunk.len()].copy_from_slice(chunk);
+                F::from_canonical_u32(u32::from_le_bytes(limb))
+            })
+            .collect::<Vec<_>>();
+        PoseidonHash::hash_no_pad(&elements)
+    }
+
+    fn check_digest(&self, digest: HashOut<F>) -> IoResult<()> {
+        if self.digest() == digest {
+            Ok(())
+        } else {
+            Err(IoError::CommonDataMismatch)
+        }
     }
 
     pub const fn degree_bits(&self) -> usize {
@@ -497,6 +640,21 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
             ..self.num_zs_partial_products_polys() + i * self.num_lookup_polys + 2
     }
 
+    /// Number of columns of the lookup argument, i.e. the width of the widest lookup table.
+    pub fn lookup_width(&self) -> usize {
+        self.luts.iter().map(|lut| lut.width()).max().unwrap_or(0)
+    }
+
+    /// Indices of the constant lookup tables, in the order of their end selectors. Dynamic tables
+    /// have no expected contents, hence no end selector.
+    pub(crate) fn static_lut_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.luts
+            .iter()
+            .enumerate()
+            .filter(|(_, lut)| !lut.is_dynamic())
+            .map(|(i, _)| i)
+    }
+
     pub(crate) fn get_fri_instance(&self, zeta: F::Extension) -> FriInstanceInfo<F, D> {
         // All polynomials are opened at zeta.
         let zeta_batch = FriBatchInfo {
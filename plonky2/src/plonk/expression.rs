@@ -0,0 +1,198 @@
+//! An arithmetic interface over which constraints can be written once, and evaluated both
+//! natively, on packed field elements, and recursively, as `ExtensionTarget`s of a circuit.
+
+use core::marker::PhantomData;
+
+use crate::field::extension::{Extendable, FieldExtension};
+use crate::field::packed::PackedField;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// Builds arithmetic expressions in some representation, `Self::Expr`.
+///
+/// Constraints written against this trait are evaluated natively with a
+/// [`PackedExpressionBuilder`], and recursively with a [`CircuitBuilder`], so that the native and
+/// recursive evaluators can't disagree.
+pub trait ExpressionBuilder<F: RichField + Extendable<D>, const D: usize> {
+    type Expr: Copy;
+
+    fn constant(&mut self, c: F) -> Self::Expr;
+
+    fn add(&mut self, x: Self::Expr, y: Self::Expr) -> Self::Expr;
+
+    fn sub(&mut self, x: Self::Expr, y: Self::Expr) -> Self::Expr;
+
+    fn mul(&mut self, x: Self::Expr, y: Self::Expr) -> Self::Expr;
+
+    fn zero(&mut self) -> Self::Expr {
+        self.constant(F::ZERO)
+    }
+
+    fn one(&mut self) -> Self::Expr {
+        self.constant(F::ONE)
+    }
+
+    fn neg(&mut self, x: Self::Expr) -> Self::Expr {
+        let zero = self.zero();
+        self.sub(zero, x)
+    }
+
+    fn square(&mut self, x: Self::Expr) -> Self::Expr {
+        self.mul(x, x)
+    }
+
+    /// Computes `x * y + z`.
+    fn mul_add(&mut self, x: Self::Expr, y: Self::Expr, z: Self::Expr) -> Self::Expr {
+        let product = self.mul(x, y);
+        self.add(product, z)
+    }
+
+    /// Computes `x * y - z`.
+    fn mul_sub(&mut self, x: Self::Expr, y: Self::Expr, z: Self::Expr) -> Self::Expr {
+        let product = self.mul(x, y);
+        self.sub(product, z)
+    }
+
+    /// Computes `c * x`.
+    fn mul_const(&mut self, c: F, x: Self::Expr) -> Self::Expr {
+        let c = self.constant(c);
+        self.mul(c, x)
+    }
+
+    /// Computes `x + c`.
+    fn add_const(&mut self, x: Self::Expr, c: F) -> Self::Expr {
+        let c = self.constant(c);
+        self.add(x, c)
+    }
+
+    /// Computes `b * x + (1 - b) * y`, which is `x` if `b = 1` and `y` if `b = 0`.
+    fn select(&mut self, b: Self::Expr, x: Self::Expr, y: Self::Expr) -> Self::Expr {
+        let diff = self.sub(x, y);
+        self.mul_add(b, diff, y)
+    }
+
+    /// Computes the sum of `terms`.
+    fn sum(&mut self, terms: &[Self::Expr]) -> Self::Expr {
+        match terms.split_first() {
+            Some((&first, rest)) => rest.iter().fold(first, |acc, &t| self.add(acc, t)),
+            None => self.zero(),
+        }
+    }
+}
+
+/// Evaluates expressions natively, on packed elements of `FE`, a degree `D2` extension of `F`.
+///
+/// This covers the evaluation of constraints on the base field, with `D2 = 1`, and on the
+/// extension field, with `P = F::Extension`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PackedExpressionBuilder<P: PackedField, const D2: usize> {
+    _phantom: PhantomData<P>,
+}
+
+impl<P: PackedField, const D2: usize> PackedExpressionBuilder<P, D2> {
+    pub const fn new() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<F, P, const D: usize, const D2: usize> ExpressionBuilder<F, D>
+    for PackedExpressionBuilder<P, D2>
+where
+    F: RichField + Extendable<D>,
+    P: PackedField,
+    P::Scalar: FieldExtension<D2, BaseField = F>,
+{
+    type Expr = P;
+
+    fn constant(&mut self, c: F) -> P {
+        P::from(P::Scalar::from_basefield(c))
+    }
+
+    fn add(&mut self, x: P, y: P) -> P {
+        x + y
+    }
+
+    fn sub(&mut self, x: P, y: P) -> P {
+        x - y
+    }
+
+    fn mul(&mut self, x: P, y: P) -> P {
+        x * y
+    }
+
+    fn neg(&mut self, x: P) -> P {
+        -x
+    }
+
+    fn mul_const(&mut self, c: F, x: P) -> P {
+        x * P::Scalar::from_basefield(c)
+    }
+
+    fn add_const(&mut self, x: P, c: F) -> P {
+        x + P::Scalar::from_basefield(c)
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> ExpressionBuilder<F, D>
+    for CircuitBuilder<F, D>
+{
+    type Expr = ExtensionTarget<D>;
+
+    fn constant(&mut self, c: F) -> ExtensionTarget<D> {
+        self.constant_extension(c.into())
+    }
+
+    fn add(&mut self, x: ExtensionTarget<D>, y: ExtensionTarget<D>) -> ExtensionTarget<D> {
+        self.add_extension(x, y)
+    }
+
+    fn sub(&mut self, x: ExtensionTarget<D>, y: ExtensionTarget<D>) -> ExtensionTarget<D> {
+        self.sub_extension(x, y)
+    }
+
+    fn mul(&mut self, x: ExtensionTarget<D>, y: ExtensionTarget<D>) -> ExtensionTarget<D> {
+        self.mul_extension(x, y)
+    }
+
+    fn mul_add(
+        &mut self,
+        x: ExtensionTarget<D>,
+        y: ExtensionTarget<D>,
+        z: ExtensionTarget<D>,
+    ) -> ExtensionTarget<D> {
+        self.mul_add_extension(x, y, z)
+    }
+
+    fn mul_sub(
+        &mut self,
+        x: ExtensionTarget<D>,
+        y: ExtensionTarget<D>,
+        z: ExtensionTarget<D>,
+    ) -> ExtensionTarget<D> {
+        self.mul_sub_extension(x, y, z)
+    }
+
+    fn mul_const(&mut self, c: F, x: ExtensionTarget<D>) -> ExtensionTarget<D> {
+        self.mul_const_extension(c, x)
+    }
+
+    fn add_const(&mut self, x: ExtensionTarget<D>, c: F) -> ExtensionTarget<D> {
+        self.add_const_extension(x, c)
+    }
+
+    fn select(
+        &mut self,
+        b: ExtensionTarget<D>,
+        x: ExtensionTarget<D>,
+        y: ExtensionTarget<D>,
+    ) -> ExtensionTarget<D> {
+        self.select_ext_generalized(b, x, y)
+    }
+
+    fn sum(&mut self, terms: &[ExtensionTarget<D>]) -> ExtensionTarget<D> {
+        self.add_many_extension(terms)
+    }
+}
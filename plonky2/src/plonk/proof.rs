@@ -21,7 +21,10 @@ use crate::iop::target::Target;
 use crate::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
 use crate::plonk::config::{GenericConfig, Hasher};
 use crate::plonk::verifier::verify_with_challenges;
-use crate::util::serialization::{Buffer, Read, Write};
+use crate::util::serialization::compact::{
+    read_compact_proof, write_compact_proof, ProofSizeBreakdown,
+};
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 #[serde(bound = "")]
@@ -248,6 +251,27 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             .map_err(anyhow::Error::msg)?;
         Ok(proof)
     }
+
+    /// Serializes the proof in the [`compact`](crate::util::serialization::compact) format, which
+    /// is smaller than the one of `to_bytes` by its framing, not its field elements.
+    pub fn to_compact_bytes(&self, common_data: &CommonCircuitData<F, D>) -> IoResult<Vec<u8>> {
+        write_compact_proof(self, common_data).map(|(bytes, _)| bytes)
+    }
+
+    pub fn from_compact_bytes(
+        bytes: Vec<u8>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> anyhow::Result<Self> {
+        read_compact_proof(&bytes, common_data).map_err(anyhow::Error::msg)
+    }
+
+    /// The size of each component of the proof in the compact format, to see which ones dominate.
+    pub fn size_breakdown(
+        &self,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<ProofSizeBreakdown> {
+        write_compact_proof(self, common_data).map(|(_, sizes)| sizes)
+    }
 }
 
 pub struct ProofChallenges<F: RichField + Extendable<D>, const D: usize> {
@@ -445,14 +469,12 @@ impl<const D: usize> OpeningSetTarget<D> {
 
 #[cfg(test)]
 mod tests {
-    use alloc::sync::Arc;
 
     use anyhow::Result;
     use itertools::Itertools;
 
     use crate::field::types::Sample;
     use crate::fri::reduction_strategies::FriReductionStrategy;
-    use crate::gates::lookup_table::LookupTable;
     use crate::gates::noop::NoopGate;
     use crate::iop::witness::PartialWitness;
     use crate::plonk::circuit_builder::CircuitBuilder;
@@ -526,10 +548,10 @@ mod tests {
             241, 25, 149, 105, 156, 51, 53, 168, 145, 247, 223, 79, 78, 226, 15, 222, 82, 115, 70,
             210, 27, 41, 1, 170, 40, 131, 192, 229, 248, 255,
         ];
-        let table: LookupTable = Arc::new((0..256).zip_eq(tip5_table).collect());
+        let table: Vec<(u16, u16)> = (0..256).zip_eq(tip5_table).collect();
         let config = CircuitConfig::standard_recursion_config();
         let mut builder = CircuitBuilder::<F, D>::new(config);
-        let lut_index = builder.add_lookup_table_from_pairs(table);
+        let lut_index = builder.add_lookup_table_from_pairs(&table);
 
         // Build dummy circuit with a lookup to get a valid proof.
         let x = F::TWO;
@@ -1,3 +1,5 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use alloc::{format, vec};
 use core::cmp::min;
@@ -6,19 +8,28 @@ use core::mem::swap;
 use anyhow::{ensure, Result};
 use hashbrown::HashMap;
 use plonky2_maybe_rayon::*;
+use rand::rngs::OsRng;
+use rand::RngCore;
+#[cfg(feature = "rand_chacha")]
+use rand::SeedableRng;
+#[cfg(feature = "rand_chacha")]
+use rand_chacha::ChaCha20Rng;
 
 use super::circuit_builder::{LookupChallenges, LookupWire};
 use crate::field::extension::Extendable;
+use crate::field::fft::FftRootTable;
 use crate::field::polynomial::{PolynomialCoeffs, PolynomialValues};
 use crate::field::types::Field;
 use crate::field::zero_poly_coset::ZeroPolyOnCoset;
-use crate::fri::oracle::PolynomialBatch;
+use crate::fri::oracle::{PolynomialBatch, SALT_SIZE};
 use crate::gates::lookup::LookupGate;
 use crate::gates::lookup_table::LookupTableGate;
-use crate::gates::selectors::LookupSelectors;
 use crate::hash::hash_types::RichField;
 use crate::iop::challenger::Challenger;
+#[cfg(not(feature = "rand_chacha"))]
 use crate::iop::generator::generate_partial_witness;
+#[cfg(feature = "rand_chacha")]
+use crate::iop::generator::generate_partial_witness_with_seed;
 use crate::iop::target::Target;
 use crate::iop::witness::{MatrixWitness, PartialWitness, PartitionWitness, Witness, WitnessWrite};
 use crate::plonk::circuit_builder::NUM_COINS_LOOKUP;
@@ -26,13 +37,113 @@ use crate::plonk::circuit_data::{CommonCircuitData, ProverOnlyCircuitData};
 use crate::plonk::config::{GenericConfig, Hasher};
 use crate::plonk::plonk_common::PlonkOracle;
 use crate::plonk::proof::{OpeningSet, Proof, ProofWithPublicInputs};
-use crate::plonk::vanishing_poly::{eval_vanishing_poly_base_batch, get_lut_poly};
+use crate::plonk::vanishing_poly::{
+    combine_lookup_columns, eval_vanishing_poly_base_batch, get_lut_poly,
+};
 use crate::plonk::vars::EvaluationVarsBaseBatch;
 use crate::timed;
 use crate::util::partial_products::{partial_products_and_z_gx, quotient_chunk_products};
 use crate::util::timing::TimingTree;
 use crate::util::{ceil_div_usize, log2_ceil, transpose};
 
+/// Options controlling how a proof is computed. They don't change what is proven: a proof computed
+/// with any options verifies against the same circuit.
+#[derive(Clone, Debug, Default)]
+pub struct ProverOptions<F: Field> {
+    /// An upper bound, in bytes, on the memory used at any one time for the low-degree extensions
+    /// of the wires, the partial products and `Z`s, and the quotient chunks. When set, these
+    /// oracles are committed to a chunk of LDE rows at a time without retaining the LDE, which is
+    /// then recomputed from the coefficients, in chunks, to evaluate the quotient and, point by
+    /// point, to answer FRI queries. This trades CPU time for memory. The bound is approximate: the
+    /// coefficients, the Merkle digests and the quotient values are not counted, and chunks are
+    /// never smaller than the polynomials' degree, so they may still exceed it. With `None`, the
+    /// whole LDEs are kept in memory, which is fastest.
+    pub lde_memory_limit: Option<usize>,
+
+    /// The thread pool to prove in, including witness generation. Provers sharing a process can
+    /// each be given their own pool so that they don't compete for the threads of the global one,
+    /// which is used if this is `None`. Without the `parallel` feature, proving is sequential
+    /// either way.
+    pub thread_pool: Option<Arc<ThreadPool>>,
+
+    /// A root table for the FFTs, replacing the one in `ProverOnlyCircuitData`. It can be the table
+    /// for any domain at least as large as the LDE, so that provers for circuits of different
+    /// sizes can share the one for the largest.
+    pub fft_root_table: Option<Arc<FftRootTable<F>>>,
+
+    /// Seeds all of the prover's randomness: the random values set by witness generation and the
+    /// zero-knowledge salts, which are then drawn from ChaCha20 RNGs. Together with the proof of
+    /// work being the smallest valid one, this makes the proof a function of the circuit, inputs
+    /// and seed alone, whatever the number of threads. This should only be set for audits and
+    /// testing, as reusing a seed with different witnesses leaks information about them. With
+    /// `None`, the randomness comes from the OS. Setting it requires the `rand_chacha` feature:
+    /// without it, proving fails.
+    pub rng_seed: Option<u64>,
+}
+
+impl<F: Field> ProverOptions<F> {
+    /// Runs `op` in `thread_pool`, if set.
+    fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        if let Some(thread_pool) = &self.thread_pool {
+            return thread_pool.install(op);
+        }
+        op()
+    }
+
+    /// Checks that the options are supported by the enabled features.
+    fn check(&self) -> Result<()> {
+        #[cfg(not(feature = "rand_chacha"))]
+        ensure!(
+            self.rng_seed.is_none(),
+            "Seeding the prover requires the `rand_chacha` feature."
+        );
+        Ok(())
+    }
+
+    /// The root table to use for the FFTs of a circuit.
+    fn fft_root_table<'a, C: GenericConfig<D, F = F>, const D: usize>(
+        &'a self,
+        prover_data: &'a ProverOnlyCircuitData<F, C, D>,
+    ) -> Option<&'a FftRootTable<F>>
+    where
+        F: RichField + Extendable<D>,
+    {
+        self.fft_root_table
+            .as_deref()
+            .or(prover_data.fft_root_table.as_ref())
+    }
+
+    /// The RNG the zero-knowledge salts are drawn from. When seeded, it uses stream 0 of the
+    /// ChaCha20 RNG, as opposed to `RandomValueGenerator`s.
+    fn blinding_rng(&self) -> Box<dyn RngCore> {
+        #[cfg(feature = "rand_chacha")]
+        {
+            let rng = match self.rng_seed {
+                Some(seed) => ChaCha20Rng::seed_from_u64(seed),
+                None => ChaCha20Rng::from_rng(OsRng).expect("OS RNG failed"),
+            };
+            Box::new(rng)
+        }
+        #[cfg(not(feature = "rand_chacha"))]
+        Box::new(OsRng)
+    }
+
+    /// The log of the number of chunks an LDE of `num_polys` polynomials over `2^lde_bits` points
+    /// should be processed in to respect `lde_memory_limit`, capped at `max_chunk_bits`. Each
+    /// chunk is held twice, as columns and as rows.
+    fn lde_chunk_bits(&self, num_polys: usize, lde_bits: usize, max_chunk_bits: usize) -> usize {
+        let Some(limit) = self.lde_memory_limit else {
+            return 0;
+        };
+        let chunk_bytes = |chunk_bits: usize| {
+            2 * num_polys * core::mem::size_of::<F>() * (1 << (lde_bits - chunk_bits))
+        };
+        (0..max_chunk_bits)
+            .find(|&chunk_bits| chunk_bytes(chunk_bits) <= limit)
+            .unwrap_or(max_chunk_bits)
+    }
+}
+
 /// Set all the lookup gate wires (including multiplicities) and pad unused LU slots.
 /// Warning: rows are in descending order: the first gate to appear is the last LU gate, and
 /// the last gate to appear is the first LUT gate.
@@ -54,50 +165,68 @@ pub fn set_lookup_wires<
         },
     ) in prover_data.lookup_rows.iter().enumerate()
     {
-        let lut_len = common_data.luts[lut_index].len();
-        let num_entries = LookupGate::num_slots(&common_data.config);
-        let num_lut_entries = LookupTableGate::num_slots(&common_data.config);
+        let lut = &common_data.luts[lut_index];
+        let width = common_data.lookup_width();
+        let num_entries = LookupGate::<F>::num_slots(&common_data.config, width);
+        let num_lut_entries = LookupTableGate::<F>::num_slots(&common_data.config, width);
+
+        // Read the rows of the LUT from the witness: the entries of dynamic tables are only known now.
+        let rows = (0..lut.len())
+            .map(|lut_entry| {
+                let row = first_lut_gate - lut_entry / num_lut_entries;
+                let slot = lut_entry % num_lut_entries;
+                (0..lut.width())
+                    .map(|col| {
+                        pw.get_target(Target::wire(
+                            row,
+                            LookupTableGate::<F>::wire_ith_looked(width, slot, col),
+                        ))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
 
         // Compute multiplicities.
-        let mut multiplicities = vec![0; lut_len];
+        let mut multiplicities = vec![0; lut.len()];
 
-        let table_value_to_idx: HashMap<u16, usize> = common_data.luts[lut_index]
+        let table_row_to_idx: HashMap<&[F], usize> = rows
             .iter()
             .enumerate()
-            .map(|(i, (inp_target, _))| (*inp_target, i))
+            .map(|(i, row)| (row.as_slice(), i))
             .collect();
 
-        for (inp_target, _) in prover_data.lut_to_lookups[lut_index].iter() {
-            let inp_value = pw.get_target(*inp_target);
-            let idx = table_value_to_idx
-                .get(&u16::try_from(inp_value.to_canonical_u64()).unwrap())
-                .unwrap();
+        for lookup in prover_data.lut_to_lookups[lut_index].iter() {
+            let values = pw.get_targets(lookup);
+            let idx = table_row_to_idx
+                .get(values.as_slice())
+                .unwrap_or_else(|| panic!("{values:?} is not a row of LUT number {lut_index}"));
 
             multiplicities[*idx] += 1;
         }
 
-        // Pad the last `LookupGate` with the first entry from the LUT.
+        // Pad the last `LookupGate` with the first row of the LUT.
         let remaining_slots = (num_entries
             - (prover_data.lut_to_lookups[lut_index].len() % num_entries))
             % num_entries;
-        let (first_inp_value, first_out_value) = common_data.luts[lut_index][0];
         for slot in (num_entries - remaining_slots)..num_entries {
-            let inp_target =
-                Target::wire(last_lut_gate - 1, LookupGate::wire_ith_looking_inp(slot));
-            let out_target =
-                Target::wire(last_lut_gate - 1, LookupGate::wire_ith_looking_out(slot));
-            pw.set_target(inp_target, F::from_canonical_u16(first_inp_value));
-            pw.set_target(out_target, F::from_canonical_u16(first_out_value));
+            for (col, &value) in rows[0].iter().enumerate() {
+                let target = Target::wire(
+                    last_lut_gate - 1,
+                    LookupGate::<F>::wire_ith_looking(width, slot, col),
+                );
+                pw.set_target(target, value);
+            }
 
             multiplicities[0] += 1;
         }
 
         // We don't need to pad the last `LookupTableGate`; extra wires are set to 0 by default, which satisfies the constraints.
-        for lut_entry in 0..lut_len {
+        for lut_entry in 0..lut.len() {
             let row = first_lut_gate - lut_entry / num_lut_entries;
             let col = lut_entry % num_lut_entries;
 
-            let mul_target = Target::wire(row, LookupTableGate::wire_ith_multiplicity(col));
+            let mul_target =
+                Target::wire(row, LookupTableGate::<F>::wire_ith_multiplicity(width, col));
 
             pw.set_target(
                 mul_target,
@@ -117,23 +246,128 @@ where
     C::Hasher: Hasher<F>,
     C::InnerHasher: Hasher<F>,
 {
-    let partition_witness = timed!(
+    prove_with_options(
+        prover_data,
+        common_data,
+        inputs,
+        &ProverOptions::default(),
         timing,
-        &format!("run {} generators", prover_data.generators.len()),
-        generate_partial_witness(inputs, prover_data, common_data)
-    );
+    )
+}
+
+/// Like `prove`, but computes the proof as set by `options`.
+pub fn prove_with_options<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    inputs: PartialWitness<F>,
+    options: &ProverOptions<F>,
+    timing: &mut TimingTree,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    options.check()?;
+    options.install(|| {
+        let partition_witness = timed!(
+            timing,
+            &format!("run {} generators", prover_data.generators.len()),
+            generate_witness(inputs, prover_data, common_data, options)
+        );
+
+        prove_with_partition_witness_with_options(
+            prover_data,
+            common_data,
+            partition_witness,
+            options,
+            timing,
+        )
+    })
+}
 
-    prove_with_partition_witness(prover_data, common_data, partition_witness, timing)
+/// Runs the generators, seeding their randomness as set by `options`.
+#[cfg_attr(not(feature = "rand_chacha"), allow(unused_variables))]
+fn generate_witness<
+    'a,
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    inputs: PartialWitness<F>,
+    prover_data: &'a ProverOnlyCircuitData<F, C, D>,
+    common_data: &'a CommonCircuitData<F, D>,
+    options: &ProverOptions<F>,
+) -> PartitionWitness<'a, F> {
+    #[cfg(feature = "rand_chacha")]
+    return generate_partial_witness_with_seed(inputs, prover_data, common_data, options.rng_seed);
+    #[cfg(not(feature = "rand_chacha"))]
+    generate_partial_witness(inputs, prover_data, common_data)
 }
 
 pub fn prove_with_partition_witness<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
     const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    partition_witness: PartitionWitness<F>,
+    timing: &mut TimingTree,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    prove_with_partition_witness_with_options(
+        prover_data,
+        common_data,
+        partition_witness,
+        &ProverOptions::default(),
+        timing,
+    )
+}
+
+/// Like `prove_with_partition_witness`, but computes the proof as set by `options`.
+pub fn prove_with_partition_witness_with_options<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    partition_witness: PartitionWitness<F>,
+    options: &ProverOptions<F>,
+    timing: &mut TimingTree,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    options.check()?;
+    options.install(|| {
+        prove_with_partition_witness_in_pool(
+            prover_data,
+            common_data,
+            partition_witness,
+            options,
+            timing,
+        )
+    })
+}
+
+fn prove_with_partition_witness_in_pool<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
 >(
     prover_data: &ProverOnlyCircuitData<F, C, D>,
     common_data: &CommonCircuitData<F, D>,
     mut partition_witness: PartitionWitness<F>,
+    options: &ProverOptions<F>,
     timing: &mut TimingTree,
 ) -> Result<ProofWithPublicInputs<F, C, D>>
 where
@@ -147,6 +381,7 @@ where
     let degree = common_data.degree();
 
     set_lookup_wires(prover_data, common_data, &mut partition_witness);
+    let mut blinding_rng = options.blinding_rng();
 
     let public_inputs = partition_witness.get_targets(&prover_data.public_inputs);
     let public_inputs_hash = C::InnerHasher::hash_no_pad(&public_inputs);
@@ -170,13 +405,14 @@ where
     let wires_commitment = timed!(
         timing,
         "compute wires commitment",
-        PolynomialBatch::<F, C, D>::from_values(
+        commit_to_values(
             wires_values,
-            config.fri_config.rate_bits,
             config.zero_knowledge && PlonkOracle::WIRES.blinding,
-            config.fri_config.cap_height,
+            &mut *blinding_rng,
+            prover_data,
+            common_data,
+            options,
             timing,
-            prover_data.fft_root_table.as_ref(),
         )
     );
 
@@ -237,13 +473,14 @@ where
     let partial_products_zs_and_lookup_commitment = timed!(
         timing,
         "commit to partial products, Z's and, if any, lookup polynomials",
-        PolynomialBatch::from_values(
+        commit_to_values(
             zs_partial_products_lookups,
-            config.fri_config.rate_bits,
             config.zero_knowledge && PlonkOracle::ZS_PARTIAL_PRODUCTS.blinding,
-            config.fri_config.cap_height,
+            &mut *blinding_rng,
+            prover_data,
+            common_data,
+            options,
             timing,
-            prover_data.fft_root_table.as_ref(),
         )
     );
 
@@ -264,6 +501,7 @@ where
             &gammas,
             &deltas,
             &alphas,
+            options,
         )
     );
 
@@ -285,13 +523,14 @@ where
     let quotient_polys_commitment = timed!(
         timing,
         "commit to quotient polys",
-        PolynomialBatch::<F, C, D>::from_coeffs(
+        commit_to_coeffs(
             all_quotient_poly_chunks,
-            config.fri_config.rate_bits,
             config.zero_knowledge && PlonkOracle::QUOTIENT.blinding,
-            config.fri_config.cap_height,
+            &mut *blinding_rng,
+            prover_data,
+            common_data,
+            options,
             timing,
-            prover_data.fft_root_table.as_ref(),
         )
     );
 
@@ -353,6 +592,73 @@ where
     })
 }
 
+/// Commits to the polynomials interpolating `values`, retaining their LDE unless `options` sets a
+/// memory limit.
+fn commit_to_values<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    values: Vec<PolynomialValues<F>>,
+    blinding: bool,
+    rng: &mut dyn RngCore,
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    options: &ProverOptions<F>,
+    timing: &mut TimingTree,
+) -> PolynomialBatch<F, C, D> {
+    let coeffs = timed!(
+        timing,
+        "IFFT",
+        values.into_par_iter().map(|v| v.ifft()).collect()
+    );
+    commit_to_coeffs(
+        coeffs,
+        blinding,
+        rng,
+        prover_data,
+        common_data,
+        options,
+        timing,
+    )
+}
+
+/// Commits to `polynomials`, retaining their LDE unless `options` sets a memory limit.
+fn commit_to_coeffs<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    polynomials: Vec<PolynomialCoeffs<F>>,
+    blinding: bool,
+    rng: &mut dyn RngCore,
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    options: &ProverOptions<F>,
+    timing: &mut TimingTree,
+) -> PolynomialBatch<F, C, D> {
+    let fri_config = &common_data.config.fri_config;
+    if options.lde_memory_limit.is_none() {
+        return PolynomialBatch::from_coeffs_with_rng(
+            polynomials,
+            fri_config.rate_bits,
+            blinding,
+            fri_config.cap_height,
+            rng,
+            timing,
+            options.fft_root_table(prover_data),
+        );
+    }
+
+    // Chunks smaller than the polynomials' degree would make the FFTs much more expensive, so
+    // there are at most `2^rate_bits` of them. They then use as much memory as the coefficients.
+    let lde_bits = common_data.degree_bits() + fri_config.rate_bits;
+    let num_columns = polynomials.len() + if blinding { SALT_SIZE } else { 0 };
+    let lde_chunk_bits = options.lde_chunk_bits(num_columns, lde_bits, fri_config.rate_bits);
+    PolynomialBatch::from_coeffs_chunked(
+        polynomials,
+        fri_config.rate_bits,
+        blinding,
+        fri_config.cap_height,
+        lde_chunk_bits,
+        rng,
+        timing,
+        options.fft_root_table(prover_data),
+    )
+}
+
 /// Compute the partial products used in the `Z` polynomials.
 fn all_wires_permutation_partial_products<
     F: RichField + Extendable<D>,
@@ -458,10 +764,11 @@ fn compute_lookup_polys<
     common_data: &CommonCircuitData<F, D>,
 ) -> Vec<PolynomialValues<F>> {
     let degree = common_data.degree();
-    let num_lu_slots = LookupGate::num_slots(&common_data.config);
+    let width = common_data.lookup_width();
+    let num_lu_slots = LookupGate::<F>::num_slots(&common_data.config, width);
     let max_lookup_degree = common_data.config.max_quotient_degree_factor - 1;
     let num_partial_lookups = ceil_div_usize(num_lu_slots, max_lookup_degree);
-    let num_lut_slots = LookupTableGate::num_slots(&common_data.config);
+    let num_lut_slots = LookupTableGate::<F>::num_slots(&common_data.config, width);
     let max_lookup_table_degree = ceil_div_usize(num_lut_slots, num_partial_lookups);
 
     // First poly is RE, the rest are partial SLDCs.
@@ -481,10 +788,11 @@ fn compute_lookup_polys<
             // Get combos for Sum.
             let looked_combos: Vec<F> = (0..num_lut_slots)
                 .map(|s| {
-                    let looked_inp = witness.get_wire(row, LookupTableGate::wire_ith_looked_inp(s));
-                    let looked_out = witness.get_wire(row, LookupTableGate::wire_ith_looked_out(s));
+                    let looked = (0..width).map(|col| {
+                        witness.get_wire(row, LookupTableGate::<F>::wire_ith_looked(width, s, col))
+                    });
 
-                    looked_inp + deltas[LookupChallenges::ChallengeA as usize] * looked_out
+                    combine_lookup_columns(looked, deltas[LookupChallenges::ChallengeA as usize])
                 })
                 .collect();
             // Get (alpha - combo).
@@ -497,10 +805,11 @@ fn compute_lookup_polys<
             // Get lookup combos, used to check the well formation of the LUT.
             let lookup_combos: Vec<F> = (0..num_lut_slots)
                 .map(|s| {
-                    let looked_inp = witness.get_wire(row, LookupTableGate::wire_ith_looked_inp(s));
-                    let looked_out = witness.get_wire(row, LookupTableGate::wire_ith_looked_out(s));
+                    let looked = (0..width).map(|col| {
+                        witness.get_wire(row, LookupTableGate::<F>::wire_ith_looked(width, s, col))
+                    });
 
-                    looked_inp + deltas[LookupChallenges::ChallengeB as usize] * looked_out
+                    combine_lookup_columns(looked, deltas[LookupChallenges::ChallengeB as usize])
                 })
                 .collect();
 
@@ -522,7 +831,8 @@ fn compute_lookup_polys<
                 let sum = (slot * max_lookup_table_degree
                     ..min((slot + 1) * max_lookup_table_degree, num_lut_slots))
                     .fold(prev, |acc, s| {
-                        acc + witness.get_wire(row, LookupTableGate::wire_ith_multiplicity(s))
+                        acc + witness
+                            .get_wire(row, LookupTableGate::<F>::wire_ith_multiplicity(width, s))
                             * looked_combo_inverses[s]
                     });
                 final_poly_vecs[slot + 1].values[row] = sum;
@@ -534,10 +844,11 @@ fn compute_lookup_polys<
             // Get looking combos.
             let looking_combos: Vec<F> = (0..num_lu_slots)
                 .map(|s| {
-                    let looking_in = witness.get_wire(row, LookupGate::wire_ith_looking_inp(s));
-                    let looking_out = witness.get_wire(row, LookupGate::wire_ith_looking_out(s));
+                    let looking = (0..width).map(|col| {
+                        witness.get_wire(row, LookupGate::<F>::wire_ith_looking(width, s, col))
+                    });
 
-                    looking_in + deltas[LookupChallenges::ChallengeA as usize] * looking_out
+                    combine_lookup_columns(looking, deltas[LookupChallenges::ChallengeA as usize])
                 })
                 .collect();
             // Get (alpha - combo).
@@ -598,6 +909,30 @@ fn compute_all_lookup_polys<
 
 const BATCH_SIZE: usize = 32;
 
+/// Where the quotient computation reads an oracle's LDE values from.
+enum LdeRows<'a, F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
+    /// The LDE retained by the commitment.
+    Retained(&'a PolynomialBatch<F, C, D>),
+    /// The rows of a recomputed chunk of the LDE, as described in `compute_quotient_polys`.
+    Chunk {
+        rows: Vec<Vec<F>>,
+        chunk_bits: usize,
+    },
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    LdeRows<'_, F, C, D>
+{
+    /// The LDE values at the `index`-th point of the quotient domain, which is every `step`-th
+    /// point of the LDE domain.
+    fn get(&self, index: usize, step: usize) -> &[F] {
+        match self {
+            Self::Retained(commitment) => commitment.get_lde_values(index, step),
+            Self::Chunk { rows, chunk_bits } => &rows[index >> chunk_bits],
+        }
+    }
+}
+
 fn compute_quotient_polys<
     'a,
     F: RichField + Extendable<D>,
@@ -613,6 +948,7 @@ fn compute_quotient_polys<
     gammas: &[F],
     deltas: &[F],
     alphas: &[F],
+    options: &ProverOptions<F>,
 ) -> Vec<PolynomialCoeffs<F>> {
     let num_challenges = common_data.config.num_challenges;
 
@@ -642,22 +978,22 @@ fn compute_quotient_polys<
     // and are the same each time in check_lookup_constraints_batched.
     // lut_poly_evals[i][j] gives the eval for the i'th challenge and the j'th lookup table
     let lut_re_poly_evals: Vec<Vec<F>> = if has_lookup {
-        let num_lut_slots = LookupTableGate::num_slots(&common_data.config);
+        let num_lut_slots =
+            LookupTableGate::<F>::num_slots(&common_data.config, common_data.lookup_width());
         (0..num_challenges)
             .map(move |i| {
                 let cur_deltas = &deltas[NUM_COINS_LOOKUP * i..NUM_COINS_LOOKUP * (i + 1)];
                 let cur_challenge_delta = cur_deltas[LookupChallenges::ChallengeDelta as usize];
 
-                (LookupSelectors::StartEnd as usize..common_data.num_lookup_selectors)
-                    .map(|r| {
-                        let lut_row_number = ceil_div_usize(
-                            common_data.luts[r - LookupSelectors::StartEnd as usize].len(),
-                            num_lut_slots,
-                        );
+                common_data
+                    .static_lut_indices()
+                    .map(|lut_index| {
+                        let lut_row_number =
+                            ceil_div_usize(common_data.luts[lut_index].len(), num_lut_slots);
 
                         get_lut_poly(
                             common_data,
-                            r - LookupSelectors::StartEnd as usize,
+                            lut_index,
                             cur_deltas,
                             num_lut_slots * lut_row_number,
                         )
@@ -673,131 +1009,183 @@ fn compute_quotient_polys<
     let lut_re_poly_evals_refs: Vec<&[F]> =
         lut_re_poly_evals.iter().map(|v| v.as_slice()).collect();
 
-    let points_batches = points.par_chunks(BATCH_SIZE);
-    let num_batches = ceil_div_usize(points.len(), BATCH_SIZE);
-
-    let quotient_values: Vec<Vec<F>> = points_batches
-        .enumerate()
-        .flat_map(|(batch_i, xs_batch)| {
-            // Each batch must be the same size, except the last one, which may be smaller.
-            debug_assert!(
-                xs_batch.len() == BATCH_SIZE
-                    || (batch_i == num_batches - 1 && xs_batch.len() <= BATCH_SIZE)
-            );
+    // Evaluates the quotient on a batch of points, given by their indices, reading the wires and
+    // the `Z`s from `wires_rows` and `zs_partial_products_and_lookup_rows`.
+    let eval_batch = |indices_batch: &[usize],
+                      wires_rows: &LdeRows<F, C, D>,
+                      zs_partial_products_and_lookup_rows: &LdeRows<F, C, D>|
+     -> Vec<Vec<F>> {
+        let xs_batch: Vec<F> = indices_batch.iter().map(|&i| points[i]).collect();
 
-            let indices_batch: Vec<usize> =
-                (BATCH_SIZE * batch_i..BATCH_SIZE * batch_i + xs_batch.len()).collect();
+        let mut shifted_xs_batch = Vec::with_capacity(xs_batch.len());
+        let mut local_zs_batch = Vec::with_capacity(xs_batch.len());
+        let mut next_zs_batch = Vec::with_capacity(xs_batch.len());
 
-            let mut shifted_xs_batch = Vec::with_capacity(xs_batch.len());
-            let mut local_zs_batch = Vec::with_capacity(xs_batch.len());
-            let mut next_zs_batch = Vec::with_capacity(xs_batch.len());
+        let mut local_lookup_batch = Vec::with_capacity(xs_batch.len());
+        let mut next_lookup_batch = Vec::with_capacity(xs_batch.len());
 
-            let mut local_lookup_batch = Vec::with_capacity(xs_batch.len());
-            let mut next_lookup_batch = Vec::with_capacity(xs_batch.len());
+        let mut partial_products_batch = Vec::with_capacity(xs_batch.len());
+        let mut s_sigmas_batch = Vec::with_capacity(xs_batch.len());
 
-            let mut partial_products_batch = Vec::with_capacity(xs_batch.len());
-            let mut s_sigmas_batch = Vec::with_capacity(xs_batch.len());
+        let mut local_constants_batch_refs = Vec::with_capacity(xs_batch.len());
+        let mut local_wires_batch_refs = Vec::with_capacity(xs_batch.len());
 
-            let mut local_constants_batch_refs = Vec::with_capacity(xs_batch.len());
-            let mut local_wires_batch_refs = Vec::with_capacity(xs_batch.len());
+        for (&i, &x) in indices_batch.iter().zip(&xs_batch) {
+            let shifted_x = F::coset_shift() * x;
+            let i_next = (i + next_step) % lde_size;
+            let local_constants_sigmas = prover_data
+                .constants_sigmas_commitment
+                .get_lde_values(i, step);
+            let local_constants = &local_constants_sigmas[common_data.constants_range()];
+            let s_sigmas = &local_constants_sigmas[common_data.sigmas_range()];
+            let local_wires = wires_rows.get(i, step);
+            let local_zs_partial_and_lookup = zs_partial_products_and_lookup_rows.get(i, step);
+            let next_zs_partial_and_lookup = zs_partial_products_and_lookup_rows.get(i_next, step);
 
-            for (&i, &x) in indices_batch.iter().zip(xs_batch) {
-                let shifted_x = F::coset_shift() * x;
-                let i_next = (i + next_step) % lde_size;
-                let local_constants_sigmas = prover_data
-                    .constants_sigmas_commitment
-                    .get_lde_values(i, step);
-                let local_constants = &local_constants_sigmas[common_data.constants_range()];
-                let s_sigmas = &local_constants_sigmas[common_data.sigmas_range()];
-                let local_wires = wires_commitment.get_lde_values(i, step);
-                let local_zs_partial_and_lookup =
-                    zs_partial_products_and_lookup_commitment.get_lde_values(i, step);
-                let next_zs_partial_and_lookup =
-                    zs_partial_products_and_lookup_commitment.get_lde_values(i_next, step);
+            let local_zs = &local_zs_partial_and_lookup[common_data.zs_range()];
 
-                let local_zs = &local_zs_partial_and_lookup[common_data.zs_range()];
+            let next_zs = &next_zs_partial_and_lookup[common_data.zs_range()];
 
-                let next_zs = &next_zs_partial_and_lookup[common_data.zs_range()];
+            let partial_products =
+                &local_zs_partial_and_lookup[common_data.partial_products_range()];
 
-                let partial_products =
-                    &local_zs_partial_and_lookup[common_data.partial_products_range()];
+            if has_lookup {
+                let local_lookup_zs = &local_zs_partial_and_lookup[common_data.lookup_range()];
 
-                if has_lookup {
-                    let local_lookup_zs = &local_zs_partial_and_lookup[common_data.lookup_range()];
+                let next_lookup_zs = &next_zs_partial_and_lookup[common_data.lookup_range()];
+                debug_assert_eq!(local_lookup_zs.len(), common_data.num_all_lookup_polys());
 
-                    let next_lookup_zs = &next_zs_partial_and_lookup[common_data.lookup_range()];
-                    debug_assert_eq!(local_lookup_zs.len(), common_data.num_all_lookup_polys());
+                local_lookup_batch.push(local_lookup_zs);
+                next_lookup_batch.push(next_lookup_zs);
+            }
 
-                    local_lookup_batch.push(local_lookup_zs);
-                    next_lookup_batch.push(next_lookup_zs);
-                }
+            debug_assert_eq!(local_wires.len(), common_data.config.num_wires);
+            debug_assert_eq!(local_zs.len(), num_challenges);
 
-                debug_assert_eq!(local_wires.len(), common_data.config.num_wires);
-                debug_assert_eq!(local_zs.len(), num_challenges);
+            local_constants_batch_refs.push(local_constants);
+            local_wires_batch_refs.push(local_wires);
 
-                local_constants_batch_refs.push(local_constants);
-                local_wires_batch_refs.push(local_wires);
+            shifted_xs_batch.push(shifted_x);
+            local_zs_batch.push(local_zs);
+            next_zs_batch.push(next_zs);
+            partial_products_batch.push(partial_products);
+            s_sigmas_batch.push(s_sigmas);
+        }
 
-                shifted_xs_batch.push(shifted_x);
-                local_zs_batch.push(local_zs);
-                next_zs_batch.push(next_zs);
-                partial_products_batch.push(partial_products);
-                s_sigmas_batch.push(s_sigmas);
+        // NB (JN): I'm not sure how (in)efficient the below is. It needs measuring.
+        let mut local_constants_batch =
+            vec![F::ZERO; xs_batch.len() * local_constants_batch_refs[0].len()];
+        for i in 0..local_constants_batch_refs[0].len() {
+            for (j, constants) in local_constants_batch_refs.iter().enumerate() {
+                local_constants_batch[i * xs_batch.len() + j] = constants[i];
             }
+        }
 
-            // NB (JN): I'm not sure how (in)efficient the below is. It needs measuring.
-            let mut local_constants_batch =
-                vec![F::ZERO; xs_batch.len() * local_constants_batch_refs[0].len()];
-            for i in 0..local_constants_batch_refs[0].len() {
-                for (j, constants) in local_constants_batch_refs.iter().enumerate() {
-                    local_constants_batch[i * xs_batch.len() + j] = constants[i];
-                }
+        let mut local_wires_batch = vec![F::ZERO; xs_batch.len() * local_wires_batch_refs[0].len()];
+        for i in 0..local_wires_batch_refs[0].len() {
+            for (j, wires) in local_wires_batch_refs.iter().enumerate() {
+                local_wires_batch[i * xs_batch.len() + j] = wires[i];
             }
+        }
 
-            let mut local_wires_batch =
-                vec![F::ZERO; xs_batch.len() * local_wires_batch_refs[0].len()];
-            for i in 0..local_wires_batch_refs[0].len() {
-                for (j, wires) in local_wires_batch_refs.iter().enumerate() {
-                    local_wires_batch[i * xs_batch.len() + j] = wires[i];
-                }
-            }
+        let vars_batch = EvaluationVarsBaseBatch::new(
+            xs_batch.len(),
+            &local_constants_batch,
+            &local_wires_batch,
+            public_inputs_hash,
+        );
 
-            let vars_batch = EvaluationVarsBaseBatch::new(
-                xs_batch.len(),
-                &local_constants_batch,
-                &local_wires_batch,
-                public_inputs_hash,
-            );
+        let mut quotient_values_batch = eval_vanishing_poly_base_batch::<F, D>(
+            common_data,
+            indices_batch,
+            &shifted_xs_batch,
+            vars_batch,
+            &local_zs_batch,
+            &next_zs_batch,
+            &local_lookup_batch,
+            &next_lookup_batch,
+            &partial_products_batch,
+            &s_sigmas_batch,
+            betas,
+            gammas,
+            deltas,
+            alphas,
+            &z_h_on_coset,
+            &lut_re_poly_evals_refs,
+        );
+
+        for (&i, quotient_values) in indices_batch.iter().zip(quotient_values_batch.iter_mut()) {
+            let denominator_inv = z_h_on_coset.eval_inverse(i);
+            quotient_values
+                .iter_mut()
+                .for_each(|v| *v *= denominator_inv);
+        }
+        quotient_values_batch
+    };
 
-            let mut quotient_values_batch = eval_vanishing_poly_base_batch::<F, D>(
-                common_data,
-                &indices_batch,
-                &shifted_xs_batch,
-                vars_batch,
-                &local_zs_batch,
-                &next_zs_batch,
-                &local_lookup_batch,
-                &next_lookup_batch,
-                &partial_products_batch,
-                &s_sigmas_batch,
-                betas,
-                gammas,
-                deltas,
-                alphas,
-                &z_h_on_coset,
-                &lut_re_poly_evals_refs,
+    let wires_retained = wires_commitment.retains_lde();
+    let zs_retained = zs_partial_products_and_lookup_commitment.retains_lde();
+    let quotient_values: Vec<Vec<F>> = if wires_retained && zs_retained {
+        let indices: Vec<usize> = (0..lde_size).collect();
+        let wires_rows = LdeRows::Retained(wires_commitment);
+        let zs_rows = LdeRows::Retained(zs_partial_products_and_lookup_commitment);
+        indices
+            .par_chunks(BATCH_SIZE)
+            .flat_map(|indices_batch| eval_batch(indices_batch, &wires_rows, &zs_rows))
+            .collect()
+    } else {
+        // The LDEs which weren't retained are recomputed a chunk at a time. Chunk `k` holds the
+        // points whose index is congruent to `k` modulo the number of chunks, which is at most
+        // `next_step` so that a point and the point after it lie in the same chunk. These
+        // points form a coset of the LDE domain, on which the LDEs are computed with an FFT.
+        let num_polys = [
+            (wires_retained, wires_commitment),
+            (zs_retained, zs_partial_products_and_lookup_commitment),
+        ]
+        .iter()
+        .filter(|(retained, _)| !retained)
+        .map(|(_, commitment)| commitment.polynomials.len())
+        .sum();
+        let chunk_bits = options.lde_chunk_bits(
+            num_polys,
+            common_data.degree_bits() + quotient_degree_bits,
+            quotient_degree_bits,
+        );
+        let lde_rows = |commitment: &'a PolynomialBatch<F, C, D>, retained: bool, chunk: usize| {
+            if retained {
+                LdeRows::Retained(commitment)
+            } else {
+                let columns = commitment.lde_values_on_coset(
+                    chunk_bits + common_data.config.fri_config.rate_bits - quotient_degree_bits,
+                    chunk * step,
+                    options.fft_root_table(prover_data),
+                );
+                LdeRows::Chunk {
+                    rows: transpose(&columns),
+                    chunk_bits,
+                }
+            }
+        };
+
+        let mut quotient_values = vec![Vec::new(); lde_size];
+        for chunk in 0..1 << chunk_bits {
+            let wires_rows = lde_rows(wires_commitment, wires_retained, chunk);
+            let zs_rows = lde_rows(
+                zs_partial_products_and_lookup_commitment,
+                zs_retained,
+                chunk,
             );
-
-            for (&i, quotient_values) in indices_batch.iter().zip(quotient_values_batch.iter_mut())
-            {
-                let denominator_inv = z_h_on_coset.eval_inverse(i);
-                quotient_values
-                    .iter_mut()
-                    .for_each(|v| *v *= denominator_inv);
+            let indices: Vec<usize> = (chunk..lde_size).step_by(1 << chunk_bits).collect();
+            let chunk_values: Vec<Vec<F>> = indices
+                .par_chunks(BATCH_SIZE)
+                .flat_map(|indices_batch| eval_batch(indices_batch, &wires_rows, &zs_rows))
+                .collect();
+            for (i, values) in indices.into_iter().zip(chunk_values) {
+                quotient_values[i] = values;
             }
-            quotient_values_batch
-        })
-        .collect();
+        }
+        quotient_values
+    };
 
     transpose(&quotient_values)
         .into_par_iter()
@@ -805,3 +1193,176 @@ fn compute_quotient_polys<
         .map(|values| values.coset_ifft(F::coset_shift()))
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    #[cfg(all(feature = "parallel", feature = "rand_chacha"))]
+    use alloc::sync::Arc;
+
+    use anyhow::Result;
+    #[cfg(all(feature = "parallel", feature = "rand_chacha"))]
+    use plonky2_maybe_rayon::rayon::ThreadPoolBuilder;
+
+    #[cfg(all(feature = "parallel", feature = "rand_chacha"))]
+    use crate::field::fft::fft_root_table;
+    use crate::field::types::Field;
+    use crate::gates::noop::NoopGate;
+    use crate::iop::generator::generate_partial_witness;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::prover::{
+        prove_with_partition_witness, prove_with_partition_witness_with_options, ProverOptions,
+    };
+    use crate::util::timing::TimingTree;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn test_circuit(zero_knowledge: bool) -> (CircuitData<F, C, D>, PartialWitness<F>) {
+        let config = CircuitConfig {
+            zero_knowledge,
+            ..CircuitConfig::standard_recursion_config()
+        };
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let z = builder.mul(x, y);
+        builder.register_public_input(z);
+        for _ in 0..200 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::ONE);
+        pw.set_target(y, F::from_canonical_u16(3));
+        (data, pw)
+    }
+
+    fn test_lde_memory_limit(zero_knowledge: bool) -> Result<()> {
+        let (data, pw) = test_circuit(zero_knowledge);
+
+        // The witness generators randomize some unused wires, so generate the witness once to
+        // compare proofs.
+        let partition_witness = generate_partial_witness(pw, &data.prover_only, &data.common);
+
+        // Small enough that every oracle is split into several chunks.
+        let options = ProverOptions {
+            lde_memory_limit: Some(1 << 16),
+            ..ProverOptions::default()
+        };
+        let proof = prove_with_partition_witness_with_options(
+            &data.prover_only,
+            &data.common,
+            partition_witness.clone(),
+            &options,
+            &mut TimingTree::default(),
+        )?;
+        data.verify(proof.clone())?;
+
+        // Without blinding, the proof doesn't depend on how it was computed.
+        if !zero_knowledge {
+            let expected = prove_with_partition_witness(
+                &data.prover_only,
+                &data.common,
+                partition_witness,
+                &mut TimingTree::default(),
+            )?;
+            assert_eq!(proof, expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lde_memory_limit_matches_default() -> Result<()> {
+        test_lde_memory_limit(false)
+    }
+
+    #[test]
+    fn test_lde_memory_limit_zero_knowledge() -> Result<()> {
+        test_lde_memory_limit(true)
+    }
+
+    #[test]
+    #[cfg(all(feature = "parallel", feature = "rand_chacha"))]
+    fn test_prover_options() -> Result<()> {
+        let (data, pw) = test_circuit(true);
+        let partition_witness =
+            generate_partial_witness(pw.clone(), &data.prover_only, &data.common);
+
+        let lde_bits = data.common.degree_bits() + data.common.config.fri_config.rate_bits;
+        let options = ProverOptions {
+            thread_pool: Some(Arc::new(ThreadPoolBuilder::new().num_threads(2).build()?)),
+            // A table for a larger domain than needed.
+            fft_root_table: Some(Arc::new(fft_root_table(1 << (lde_bits + 2)))),
+            rng_seed: Some(42),
+            ..ProverOptions::default()
+        };
+        let proof = prove_with_partition_witness_with_options(
+            &data.prover_only,
+            &data.common,
+            partition_witness.clone(),
+            &options,
+            &mut TimingTree::default(),
+        )?;
+        data.verify(proof.clone())?;
+
+        // The salts only depend on the seed, so the proof doesn't depend on the other options.
+        let seed_only = ProverOptions {
+            rng_seed: Some(42),
+            ..ProverOptions::default()
+        };
+        let expected = prove_with_partition_witness_with_options(
+            &data.prover_only,
+            &data.common,
+            partition_witness,
+            &seed_only,
+            &mut TimingTree::default(),
+        )?;
+        assert_eq!(proof, expected);
+
+        data.verify(data.prove_with_options(pw, &options)?)
+    }
+
+    #[test]
+    #[cfg(all(feature = "parallel", feature = "rand_chacha"))]
+    fn test_seeded_proofs_are_reproducible() -> Result<()> {
+        let (data, pw) = test_circuit(true);
+        let seeded = |rng_seed: u64, num_threads: usize| -> Result<ProverOptions<F>> {
+            Ok(ProverOptions {
+                thread_pool: Some(Arc::new(
+                    ThreadPoolBuilder::new().num_threads(num_threads).build()?,
+                )),
+                rng_seed: Some(rng_seed),
+                ..ProverOptions::default()
+            })
+        };
+
+        // The seed covers the random wires set by witness generation as well as the salts.
+        let proof = data.prove_with_options(pw.clone(), &seeded(7, 1)?)?;
+        data.verify(proof.clone())?;
+        assert_eq!(proof, data.prove_with_options(pw.clone(), &seeded(7, 1)?)?);
+        assert_eq!(proof, data.prove_with_options(pw.clone(), &seeded(7, 4)?)?);
+
+        let other_seed = data.prove_with_options(pw, &seeded(8, 1)?)?;
+        assert_ne!(proof.proof.wires_cap, other_seed.proof.wires_cap);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lde_chunk_bits() {
+        let options = ProverOptions::<F> {
+            lde_memory_limit: Some(1 << 20),
+            ..ProverOptions::default()
+        };
+        // 2 * 8 polynomials * 8 bytes * 2^(16 - b) <= 2^20 iff b >= 3.
+        assert_eq!(options.lde_chunk_bits(8, 16, 10), 3);
+        assert_eq!(options.lde_chunk_bits(8, 16, 2), 2);
+        assert_eq!(options.lde_chunk_bits(8, 12, 10), 0);
+        assert_eq!(ProverOptions::<F>::default().lde_chunk_bits(8, 16, 10), 0);
+    }
+}
@@ -27,6 +27,29 @@ use crate::util::reducing::ReducingFactorTarget;
 use crate::util::strided_view::PackedStridedView;
 use crate::with_context;
 
+/// Combines the columns of a row of the lookup argument with powers of `challenge`, i.e. computes
+/// `col_0 + challenge * col_1 + challenge^2 * col_2 + ...`.
+pub(crate) fn combine_lookup_columns<F: Field>(
+    columns: impl DoubleEndedIterator<Item = F>,
+    challenge: F,
+) -> F {
+    columns
+        .rev()
+        .fold(F::ZERO, |acc, col| acc * challenge + col)
+}
+
+/// Same as `combine_lookup_columns`, but in the circuit. There must be at least one column.
+fn combine_lookup_columns_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    columns: impl DoubleEndedIterator<Item = ExtensionTarget<D>>,
+    challenge: ExtensionTarget<D>,
+) -> ExtensionTarget<D> {
+    columns
+        .rev()
+        .reduce(|acc, col| builder.mul_add_extension(acc, challenge, col))
+        .expect("The lookup argument has no columns")
+}
+
 /// Get the polynomial associated to a lookup table with current challenges.
 pub(crate) fn get_lut_poly<F: RichField + Extendable<D>, const D: usize>(
     common_data: &CommonCircuitData<F, D>,
@@ -37,8 +60,8 @@ pub(crate) fn get_lut_poly<F: RichField + Extendable<D>, const D: usize>(
     let b = deltas[LookupChallenges::ChallengeB as usize];
     let mut coeffs = Vec::with_capacity(common_data.luts[lut_index].len());
     let n = common_data.luts[lut_index].len();
-    for (input, output) in common_data.luts[lut_index].iter() {
-        coeffs.push(F::from_canonical_u16(*input) + b * F::from_canonical_u16(*output));
+    for row in common_data.luts[lut_index].rows() {
+        coeffs.push(combine_lookup_columns(row.iter().copied(), b));
     }
     coeffs.append(&mut vec![F::ZERO; degree - n]);
     coeffs.reverse();
@@ -328,8 +351,10 @@ pub(crate) fn eval_vanishing_poly_base_batch<F: RichField + Extendable<D>, const
 ///
 /// There are three polynomials to check:
 /// - RE ensures the well formation of lookup tables;
-/// - Sum is a running sum of m_i/(X - (input_i + a * output_i)) where (input_i, output_i) are input pairs in the lookup table (LUT);
-/// - LDC is a running sum of 1/(X - (input_i + a * output_i)) where (input_i, output_i) are input pairs that look in the LUT.
+/// - Sum is a running sum of m_i/(X - combo_i) where combo_i = row_i[0] + a * row_i[1] + a^2 * row_i[2] + ... for the rows row_i of the lookup table (LUT);
+/// - LDC is a running sum of 1/(X - combo_i) where combo_i is computed in the same way for the tuples that look in the LUT.
+///   Tuples and rows narrower than the widest LUT are padded with zeros, which doesn't change their combos.
+///
 /// Sum and LDC are broken down in partial polynomials to lower the constraint degree, similarly to the permutation argument.
 /// They also share the same partial SLDC polynomials, so that the last SLDC value is Sum(end) - LDC(end). The final constraint
 /// Sum(end) = LDC(end) becomes simply SLDC(end) = 0, and we can remove the LDC initial constraint.
@@ -341,8 +366,9 @@ pub fn check_lookup_constraints<F: RichField + Extendable<D>, const D: usize>(
     lookup_selectors: &[F::Extension],
     deltas: &[F; 4],
 ) -> Vec<F::Extension> {
-    let num_lu_slots = LookupGate::num_slots(&common_data.config);
-    let num_lut_slots = LookupTableGate::num_slots(&common_data.config);
+    let width = common_data.lookup_width();
+    let num_lu_slots = LookupGate::<F>::num_slots(&common_data.config, width);
+    let num_lut_slots = LookupTableGate::<F>::num_slots(&common_data.config, width);
     let lu_degree = common_data.quotient_degree_factor - 1;
     let num_sldc_polys = local_lookup_zs.len() - 1;
     let lut_degree = ceil_div_usize(num_lut_slots, num_sldc_polys);
@@ -361,29 +387,25 @@ pub fn check_lookup_constraints<F: RichField + Extendable<D>, const D: usize>(
     let delta_challenge_b = F::Extension::from(deltas[LookupChallenges::ChallengeB as usize]);
 
     // Compute all current looked and looking combos, i.e. the combos we need for the SLDC polynomials.
+    let looked_wires = |s| {
+        (0..width)
+            .map(move |col| vars.local_wires[LookupTableGate::<F>::wire_ith_looked(width, s, col)])
+    };
     let current_looked_combos: Vec<F::Extension> = (0..num_lut_slots)
-        .map(|s| {
-            let input_wire = vars.local_wires[LookupTableGate::wire_ith_looked_inp(s)];
-            let output_wire = vars.local_wires[LookupTableGate::wire_ith_looked_out(s)];
-            input_wire + delta_challenge_a * output_wire
-        })
+        .map(|s| combine_lookup_columns(looked_wires(s), delta_challenge_a))
         .collect();
 
     let current_looking_combos: Vec<F::Extension> = (0..num_lu_slots)
         .map(|s| {
-            let input_wire = vars.local_wires[LookupGate::wire_ith_looking_inp(s)];
-            let output_wire = vars.local_wires[LookupGate::wire_ith_looking_out(s)];
-            input_wire + delta_challenge_a * output_wire
+            let looking_wires = (0..width)
+                .map(|col| vars.local_wires[LookupGate::<F>::wire_ith_looking(width, s, col)]);
+            combine_lookup_columns(looking_wires, delta_challenge_a)
         })
         .collect();
 
     // Compute all current lookup combos, i.e. the combos used to check that the LUT is correct.
     let current_lookup_combos: Vec<F::Extension> = (0..num_lut_slots)
-        .map(|s| {
-            let input_wire = vars.local_wires[LookupTableGate::wire_ith_looked_inp(s)];
-            let output_wire = vars.local_wires[LookupTableGate::wire_ith_looked_out(s)];
-            input_wire + delta_challenge_b * output_wire
-        })
+        .map(|s| combine_lookup_columns(looked_wires(s), delta_challenge_b))
         .collect();
 
     // Check last LDC constraint.
@@ -400,15 +422,14 @@ pub fn check_lookup_constraints<F: RichField + Extendable<D>, const D: usize>(
     let current_delta = deltas[LookupChallenges::ChallengeDelta as usize];
 
     // Check final RE constraints for each different LUT.
-    for r in LookupSelectors::StartEnd as usize..common_data.num_lookup_selectors {
+    for (r, lut_index) in (LookupSelectors::StartEnd as usize..common_data.num_lookup_selectors)
+        .zip(common_data.static_lut_indices())
+    {
         let cur_ends_selector = lookup_selectors[r];
-        let lut_row_number = ceil_div_usize(
-            common_data.luts[r - LookupSelectors::StartEnd as usize].len(),
-            num_lut_slots,
-        );
+        let lut_row_number = ceil_div_usize(common_data.luts[lut_index].len(), num_lut_slots);
         let cur_function_eval = get_lut_poly(
             common_data,
-            r - LookupSelectors::StartEnd as usize,
+            lut_index,
             deltas,
             num_lut_slots * lut_row_number,
         )
@@ -480,7 +501,8 @@ pub fn check_lookup_constraints<F: RichField + Extendable<D>, const D: usize>(
         let lut_sum_prods_with_mul = (poly * lut_degree
             ..min((poly + 1) * lut_degree, num_lut_slots))
             .fold(F::Extension::ZERO, |acc, i| {
-                acc + vars.local_wires[LookupTableGate::wire_ith_multiplicity(i)] * lut_prod_i(i)
+                acc + vars.local_wires[LookupTableGate::<F>::wire_ith_multiplicity(width, i)]
+                    * lut_prod_i(i)
             });
 
         // The previous element is the previous poly of the current row or the last poly of the next row.
@@ -515,8 +537,9 @@ pub fn check_lookup_constraints_batch<F: RichField + Extendable<D>, const D: usi
     deltas: &[F; 4],
     lut_re_poly_evals: &[F],
 ) -> Vec<F> {
-    let num_lu_slots = LookupGate::num_slots(&common_data.config);
-    let num_lut_slots = LookupTableGate::num_slots(&common_data.config);
+    let width = common_data.lookup_width();
+    let num_lu_slots = LookupGate::<F>::num_slots(&common_data.config, width);
+    let num_lut_slots = LookupTableGate::<F>::num_slots(&common_data.config, width);
     let lu_degree = common_data.quotient_degree_factor - 1;
     let num_sldc_polys = local_lookup_zs.len() - 1;
     let lut_degree = ceil_div_usize(num_lut_slots, num_sldc_polys);
@@ -532,28 +555,34 @@ pub fn check_lookup_constraints_batch<F: RichField + Extendable<D>, const D: usi
     let z_gx_lookup_sldcs = &next_lookup_zs[1..num_sldc_polys + 1];
 
     // Compute all current looked and looking combos, i.e. the combos we need for the SLDC polynomials.
+    let looked_wires = |s| {
+        (0..width)
+            .map(move |col| vars.local_wires[LookupTableGate::<F>::wire_ith_looked(width, s, col)])
+    };
     let current_looked_combos: Vec<F> = (0..num_lut_slots)
         .map(|s| {
-            let input_wire = vars.local_wires[LookupTableGate::wire_ith_looked_inp(s)];
-            let output_wire = vars.local_wires[LookupTableGate::wire_ith_looked_out(s)];
-            input_wire + deltas[LookupChallenges::ChallengeA as usize] * output_wire
+            combine_lookup_columns(
+                looked_wires(s),
+                deltas[LookupChallenges::ChallengeA as usize],
+            )
         })
         .collect();
 
     let current_looking_combos: Vec<F> = (0..num_lu_slots)
         .map(|s| {
-            let input_wire = vars.local_wires[LookupGate::wire_ith_looking_inp(s)];
-            let output_wire = vars.local_wires[LookupGate::wire_ith_looking_out(s)];
-            input_wire + deltas[LookupChallenges::ChallengeA as usize] * output_wire
+            let looking_wires = (0..width)
+                .map(|col| vars.local_wires[LookupGate::<F>::wire_ith_looking(width, s, col)]);
+            combine_lookup_columns(looking_wires, deltas[LookupChallenges::ChallengeA as usize])
         })
         .collect();
 
     // Compute all current lookup combos, i.e. the combos used to check that the LUT is correct.
     let current_lookup_combos: Vec<F> = (0..num_lut_slots)
         .map(|s| {
-            let input_wire = vars.local_wires[LookupTableGate::wire_ith_looked_inp(s)];
-            let output_wire = vars.local_wires[LookupTableGate::wire_ith_looked_out(s)];
-            input_wire + deltas[LookupChallenges::ChallengeB as usize] * output_wire
+            combine_lookup_columns(
+                looked_wires(s),
+                deltas[LookupChallenges::ChallengeB as usize],
+            )
         })
         .collect();
 
@@ -633,7 +662,8 @@ pub fn check_lookup_constraints_batch<F: RichField + Extendable<D>, const D: usi
         let lut_sum_prods_with_mul = (poly * lut_degree
             ..min((poly + 1) * lut_degree, num_lut_slots))
             .fold(F::ZERO, |acc, i| {
-                acc + vars.local_wires[LookupTableGate::wire_ith_multiplicity(i)] * lut_prod_i(i)
+                acc + vars.local_wires[LookupTableGate::<F>::wire_ith_multiplicity(width, i)]
+                    * lut_prod_i(i)
             });
 
         // The previous element is the previous poly of the current row or the last poly of the next row.
@@ -758,10 +788,14 @@ pub(crate) fn get_lut_poly_circuit<F: RichField + Extendable<D>, const D: usize>
     let delta = deltas[LookupChallenges::ChallengeDelta as usize];
     let n = common_data.luts[lut_index].len();
     let mut coeffs: Vec<Target> = common_data.luts[lut_index]
-        .iter()
-        .map(|(input, output)| {
-            let temp = builder.mul_const(F::from_canonical_u16(*output), b);
-            builder.add_const(temp, F::from_canonical_u16(*input))
+        .rows()
+        .map(|row| {
+            let (&last, rest) = row.split_last().unwrap();
+            let last = builder.constant(last);
+            rest.iter().rev().fold(last, |acc, &col| {
+                let temp = builder.mul(acc, b);
+                builder.add_const(temp, col)
+            })
         })
         .collect();
     for _ in n..degree {
@@ -927,8 +961,9 @@ pub fn check_lookup_constraints_circuit<F: RichField + Extendable<D>, const D: u
     lookup_selectors: &[ExtensionTarget<D>],
     deltas: &[Target],
 ) -> Vec<ExtensionTarget<D>> {
-    let num_lu_slots = LookupGate::num_slots(&common_data.config);
-    let num_lut_slots = LookupTableGate::num_slots(&common_data.config);
+    let width = common_data.lookup_width();
+    let num_lu_slots = LookupGate::<F>::num_slots(&common_data.config, width);
+    let num_lut_slots = LookupTableGate::<F>::num_slots(&common_data.config, width);
     let lu_degree = common_data.quotient_degree_factor - 1;
     let num_sldc_polys = local_lookup_zs.len() - 1;
     let lut_degree = ceil_div_usize(num_lut_slots, num_sldc_polys);
@@ -950,25 +985,27 @@ pub fn check_lookup_constraints_circuit<F: RichField + Extendable<D>, const D: u
         .collect::<Vec<_>>();
 
     // Computing all current looked and looking combos, i.e. the combos we need for the SLDC polynomials.
+    let looked_wires = |s| {
+        (0..width)
+            .map(move |col| vars.local_wires[LookupTableGate::<F>::wire_ith_looked(width, s, col)])
+    };
     let current_looked_combos = (0..num_lut_slots)
         .map(|s| {
-            let input_wire = vars.local_wires[LookupTableGate::wire_ith_looked_inp(s)];
-            let output_wire = vars.local_wires[LookupTableGate::wire_ith_looked_out(s)];
-            builder.mul_add_extension(
+            combine_lookup_columns_circuit(
+                builder,
+                looked_wires(s),
                 ext_deltas[LookupChallenges::ChallengeA as usize],
-                output_wire,
-                input_wire,
             )
         })
         .collect::<Vec<_>>();
     let current_looking_combos = (0..num_lu_slots)
         .map(|s| {
-            let input_wire = vars.local_wires[LookupGate::wire_ith_looking_inp(s)];
-            let output_wire = vars.local_wires[LookupGate::wire_ith_looking_out(s)];
-            builder.mul_add_extension(
+            let looking_wires = (0..width)
+                .map(|col| vars.local_wires[LookupGate::<F>::wire_ith_looking(width, s, col)]);
+            combine_lookup_columns_circuit(
+                builder,
+                looking_wires,
                 ext_deltas[LookupChallenges::ChallengeA as usize],
-                output_wire,
-                input_wire,
             )
         })
         .collect::<Vec<_>>();
@@ -994,12 +1031,10 @@ pub fn check_lookup_constraints_circuit<F: RichField + Extendable<D>, const D: u
     // Computing all current lookup combos, i.e. the combos used to check that the LUT is correct.
     let current_lookup_combos = (0..num_lut_slots)
         .map(|s| {
-            let input_wire = vars.local_wires[LookupTableGate::wire_ith_looked_inp(s)];
-            let output_wire = vars.local_wires[LookupTableGate::wire_ith_looked_out(s)];
-            builder.mul_add_extension(
+            combine_lookup_columns_circuit(
+                builder,
+                looked_wires(s),
                 ext_deltas[LookupChallenges::ChallengeB as usize],
-                output_wire,
-                input_wire,
             )
         })
         .collect::<Vec<_>>();
@@ -1021,16 +1056,15 @@ pub fn check_lookup_constraints_circuit<F: RichField + Extendable<D>, const D: u
         .push(builder.mul_extension(lookup_selectors[LookupSelectors::InitSre as usize], z_re));
 
     // Check final RE constraints for each different LUT.
-    for r in LookupSelectors::StartEnd as usize..common_data.num_lookup_selectors {
+    for (r, lut_index) in (LookupSelectors::StartEnd as usize..common_data.num_lookup_selectors)
+        .zip(common_data.static_lut_indices())
+    {
         let cur_ends_selectors = lookup_selectors[r];
-        let lut_row_number = ceil_div_usize(
-            common_data.luts[r - LookupSelectors::StartEnd as usize].len(),
-            num_lut_slots,
-        );
+        let lut_row_number = ceil_div_usize(common_data.luts[lut_index].len(), num_lut_slots);
         let cur_function_eval = get_lut_poly_circuit(
             builder,
             common_data,
-            r - LookupSelectors::StartEnd as usize,
+            lut_index,
             deltas,
             num_lut_slots * lut_row_number,
         );
@@ -1097,7 +1131,7 @@ pub fn check_lookup_constraints_circuit<F: RichField + Extendable<D>, const D: u
                 }
                 builder.mul_add_extension(
                     prod_i,
-                    vars.local_wires[LookupTableGate::wire_ith_multiplicity(i)],
+                    vars.local_wires[LookupTableGate::<F>::wire_ith_multiplicity(width, i)],
                     acc,
                 )
             });
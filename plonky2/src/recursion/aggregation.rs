@@ -0,0 +1,410 @@
+//! Aggregation of many proofs of the same circuit into a single proof, along a tree.
+//!
+//! An aggregation circuit verifies up to `arity` children, each of which is either a proof of the
+//! leaf circuit or a proof of the aggregation circuit itself, and combines their public inputs with
+//! a user-supplied closure. The combined public inputs have the same length as the leaf circuit's,
+//! so that aggregate proofs can stand in for leaf proofs higher up in the tree.
+//!
+//! Since the aggregation circuit verifies its own proofs, it uses cyclic recursion: its verifier
+//! data is appended to its public inputs, and callers must use [`AggregationCircuitData::verify`],
+//! which also checks that verifier data, rather than verifying the raw proof.
+
+use alloc::vec::Vec;
+
+use anyhow::{ensure, Result};
+use hashbrown::HashMap;
+use plonky2_maybe_rayon::*;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::{PartialWitness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{
+    CircuitData, CommonCircuitData, VerifierCircuitData, VerifierCircuitTarget,
+};
+use crate::plonk::config::{AlgebraicHasher, GenericConfig};
+use crate::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
+use crate::recursion::cyclic_recursion::check_cyclic_proof_verifier_data;
+use crate::recursion::dummy_circuit::{cyclic_base_proof, dummy_circuit, dummy_proof};
+
+/// The maximum number of rebuilds when looking for the aggregation circuit's `CommonCircuitData`.
+const MAX_COMMON_DATA_ITERATIONS: usize = 16;
+
+/// A child of an aggregation node.
+#[derive(Clone, Debug)]
+pub enum AggregationChild<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+{
+    /// A proof of the leaf circuit.
+    Leaf(ProofWithPublicInputs<F, C, D>),
+    /// A proof of the aggregation circuit.
+    Aggregate(ProofWithPublicInputs<F, C, D>),
+    /// No proof, which fills the last slots of a node with fewer than `arity` children.
+    Absent,
+}
+
+#[derive(Debug)]
+struct AggregationChildTarget<const D: usize> {
+    is_present: BoolTarget,
+    is_agg: BoolTarget,
+    agg_proof: ProofWithPublicInputsTarget<D>,
+    leaf_proof: ProofWithPublicInputsTarget<D>,
+}
+
+/// A circuit aggregating `arity` proofs, each of either a leaf circuit or itself.
+#[derive(Debug)]
+pub struct AggregationCircuitData<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+> {
+    pub circuit: CircuitData<F, C, D>,
+    children: Vec<AggregationChildTarget<D>>,
+    verifier_data: VerifierCircuitTarget,
+    /// A proof of the aggregation circuit's shape, carrying its verifier data, which fills the
+    /// unused aggregate slot of a leaf child.
+    base_proof: ProofWithPublicInputs<F, C, D>,
+    /// A proof of the leaf circuit's shape, which fills the unused leaf slot of an aggregate child.
+    dummy_leaf_proof: ProofWithPublicInputs<F, C, D>,
+}
+
+impl<F, C, const D: usize> AggregationCircuitData<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + 'static,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    /// Builds a circuit aggregating `arity` proofs of `leaf`, or of itself.
+    ///
+    /// `combiner` receives the public inputs of each child, in order, along with whether the child
+    /// is present. The public inputs of an absent child are zeros. It must return as many targets
+    /// as the leaf circuit has public inputs; these become the aggregate's public inputs.
+    /// It may be called several times, as the circuit is rebuilt until its `CommonCircuitData`
+    /// reaches a fixed point.
+    ///
+    /// The aggregation circuit uses the leaf circuit's config. As with
+    /// `conditionally_verify_proof_or_dummy`, the leaf circuit must be one for which dummy proofs
+    /// can be generated, i.e. without zero-knowledge or lookups.
+    pub fn new<Combiner>(
+        leaf: &VerifierCircuitData<F, C, D>,
+        arity: usize,
+        combiner: Combiner,
+    ) -> Result<Self>
+    where
+        Combiner: Fn(&mut CircuitBuilder<F, D>, &[(BoolTarget, Vec<Target>)]) -> Vec<Target>,
+    {
+        ensure!(
+            arity >= 2,
+            "An aggregation circuit needs at least two children"
+        );
+
+        // Start from an empty circuit with the right public inputs, and rebuild the aggregation
+        // circuit against the common data of the previous build until it no longer changes.
+        let mut builder = CircuitBuilder::<F, D>::new(leaf.common.config.clone());
+        for _ in 0..leaf.common.num_public_inputs {
+            builder.add_virtual_public_input();
+        }
+        builder.add_verifier_data_public_inputs();
+        let mut common_data = builder.build::<C>().common;
+        let mut converged = false;
+        for _ in 0..MAX_COMMON_DATA_ITERATIONS {
+            let (circuit, _, _) = Self::build(leaf, arity, &combiner, &common_data, false)?;
+            if circuit.common == common_data {
+                converged = true;
+                break;
+            }
+            common_data = circuit.common;
+        }
+        ensure!(
+            converged,
+            "The aggregation circuit's common data did not converge"
+        );
+
+        let (circuit, children, verifier_data) =
+            Self::build(leaf, arity, &combiner, &common_data, true)?;
+        let base_proof = cyclic_base_proof(&circuit.common, &circuit.verifier_only, HashMap::new());
+        let dummy_leaf_proof =
+            dummy_proof::<F, C, D>(&dummy_circuit::<F, C, D>(&leaf.common), HashMap::new())?;
+
+        Ok(Self {
+            circuit,
+            children,
+            verifier_data,
+            base_proof,
+            dummy_leaf_proof,
+        })
+    }
+
+    /// Builds the aggregation circuit, verifying aggregate children against `common_data`.
+    ///
+    /// Unless `with_dummies` is set, the dummy proofs verified in place of absent children are
+    /// left as unset virtual targets. This yields the same circuit shape much faster, which is
+    /// all the search for `common_data` needs.
+    fn build<Combiner>(
+        leaf: &VerifierCircuitData<F, C, D>,
+        arity: usize,
+        combiner: &Combiner,
+        common_data: &CommonCircuitData<F, D>,
+        with_dummies: bool,
+    ) -> Result<(
+        CircuitData<F, C, D>,
+        Vec<AggregationChildTarget<D>>,
+        VerifierCircuitTarget,
+    )>
+    where
+        Combiner: Fn(&mut CircuitBuilder<F, D>, &[(BoolTarget, Vec<Target>)]) -> Vec<Target>,
+    {
+        let num_leaf_public_inputs = leaf.common.num_public_inputs;
+        let cap_height = leaf.common.config.fri_config.cap_height;
+
+        let mut builder = CircuitBuilder::<F, D>::new(leaf.common.config.clone());
+        let public_inputs = (0..num_leaf_public_inputs)
+            .map(|_| builder.add_virtual_public_input())
+            .collect::<Vec<_>>();
+        let verifier_data = builder.add_verifier_data_public_inputs();
+        let leaf_verifier_data = builder.constant_verifier_data(&leaf.verifier_only);
+
+        // A single pair of dummy proofs is shared by all children, rather than generating one per
+        // child with `conditionally_verify_proof_or_dummy`.
+        let (dummy_agg_proof, dummy_agg_verifier_data) = if with_dummies {
+            builder.dummy_proof_and_vk::<C>(common_data)?
+        } else {
+            (
+                builder.add_virtual_proof_with_pis(common_data),
+                builder.add_virtual_verifier_data(cap_height),
+            )
+        };
+        let (dummy_leaf_proof, dummy_leaf_verifier_data) = if with_dummies {
+            builder.dummy_proof_and_vk::<C>(&leaf.common)?
+        } else {
+            (
+                builder.add_virtual_proof_with_pis(&leaf.common),
+                builder.add_virtual_verifier_data(cap_height),
+            )
+        };
+
+        let mut children = Vec::with_capacity(arity);
+        let mut children_public_inputs = Vec::with_capacity(arity);
+        for _ in 0..arity {
+            let is_present = builder.add_virtual_bool_target_safe();
+            let is_agg = builder.add_virtual_bool_target_safe();
+            // An aggregate child is present, so `is_leaf` is boolean.
+            let is_leaf = BoolTarget::new_unsafe(builder.sub(is_present.target, is_agg.target));
+            builder.assert_bool(is_leaf);
+            let agg_proof = builder.add_virtual_proof_with_pis(common_data);
+            let leaf_proof = builder.add_virtual_proof_with_pis(&leaf.common);
+
+            builder.conditionally_verify_cyclic_proof::<C>(
+                is_agg,
+                &agg_proof,
+                &dummy_agg_proof,
+                &dummy_agg_verifier_data,
+                common_data,
+            )?;
+            builder.conditionally_verify_proof::<C>(
+                is_leaf,
+                &leaf_proof,
+                &leaf_verifier_data,
+                &dummy_leaf_proof,
+                &dummy_leaf_verifier_data,
+                &leaf.common,
+            );
+
+            let child_public_inputs = agg_proof.public_inputs[..num_leaf_public_inputs]
+                .iter()
+                .zip(&leaf_proof.public_inputs)
+                .map(|(&agg_pi, &leaf_pi)| {
+                    let leaf_pi = builder.mul(is_leaf.target, leaf_pi);
+                    builder.mul_add(is_agg.target, agg_pi, leaf_pi)
+                })
+                .collect::<Vec<_>>();
+            children_public_inputs.push((is_present, child_public_inputs));
+            children.push(AggregationChildTarget {
+                is_present,
+                is_agg,
+                agg_proof,
+                leaf_proof,
+            });
+        }
+
+        let combined = combiner(&mut builder, &children_public_inputs);
+        ensure!(
+            combined.len() == num_leaf_public_inputs,
+            "The combiner returned {} public inputs, but the leaf circuit has {}",
+            combined.len(),
+            num_leaf_public_inputs
+        );
+        for (&pi, &value) in public_inputs.iter().zip(&combined) {
+            builder.connect(pi, value);
+        }
+
+        if !with_dummies {
+            // The common data may not be the final one yet, which `build` would warn about.
+            builder.goal_common_data = None;
+        }
+
+        Ok((builder.build::<C>(), children, verifier_data))
+    }
+
+    /// The number of children aggregated by each proof.
+    pub fn arity(&self) -> usize {
+        self.children.len()
+    }
+
+    /// Proves one aggregation node, given exactly `arity` children, the absent ones included.
+    pub fn prove(
+        &self,
+        children: &[AggregationChild<F, C, D>],
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        ensure!(
+            children.len() == self.arity(),
+            "Expected {} children, got {}",
+            self.arity(),
+            children.len()
+        );
+
+        let mut pw = PartialWitness::new();
+        for (target, child) in self.children.iter().zip(children) {
+            let (is_present, is_agg, agg_proof, leaf_proof) = match child {
+                AggregationChild::Leaf(proof) => (true, false, &self.base_proof, proof),
+                AggregationChild::Aggregate(proof) => (true, true, proof, &self.dummy_leaf_proof),
+                AggregationChild::Absent => {
+                    (false, false, &self.base_proof, &self.dummy_leaf_proof)
+                }
+            };
+            pw.set_bool_target(target.is_present, is_present);
+            pw.set_bool_target(target.is_agg, is_agg);
+            pw.set_proof_with_pis_target(&target.agg_proof, agg_proof);
+            pw.set_proof_with_pis_target(&target.leaf_proof, leaf_proof);
+        }
+        pw.set_verifier_data_target(&self.verifier_data, &self.circuit.verifier_only);
+
+        self.circuit.prove(pw)
+    }
+
+    /// Aggregates `leaves` into a single proof, proving each level of the tree in parallel.
+    ///
+    /// Leaves are grouped in order; when a level's length isn't a multiple of `arity`, its last
+    /// group is padded with absent children. A single leaf is aggregated on its own.
+    pub fn prove_tree(
+        &self,
+        leaves: Vec<ProofWithPublicInputs<F, C, D>>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        ensure!(
+            !leaves.is_empty(),
+            "Cannot aggregate an empty list of proofs"
+        );
+
+        let arity = self.arity();
+        let mut level = leaves
+            .into_iter()
+            .map(AggregationChild::Leaf)
+            .collect::<Vec<_>>();
+        loop {
+            level.resize(
+                level.len().next_multiple_of(arity),
+                AggregationChild::Absent,
+            );
+            let mut next_level = level
+                .par_chunks(arity)
+                .map(|children| self.prove(children))
+                .collect::<Result<Vec<_>>>()?;
+            if next_level.len() == 1 {
+                return Ok(next_level.pop().unwrap());
+            }
+            level = next_level
+                .into_iter()
+                .map(AggregationChild::Aggregate)
+                .collect();
+        }
+    }
+
+    /// Verifies an aggregate proof, including the verifier data in its public inputs.
+    pub fn verify(&self, proof: ProofWithPublicInputs<F, C, D>) -> Result<()> {
+        check_cyclic_proof_verifier_data(
+            &proof,
+            &self.circuit.verifier_only,
+            &self.circuit.common,
+        )?;
+        self.circuit.verify(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::types::Field;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type Proof = ProofWithPublicInputs<F, C, D>;
+
+    /// Builds a leaf circuit proving knowledge of a 32-bit square root of its public input, and
+    /// proves it for each of `roots`.
+    fn square_leaves(
+        roots: impl Iterator<Item = u64>,
+    ) -> Result<(CircuitData<F, C, D>, Vec<Proof>)> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let root = builder.add_virtual_target();
+        builder.range_check(root, 32);
+        let square = builder.square(root);
+        builder.register_public_input(square);
+        let leaf = builder.build::<C>();
+
+        let proofs = roots
+            .map(|i| {
+                let mut pw = PartialWitness::new();
+                pw.set_target(root, F::from_canonical_u64(i));
+                leaf.prove(pw)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok((leaf, proofs))
+    }
+
+    #[test]
+    fn test_aggregation_tree() -> Result<()> {
+        let (leaf, leaf_proofs) = square_leaves(1..=3)?;
+
+        let agg = AggregationCircuitData::new(&leaf.verifier_data(), 2, |builder, children| {
+            let sum = builder.add_many(children.iter().map(|(_, pis)| pis[0]));
+            vec![sum]
+        })?;
+
+        // Three leaves make the root aggregate an aggregate proof with an aggregate of a leaf.
+        let proof = agg.prove_tree(leaf_proofs.clone())?;
+        assert_eq!(proof.public_inputs[0], F::from_canonical_u64(1 + 4 + 9));
+        agg.verify(proof)?;
+
+        let proof = agg.prove_tree(leaf_proofs[..1].to_vec())?;
+        assert_eq!(proof.public_inputs[0], F::ONE);
+        agg.verify(proof)?;
+
+        assert!(agg.prove_tree(Vec::new()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_tree_with_absent_children() -> Result<()> {
+        let (leaf, leaf_proofs) = square_leaves(1..=5)?;
+
+        // The product of the present children's public inputs, which absent children must not
+        // zero out.
+        let agg = AggregationCircuitData::new(&leaf.verifier_data(), 3, |builder, children| {
+            let one = builder.one();
+            let factors = children
+                .iter()
+                .map(|&(is_present, ref pis)| builder.select(is_present, pis[0], one))
+                .collect::<Vec<_>>();
+            vec![builder.mul_many(factors)]
+        })?;
+
+        // Five leaves fill a node and a half, and the root has two children.
+        let proof = agg.prove_tree(leaf_proofs)?;
+        assert_eq!(proof.public_inputs[0], F::from_canonical_u64(120 * 120));
+        agg.verify(proof)
+    }
+}
@@ -1,3 +1,4 @@
+pub mod aggregation;
 pub mod conditional_recursive_verifier;
 pub mod cyclic_recursion;
 pub mod dummy_circuit;
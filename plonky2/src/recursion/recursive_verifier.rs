@@ -191,7 +191,6 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
 #[cfg(test)]
 mod tests {
-    use alloc::sync::Arc;
 
     use anyhow::Result;
     use itertools::Itertools;
@@ -201,7 +200,6 @@ mod tests {
     use crate::fri::reduction_strategies::FriReductionStrategy;
     use crate::fri::FriConfig;
     use crate::gadgets::lookup::{OTHER_TABLE, TIP5_TABLE};
-    use crate::gates::lookup_table::LookupTable;
     use crate::gates::noop::NoopGate;
     use crate::iop::witness::{PartialWitness, WitnessWrite};
     use crate::plonk::circuit_data::{CircuitConfig, VerifierOnlyCircuitData};
@@ -258,6 +256,22 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_recursive_verifier_memory() -> Result<()> {
+        init_logger();
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+
+        let (proof, vd, common_data) = dummy_memory_proof::<F, C, D>(&config)?;
+        let (proof, vd, common_data) =
+            recursive_proof::<F, C, C, D>(proof, vd, common_data, &config, None, true, true)?;
+        test_serialization(&proof, &vd, &common_data)?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_recursive_verifier_too_many_rows() -> Result<()> {
         init_logger();
@@ -443,12 +457,12 @@ mod tests {
         let look_val_b = 2;
 
         let tip5_table = TIP5_TABLE.to_vec();
-        let table: LookupTable = Arc::new((0..256).zip_eq(tip5_table).collect());
+        let table: Vec<(u16, u16)> = (0..256).zip_eq(tip5_table).collect();
 
         let out_a = table[look_val_a].1;
         let out_b = table[look_val_b].1;
 
-        let tip5_index = builder.add_lookup_table_from_pairs(table);
+        let tip5_index = builder.add_lookup_table_from_pairs(&table);
 
         let output_a = builder.add_lookup_from_index(initial_a, tip5_index);
         let output_b = builder.add_lookup_from_index(initial_b, tip5_index);
@@ -504,11 +518,11 @@ mod tests {
         let first_out = tip5_table[look_val_a];
         let second_out = tip5_table[look_val_b];
 
-        let table: LookupTable = Arc::new((0..256).zip_eq(tip5_table).collect());
+        let table: Vec<(u16, u16)> = (0..256).zip_eq(tip5_table).collect();
 
         let other_table = OTHER_TABLE.to_vec();
 
-        let tip5_index = builder.add_lookup_table_from_pairs(table);
+        let tip5_index = builder.add_lookup_table_from_pairs(&table);
         let output_a = builder.add_lookup_from_index(initial_a, tip5_index);
 
         let output_b = builder.add_lookup_from_index(initial_b, tip5_index);
@@ -517,9 +531,9 @@ mod tests {
         let s = first_out + second_out;
         let final_out = other_table[s as usize];
 
-        let table2: LookupTable = Arc::new((0..256).zip_eq(other_table).collect());
+        let table2: Vec<(u16, u16)> = (0..256).zip_eq(other_table).collect();
 
-        let other_index = builder.add_lookup_table_from_pairs(table2);
+        let other_index = builder.add_lookup_table_from_pairs(&table2);
         let output_final = builder.add_lookup_from_index(sum, other_index);
 
         builder.register_public_input(initial_a);
@@ -561,6 +575,49 @@ mod tests {
         Ok((proof, data.verifier_only, data.common))
     }
 
+    /// Creates a dummy proof which swaps two cells of a memory, next to a lookup to a constant LUT.
+    fn dummy_memory_proof<
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        const D: usize,
+    >(
+        config: &CircuitConfig,
+    ) -> Result<Proof<F, C, D>> {
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let table: Vec<(u16, u16)> = (0..256).zip_eq(TIP5_TABLE).collect();
+        let tip5_index = builder.add_lookup_table_from_pairs(&table);
+
+        let cells = builder.add_virtual_targets(64);
+        let memory = builder.add_memory(&cells);
+        let i = builder.add_virtual_target();
+        let j = builder.add_virtual_target();
+        let cell_i = builder.memory_read(memory, i);
+        let cell_j = builder.memory_read(memory, j);
+        builder.memory_write(memory, i, cell_j);
+        builder.memory_write(memory, j, cell_i);
+        let new_cell_i = builder.memory_read(memory, i);
+        let output = builder.add_lookup_from_index(new_cell_i, tip5_index);
+        builder.register_public_input(output);
+
+        let data = builder.build::<C>();
+        let mut inputs = PartialWitness::new();
+        for (k, &cell) in cells.iter().enumerate() {
+            inputs.set_target(cell, F::from_canonical_usize(k));
+        }
+        inputs.set_target(i, F::from_canonical_usize(3));
+        inputs.set_target(j, F::from_canonical_usize(42));
+
+        let proof = data.prove(inputs)?;
+        data.verify(proof.clone())?;
+
+        assert_eq!(
+            proof.public_inputs[0],
+            F::from_canonical_u16(TIP5_TABLE[42])
+        );
+
+        Ok((proof, data.verifier_only, data.common))
+    }
+
     /// Creates a dummy proof which has more than 256 lookups to one LUT.
     fn dummy_too_many_rows_proof<
         F: RichField + Extendable<D>,
@@ -578,12 +635,12 @@ mod tests {
         let look_val_b = 2;
 
         let tip5_table = TIP5_TABLE.to_vec();
-        let table: LookupTable = Arc::new((0..256).zip_eq(tip5_table).collect());
+        let table: Vec<(u16, u16)> = (0..256).zip_eq(tip5_table).collect();
 
         let out_a = table[look_val_a].1;
         let out_b = table[look_val_b].1;
 
-        let tip5_index = builder.add_lookup_table_from_pairs(table);
+        let tip5_index = builder.add_lookup_table_from_pairs(&table);
         let output_b = builder.add_lookup_from_index(initial_b, tip5_index);
         let mut output = builder.add_lookup_from_index(initial_a, tip5_index);
         for _ in 0..514 {
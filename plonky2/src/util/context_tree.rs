@@ -50,6 +50,37 @@ impl ContextTree {
         }
     }
 
+    /// A description of the stack of scopes which were open when the gate at `gate_index` was
+    /// added.
+    #[cfg(feature = "generator_origins")]
+    pub fn stack_at_gate(&self, gate_index: usize) -> String {
+        let mut stack = Vec::new();
+        self.stack_at_gate_helper(gate_index, &mut stack);
+        stack.join(" > ")
+    }
+
+    #[cfg(feature = "generator_origins")]
+    fn stack_at_gate_helper(&self, gate_index: usize, stack: &mut Vec<String>) {
+        if !self.contains_gate(gate_index) {
+            return;
+        }
+        stack.push(self.name.clone());
+        // Children are sorted by gate count and don't overlap, so only the last one entered before
+        // the gate may contain it.
+        let num_entered = self
+            .children
+            .partition_point(|c| c.enter_gate_count <= gate_index);
+        if let Some(child) = num_entered.checked_sub(1).map(|i| &self.children[i]) {
+            child.stack_at_gate_helper(gate_index, stack);
+        }
+    }
+
+    #[cfg(feature = "generator_origins")]
+    fn contains_gate(&self, gate_index: usize) -> bool {
+        self.enter_gate_count <= gate_index
+            && self.exit_gate_count.is_none_or(|exit| gate_index < exit)
+    }
+
     pub fn push(&mut self, ctx: &str, mut level: log::Level, current_gate_count: usize) {
         assert!(self.is_open());
 
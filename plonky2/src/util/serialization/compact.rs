@@ -0,0 +1,776 @@
+//! A compact wire format for [`CompressedProofWithPublicInputs`].
+//!
+//! Compared to [`Write::write_compressed_proof_with_public_inputs`], this format
+//! - writes data as a bit stream, so that query indices take `lde_bits` bits, and the proof of
+//!   work witness, which the prover picks as small as possible, only takes as many bits as it needs;
+//! - omits the lengths of compressed Merkle proofs, which the reader recomputes from the query
+//!   indices, as the shared siblings only depend on them;
+//! - omits the trailing zero coefficients of the FRI final polynomial;
+//! - writes small public inputs in 33 bits instead of 64.
+//!
+//! The savings are limited to this framing. Every other field element, i.e. the openings, the FRI
+//! evaluations and final polynomial coefficients, is written as its 64-bit canonical value, and
+//! hashes as is. These values are uniformly distributed, so varints would make them longer, and
+//! the Goldilocks order is above `2^63`, so they can't be packed losslessly in fewer bits. As they
+//! make up most of a proof, the savings are a small fraction of its size; use
+//! [`ProofSizeBreakdown`] to see where the bits go.
+//!
+//! The data starts with a header holding [`COMPACT_PROOF_MAGIC`], [`COMPACT_PROOF_VERSION`] and
+//! the degree of the circuit, so that proofs are rejected by readers of another version or with
+//! the wrong circuit.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+
+use hashbrown::HashMap;
+
+use crate::field::extension::Extendable;
+use crate::field::polynomial::PolynomialCoeffs;
+use crate::field::types::Field;
+use crate::fri::proof::{
+    CompressedFriProof, CompressedFriQueryRounds, FriInitialTreeProof, FriQueryStep,
+};
+use crate::gates::gate::GateRef;
+use crate::hash::hash_types::RichField;
+use crate::hash::merkle_proofs::MerkleProof;
+use crate::hash::path_compression::compressed_merkle_proof_lengths;
+use crate::iop::generator::WitnessGeneratorRef;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::plonk::config::{GenericConfig, Hasher};
+use crate::plonk::plonk_common::salt_size;
+use crate::plonk::proof::{CompressedProof, CompressedProofWithPublicInputs};
+use crate::util::log2_ceil;
+use crate::util::serialization::{
+    GateSerializer, IoError, IoResult, Read, WitnessGeneratorSerializer, Write,
+};
+
+/// The first bytes of every compact proof.
+pub const COMPACT_PROOF_MAGIC: [u8; 4] = *b"P2CP";
+
+/// The version of the compact proof format, bumped on every incompatible change.
+pub const COMPACT_PROOF_VERSION: u8 = 1;
+
+/// The number of bits used by each component of a compact proof.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ProofSizeBreakdown {
+    pub header: usize,
+    /// The Merkle caps of the wires, `Z`s and partial products, and quotient polynomials, and of
+    /// the FRI commit phase.
+    pub merkle_caps: usize,
+    pub openings: usize,
+    pub query_indices: usize,
+    /// The leaves opened in the initial trees, i.e. the ones committed to by the PLONK prover.
+    pub initial_tree_evals: usize,
+    pub initial_tree_siblings: usize,
+    /// The cosets opened in the FRI commit phase trees.
+    pub step_evals: usize,
+    pub step_siblings: usize,
+    pub final_poly: usize,
+    pub pow_witness: usize,
+    pub public_inputs: usize,
+}
+
+impl ProofSizeBreakdown {
+    /// The components of the proof, with their names and sizes in bits.
+    pub const fn components(&self) -> [(&'static str, usize); 11] {
+        [
+            ("header", self.header),
+            ("merkle_caps", self.merkle_caps),
+            ("openings", self.openings),
+            ("query_indices", self.query_indices),
+            ("initial_tree_evals", self.initial_tree_evals),
+            ("initial_tree_siblings", self.initial_tree_siblings),
+            ("step_evals", self.step_evals),
+            ("step_siblings", self.step_siblings),
+            ("final_poly", self.final_poly),
+            ("pow_witness", self.pow_witness),
+            ("public_inputs", self.public_inputs),
+        ]
+    }
+
+    pub fn total_bits(&self) -> usize {
+        self.components().iter().map(|&(_, bits)| bits).sum()
+    }
+
+    /// The size of the serialized proof, including the padding of the last byte.
+    pub fn total_bytes(&self) -> usize {
+        self.total_bits().div_ceil(8)
+    }
+}
+
+impl Display for ProofSizeBreakdown {
+    /// Lists the components from largest to smallest, with their share of the proof.
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let total = self.total_bits();
+        let mut components = self.components();
+        components.sort_by_key(|&(_, bits)| core::cmp::Reverse(bits));
+        for (name, bits) in components {
+            writeln!(
+                f,
+                "{name:>21}: {:>9.1} bytes ({:>5.1}%)",
+                bits as f64 / 8.0,
+                100.0 * bits as f64 / total as f64
+            )?;
+        }
+        write!(f, "{:>21}: {:>9} bytes", "total", self.total_bytes())
+    }
+}
+
+/// Writes bits to a byte vector, least significant bits first.
+#[derive(Debug, Default)]
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    num_bits: usize,
+}
+
+impl BitWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of bits written so far.
+    pub const fn num_bits(&self) -> usize {
+        self.num_bits
+    }
+
+    /// Writes the `num_bits` low bits of `value`.
+    pub fn write_bits(&mut self, value: u64, num_bits: usize) {
+        debug_assert!(num_bits == 64 || value >> num_bits == 0);
+        for i in 0..num_bits {
+            let offset = self.num_bits % 8;
+            if offset == 0 {
+                self.bytes.push(0);
+            }
+            let bit = ((value >> i) & 1) as u8;
+            *self.bytes.last_mut().unwrap() |= bit << offset;
+            self.num_bits += 1;
+        }
+    }
+
+    /// Returns the written bytes, with the last one padded with zeros.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl Write for BitWriter {
+    type Error = IoError;
+
+    fn write_all(&mut self, bytes: &[u8]) -> IoResult<()> {
+        let is_aligned = self.num_bits & 7 == 0;
+        if is_aligned {
+            self.bytes.extend_from_slice(bytes);
+            self.num_bits += 8 * bytes.len();
+        } else {
+            for &b in bytes {
+                self.write_bits(b as u64, 8);
+            }
+        }
+        Ok(())
+    }
+
+    /// Gates aren't part of proofs, so they can't be written here.
+    fn write_gate<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        gate: &GateRef<F, D>,
+        _gate_serializer: &dyn GateSerializer<F, D>,
+        _common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<()> {
+        Err(IoError::UnsupportedGate(gate.0.id()))
+    }
+
+    /// Generators aren't part of proofs, so they can't be written here.
+    fn write_generator<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        generator: &WitnessGeneratorRef<F, D>,
+        _generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+        _common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<()> {
+        Err(IoError::UnsupportedGenerator(generator.0.id()))
+    }
+}
+
+/// Reads bits written by a [`BitWriter`].
+#[derive(Debug)]
+pub struct BitReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub const fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    /// The number of bits left to read, including the padding of the last byte.
+    pub const fn remaining_bits(&self) -> usize {
+        8 * self.bytes.len() - self.pos
+    }
+
+    /// Reads `num_bits` bits, as the low bits of a `u64`.
+    pub fn read_bits(&mut self, num_bits: usize) -> IoResult<u64> {
+        if num_bits > 64 {
+            return Err(IoError::InvalidData);
+        }
+        if self.remaining_bits() < num_bits {
+            return Err(self.unexpected_eof(num_bits));
+        }
+        let mut value = 0;
+        for i in 0..num_bits {
+            let bit = (self.bytes[self.pos / 8] >> (self.pos % 8)) & 1;
+            value |= (bit as u64) << i;
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+
+    fn unexpected_eof(&self, num_bits: usize) -> IoError {
+        IoError::UnexpectedEof {
+            offset: self.pos / 8,
+            needed: num_bits.div_ceil(8),
+        }
+    }
+
+    /// Checks that only the zero padding of the last byte is left.
+    pub fn finish(mut self) -> IoResult<()> {
+        let padding = self.remaining_bits();
+        if padding < 8 && self.read_bits(padding)? == 0 {
+            Ok(())
+        } else {
+            Err(IoError::InvalidData)
+        }
+    }
+}
+
+impl<'a> Read for BitReader<'a> {
+    fn read_exact(&mut self, bytes: &mut [u8]) -> IoResult<()> {
+        if self.remaining_bits() < 8 * bytes.len() {
+            return Err(self.unexpected_eof(8 * bytes.len()));
+        }
+        let is_aligned = self.pos & 7 == 0;
+        if is_aligned {
+            let start = self.pos / 8;
+            bytes.copy_from_slice(&self.bytes[start..start + bytes.len()]);
+            self.pos += 8 * bytes.len();
+        } else {
+            for b in bytes.iter_mut() {
+                *b = self.read_bits(8)? as u8;
+            }
+        }
+        Ok(())
+    }
+
+    fn max_remaining(&self) -> usize {
+        self.remaining_bits() / 8
+    }
+
+    /// Gates aren't part of proofs, so they can't be read here.
+    fn read_gate<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        _gate_serializer: &dyn GateSerializer<F, D>,
+        _common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<GateRef<F, D>> {
+        Err(IoError::InvalidData)
+    }
+
+    /// Generators aren't part of proofs, so they can't be read here.
+    fn read_generator<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        _generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+        _common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<WitnessGeneratorRef<F, D>> {
+        Err(IoError::InvalidData)
+    }
+}
+
+/// The number of bits used to store the length of the proof of work witness.
+const POW_WITNESS_LEN_BITS: usize = 7;
+
+/// Public inputs below `2^SMALL_PUBLIC_INPUT_BITS` are stored in that many bits, plus a flag.
+const SMALL_PUBLIC_INPUT_BITS: usize = 32;
+
+/// Serializes `proof_with_pis` in the compact format, returning the bytes along with the size of
+/// each component.
+pub(crate) fn write_compact_proof<F, C, const D: usize>(
+    proof_with_pis: &CompressedProofWithPublicInputs<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> IoResult<(Vec<u8>, ProofSizeBreakdown)>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let CompressedProofWithPublicInputs {
+        proof,
+        public_inputs,
+    } = proof_with_pis;
+    let CompressedProof {
+        wires_cap,
+        plonk_zs_partial_products_cap,
+        quotient_polys_cap,
+        openings,
+        opening_proof,
+    } = proof;
+    let CompressedFriProof {
+        commit_phase_merkle_caps,
+        query_round_proofs,
+        final_poly,
+        pow_witness,
+    } = opening_proof;
+    if public_inputs.len() != common_data.num_public_inputs {
+        return Err(IoError::InvalidData);
+    }
+
+    let mut w = BitWriter::new();
+    let mut sizes = ProofSizeBreakdown::default();
+
+    let start = w.num_bits();
+    w.write_all(&COMPACT_PROOF_MAGIC)?;
+    w.write_u8(COMPACT_PROOF_VERSION)?;
+    w.write_u8(common_data.degree_bits() as u8)?;
+    sizes.header = w.num_bits() - start;
+
+    let start = w.num_bits();
+    w.write_merkle_cap(wires_cap)?;
+    w.write_merkle_cap(plonk_zs_partial_products_cap)?;
+    w.write_merkle_cap(quotient_polys_cap)?;
+    for cap in commit_phase_merkle_caps {
+        w.write_merkle_cap(cap)?;
+    }
+    sizes.merkle_caps = w.num_bits() - start;
+
+    let start = w.num_bits();
+    w.write_opening_set(openings)?;
+    sizes.openings = w.num_bits() - start;
+
+    write_query_rounds::<F, C, D>(&mut w, &mut sizes, query_round_proofs, common_data)?;
+
+    let start = w.num_bits();
+    let final_poly_len = common_data.fri_params.final_poly_len();
+    if final_poly.len() != final_poly_len {
+        return Err(IoError::InvalidData);
+    }
+    let trimmed_len = final_poly.degree_plus_one();
+    w.write_bits(trimmed_len as u64, final_poly_len_bits(final_poly_len));
+    w.write_field_ext_vec::<F, D>(&final_poly.coeffs[..trimmed_len])?;
+    sizes.final_poly = w.num_bits() - start;
+
+    let start = w.num_bits();
+    let pow_witness = pow_witness.to_canonical_u64();
+    let pow_witness_len = 64 - pow_witness.leading_zeros() as usize;
+    w.write_bits(pow_witness_len as u64, POW_WITNESS_LEN_BITS);
+    w.write_bits(pow_witness, pow_witness_len);
+    sizes.pow_witness = w.num_bits() - start;
+
+    let start = w.num_bits();
+    for pi in public_inputs {
+        let pi = pi.to_canonical_u64();
+        let is_small = pi >> SMALL_PUBLIC_INPUT_BITS == 0;
+        w.write_bits(is_small as u64, 1);
+        w.write_bits(
+            pi,
+            if is_small {
+                SMALL_PUBLIC_INPUT_BITS
+            } else {
+                64
+            },
+        );
+    }
+    sizes.public_inputs = w.num_bits() - start;
+
+    debug_assert_eq!(sizes.total_bits(), w.num_bits());
+    Ok((w.into_bytes(), sizes))
+}
+
+fn write_query_rounds<F, C, const D: usize>(
+    w: &mut BitWriter,
+    sizes: &mut ProofSizeBreakdown,
+    query_round_proofs: &CompressedFriQueryRounds<F, C::Hasher, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> IoResult<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let CompressedFriQueryRounds {
+        indices,
+        initial_trees_proofs,
+        steps,
+    } = query_round_proofs;
+    let params = &common_data.fri_params;
+    let cap_height = params.config.cap_height;
+    if indices.len() != params.config.num_query_rounds {
+        return Err(IoError::InvalidData);
+    }
+
+    let start = w.num_bits();
+    let mut height = params.lde_bits();
+    for &i in indices {
+        if i >> height != 0 {
+            return Err(IoError::InvalidData);
+        }
+        w.write_bits(i as u64, height);
+    }
+    sizes.query_indices += w.num_bits() - start;
+
+    // All initial trees have the same height and are opened at the same indices, so their
+    // compressed proofs have the same lengths.
+    for (index, proof_len) in merkle_proof_lengths(height, cap_height, indices) {
+        let initial_trees_proof = initial_trees_proofs
+            .get(&index)
+            .ok_or(IoError::InvalidData)?;
+        for (evals, merkle_proof) in &initial_trees_proof.evals_proofs {
+            let start = w.num_bits();
+            w.write_field_vec(evals)?;
+            sizes.initial_tree_evals += w.num_bits() - start;
+
+            let start = w.num_bits();
+            write_merkle_proof::<F, C::Hasher>(w, merkle_proof, proof_len)?;
+            sizes.initial_tree_siblings += w.num_bits() - start;
+        }
+    }
+
+    let mut indices = indices.clone();
+    for (&arity_bits, steps) in params.reduction_arity_bits.iter().zip(steps) {
+        indices.iter_mut().for_each(|i| *i >>= arity_bits);
+        height -= arity_bits;
+        for (index, proof_len) in merkle_proof_lengths(height, cap_height, &indices) {
+            let step = steps.get(&index).ok_or(IoError::InvalidData)?;
+
+            let start = w.num_bits();
+            w.write_field_ext_vec::<F, D>(&step.evals)?;
+            sizes.step_evals += w.num_bits() - start;
+
+            let start = w.num_bits();
+            write_merkle_proof::<F, C::Hasher>(w, &step.merkle_proof, proof_len)?;
+            sizes.step_siblings += w.num_bits() - start;
+        }
+    }
+
+    Ok(())
+}
+
+/// Deserializes a proof written by [`write_compact_proof`].
+pub(crate) fn read_compact_proof<F, C, const D: usize>(
+    bytes: &[u8],
+    common_data: &CommonCircuitData<F, D>,
+) -> IoResult<CompressedProofWithPublicInputs<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let mut r = BitReader::new(bytes);
+    let mut magic = [0; 4];
+    r.read_exact(&mut magic)?;
+    if magic != COMPACT_PROOF_MAGIC {
+        return Err(IoError::InvalidMagic);
+    }
+    let version = r.read_u8()?;
+    if version != COMPACT_PROOF_VERSION {
+        return Err(IoError::UnsupportedVersion {
+            expected: COMPACT_PROOF_VERSION as u32,
+            found: version as u32,
+        });
+    }
+    if r.read_u8()? as usize != common_data.degree_bits() {
+        return Err(IoError::CommonDataMismatch);
+    }
+
+    let params = &common_data.fri_params;
+    let cap_height = params.config.cap_height;
+    let wires_cap = r.read_merkle_cap(cap_height)?;
+    let plonk_zs_partial_products_cap = r.read_merkle_cap(cap_height)?;
+    let quotient_polys_cap = r.read_merkle_cap(cap_height)?;
+    let commit_phase_merkle_caps = (0..params.reduction_arity_bits.len())
+        .map(|_| r.read_merkle_cap(cap_height))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let openings = r.read_opening_set::<F, C, D>(common_data)?;
+
+    let query_round_proofs = read_query_rounds::<F, C, D>(&mut r, common_data)?;
+
+    let final_poly_len = params.final_poly_len();
+    let trimmed_len = r.read_bits(final_poly_len_bits(final_poly_len))? as usize;
+    if trimmed_len > final_poly_len {
+        return Err(IoError::InvalidData);
+    }
+    let mut final_poly = r.read_field_ext_vec::<F, D>(trimmed_len)?;
+    final_poly.resize(final_poly_len, F::Extension::ZERO);
+
+    let pow_witness_len = r.read_bits(POW_WITNESS_LEN_BITS)? as usize;
+    let pow_witness = r.read_bits(pow_witness_len)?;
+    if pow_witness >= F::ORDER {
+        return Err(IoError::InvalidData);
+    }
+
+    let public_inputs = (0..common_data.num_public_inputs)
+        .map(|_| {
+            let is_small = r.read_bits(1)? == 1;
+            let pi = r.read_bits(if is_small {
+                SMALL_PUBLIC_INPUT_BITS
+            } else {
+                64
+            })?;
+            if pi < F::ORDER {
+                Ok(F::from_canonical_u64(pi))
+            } else {
+                Err(IoError::InvalidData)
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    r.finish()?;
+
+    Ok(CompressedProofWithPublicInputs {
+        proof: CompressedProof {
+            wires_cap,
+            plonk_zs_partial_products_cap,
+            quotient_polys_cap,
+            openings,
+            opening_proof: CompressedFriProof {
+                commit_phase_merkle_caps,
+                query_round_proofs,
+                final_poly: PolynomialCoeffs::new(final_poly),
+                pow_witness: F::from_canonical_u64(pow_witness),
+            },
+        },
+        public_inputs,
+    })
+}
+
+fn read_query_rounds<F, C, const D: usize>(
+    r: &mut BitReader,
+    common_data: &CommonCircuitData<F, D>,
+) -> IoResult<CompressedFriQueryRounds<F, C::Hasher, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let config = &common_data.config;
+    let params = &common_data.fri_params;
+    let cap_height = params.config.cap_height;
+
+    let mut height = params.lde_bits();
+    let indices = (0..params.config.num_query_rounds)
+        .map(|_| r.read_bits(height).map(|i| i as usize))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // The leaves of the constants and sigmas, wires, `Z`s and partial products, and quotient
+    // trees, as in `Read::read_fri_initial_proof`.
+    let salt = salt_size(params.hiding);
+    let initial_leaf_lens = [
+        common_data.num_constants + config.num_routed_wires,
+        config.num_wires + salt,
+        config.num_challenges
+            * (1 + common_data.num_partial_products + common_data.num_lookup_polys)
+            + salt,
+        config.num_challenges * common_data.quotient_degree_factor + salt,
+    ];
+    let mut initial_trees_proofs = HashMap::new();
+    for (index, proof_len) in merkle_proof_lengths(height, cap_height, &indices) {
+        let evals_proofs = initial_leaf_lens
+            .iter()
+            .map(|&leaf_len| {
+                let evals = r.read_field_vec(leaf_len)?;
+                let merkle_proof = read_merkle_proof::<F, C::Hasher>(r, proof_len)?;
+                Ok((evals, merkle_proof))
+            })
+            .collect::<IoResult<Vec<_>>>()?;
+        initial_trees_proofs.insert(index, FriInitialTreeProof { evals_proofs });
+    }
+
+    let mut step_indices = indices.clone();
+    let mut steps = Vec::with_capacity(params.reduction_arity_bits.len());
+    for &arity_bits in &params.reduction_arity_bits {
+        step_indices.iter_mut().for_each(|i| *i >>= arity_bits);
+        height -= arity_bits;
+        let mut step = HashMap::new();
+        for (index, proof_len) in merkle_proof_lengths(height, cap_height, &step_indices) {
+            let evals = r.read_field_ext_vec::<F, D>((1 << arity_bits) - 1)?;
+            let merkle_proof = read_merkle_proof::<F, C::Hasher>(r, proof_len)?;
+            step.insert(
+                index,
+                FriQueryStep {
+                    evals,
+                    merkle_proof,
+                },
+            );
+        }
+        steps.push(step);
+    }
+
+    Ok(CompressedFriQueryRounds {
+        indices,
+        initial_trees_proofs,
+        steps,
+    })
+}
+
+/// The length of the compressed Merkle proof stored for each distinct index, in increasing order.
+/// As in `FriProof::compress`, it is the proof of the first query at that index.
+fn merkle_proof_lengths(
+    height: usize,
+    cap_height: usize,
+    indices: &[usize],
+) -> Vec<(usize, usize)> {
+    let mut lengths = BTreeMap::new();
+    for (&index, len) in indices
+        .iter()
+        .zip(compressed_merkle_proof_lengths(height, cap_height, indices))
+    {
+        lengths.entry(index).or_insert(len);
+    }
+    lengths.into_iter().collect()
+}
+
+/// The number of bits needed to store a length between 0 and `final_poly_len`.
+fn final_poly_len_bits(final_poly_len: usize) -> usize {
+    log2_ceil(final_poly_len + 1)
+}
+
+fn write_merkle_proof<F: RichField, H: Hasher<F>>(
+    w: &mut BitWriter,
+    merkle_proof: &MerkleProof<F, H>,
+    len: usize,
+) -> IoResult<()> {
+    if merkle_proof.siblings.len() != len {
+        return Err(IoError::InvalidData);
+    }
+    for &sibling in &merkle_proof.siblings {
+        w.write_hash::<F, H>(sibling)?;
+    }
+    Ok(())
+}
+
+fn read_merkle_proof<F: RichField, H: Hasher<F>>(
+    r: &mut BitReader,
+    len: usize,
+) -> IoResult<MerkleProof<F, H>> {
+    Ok(MerkleProof {
+        siblings: r.read_hash_vec::<F, H>(len)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn prove_compressed(
+        config: CircuitConfig,
+    ) -> Result<(
+        CircuitData<F, C, D>,
+        CompressedProofWithPublicInputs<F, C, D>,
+    )> {
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let mut y = x;
+        for _ in 0..1000 {
+            y = builder.mul_add(y, y, x);
+        }
+        builder.register_public_input(x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        let proof = data.prove(pw)?;
+        let compressed = data.compress(proof)?;
+        Ok((data, compressed))
+    }
+
+    #[test]
+    fn test_compact_proof_round_trip() -> Result<()> {
+        let (data, proof) = prove_compressed(CircuitConfig::standard_recursion_config())?;
+        let bytes = proof.to_compact_bytes(&data.common).unwrap();
+        let sizes = proof.size_breakdown(&data.common).unwrap();
+        assert_eq!(sizes.total_bytes(), bytes.len());
+        assert!(bytes.len() < proof.to_bytes().len());
+        // The first public input is small, the second one is not.
+        assert_eq!(sizes.public_inputs, 1 + 32 + 1 + 64);
+
+        let read_proof =
+            CompressedProofWithPublicInputs::from_compact_bytes(bytes.clone(), &data.common)?;
+        assert_eq!(read_proof, proof);
+        data.verify_compressed(read_proof)?;
+
+        // Proofs with another version, or for another circuit, are rejected.
+        let mut other_version = bytes.clone();
+        other_version[COMPACT_PROOF_MAGIC.len()] += 1;
+        assert!(
+            CompressedProofWithPublicInputs::<F, C, D>::from_compact_bytes(
+                other_version,
+                &data.common
+            )
+            .is_err()
+        );
+        let mut other_degree = bytes.clone();
+        other_degree[COMPACT_PROOF_MAGIC.len() + 1] += 1;
+        assert!(
+            CompressedProofWithPublicInputs::<F, C, D>::from_compact_bytes(
+                other_degree,
+                &data.common
+            )
+            .is_err()
+        );
+        let mut extended = bytes;
+        extended.push(0);
+        assert!(
+            CompressedProofWithPublicInputs::<F, C, D>::from_compact_bytes(extended, &data.common)
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_size_optimized_config() -> Result<()> {
+        let (standard_data, standard_proof) =
+            prove_compressed(CircuitConfig::standard_recursion_config())?;
+        let (data, proof) = prove_compressed(CircuitConfig::size_optimized_config())?;
+        let standard_size = standard_proof
+            .size_breakdown(&standard_data.common)
+            .unwrap();
+        let size = proof.size_breakdown(&data.common).unwrap();
+        assert!(size.total_bytes() < standard_size.total_bytes());
+
+        let bytes = proof.to_compact_bytes(&data.common).unwrap();
+        let read_proof = CompressedProofWithPublicInputs::from_compact_bytes(bytes, &data.common)?;
+        data.verify_compressed(read_proof)
+    }
+
+    #[test]
+    fn test_bits_round_trip() {
+        let values = [
+            (5, 3),
+            (0, 0),
+            (u64::MAX, 64),
+            (1, 1),
+            (0xabcd, 16),
+            (7, 60),
+        ];
+        let mut w = BitWriter::new();
+        for (value, num_bits) in values {
+            w.write_bits(value, num_bits);
+        }
+        w.write_all(&[1, 2, 3]).unwrap();
+        assert_eq!(w.num_bits(), 3 + 64 + 1 + 16 + 60 + 24);
+
+        let bytes = w.into_bytes();
+        let mut r = BitReader::new(&bytes);
+        for (value, num_bits) in values {
+            assert_eq!(r.read_bits(num_bits).unwrap(), value);
+        }
+        let mut buf = [0; 3];
+        r.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [1, 2, 3]);
+        r.finish().unwrap();
+    }
+}
@@ -9,6 +9,9 @@ use crate::hash::hash_types::RichField;
 use crate::plonk::circuit_data::CommonCircuitData;
 use crate::util::serialization::{Buffer, IoResult};
 
+/// Reads and writes gates of the types it supports, identified by tags derived from their names.
+/// Errors from a gate's own serialization are reported within its name, see
+/// [`IoError::Within`](crate::util::serialization::IoError::Within).
 pub trait GateSerializer<F: RichField + Extendable<D>, const D: usize> {
     fn read_gate(
         &self,
@@ -25,27 +28,27 @@ pub trait GateSerializer<F: RichField + Extendable<D>, const D: usize> {
 
 #[macro_export]
 macro_rules! read_gate_impl {
-    ($buf:expr, $tag:expr, $common:expr, $($gate_types:ty),+) => {{
-        let tag = $tag;
+    ($buf:expr, $tag:expr, $common:expr, $($names:literal => $gate_types:ty),+) => {{
+        let tag: u32 = $tag;
         let buf = $buf;
-        let mut i = 0..;
-        $(if tag == i.next().unwrap() {
-            let gate = <$gate_types as $crate::gates::gate::Gate<F, D>>::deserialize(buf, $common)?;
+        $(if tag == $crate::util::serialization::type_tag($names) {
+            let gate = <$gate_types as $crate::gates::gate::Gate<F, D>>::deserialize(buf, $common);
+            let gate = $crate::util::serialization::IoResultExt::within(gate, $names)?;
             Ok($crate::gates::gate::GateRef::<F, D>::new(gate))
         } else)*
         {
-            Err($crate::util::serialization::IoError)
+            Err($crate::util::serialization::IoError::UnknownGateTag(tag))
         }
     }}
 }
 
+/// Returns the tag and name of the gate's type.
 #[macro_export]
 macro_rules! get_gate_tag_impl {
-    ($gate:expr, $($gate_types:ty),+) => {{
+    ($gate:expr, $($names:literal => $gate_types:ty),+) => {{
         let gate_any = $gate.0.as_any();
-        let mut i = 0..;
-        $(if let (tag, true) = (i.next().unwrap(), gate_any.is::<$gate_types>()) {
-            Ok(tag)
+        $(if gate_any.is::<$gate_types>() {
+            Ok(($crate::util::serialization::type_tag($names), $names))
         } else)*
         {
             log::log!(
@@ -53,7 +56,7 @@ macro_rules! get_gate_tag_impl {
                 "attempted to serialize gate with id `{}` which is unsupported by this gate serializer",
                 $gate.0.id()
             );
-            Err($crate::util::serialization::IoError)
+            Err($crate::util::serialization::IoError::UnsupportedGate($gate.0.id()))
         }
     }};
 }
@@ -62,16 +65,26 @@ macro_rules! get_gate_tag_impl {
 /// Macro implementing the `GateSerializer` trait.
 /// To serialize a list of gates used for a circuit,
 /// this macro should be called with a struct on which to implement
-/// this as first argument, followed by all the targeted gates.
+/// this as first argument, followed by all the targeted gates, each given a name:
+/// `"ArithmeticGate" => ArithmeticGate`.
+/// Gates are written with a 32-bit tag derived from their name, see
+/// [`type_tag`](crate::util::serialization::type_tag), so the list can be reordered, and the types
+/// renamed or moved, without breaking previously serialized data, as long as the names are kept.
 macro_rules! impl_gate_serializer {
-    ($target:ty, $($gate_types:ty),+) => {
+    ($target:ty, $($names:literal => $gate_types:ty),+) => {
         fn read_gate(
             &self,
             buf: &mut $crate::util::serialization::Buffer,
             common: &$crate::plonk::circuit_data::CommonCircuitData<F, D>,
         ) -> $crate::util::serialization::IoResult<$crate::gates::gate::GateRef<F, D>> {
+            const _: () = assert!(
+                $crate::util::serialization::tags_are_distinct(&[
+                    $($crate::util::serialization::type_tag($names)),+
+                ]),
+                "gate names must have distinct tags"
+            );
             let tag = $crate::util::serialization::Read::read_u32(buf)?;
-            read_gate_impl!(buf, tag, common, $($gate_types),+)
+            read_gate_impl!(buf, tag, common, $($names => $gate_types),+)
         }
 
         fn write_gate(
@@ -80,11 +93,11 @@ macro_rules! impl_gate_serializer {
             gate: &$crate::gates::gate::GateRef<F, D>,
             common: &$crate::plonk::circuit_data::CommonCircuitData<F, D>,
         ) -> $crate::util::serialization::IoResult<()> {
-            let tag = get_gate_tag_impl!(gate, $($gate_types),+)?;
+            let (tag, name) = get_gate_tag_impl!(gate, $($names => $gate_types),+)?;
 
             $crate::util::serialization::Write::write_u32(buf, tag)?;
-            gate.0.serialize(buf, common)?;
-            Ok(())
+            let result = gate.0.serialize(buf, common);
+            $crate::util::serialization::IoResultExt::within(result, name)
         }
     };
 }
@@ -115,22 +128,22 @@ pub mod default {
     impl<F: RichField + Extendable<D>, const D: usize> GateSerializer<F, D> for DefaultGateSerializer {
         impl_gate_serializer! {
             DefaultGateSerializer,
-            ArithmeticGate,
-            ArithmeticExtensionGate<D>,
-            BaseSumGate<2>,
-            ConstantGate,
-            CosetInterpolationGate<F, D>,
-            ExponentiationGate<F, D>,
-            LookupGate,
-            LookupTableGate,
-            MulExtensionGate<D>,
-            NoopGate,
-            PoseidonMdsGate<F, D>,
-            PoseidonGate<F, D>,
-            PublicInputGate,
-            RandomAccessGate<F, D>,
-            ReducingExtensionGate<D>,
-            ReducingGate<D>
+            "ArithmeticGate" => ArithmeticGate,
+            "ArithmeticExtensionGate" => ArithmeticExtensionGate<D>,
+            "BaseSumGate" => BaseSumGate<2>,
+            "ConstantGate" => ConstantGate,
+            "CosetInterpolationGate" => CosetInterpolationGate<F, D>,
+            "ExponentiationGate" => ExponentiationGate<F, D>,
+            "LookupGate" => LookupGate<F>,
+            "LookupTableGate" => LookupTableGate<F>,
+            "MulExtensionGate" => MulExtensionGate<D>,
+            "NoopGate" => NoopGate,
+            "PoseidonMdsGate" => PoseidonMdsGate<F, D>,
+            "PoseidonGate" => PoseidonGate<F, D>,
+            "PublicInputGate" => PublicInputGate,
+            "RandomAccessGate" => RandomAccessGate<F, D>,
+            "ReducingExtensionGate" => ReducingExtensionGate<D>,
+            "ReducingGate" => ReducingGate<D>
         }
     }
 }
@@ -9,6 +9,9 @@ use crate::iop::generator::WitnessGeneratorRef;
 use crate::plonk::circuit_data::CommonCircuitData;
 use crate::util::serialization::{Buffer, IoResult};
 
+/// Reads and writes generators of the types it supports, identified by tags derived from their
+/// names. Errors from a generator's own serialization are reported within its name, see
+/// [`IoError::Within`](crate::util::serialization::IoError::Within).
 pub trait WitnessGeneratorSerializer<F: RichField + Extendable<D>, const D: usize> {
     fn read_generator(
         &self,
@@ -26,30 +29,30 @@ pub trait WitnessGeneratorSerializer<F: RichField + Extendable<D>, const D: usiz
 
 #[macro_export]
 macro_rules! read_generator_impl {
-    ($buf:expr, $tag:expr, $common:expr, $($generator_types:ty),+) => {{
-        let tag = $tag;
+    ($buf:expr, $tag:expr, $common:expr, $($names:literal => $generator_types:ty),+) => {{
+        let tag: u32 = $tag;
         let buf = $buf;
-        let mut i = 0..;
 
-        $(if tag == i.next().unwrap() {
+        $(if tag == $crate::util::serialization::type_tag($names) {
         let generator =
-            <$generator_types as $crate::iop::generator::SimpleGenerator<F, D>>::deserialize(buf, $common)?;
+            <$generator_types as $crate::iop::generator::SimpleGenerator<F, D>>::deserialize(buf, $common);
+        let generator = $crate::util::serialization::IoResultExt::within(generator, $names)?;
         Ok($crate::iop::generator::WitnessGeneratorRef::<F, D>::new(
             $crate::iop::generator::SimpleGenerator::<F, D>::adapter(generator),
         ))
         } else)*
         {
-            Err($crate::util::serialization::IoError)
+            Err($crate::util::serialization::IoError::UnknownGeneratorTag(tag))
         }
     }};
 }
 
+/// Returns the tag and name of the generator's type.
 #[macro_export]
 macro_rules! get_generator_tag_impl {
-    ($generator:expr, $($generator_types:ty),+) => {{
-        let mut i = 0..;
-        $(if let (tag, true) = (i.next().unwrap(), $generator.0.id() == $crate::iop::generator::SimpleGenerator::<F, D>::id(&<$generator_types>::default())) {
-            Ok(tag)
+    ($generator:expr, $($names:literal => $generator_types:ty),+) => {{
+        $(if $generator.0.id() == $crate::iop::generator::SimpleGenerator::<F, D>::id(&<$generator_types>::default()) {
+            Ok(($crate::util::serialization::type_tag($names), $names))
         } else)*
         {
             log::log!(
@@ -57,7 +60,7 @@ macro_rules! get_generator_tag_impl {
                 "attempted to serialize generator with id {} which is unsupported by this generator serializer",
                 $generator.0.id()
             );
-            Err($crate::util::serialization::IoError)
+            Err($crate::util::serialization::IoError::UnsupportedGenerator($generator.0.id()))
         }
     }};
 }
@@ -66,16 +69,26 @@ macro_rules! get_generator_tag_impl {
 /// Macro implementing the `WitnessGeneratorSerializer` trait.
 /// To serialize a list of generators used for a circuit,
 /// this macro should be called with a struct on which to implement
-/// this as first argument, followed by all the targeted generators.
+/// this as first argument, followed by all the targeted generators, each given a name:
+/// `"ConstantGenerator" => ConstantGenerator<F>`.
+/// Generators are written with a 32-bit tag derived from their name, see
+/// [`type_tag`](crate::util::serialization::type_tag), so the list can be reordered, and the types
+/// renamed or moved, without breaking previously serialized data, as long as the names are kept.
 macro_rules! impl_generator_serializer {
-    ($target:ty, $($generator_types:ty),+) => {
+    ($target:ty, $($names:literal => $generator_types:ty),+) => {
         fn read_generator(
             &self,
             buf: &mut $crate::util::serialization::Buffer,
             common: &$crate::plonk::circuit_data::CommonCircuitData<F, D>,
         ) -> $crate::util::serialization::IoResult<$crate::iop::generator::WitnessGeneratorRef<F, D>> {
+            const _: () = assert!(
+                $crate::util::serialization::tags_are_distinct(&[
+                    $($crate::util::serialization::type_tag($names)),+
+                ]),
+                "generator names must have distinct tags"
+            );
             let tag = $crate::util::serialization::Read::read_u32(buf)?;
-            read_generator_impl!(buf, tag, common, $($generator_types),+)
+            read_generator_impl!(buf, tag, common, $($names => $generator_types),+)
         }
 
         fn write_generator(
@@ -84,11 +97,11 @@ macro_rules! impl_generator_serializer {
             generator: &$crate::iop::generator::WitnessGeneratorRef<F, D>,
             common: &$crate::plonk::circuit_data::CommonCircuitData<F, D>,
         ) -> $crate::util::serialization::IoResult<()> {
-            let tag = get_generator_tag_impl!(generator, $($generator_types),+)?;
+            let (tag, name) = get_generator_tag_impl!(generator, $($names => $generator_types),+)?;
 
             $crate::util::serialization::Write::write_u32(buf, tag)?;
-            generator.0.serialize(buf, common)?;
-            Ok(())
+            let result = generator.0.serialize(buf, common);
+            $crate::util::serialization::IoResultExt::within(result, name)
         }
     };
 }
@@ -100,6 +113,7 @@ pub mod default {
 
     use crate::gadgets::arithmetic::EqualityGenerator;
     use crate::gadgets::arithmetic_extension::QuotientGeneratorExtension;
+    use crate::gadgets::memory::{MemoryGenerator, MemorySortGenerator};
     use crate::gadgets::range_check::LowHighGenerator;
     use crate::gadgets::split_base::BaseSumGenerator;
     use crate::gadgets::split_join::{SplitGenerator, WireSplitGenerator};
@@ -136,30 +150,32 @@ pub mod default {
     {
         impl_generator_serializer! {
             DefaultGeneratorSerializer,
-            ArithmeticBaseGenerator<F, D>,
-            ArithmeticExtensionGenerator<F, D>,
-            BaseSplitGenerator<2>,
-            BaseSumGenerator<2>,
-            ConstantGenerator<F>,
-            CopyGenerator,
-            DummyProofGenerator<F, C, D>,
-            EqualityGenerator,
-            ExponentiationGenerator<F, D>,
-            InterpolationGenerator<F, D>,
-            LookupGenerator,
-            LookupTableGenerator,
-            LowHighGenerator,
-            MulExtensionGenerator<F, D>,
-            NonzeroTestGenerator,
-            PoseidonGenerator<F, D>,
-            PoseidonMdsGenerator<D>,
-            QuotientGeneratorExtension<D>,
-            RandomAccessGenerator<F, D>,
-            RandomValueGenerator,
-            ReducingGenerator<D>,
-            ReducingExtensionGenerator<D>,
-            SplitGenerator,
-            WireSplitGenerator
+            "ArithmeticBaseGenerator" => ArithmeticBaseGenerator<F, D>,
+            "ArithmeticExtensionGenerator" => ArithmeticExtensionGenerator<F, D>,
+            "BaseSplitGenerator" => BaseSplitGenerator<2>,
+            "BaseSumGenerator" => BaseSumGenerator<2>,
+            "ConstantGenerator" => ConstantGenerator<F>,
+            "CopyGenerator" => CopyGenerator,
+            "DummyProofGenerator" => DummyProofGenerator<F, C, D>,
+            "EqualityGenerator" => EqualityGenerator,
+            "ExponentiationGenerator" => ExponentiationGenerator<F, D>,
+            "InterpolationGenerator" => InterpolationGenerator<F, D>,
+            "LookupGenerator" => LookupGenerator<F>,
+            "LookupTableGenerator" => LookupTableGenerator<F>,
+            "LowHighGenerator" => LowHighGenerator,
+            "MemoryGenerator" => MemoryGenerator,
+            "MemorySortGenerator" => MemorySortGenerator,
+            "MulExtensionGenerator" => MulExtensionGenerator<F, D>,
+            "NonzeroTestGenerator" => NonzeroTestGenerator,
+            "PoseidonGenerator" => PoseidonGenerator<F, D>,
+            "PoseidonMdsGenerator" => PoseidonMdsGenerator<D>,
+            "QuotientGeneratorExtension" => QuotientGeneratorExtension<D>,
+            "RandomAccessGenerator" => RandomAccessGenerator<F, D>,
+            "RandomValueGenerator" => RandomValueGenerator,
+            "ReducingGenerator" => ReducingGenerator<D>,
+            "ReducingExtensionGenerator" => ReducingExtensionGenerator<D>,
+            "SplitGenerator" => SplitGenerator,
+            "WireSplitGenerator" => WireSplitGenerator
         }
     }
 }
@@ -4,10 +4,13 @@ pub mod generator_serialization;
 #[macro_use]
 pub mod gate_serialization;
 
+pub mod compact;
+
+use alloc::boxed::Box;
 use alloc::collections::BTreeMap;
-use alloc::sync::Arc;
-use alloc::vec;
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
+use alloc::{format, vec};
 use core::convert::Infallible;
 use core::fmt::{Debug, Display, Formatter};
 use core::mem::size_of;
@@ -32,6 +35,7 @@ use crate::fri::{FriConfig, FriParams};
 use crate::gadgets::polynomial::PolynomialCoeffsExtTarget;
 use crate::gates::gate::GateRef;
 use crate::gates::lookup::Lookup;
+use crate::gates::lookup_table::LookupTable;
 use crate::gates::selectors::SelectorsInfo;
 use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
 use crate::hash::merkle_proofs::{MerkleProof, MerkleProofTarget};
@@ -53,18 +57,195 @@ use crate::plonk::proof::{
 };
 
 /// A no_std compatible variant of `std::io::Error`
-#[derive(Debug)]
-pub struct IoError;
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum IoError {
+    /// The data is malformed.
+    InvalidData,
+    /// The data ended while reading `needed` bytes from byte `offset`.
+    UnexpectedEof { offset: usize, needed: usize },
+    /// A field element isn't in canonical form.
+    InvalidFieldElement(u64),
+    /// A length doesn't fit in a `usize`, or is larger than the data left to read.
+    LengthOverflow(u64),
+    /// The data doesn't start with [`FORMAT_MAGIC`], so it wasn't written by `to_bytes`.
+    InvalidMagic,
+    /// The data was written with another version of the format.
+    UnsupportedVersion { expected: u32, found: u32 },
+    /// The data holds another kind of artifact, e.g. verifier data when prover data was expected.
+    UnexpectedArtifact { expected: ArtifactKind, found: u8 },
+    /// The data refers to a gate by a tag which the `GateSerializer` doesn't know.
+    UnknownGateTag(u32),
+    /// The data refers to a generator by a tag which the `WitnessGeneratorSerializer` doesn't
+    /// know.
+    UnknownGeneratorTag(u32),
+    /// The `GateSerializer` can't write the gate with this ID.
+    UnsupportedGate(String),
+    /// The `WitnessGeneratorSerializer` can't write the generator with this ID.
+    UnsupportedGenerator(String),
+    /// The digest of the common circuit data doesn't match the one the data was written with.
+    CommonDataMismatch,
+    /// An error which occurred while reading or writing `structure`, a field or an element of the
+    /// structure in any enclosing `Within`.
+    Within {
+        structure: String,
+        source: Box<IoError>,
+    },
+}
+
+impl IoError {
+    /// The path of the structure in which the error occurred, from the outermost one.
+    pub fn path(&self) -> Vec<&str> {
+        let mut path = Vec::new();
+        let mut error = self;
+        while let Self::Within { structure, source } = error {
+            path.push(structure.as_str());
+            error = source;
+        }
+        path
+    }
+
+    /// The error without the path of the structure in which it occurred.
+    pub fn root_cause(&self) -> &Self {
+        match self {
+            Self::Within { source, .. } => source.root_cause(),
+            _ => self,
+        }
+    }
+}
+
+/// Adds the path of the structure being read or written to an [`IoError`].
+pub trait IoResultExt<T> {
+    /// Records that the error occurred within `structure`.
+    fn within(self, structure: &str) -> IoResult<T>;
+
+    /// Records that the error occurred within the element `index` of `structure`.
+    fn within_index(self, structure: &str, index: usize) -> IoResult<T>;
+}
+
+impl<T> IoResultExt<T> for IoResult<T> {
+    fn within(self, structure: &str) -> IoResult<T> {
+        self.map_err(|source| IoError::Within {
+            structure: structure.to_string(),
+            source: Box::new(source),
+        })
+    }
+
+    fn within_index(self, structure: &str, index: usize) -> IoResult<T> {
+        self.map_err(|source| IoError::Within {
+            structure: format!("{structure}[{index}]"),
+            source: Box::new(source),
+        })
+    }
+}
 
 impl Display for IoError {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        Debug::fmt(self, f)
+        match self {
+            Self::InvalidData => write!(f, "invalid data"),
+            Self::UnexpectedEof { offset, needed } => write!(
+                f,
+                "unexpected end of data while reading {needed} bytes at byte {offset}"
+            ),
+            Self::InvalidFieldElement(x) => write!(f, "{x} isn't a canonical field element"),
+            Self::LengthOverflow(length) => {
+                write!(f, "length {length} exceeds the size of the data")
+            }
+            Self::InvalidMagic => write!(f, "data isn't in the plonky2 serialization format"),
+            Self::UnsupportedVersion { expected, found } => write!(
+                f,
+                "data has format version {found}, but this version of plonky2 reads {expected}"
+            ),
+            Self::UnexpectedArtifact { expected, found } => {
+                write!(f, "expected {expected:?}, found artifact kind {found}")
+            }
+            Self::UnknownGateTag(tag) => {
+                write!(
+                    f,
+                    "gate tag {tag:#010x} isn't supported by the gate serializer"
+                )
+            }
+            Self::UnknownGeneratorTag(tag) => {
+                write!(
+                    f,
+                    "generator tag {tag:#010x} isn't supported by the generator serializer"
+                )
+            }
+            Self::UnsupportedGate(id) => {
+                write!(f, "gate `{id}` isn't supported by the gate serializer")
+            }
+            Self::UnsupportedGenerator(id) => {
+                write!(
+                    f,
+                    "generator `{id}` isn't supported by the generator serializer"
+                )
+            }
+            Self::CommonDataMismatch => write!(
+                f,
+                "data was written for other common circuit data, or is corrupted"
+            ),
+            Self::Within { .. } => {
+                write!(f, "in `{}`: {}", self.path().join("."), self.root_cause())
+            }
+        }
     }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for IoError {}
+
 /// A no_std compatible variant of `std::io::Result`
 pub type IoResult<T> = Result<T, IoError>;
 
+/// The first bytes of circuit data serialized with `to_bytes`.
+pub const FORMAT_MAGIC: [u8; 4] = *b"P2CD";
+
+/// The version of the circuit data format, bumped on every incompatible change.
+pub const FORMAT_VERSION: u32 = 2;
+
+/// The kinds of circuit data, recorded in the header of their serialization.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub enum ArtifactKind {
+    CircuitData = 0,
+    ProverCircuitData = 1,
+    VerifierCircuitData = 2,
+    CommonCircuitData = 3,
+    ProverOnlyCircuitData = 4,
+    VerifierOnlyCircuitData = 5,
+}
+
+/// The tag identifying a gate or generator type in serialized data: the 32-bit FNV-1a hash of
+/// the name it is given in `impl_gate_serializer!` or `impl_generator_serializer!`. It only
+/// changes with that name, not with the type's path or its position in the list.
+pub const fn type_tag(name: &str) -> u32 {
+    let bytes = name.as_bytes();
+    let mut hash = 0x811c_9dc5_u32;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+        i += 1;
+    }
+    hash
+}
+
+/// Whether `tags` are pairwise distinct. The serializer macros check this at compile time, so
+/// that two names with the same hash can't be listed in one serializer.
+pub const fn tags_are_distinct(tags: &[u32]) -> bool {
+    let mut i = 0;
+    while i < tags.len() {
+        let mut j = i + 1;
+        while j < tags.len() {
+            if tags[i] == tags[j] {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    true
+}
+
 /// A `Read` which is able to report how many bytes are remaining.
 pub trait Remaining: Read {
     /// Returns the number of bytes remaining in the buffer.
@@ -88,7 +269,7 @@ pub trait Read {
         match i {
             0 => Ok(false),
             1 => Ok(true),
-            _ => Err(IoError),
+            _ => Err(IoError::InvalidData),
         }
     }
 
@@ -101,7 +282,7 @@ pub trait Read {
     /// Reads a vector of `BoolTarget` from `self`.
     #[inline]
     fn read_target_bool_vec(&mut self) -> IoResult<Vec<BoolTarget>> {
-        let length = self.read_usize()?;
+        let length = self.read_length()?;
         (0..length)
             .map(|_| self.read_target_bool())
             .collect::<Result<Vec<_>, _>>()
@@ -136,13 +317,31 @@ pub trait Read {
     fn read_usize(&mut self) -> IoResult<usize> {
         let mut buf = [0; core::mem::size_of::<u64>()];
         self.read_exact(&mut buf)?;
-        Ok(u64::from_le_bytes(buf) as usize)
+        let x = u64::from_le_bytes(buf);
+        usize::try_from(x).map_err(|_| IoError::LengthOverflow(x))
+    }
+
+    /// An upper bound on the number of bytes left to read, used to reject corrupted lengths
+    /// before allocating for them.
+    #[inline]
+    fn max_remaining(&self) -> usize {
+        usize::MAX
+    }
+
+    /// Reads the length of a collection whose elements take at least one byte each.
+    #[inline]
+    fn read_length(&mut self) -> IoResult<usize> {
+        let length = self.read_usize()?;
+        if length > self.max_remaining() {
+            return Err(IoError::LengthOverflow(length as u64));
+        }
+        Ok(length)
     }
 
     /// Reads a vector of `usize` value from `self`.
     #[inline]
     fn read_usize_vec(&mut self) -> IoResult<Vec<usize>> {
-        let len = self.read_usize()?;
+        let len = self.read_length()?;
         let mut res = Vec::with_capacity(len);
         for _ in 0..len {
             res.push(self.read_usize()?);
@@ -151,7 +350,42 @@ pub trait Read {
         Ok(res)
     }
 
-    /// Reads a element from the field `F` with size less than `2^64` from `self.`
+    /// Reads a string from `self`.
+    #[inline]
+    fn read_string(&mut self) -> IoResult<String> {
+        let len = self.read_length()?;
+        let mut bytes = vec![0; len];
+        self.read_exact(&mut bytes)?;
+        String::from_utf8(bytes).map_err(|_| IoError::InvalidData)
+    }
+
+    /// Reads the header written by [`Write::write_header`], checking that it is for an artifact
+    /// of the given `kind` and the current [`FORMAT_VERSION`].
+    fn read_header(&mut self, kind: ArtifactKind) -> IoResult<()> {
+        let mut magic = [0; FORMAT_MAGIC.len()];
+        self.read_exact(&mut magic)?;
+        if magic != FORMAT_MAGIC {
+            return Err(IoError::InvalidMagic);
+        }
+        let version = self.read_u32()?;
+        if version != FORMAT_VERSION {
+            return Err(IoError::UnsupportedVersion {
+                expected: FORMAT_VERSION,
+                found: version,
+            });
+        }
+        let found = self.read_u8()?;
+        if found != kind as u8 {
+            return Err(IoError::UnexpectedArtifact {
+                expected: kind,
+                found,
+            });
+        }
+        Ok(())
+    }
+
+    /// Reads a element from the field `F` with size less than `2^64` from `self`, rejecting
+    /// non-canonical encodings.
     #[inline]
     fn read_field<F>(&mut self) -> IoResult<F>
     where
@@ -159,7 +393,12 @@ pub trait Read {
     {
         let mut buf = [0; size_of::<u64>()];
         self.read_exact(&mut buf)?;
-        Ok(F::from_canonical_u64(u64::from_le_bytes(buf)))
+        let x = u64::from_le_bytes(buf);
+        if x < F::ORDER {
+            Ok(F::from_canonical_u64(x))
+        } else {
+            Err(IoError::InvalidFieldElement(x))
+        }
     }
 
     /// Reads a vector of elements from the field `F` from `self`.
@@ -237,7 +476,7 @@ pub trait Read {
     /// Reads a vector of Target from `self`.
     #[inline]
     fn read_target_vec(&mut self) -> IoResult<Vec<Target>> {
-        let length = self.read_usize()?;
+        let length = self.read_length()?;
         (0..length)
             .map(|_| self.read_target())
             .collect::<Result<Vec<_>, _>>()
@@ -246,7 +485,7 @@ pub trait Read {
     /// Reads a vector of ExtensionTarget from `self`.
     #[inline]
     fn read_target_ext_vec<const D: usize>(&mut self) -> IoResult<Vec<ExtensionTarget<D>>> {
-        let length = self.read_usize()?;
+        let length = self.read_length()?;
         (0..length)
             .map(|_| self.read_target_ext::<D>())
             .collect::<Result<Vec<_>, _>>()
@@ -305,7 +544,7 @@ pub trait Read {
     /// Reads a value of type [`MerkleCapTarget`] from `self`.
     #[inline]
     fn read_target_merkle_cap(&mut self) -> IoResult<MerkleCapTarget> {
-        let length = self.read_usize()?;
+        let length = self.read_length()?;
         Ok(MerkleCapTarget(
             (0..length)
                 .map(|_| self.read_target_hash())
@@ -320,14 +559,14 @@ pub trait Read {
         F: RichField,
         H: Hasher<F>,
     {
-        let leaves_len = self.read_usize()?;
+        let leaves_len = self.read_length()?;
         let mut leaves = Vec::with_capacity(leaves_len);
         for _ in 0..leaves_len {
-            let leaf_len = self.read_usize()?;
+            let leaf_len = self.read_length()?;
             leaves.push(self.read_field_vec(leaf_len)?);
         }
 
-        let digests_len = self.read_usize()?;
+        let digests_len = self.read_length()?;
         let digests = self.read_hash_vec::<F, H>(digests_len)?;
         let cap_height = self.read_usize()?;
         let cap = self.read_merkle_cap::<F, H>(cap_height)?;
@@ -468,7 +707,7 @@ pub trait Read {
     /// Reads a value of type [`FriInitialTreeProofTarget`] from `self`.
     #[inline]
     fn read_target_fri_initial_proof(&mut self) -> IoResult<FriInitialTreeProofTarget> {
-        let len = self.read_usize()?;
+        let len = self.read_length()?;
         let mut evals_proofs = Vec::with_capacity(len);
 
         for _ in 0..len {
@@ -572,12 +811,16 @@ pub trait Read {
         let config = &common_data.config;
         let commit_phase_merkle_caps = (0..common_data.fri_params.reduction_arity_bits.len())
             .map(|_| self.read_merkle_cap(config.fri_config.cap_height))
-            .collect::<Result<Vec<_>, _>>()?;
-        let query_round_proofs = self.read_fri_query_rounds::<F, C, D>(common_data)?;
+            .collect::<Result<Vec<_>, _>>()
+            .within("commit_phase_merkle_caps")?;
+        let query_round_proofs = self
+            .read_fri_query_rounds::<F, C, D>(common_data)
+            .within("query_round_proofs")?;
         let final_poly = PolynomialCoeffs::new(
-            self.read_field_ext_vec::<F, D>(common_data.fri_params.final_poly_len())?,
+            self.read_field_ext_vec::<F, D>(common_data.fri_params.final_poly_len())
+                .within("final_poly")?,
         );
-        let pow_witness = self.read_field()?;
+        let pow_witness = self.read_field().within("pow_witness")?;
         Ok(FriProof {
             commit_phase_merkle_caps,
             query_round_proofs,
@@ -589,7 +832,7 @@ pub trait Read {
     /// Reads a value of type [`FriProofTarget`] from `self`.
     #[inline]
     fn read_target_fri_proof<const D: usize>(&mut self) -> IoResult<FriProofTarget<D>> {
-        let length = self.read_usize()?;
+        let length = self.read_length()?;
         let commit_phase_merkle_caps = (0..length)
             .map(|_| self.read_target_merkle_cap())
             .collect::<Result<Vec<_>, _>>()?;
@@ -629,10 +872,10 @@ pub trait Read {
                         let max = self.read_usize()?;
                         Ok(FriReductionStrategy::MinSize(Some(max)))
                     }
-                    _ => Err(IoError),
+                    _ => Err(IoError::InvalidData),
                 }
             }
-            _ => Err(IoError),
+            _ => Err(IoError::InvalidData),
         }
     }
 
@@ -660,6 +903,7 @@ pub trait Read {
         let num_challenges = self.read_usize()?;
         let max_quotient_degree_factor = self.read_usize()?;
         let use_base_arithmetic_gate = self.read_bool()?;
+        let use_lookup_range_checks = self.read_bool()?;
         let zero_knowledge = self.read_bool()?;
         let fri_config = self.read_fri_config()?;
 
@@ -671,6 +915,7 @@ pub trait Read {
             num_challenges,
             max_quotient_degree_factor,
             use_base_arithmetic_gate,
+            use_lookup_range_checks,
             zero_knowledge,
             fri_config,
         })
@@ -704,7 +949,7 @@ pub trait Read {
 
     fn read_selectors_info(&mut self) -> IoResult<SelectorsInfo> {
         let selector_indices = self.read_usize_vec()?;
-        let groups_len = self.read_usize()?;
+        let groups_len = self.read_length()?;
         let mut groups = Vec::with_capacity(groups_len);
         for _ in 0..groups_len {
             let start = self.read_usize()?;
@@ -725,7 +970,7 @@ pub trait Read {
     >(
         &mut self,
     ) -> IoResult<PolynomialBatch<F, C, D>> {
-        let poly_len = self.read_usize()?;
+        let poly_len = self.read_length()?;
         let mut polynomials = Vec::with_capacity(poly_len);
         for _ in 0..poly_len {
             let plen = self.read_usize()?;
@@ -736,6 +981,8 @@ pub trait Read {
         let degree_log = self.read_usize()?;
         let rate_bits = self.read_usize()?;
         let blinding = self.read_bool()?;
+        let salts_len = self.read_length()?;
+        let salts = self.read_field_vec(salts_len)?;
 
         Ok(PolynomialBatch {
             polynomials,
@@ -743,6 +990,7 @@ pub trait Read {
             degree_log,
             rate_bits,
             blinding,
+            salts,
         })
     }
 
@@ -750,30 +998,30 @@ pub trait Read {
         &mut self,
         gate_serializer: &dyn GateSerializer<F, D>,
     ) -> IoResult<CommonCircuitData<F, D>> {
-        let config = self.read_circuit_config()?;
-        let fri_params = self.read_fri_params()?;
+        let config = self.read_circuit_config().within("config")?;
+        let fri_params = self.read_fri_params().within("fri_params")?;
 
-        let selectors_info = self.read_selectors_info()?;
+        let selectors_info = self.read_selectors_info().within("selectors_info")?;
         let quotient_degree_factor = self.read_usize()?;
         let num_gate_constraints = self.read_usize()?;
         let num_constants = self.read_usize()?;
         let num_public_inputs = self.read_usize()?;
 
-        let k_is_len = self.read_usize()?;
-        let k_is = self.read_field_vec(k_is_len)?;
+        let k_is_len = self.read_length()?;
+        let k_is = self.read_field_vec(k_is_len).within("k_is")?;
 
         let num_partial_products = self.read_usize()?;
 
         let num_lookup_polys = self.read_usize()?;
         let num_lookup_selectors = self.read_usize()?;
-        let length = self.read_usize()?;
+        let length = self.read_length()?;
         let mut luts = Vec::with_capacity(length);
 
-        for _ in 0..length {
-            luts.push(Arc::new(self.read_lut()?));
+        for i in 0..length {
+            luts.push(self.read_lut().within_index("luts", i)?);
         }
 
-        let gates_len = self.read_usize()?;
+        let gates_len = self.read_length()?;
         let mut gates = Vec::with_capacity(gates_len);
 
         // We construct the common data without gates first,
@@ -794,8 +1042,10 @@ pub trait Read {
             luts,
         };
 
-        for _ in 0..gates_len {
-            let gate = self.read_gate::<F, D>(gate_serializer, &common_data)?;
+        for i in 0..gates_len {
+            let gate = self
+                .read_gate::<F, D>(gate_serializer, &common_data)
+                .within_index("gates", i)?;
             gates.push(gate);
         }
 
@@ -813,9 +1063,15 @@ pub trait Read {
         gate_serializer: &dyn GateSerializer<F, D>,
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
     ) -> IoResult<CircuitData<F, C, D>> {
-        let common = self.read_common_circuit_data(gate_serializer)?;
-        let prover_only = self.read_prover_only_circuit_data(generator_serializer, &common)?;
-        let verifier_only = self.read_verifier_only_circuit_data()?;
+        let common = self
+            .read_common_circuit_data(gate_serializer)
+            .within("common")?;
+        let prover_only = self
+            .read_prover_only_circuit_data(generator_serializer, &common)
+            .within("prover_only")?;
+        let verifier_only = self
+            .read_verifier_only_circuit_data()
+            .within("verifier_only")?;
         Ok(CircuitData {
             prover_only,
             verifier_only,
@@ -832,27 +1088,32 @@ pub trait Read {
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
         common_data: &CommonCircuitData<F, D>,
     ) -> IoResult<ProverOnlyCircuitData<F, C, D>> {
-        let gen_len = self.read_usize()?;
+        let gen_len = self.read_length()?;
         let mut generators = Vec::with_capacity(gen_len);
-        for _ in 0..gen_len {
-            generators.push(self.read_generator(generator_serializer, common_data)?);
+        for i in 0..gen_len {
+            generators.push(
+                self.read_generator(generator_serializer, common_data)
+                    .within_index("generators", i)?,
+            );
         }
-        let map_len = self.read_usize()?;
+        let map_len = self.read_length()?;
         let mut generator_indices_by_watches = BTreeMap::new();
         for _ in 0..map_len {
             let k = self.read_usize()?;
             generator_indices_by_watches.insert(k, self.read_usize_vec()?);
         }
 
-        let constants_sigmas_commitment = self.read_polynomial_batch()?;
-        let sigmas_len = self.read_usize()?;
+        let constants_sigmas_commitment = self
+            .read_polynomial_batch()
+            .within("constants_sigmas_commitment")?;
+        let sigmas_len = self.read_length()?;
         let mut sigmas = Vec::with_capacity(sigmas_len);
         for _ in 0..sigmas_len {
-            let sigma_len = self.read_usize()?;
+            let sigma_len = self.read_length()?;
             sigmas.push(self.read_field_vec(sigma_len)?);
         }
 
-        let subgroup_len = self.read_usize()?;
+        let subgroup_len = self.read_length()?;
         let subgroup = self.read_field_vec(subgroup_len)?;
 
         let public_inputs = self.read_target_vec()?;
@@ -862,10 +1123,10 @@ pub trait Read {
         let is_some = self.read_bool()?;
         let fft_root_table = match is_some {
             true => {
-                let table_len = self.read_usize()?;
+                let table_len = self.read_length()?;
                 let mut table = Vec::with_capacity(table_len);
                 for _ in 0..table_len {
-                    let len = self.read_usize()?;
+                    let len = self.read_length()?;
                     table.push(self.read_field_vec(len)?);
                 }
                 Some(table)
@@ -873,9 +1134,11 @@ pub trait Read {
             false => None,
         };
 
-        let circuit_digest = self.read_hash::<F, <C as GenericConfig<D>>::Hasher>()?;
+        let circuit_digest = self
+            .read_hash::<F, <C as GenericConfig<D>>::Hasher>()
+            .within("circuit_digest")?;
 
-        let length = self.read_usize()?;
+        let length = self.read_length()?;
         let mut lookup_rows = Vec::with_capacity(length);
         for _ in 0..length {
             lookup_rows.push(LookupWire {
@@ -885,7 +1148,7 @@ pub trait Read {
             });
         }
 
-        let length = self.read_usize()?;
+        let length = self.read_length()?;
         let mut lut_to_lookups = Vec::with_capacity(length);
         for _ in 0..length {
             lut_to_lookups.push(self.read_target_lut()?);
@@ -893,6 +1156,8 @@ pub trait Read {
 
         Ok(ProverOnlyCircuitData {
             generators,
+            #[cfg(feature = "generator_origins")]
+            generator_origins: Vec::new(),
             generator_indices_by_watches,
             constants_sigmas_commitment,
             sigmas,
@@ -915,8 +1180,12 @@ pub trait Read {
         gate_serializer: &dyn GateSerializer<F, D>,
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
     ) -> IoResult<ProverCircuitData<F, C, D>> {
-        let common = self.read_common_circuit_data(gate_serializer)?;
-        let prover_only = self.read_prover_only_circuit_data(generator_serializer, &common)?;
+        let common = self
+            .read_common_circuit_data(gate_serializer)
+            .within("common")?;
+        let prover_only = self
+            .read_prover_only_circuit_data(generator_serializer, &common)
+            .within("prover_only")?;
         Ok(ProverCircuitData {
             prover_only,
             common,
@@ -931,8 +1200,12 @@ pub trait Read {
         &mut self,
     ) -> IoResult<VerifierOnlyCircuitData<C, D>> {
         let height = self.read_usize()?;
-        let constants_sigmas_cap = self.read_merkle_cap(height)?;
-        let circuit_digest = self.read_hash::<F, <C as GenericConfig<D>>::Hasher>()?;
+        let constants_sigmas_cap = self
+            .read_merkle_cap(height)
+            .within("constants_sigmas_cap")?;
+        let circuit_digest = self
+            .read_hash::<F, <C as GenericConfig<D>>::Hasher>()
+            .within("circuit_digest")?;
         Ok(VerifierOnlyCircuitData {
             constants_sigmas_cap,
             circuit_digest,
@@ -947,8 +1220,12 @@ pub trait Read {
         &mut self,
         gate_serializer: &dyn GateSerializer<F, D>,
     ) -> IoResult<VerifierCircuitData<F, C, D>> {
-        let verifier_only = self.read_verifier_only_circuit_data()?;
-        let common = self.read_common_circuit_data(gate_serializer)?;
+        let verifier_only = self
+            .read_verifier_only_circuit_data()
+            .within("verifier_only")?;
+        let common = self
+            .read_common_circuit_data(gate_serializer)
+            .within("common")?;
         Ok(VerifierCircuitData {
             verifier_only,
             common,
@@ -975,11 +1252,21 @@ pub trait Read {
         C: GenericConfig<D, F = F>,
     {
         let config = &common_data.config;
-        let wires_cap = self.read_merkle_cap(config.fri_config.cap_height)?;
-        let plonk_zs_partial_products_cap = self.read_merkle_cap(config.fri_config.cap_height)?;
-        let quotient_polys_cap = self.read_merkle_cap(config.fri_config.cap_height)?;
-        let openings = self.read_opening_set::<F, C, D>(common_data)?;
-        let opening_proof = self.read_fri_proof::<F, C, D>(common_data)?;
+        let wires_cap = self
+            .read_merkle_cap(config.fri_config.cap_height)
+            .within("wires_cap")?;
+        let plonk_zs_partial_products_cap = self
+            .read_merkle_cap(config.fri_config.cap_height)
+            .within("plonk_zs_partial_products_cap")?;
+        let quotient_polys_cap = self
+            .read_merkle_cap(config.fri_config.cap_height)
+            .within("quotient_polys_cap")?;
+        let openings = self
+            .read_opening_set::<F, C, D>(common_data)
+            .within("openings")?;
+        let opening_proof = self
+            .read_fri_proof::<F, C, D>(common_data)
+            .within("opening_proof")?;
         Ok(Proof {
             wires_cap,
             plonk_zs_partial_products_cap,
@@ -1017,9 +1304,9 @@ pub trait Read {
         F: RichField + Extendable<D>,
         C: GenericConfig<D, F = F>,
     {
-        let proof = self.read_proof(common_data)?;
-        let pi_len = self.read_usize()?;
-        let public_inputs = self.read_field_vec(pi_len)?;
+        let proof = self.read_proof(common_data).within("proof")?;
+        let pi_len = self.read_length()?;
+        let public_inputs = self.read_field_vec(pi_len).within("public_inputs")?;
         Ok(ProofWithPublicInputs {
             proof,
             public_inputs,
@@ -1100,12 +1387,16 @@ pub trait Read {
         let config = &common_data.config;
         let commit_phase_merkle_caps = (0..common_data.fri_params.reduction_arity_bits.len())
             .map(|_| self.read_merkle_cap(config.fri_config.cap_height))
-            .collect::<Result<Vec<_>, _>>()?;
-        let query_round_proofs = self.read_compressed_fri_query_rounds::<F, C, D>(common_data)?;
+            .collect::<Result<Vec<_>, _>>()
+            .within("commit_phase_merkle_caps")?;
+        let query_round_proofs = self
+            .read_compressed_fri_query_rounds::<F, C, D>(common_data)
+            .within("query_round_proofs")?;
         let final_poly = PolynomialCoeffs::new(
-            self.read_field_ext_vec::<F, D>(common_data.fri_params.final_poly_len())?,
+            self.read_field_ext_vec::<F, D>(common_data.fri_params.final_poly_len())
+                .within("final_poly")?,
         );
-        let pow_witness = self.read_field()?;
+        let pow_witness = self.read_field().within("pow_witness")?;
         Ok(CompressedFriProof {
             commit_phase_merkle_caps,
             query_round_proofs,
@@ -1125,11 +1416,21 @@ pub trait Read {
         C: GenericConfig<D, F = F>,
     {
         let config = &common_data.config;
-        let wires_cap = self.read_merkle_cap(config.fri_config.cap_height)?;
-        let plonk_zs_partial_products_cap = self.read_merkle_cap(config.fri_config.cap_height)?;
-        let quotient_polys_cap = self.read_merkle_cap(config.fri_config.cap_height)?;
-        let openings = self.read_opening_set::<F, C, D>(common_data)?;
-        let opening_proof = self.read_compressed_fri_proof::<F, C, D>(common_data)?;
+        let wires_cap = self
+            .read_merkle_cap(config.fri_config.cap_height)
+            .within("wires_cap")?;
+        let plonk_zs_partial_products_cap = self
+            .read_merkle_cap(config.fri_config.cap_height)
+            .within("plonk_zs_partial_products_cap")?;
+        let quotient_polys_cap = self
+            .read_merkle_cap(config.fri_config.cap_height)
+            .within("quotient_polys_cap")?;
+        let openings = self
+            .read_opening_set::<F, C, D>(common_data)
+            .within("openings")?;
+        let opening_proof = self
+            .read_compressed_fri_proof::<F, C, D>(common_data)
+            .within("opening_proof")?;
         Ok(CompressedProof {
             wires_cap,
             plonk_zs_partial_products_cap,
@@ -1150,33 +1451,46 @@ pub trait Read {
         F: RichField + Extendable<D>,
         C: GenericConfig<D, F = F>,
     {
-        let proof = self.read_compressed_proof(common_data)?;
-        let public_inputs = self.read_field_vec(self.remaining() / size_of::<u64>())?;
+        let proof = self.read_compressed_proof(common_data).within("proof")?;
+        let public_inputs = self
+            .read_field_vec(self.remaining() / size_of::<u64>())
+            .within("public_inputs")?;
         Ok(CompressedProofWithPublicInputs {
             proof,
             public_inputs,
         })
     }
 
-    /// Reads a lookup table stored as `Vec<(u16, u16)>` from `self`.
+    /// Reads a lookup table from `self`.
     #[inline]
-    fn read_lut(&mut self) -> IoResult<Vec<(u16, u16)>> {
+    fn read_lut<F: RichField>(&mut self) -> IoResult<LookupTable<F>> {
+        let width = self.read_length()?;
+        // The rows of a dynamic table aren't stored, so its length isn't bounded by the data left.
         let length = self.read_usize()?;
-        let mut lut = Vec::with_capacity(length);
-        for _ in 0..length {
-            lut.push((self.read_u16()?, self.read_u16()?));
+        if width == 0 || length == 0 {
+            return Err(IoError::InvalidData);
+        }
+        if self.read_bool()? {
+            return Ok(LookupTable::dynamic(width, length));
         }
+        let num_entries = width
+            .checked_mul(length)
+            .filter(|&n| n <= self.max_remaining())
+            .ok_or(IoError::LengthOverflow(length as u64))?;
+        let entries = self.read_field_vec::<F>(num_entries)?;
 
-        Ok(lut)
+        Ok(LookupTable::new(
+            entries.chunks_exact(width).map(<[F]>::to_vec).collect(),
+        ))
     }
 
     /// Reads a target lookup table stored as `Lookup` from `self`.
     #[inline]
     fn read_target_lut(&mut self) -> IoResult<Lookup> {
-        let length = self.read_usize()?;
+        let length = self.read_length()?;
         let mut lut = Vec::with_capacity(length);
         for _ in 0..length {
-            lut.push((self.read_target()?, self.read_target()?));
+            lut.push(self.read_target_vec()?);
         }
 
         Ok(lut)
@@ -1249,6 +1563,20 @@ pub trait Write {
         Ok(())
     }
 
+    /// Writes a string `s` to `self`.
+    #[inline]
+    fn write_str(&mut self, s: &str) -> IoResult<()> {
+        self.write_usize(s.len())?;
+        self.write_all(s.as_bytes())
+    }
+
+    /// Writes a header identifying the format version and the `kind` of artifact that follows.
+    fn write_header(&mut self, kind: ArtifactKind) -> IoResult<()> {
+        self.write_all(&FORMAT_MAGIC)?;
+        self.write_u32(FORMAT_VERSION)?;
+        self.write_u8(kind as u8)
+    }
+
     /// Writes an element `x` from the field `F` to `self`.
     #[inline]
     fn write_field<F>(&mut self, x: F) -> IoResult<()>
@@ -1695,6 +2023,7 @@ pub trait Write {
             num_challenges,
             max_quotient_degree_factor,
             use_base_arithmetic_gate,
+            use_lookup_range_checks,
             zero_knowledge,
             fri_config,
         } = config;
@@ -1706,6 +2035,7 @@ pub trait Write {
         self.write_usize(*num_challenges)?;
         self.write_usize(*max_quotient_degree_factor)?;
         self.write_bool(*use_base_arithmetic_gate)?;
+        self.write_bool(*use_lookup_range_checks)?;
         self.write_bool(*zero_knowledge)?;
         self.write_fri_config(fri_config)?;
 
@@ -1758,6 +2088,8 @@ pub trait Write {
         self.write_usize(poly_batch.degree_log)?;
         self.write_usize(poly_batch.rate_bits)?;
         self.write_bool(poly_batch.blinding)?;
+        self.write_usize(poly_batch.salts.len())?;
+        self.write_field_vec(&poly_batch.salts)?;
 
         Ok(())
     }
@@ -1843,6 +2175,8 @@ pub trait Write {
     ) -> IoResult<()> {
         let ProverOnlyCircuitData {
             generators,
+            #[cfg(feature = "generator_origins")]
+                generator_origins: _,
             generator_indices_by_watches,
             constants_sigmas_commitment,
             sigmas,
@@ -2112,11 +2446,15 @@ pub trait Write {
 
     /// Writes a lookup table to `self`.
     #[inline]
-    fn write_lut(&mut self, lut: &[(u16, u16)]) -> IoResult<()> {
+    fn write_lut<F: RichField>(&mut self, lut: &LookupTable<F>) -> IoResult<()> {
+        self.write_usize(lut.width())?;
         self.write_usize(lut.len())?;
-        for (a, b) in lut.iter() {
-            self.write_u16(*a)?;
-            self.write_u16(*b)?;
+        self.write_bool(lut.is_dynamic())?;
+        if lut.is_dynamic() {
+            return Ok(());
+        }
+        for row in lut.rows() {
+            self.write_field_vec(row)?;
         }
 
         Ok(())
@@ -2124,11 +2462,10 @@ pub trait Write {
 
     /// Writes a target lookup table to `self`.
     #[inline]
-    fn write_target_lut(&mut self, lut: &[(Target, Target)]) -> IoResult<()> {
+    fn write_target_lut(&mut self, lut: &[Vec<Target>]) -> IoResult<()> {
         self.write_usize(lut.len())?;
-        for (a, b) in lut.iter() {
-            self.write_target(*a)?;
-            self.write_target(*b)?;
+        for lookup in lut.iter() {
+            self.write_target_vec(lookup)?;
         }
 
         Ok(())
@@ -2203,11 +2540,19 @@ impl<'a> Remaining for Buffer<'a> {
 }
 
 impl<'a> Read for Buffer<'a> {
+    #[inline]
+    fn max_remaining(&self) -> usize {
+        self.remaining()
+    }
+
     #[inline]
     fn read_exact(&mut self, bytes: &mut [u8]) -> IoResult<()> {
         let n = bytes.len();
         if self.remaining() < n {
-            Err(IoError)
+            Err(IoError::UnexpectedEof {
+                offset: self.pos,
+                needed: n,
+            })
         } else {
             bytes.copy_from_slice(&self.bytes[self.pos..][..n]);
             self.pos += n;
@@ -2231,3 +2576,236 @@ impl<'a> Read for Buffer<'a> {
         generator_serializer.read_generator(self, common_data)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use core::marker::PhantomData;
+
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::gates::arithmetic_base::ArithmeticGate;
+    use crate::gates::constant::ConstantGate;
+    use crate::gates::noop::NoopGate;
+    use crate::gates::poseidon::PoseidonGate;
+    use crate::gates::public_input::PublicInputGate;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData, ProverOnlyCircuitData};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// The gates of `circuit` below, listed in another order and spelled differently than in
+    /// `DefaultGateSerializer`.
+    struct ReorderedGateSerializer;
+    impl GateSerializer<F, D> for ReorderedGateSerializer {
+        impl_gate_serializer! {
+            ReorderedGateSerializer,
+            "PublicInputGate" => PublicInputGate,
+            "PoseidonGate" => PoseidonGate<F, D>,
+            "NoopGate" => crate::gates::noop::NoopGate,
+            "ConstantGate" => ConstantGate,
+            "ArithmeticGate" => ArithmeticGate
+        }
+    }
+
+    struct NoopOnlyGateSerializer;
+    impl GateSerializer<F, D> for NoopOnlyGateSerializer {
+        impl_gate_serializer! {
+            NoopOnlyGateSerializer,
+            "NoopGate" => NoopGate
+        }
+    }
+
+    fn circuit(num_squarings: usize) -> CircuitData<F, C, D> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let mut x = builder.add_virtual_target();
+        builder.register_public_input(x);
+        for _ in 0..num_squarings {
+            x = builder.square(x);
+        }
+        let one = builder.one();
+        let y = builder.add(x, one);
+        builder.register_public_input(y);
+        builder.build::<C>()
+    }
+
+    #[test]
+    fn test_gate_order_independence() -> Result<()> {
+        let data = circuit(1);
+        let generator_serializer = DefaultGeneratorSerializer::<C, D> {
+            _phantom: PhantomData,
+        };
+        let bytes = data.to_bytes(&DefaultGateSerializer, &generator_serializer)?;
+        let data_from_bytes = CircuitData::<F, C, D>::from_bytes(
+            &bytes,
+            &ReorderedGateSerializer,
+            &generator_serializer,
+        )?;
+        assert_eq!(data.common, data_from_bytes.common);
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(data.prover_only.public_inputs[0], F::TWO);
+        let proof = data_from_bytes.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_type_tag() {
+        // Serialized data depends on these values, so they must not change.
+        assert_eq!(type_tag(""), 0x811c_9dc5);
+        assert_eq!(type_tag("NoopGate"), 0x7125_bc7a);
+        assert!(tags_are_distinct(&[
+            type_tag("NoopGate"),
+            type_tag("ConstantGate")
+        ]));
+        assert!(!tags_are_distinct(&[1, 2, 1]));
+    }
+
+    #[test]
+    fn test_read_lut_rejects_bad_sizes() -> Result<()> {
+        let lut = LookupTable::<F>::new(vec![vec![F::ONE, F::TWO]]);
+        let mut bytes = Vec::new();
+        bytes.write_lut(&lut)?;
+        assert_eq!(Buffer::new(&bytes).read_lut::<F>()?, lut);
+
+        let lut_bytes = |width: usize, length: usize| {
+            let mut bytes = Vec::new();
+            bytes.write_usize(width).unwrap();
+            bytes.write_usize(length).unwrap();
+            bytes.write_bool(false).unwrap();
+            bytes
+        };
+        let read = |bytes: Vec<u8>| Buffer::new(&bytes).read_lut::<F>().unwrap_err();
+        assert!(matches!(read(lut_bytes(0, 1)), IoError::InvalidData));
+        assert!(matches!(read(lut_bytes(1, 0)), IoError::InvalidData));
+        assert!(matches!(
+            read(lut_bytes(2, usize::MAX)),
+            IoError::LengthOverflow(_)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_gate_tag() -> Result<()> {
+        let data = circuit(1);
+        let bytes = data.common.to_bytes(&DefaultGateSerializer)?;
+        let err =
+            CommonCircuitData::<F, D>::from_bytes(bytes, &NoopOnlyGateSerializer).unwrap_err();
+        assert!(matches!(err.path()[..], [gates] if gates.starts_with("gates[")));
+        assert!(matches!(err.root_cause(), IoError::UnknownGateTag(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_context() -> Result<()> {
+        let data = circuit(1);
+        let generator_serializer = DefaultGeneratorSerializer::<C, D> {
+            _phantom: PhantomData,
+        };
+        let bytes = data.to_bytes(&DefaultGateSerializer, &generator_serializer)?;
+
+        // The circuit digest is last, so this truncates it.
+        let truncated = &bytes[..bytes.len() - 1];
+        let err = CircuitData::<F, C, D>::from_bytes(
+            truncated,
+            &DefaultGateSerializer,
+            &generator_serializer,
+        )
+        .unwrap_err();
+        assert_eq!(err.path(), ["verifier_only", "circuit_digest"]);
+        assert_eq!(
+            err.root_cause(),
+            &IoError::UnexpectedEof {
+                offset: bytes.len() - 32,
+                needed: 32,
+            }
+        );
+        assert!(err
+            .to_string()
+            .starts_with("in `verifier_only.circuit_digest`: unexpected end of data"));
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(data.prover_only.public_inputs[0], F::TWO);
+        let proof = data.prove(pw)?;
+        let mut proof_bytes = proof.to_bytes();
+        let pow_witness_start = proof_bytes.len() - 8 * (1 + 1 + proof.public_inputs.len());
+        proof_bytes[pow_witness_start..pow_witness_start + 8].fill(0xff);
+        let err = Buffer::new(&proof_bytes)
+            .read_proof_with_public_inputs::<F, C, D>(&data.common)
+            .unwrap_err();
+        assert_eq!(err.path(), ["proof", "opening_proof", "pow_witness"]);
+        assert_eq!(err.root_cause(), &IoError::InvalidFieldElement(u64::MAX));
+
+        // A public input count larger than the remaining data.
+        let len_start = pow_witness_start + 8;
+        proof_bytes[len_start..len_start + 8].fill(0x7f);
+        let mut buffer = Buffer::new(&proof_bytes[len_start..]);
+        assert_eq!(
+            buffer.read_length(),
+            Err(IoError::LengthOverflow(0x7f7f7f7f7f7f7f7f))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_header_mismatches() -> Result<()> {
+        let data = circuit(1);
+        let mut bytes = data.common.to_bytes(&DefaultGateSerializer)?;
+
+        let verifier_only_bytes = data.verifier_only.to_bytes()?;
+        let err =
+            CommonCircuitData::<F, D>::from_bytes(verifier_only_bytes, &DefaultGateSerializer)
+                .unwrap_err();
+        assert_eq!(
+            err,
+            IoError::UnexpectedArtifact {
+                expected: ArtifactKind::CommonCircuitData,
+                found: ArtifactKind::VerifierOnlyCircuitData as u8,
+            }
+        );
+
+        bytes[FORMAT_MAGIC.len()] += 1;
+        let err = CommonCircuitData::<F, D>::from_bytes(bytes.clone(), &DefaultGateSerializer)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            IoError::UnsupportedVersion {
+                expected: FORMAT_VERSION,
+                found: FORMAT_VERSION + 1,
+            }
+        );
+
+        bytes[0] = 0;
+        let err = CommonCircuitData::<F, D>::from_bytes(bytes, &DefaultGateSerializer).unwrap_err();
+        assert_eq!(err, IoError::InvalidMagic);
+        Ok(())
+    }
+
+    #[test]
+    fn test_common_data_mismatch() -> Result<()> {
+        let data = circuit(1);
+        let other = circuit(100);
+        let generator_serializer = DefaultGeneratorSerializer::<C, D> {
+            _phantom: PhantomData,
+        };
+        assert_ne!(data.common.digest(), other.common.digest());
+
+        let bytes = data
+            .prover_only
+            .to_bytes(&generator_serializer, &data.common)?;
+        ProverOnlyCircuitData::<F, C, D>::from_bytes(&bytes, &generator_serializer, &data.common)?;
+        let err = ProverOnlyCircuitData::<F, C, D>::from_bytes(
+            &bytes,
+            &generator_serializer,
+            &other.common,
+        )
+        .unwrap_err();
+        assert_eq!(err, IoError::CommonDataMismatch);
+        Ok(())
+    }
+}
@@ -1,5 +1,11 @@
+#[cfg(feature = "std")]
+use anyhow::{anyhow, Result};
+use plonky2::field::extension::Extendable;
 use plonky2::fri::reduction_strategies::FriReductionStrategy;
+#[cfg(feature = "std")]
+use plonky2::fri::security::{SecurityAssumption, SecurityReport};
 use plonky2::fri::{FriConfig, FriParams};
+use plonky2::hash::hash_types::RichField;
 
 pub struct StarkConfig {
     pub security_bits: usize,
@@ -31,4 +37,61 @@ impl StarkConfig {
     pub(crate) fn fri_params(&self, degree_bits: usize) -> FriParams {
         self.fri_config.fri_params(degree_bits, false)
     }
+
+    /// Estimates the security of proofs for traces with `2^degree_bits` rows.
+    #[cfg(feature = "std")]
+    pub fn security<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        degree_bits: usize,
+    ) -> SecurityReport {
+        SecurityReport::new(
+            &self.fri_params(degree_bits),
+            (F::ORDER as f64).log2(),
+            D,
+            self.num_challenges,
+        )
+    }
+
+    /// Checks that proofs for traces with `2^degree_bits` rows achieve `security_bits` under
+    /// `assumption`.
+    #[cfg(feature = "std")]
+    pub fn check_security<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        degree_bits: usize,
+        assumption: SecurityAssumption,
+    ) -> Result<()> {
+        self.security::<F, D>(degree_bits)
+            .check(assumption, self.security_bits)
+            .map_err(|e| anyhow!(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::fri::security::SecurityAssumption;
+
+    use crate::config::StarkConfig;
+
+    #[test]
+    fn test_standard_fast_config_security() {
+        type F = GoldilocksField;
+        const D: usize = 2;
+
+        let config = StarkConfig::standard_fast_config();
+        config
+            .check_security::<F, D>(10, SecurityAssumption::Conjectured)
+            .unwrap();
+        assert!(config
+            .check_security::<F, D>(10, SecurityAssumption::Proven)
+            .is_err());
+
+        let insecure = StarkConfig {
+            num_challenges: 1,
+            ..StarkConfig::standard_fast_config()
+        };
+        assert!(insecure
+            .check_security::<F, D>(10, SecurityAssumption::Conjectured)
+            .is_err());
+    }
 }
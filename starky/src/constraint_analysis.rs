@@ -0,0 +1,295 @@
+//! Symbolic analysis of the constraints of a STARK, which complements the randomized checks of
+//! [`stark_testing`](crate::stark_testing) with exact degrees and column dependencies.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::{Display, Formatter};
+
+use anyhow::{ensure, Result};
+use itertools::Itertools;
+use plonky2::field::extension::Extendable;
+use plonky2::field::packed::PackedField;
+use plonky2::hash::hash_types::RichField;
+
+use crate::constraint_consumer::{ConstraintConsumer, ConstraintFilter};
+use crate::evaluation_frame::StarkEvaluationFrame;
+use crate::stark::Stark;
+use crate::symbolic::SymbolicValue;
+
+/// What the analysis found about a single constraint.
+#[derive(Clone, Debug)]
+pub struct ConstraintInfo {
+    /// The filter the constraint was added with.
+    pub filter: ConstraintFilter,
+    /// The degree of the constraint in the columns, before applying the filter, or `None` if the
+    /// constraint is identically zero.
+    pub degree: Option<usize>,
+    /// The trace columns the constraint depends on.
+    pub trace_columns: Vec<usize>,
+    /// The row offsets, among [`Stark::row_offsets`], of the trace or preprocessed values the
+    /// constraint depends on.
+    pub row_offsets: Vec<isize>,
+}
+
+impl ConstraintInfo {
+    /// The degree this constraint requires from [`Stark::constraint_degree`]. The first and last
+    /// row filters are Lagrange polynomials, which add one to the degree of the constraint.
+    pub fn required_degree(&self) -> usize {
+        let degree = self.degree.unwrap_or(0);
+        match self.filter {
+            ConstraintFilter::None | ConstraintFilter::Transition => degree,
+            ConstraintFilter::FirstRow | ConstraintFilter::LastRow => degree + 1,
+        }
+    }
+
+    /// Whether this constraint reads rows after the current one while applying to the last row,
+    /// where those rows wrap around to the start of the trace, or rows before the current one
+    /// while applying to the first row, where they wrap around to the end. The transition filter
+    /// only excludes the last row, so it doesn't help with the latter.
+    pub fn is_missing_transition_filter(&self) -> bool {
+        let reads_next = self.row_offsets.iter().any(|&offset| offset > 0);
+        let reads_previous = self.row_offsets.iter().any(|&offset| offset < 0);
+        match self.filter {
+            ConstraintFilter::None => reads_next || reads_previous,
+            ConstraintFilter::LastRow => reads_next,
+            ConstraintFilter::FirstRow | ConstraintFilter::Transition => reads_previous,
+        }
+    }
+}
+
+/// The result of [`analyze_constraints`].
+#[derive(Clone, Debug)]
+pub struct ConstraintAnalysis {
+    /// The constraints, in the order they were added by `eval_packed_generic`.
+    pub constraints: Vec<ConstraintInfo>,
+    /// The trace columns which no constraint or permutation argument depends on.
+    pub unconstrained_columns: Vec<usize>,
+    /// The degree declared by [`Stark::constraint_degree`].
+    pub declared_degree: usize,
+    /// The smallest degree [`Stark::constraint_degree`] may declare.
+    pub required_degree: usize,
+}
+
+impl ConstraintAnalysis {
+    /// The indices of the constraints which read rows that wrap around the trace, see
+    /// [`ConstraintInfo::is_missing_transition_filter`]. These are only sound if the constraint is
+    /// disabled on the rows at the ends of the trace in some other way, e.g. by a selector column.
+    pub fn missing_transition_filters(&self) -> Vec<usize> {
+        self.constraints
+            .iter()
+            .positions(ConstraintInfo::is_missing_transition_filter)
+            .collect()
+    }
+
+    /// The indices of the constraints which are identically zero, and thus have no effect.
+    pub fn trivial_constraints(&self) -> Vec<usize> {
+        self.constraints
+            .iter()
+            .positions(|c| c.degree.is_none())
+            .collect()
+    }
+
+    /// Whether the declared degree is lower than some constraint's, which makes proving fail.
+    pub fn is_degree_too_tight(&self) -> bool {
+        self.declared_degree < self.required_degree
+    }
+
+    /// Whether the declared degree results in more quotient chunks than necessary.
+    pub fn is_degree_too_loose(&self) -> bool {
+        // The quotient degree factor is `max(1, constraint_degree - 1)`.
+        self.declared_degree > self.required_degree.max(2)
+    }
+
+    /// Fails if the analysis found any issue.
+    pub fn check(&self) -> Result<()> {
+        ensure!(!self.has_issues(), "{}", self);
+        Ok(())
+    }
+
+    fn has_issues(&self) -> bool {
+        !self.unconstrained_columns.is_empty()
+            || !self.missing_transition_filters().is_empty()
+            || !self.trivial_constraints().is_empty()
+            || self.is_degree_too_tight()
+            || self.is_degree_too_loose()
+    }
+}
+
+impl Display for ConstraintAnalysis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, c) in self.constraints.iter().enumerate() {
+            let degree = c
+                .degree
+                .map_or_else(|| "zero".into(), |d| alloc::format!("degree {d}"));
+            writeln!(
+                f,
+                "constraint {i} ({:?}): {degree}, columns {:?}, rows {:?}",
+                c.filter, c.trace_columns, c.row_offsets
+            )?;
+        }
+        writeln!(
+            f,
+            "declared degree {}, required degree {}",
+            self.declared_degree, self.required_degree
+        )?;
+        if self.is_degree_too_tight() {
+            writeln!(f, "error: the declared constraint degree is too low")?;
+        }
+        if self.is_degree_too_loose() {
+            writeln!(
+                f,
+                "warning: the declared constraint degree is higher than needed"
+            )?;
+        }
+        if !self.unconstrained_columns.is_empty() {
+            writeln!(
+                f,
+                "warning: unconstrained columns {:?}",
+                self.unconstrained_columns
+            )?;
+        }
+        for i in self.missing_transition_filters() {
+            writeln!(
+                f,
+                "warning: constraint {i} reads later rows but also applies to the last row"
+            )?;
+        }
+        for i in self.trivial_constraints() {
+            writeln!(f, "warning: constraint {i} is identically zero")?;
+        }
+        Ok(())
+    }
+}
+
+/// A variable of the evaluation frame.
+#[derive(Copy, Clone)]
+enum Variable {
+    /// A trace column, at the given index of `Stark::row_offsets`.
+    Trace {
+        row: usize,
+        column: usize,
+    },
+    /// A preprocessed column, in the local (`0`) or next (`1`) row.
+    Preprocessed {
+        row: usize,
+    },
+    Periodic,
+}
+
+/// Evaluates the constraints of `stark` symbolically, to find the exact degree of each constraint,
+/// the columns it depends on, and common mistakes in the constraints.
+///
+/// Only the constraints of `eval_packed_generic` are analyzed, not the permutation arguments. The
+/// degrees are exact with overwhelming probability, and must be below
+/// [`SYMBOLIC_WIDTH`](crate::symbolic::SYMBOLIC_WIDTH).
+pub fn analyze_constraints<F, S, const D: usize>(stark: &S) -> ConstraintAnalysis
+where
+    F: RichField + Extendable<D>,
+    S: Stark<F, D>,
+    SymbolicValue<F>: PackedField<Scalar = F>,
+{
+    let row_offsets = stark.row_offsets();
+    let variables = (0..row_offsets.len())
+        .flat_map(|row| (0..S::COLUMNS).map(move |column| Variable::Trace { row, column }))
+        .chain((0..S::PREPROCESSED_COLUMNS).map(|_| Variable::Preprocessed { row: 0 }))
+        .chain((0..S::PREPROCESSED_COLUMNS).map(|_| Variable::Preprocessed { row: 1 }))
+        .chain((0..S::PERIODIC_COLUMNS).map(|_| Variable::Periodic))
+        .collect::<Vec<_>>();
+    let constants = F::rand_vec(variables.len());
+    let slopes = F::rand_vec(variables.len());
+    let public_inputs = F::rand_vec(S::PUBLIC_INPUTS);
+
+    // Evaluates the constraints, with the variables selected by `is_varying` set to random linear
+    // polynomials, and the others to random constants.
+    let eval = |is_varying: &dyn Fn(usize) -> bool| {
+        let values = (0..variables.len())
+            .map(|i| {
+                if is_varying(i) {
+                    SymbolicValue::linear(constants[i], slopes[i])
+                } else {
+                    SymbolicValue::constant(constants[i])
+                }
+            })
+            .collect::<Vec<_>>();
+        let (trace_values, fixed_values) = values.split_at(row_offsets.len() * S::COLUMNS);
+        let rows = trace_values.chunks(S::COLUMNS).collect::<Vec<_>>();
+        let (preprocessed_local_values, rest) = fixed_values.split_at(S::PREPROCESSED_COLUMNS);
+        let (preprocessed_next_values, periodic_values) = rest.split_at(S::PREPROCESSED_COLUMNS);
+        let vars = S::EvaluationFrame::from_values_with_fixed(
+            rows[0],
+            rows[1],
+            preprocessed_local_values,
+            preprocessed_next_values,
+            periodic_values,
+            &public_inputs,
+        )
+        .with_extra_rows(&rows[2..].iter().map(|row| row.to_vec()).collect::<Vec<_>>());
+        let mut consumer = ConstraintConsumer::new_recording();
+        stark.eval_packed_generic(&vars, &mut consumer);
+        consumer
+            .recorded_constraints()
+            .expect("The consumer records constraints")
+    };
+
+    let mut constraints = eval(&|_| true)
+        .into_iter()
+        .map(|(filter, constraint)| ConstraintInfo {
+            filter,
+            degree: constraint.degree(),
+            trace_columns: vec![],
+            row_offsets: vec![],
+        })
+        .collect::<Vec<_>>();
+    for (i, &variable) in variables.iter().enumerate() {
+        let dependent = eval(&|j| j == i);
+        for (info, (_, constraint)) in constraints.iter_mut().zip(dependent) {
+            if constraint.degree().unwrap_or(0) == 0 {
+                continue;
+            }
+            let row = match variable {
+                Variable::Trace { row, column } => {
+                    info.trace_columns.push(column);
+                    Some(row)
+                }
+                Variable::Preprocessed { row } => Some(row),
+                Variable::Periodic => None,
+            };
+            info.row_offsets.extend(row.map(|row| row_offsets[row]));
+        }
+    }
+    for info in &mut constraints {
+        info.trace_columns.sort_unstable();
+        info.trace_columns.dedup();
+        info.row_offsets.sort_unstable();
+        info.row_offsets.dedup();
+    }
+
+    let permuted_columns = stark
+        .permutation_pairs()
+        .into_iter()
+        .flat_map(|pair| pair.column_pairs)
+        .flat_map(|(lhs, rhs)| [lhs, rhs])
+        .collect::<Vec<_>>();
+    let unconstrained_columns = (0..S::COLUMNS)
+        .filter(|column| {
+            !permuted_columns.contains(column)
+                && constraints
+                    .iter()
+                    .all(|info| !info.trace_columns.contains(column))
+        })
+        .collect();
+    let required_degree = constraints
+        .iter()
+        .map(ConstraintInfo::required_degree)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    ConstraintAnalysis {
+        constraints,
+        unconstrained_columns,
+        declared_degree: stark.constraint_degree(),
+        required_degree,
+    }
+}
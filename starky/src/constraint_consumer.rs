@@ -9,6 +9,20 @@ use plonky2::iop::ext_target::ExtensionTarget;
 use plonky2::iop::target::Target;
 use plonky2::plonk::circuit_builder::CircuitBuilder;
 
+/// The filter a constraint is multiplied by before being added to a [`ConstraintConsumer`], which
+/// determines the rows it applies to.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ConstraintFilter {
+    /// The constraint applies to all rows.
+    None,
+    /// The constraint applies to all rows except the last.
+    Transition,
+    /// The constraint applies to the first row only.
+    FirstRow,
+    /// The constraint applies to the last row only.
+    LastRow,
+}
+
 pub struct ConstraintConsumer<P: PackedField> {
     /// Random values used to combine multiple constraints into one.
     alphas: Vec<P::Scalar>,
@@ -28,6 +42,9 @@ pub struct ConstraintConsumer<P: PackedField> {
     /// The evaluation of the Lagrange basis polynomial which is nonzero at the point associated
     /// with the last trace row, and zero at other points in the subgroup.
     lagrange_basis_last: P,
+
+    /// If set, the unfiltered constraints emitted so far, along with their filters.
+    recorded: Option<Vec<(ConstraintFilter, P)>>,
 }
 
 impl<P: PackedField> ConstraintConsumer<P> {
@@ -43,6 +60,20 @@ impl<P: PackedField> ConstraintConsumer<P> {
             z_last,
             lagrange_basis_first,
             lagrange_basis_last,
+            recorded: None,
+        }
+    }
+
+    /// Creates a consumer which doesn't combine the constraints, but records each of them along
+    /// with its filter, see `recorded_constraints`.
+    pub fn new_recording() -> Self {
+        Self {
+            alphas: vec![],
+            constraint_accs: vec![],
+            z_last: P::ZEROS,
+            lagrange_basis_first: P::ZEROS,
+            lagrange_basis_last: P::ZEROS,
+            recorded: Some(vec![]),
         }
     }
 
@@ -50,29 +81,59 @@ impl<P: PackedField> ConstraintConsumer<P> {
         self.constraint_accs
     }
 
+    /// The unfiltered constraints emitted so far, if this consumer was created with
+    /// `new_recording`.
+    pub fn recorded_constraints(self) -> Option<Vec<(ConstraintFilter, P)>> {
+        self.recorded
+    }
+
     /// Add one constraint valid on all rows except the last.
     pub fn constraint_transition(&mut self, constraint: P) {
-        self.constraint(constraint * self.z_last);
+        self.add(ConstraintFilter::Transition, constraint, self.z_last);
     }
 
     /// Add one constraint on all rows.
     pub fn constraint(&mut self, constraint: P) {
-        for (&alpha, acc) in self.alphas.iter().zip(&mut self.constraint_accs) {
-            *acc *= alpha;
-            *acc += constraint;
-        }
+        self.record(ConstraintFilter::None, constraint);
+        self.accumulate(constraint);
     }
 
     /// Add one constraint, but first multiply it by a filter such that it will only apply to the
     /// first row of the trace.
     pub fn constraint_first_row(&mut self, constraint: P) {
-        self.constraint(constraint * self.lagrange_basis_first);
+        self.add(
+            ConstraintFilter::FirstRow,
+            constraint,
+            self.lagrange_basis_first,
+        );
     }
 
     /// Add one constraint, but first multiply it by a filter such that it will only apply to the
     /// last row of the trace.
     pub fn constraint_last_row(&mut self, constraint: P) {
-        self.constraint(constraint * self.lagrange_basis_last);
+        self.add(
+            ConstraintFilter::LastRow,
+            constraint,
+            self.lagrange_basis_last,
+        );
+    }
+
+    fn add(&mut self, filter_kind: ConstraintFilter, constraint: P, filter: P) {
+        self.record(filter_kind, constraint);
+        self.accumulate(constraint * filter);
+    }
+
+    fn record(&mut self, filter_kind: ConstraintFilter, constraint: P) {
+        if let Some(recorded) = &mut self.recorded {
+            recorded.push((filter_kind, constraint));
+        }
+    }
+
+    fn accumulate(&mut self, filtered_constraint: P) {
+        for (&alpha, acc) in self.alphas.iter().zip(&mut self.constraint_accs) {
+            *acc *= alpha;
+            *acc += filtered_constraint;
+        }
     }
 }
 
@@ -1,3 +1,5 @@
+use alloc::vec::Vec;
+
 /// A trait for viewing an evaluation frame of a STARK table.
 ///
 /// It allows to access the current and next rows at a given step
@@ -9,6 +11,12 @@ pub trait StarkEvaluationFrame<T: Copy + Clone + Default, U: Copy + Clone + Defa
     /// The number of columns for the STARK table this evaluation frame views.
     const COLUMNS: usize;
     const PUBLIC_INPUTS: usize;
+    /// The number of preprocessed columns, which are fixed for a given trace length and committed
+    /// once in the verifier key.
+    const PREPROCESSED_COLUMNS: usize = 0;
+    /// The number of periodic columns, which repeat a short list of values and are evaluated
+    /// directly by the verifier.
+    const PERIODIC_COLUMNS: usize = 0;
 
     /// Returns the local values (i.e. current row) for this evaluation frame.
     fn get_local_values(&self) -> &[T];
@@ -17,51 +25,159 @@ pub trait StarkEvaluationFrame<T: Copy + Clone + Default, U: Copy + Clone + Defa
 
     fn get_public_inputs(&self) -> &[U];
 
-    /// Outputs a new evaluation frame from the provided local and next values.
+    /// Returns the values of the preprocessed columns in the current row.
+    fn get_preprocessed_local_values(&self) -> &[T] {
+        &[]
+    }
+    /// Returns the values of the preprocessed columns in the next row.
+    fn get_preprocessed_next_values(&self) -> &[T] {
+        &[]
+    }
+    /// Returns the values of the periodic columns in the current row.
+    fn get_periodic_values(&self) -> &[T] {
+        &[]
+    }
+
+    /// Returns the values of the row at the `index`-th offset of
+    /// [`Stark::row_offsets`](crate::stark::Stark::row_offsets), so that indices `0` and `1` are the
+    /// local and next rows.
+    fn get_row_values(&self, index: usize) -> &[T] {
+        match index {
+            0 => self.get_local_values(),
+            1 => self.get_next_values(),
+            _ => panic!("This evaluation frame only holds the local and next rows"),
+        }
+    }
+
+    /// Outputs a new evaluation frame from the provided local and next values, for a STARK
+    /// without preprocessed or periodic columns.
+    fn from_values(lv: &[T], nv: &[T], pis: &[U]) -> Self {
+        Self::from_values_with_fixed(lv, nv, &[], &[], &[], pis)
+    }
+
+    /// Outputs a new evaluation frame from the provided local and next values of the trace and
+    /// preprocessed columns, and the local values of the periodic columns.
     ///
     /// **NOTE**: Concrete implementations of this method SHOULD ensure that
-    /// the provided slices lengths match the `Self::COLUMNS` value.
-    fn from_values(lv: &[T], nv: &[T], pis: &[U]) -> Self;
+    /// the provided slices lengths match the `Self::COLUMNS`, `Self::PREPROCESSED_COLUMNS`
+    /// and `Self::PERIODIC_COLUMNS` values.
+    fn from_values_with_fixed(
+        lv: &[T],
+        nv: &[T],
+        preprocessed_lv: &[T],
+        preprocessed_nv: &[T],
+        periodic: &[T],
+        pis: &[U],
+    ) -> Self;
+
+    /// Adds the rows at the offsets following the local and next rows to this evaluation frame.
+    /// Frames which only hold the local and next rows accept no extra rows.
+    fn with_extra_rows(self, extra_rows: &[Vec<T>]) -> Self {
+        assert!(
+            extra_rows.is_empty(),
+            "This evaluation frame only holds the local and next rows"
+        );
+        self
+    }
 }
 
+/// An evaluation frame with `N` columns, `N2` public inputs, `N3` preprocessed columns and `N4`
+/// periodic columns, viewing `R` rows of the trace at the offsets given by
+/// [`Stark::row_offsets`](crate::stark::Stark::row_offsets).
 pub struct StarkFrame<
     T: Copy + Clone + Default,
     U: Copy + Clone + Default,
     const N: usize,
     const N2: usize,
+    const N3: usize = 0,
+    const N4: usize = 0,
+    const R: usize = 2,
 > {
-    local_values: [T; N],
-    next_values: [T; N],
+    /// The local and next rows, followed by the rows at the extra offsets.
+    rows: [[T; N]; R],
     public_inputs: [U; N2],
+    preprocessed_local_values: [T; N3],
+    preprocessed_next_values: [T; N3],
+    periodic_values: [T; N4],
 }
 
-impl<T: Copy + Clone + Default, U: Copy + Clone + Default, const N: usize, const N2: usize>
-    StarkEvaluationFrame<T, U> for StarkFrame<T, U, N, N2>
+impl<
+        T: Copy + Clone + Default,
+        U: Copy + Clone + Default,
+        const N: usize,
+        const N2: usize,
+        const N3: usize,
+        const N4: usize,
+        const R: usize,
+    > StarkEvaluationFrame<T, U> for StarkFrame<T, U, N, N2, N3, N4, R>
 {
     const COLUMNS: usize = N;
     const PUBLIC_INPUTS: usize = N2;
+    const PREPROCESSED_COLUMNS: usize = N3;
+    const PERIODIC_COLUMNS: usize = N4;
 
     fn get_local_values(&self) -> &[T] {
-        &self.local_values
+        &self.rows[0]
     }
 
     fn get_next_values(&self) -> &[T] {
-        &self.next_values
+        &self.rows[1]
     }
 
     fn get_public_inputs(&self) -> &[U] {
         &self.public_inputs
     }
 
-    fn from_values(lv: &[T], nv: &[T], pis: &[U]) -> Self {
+    fn get_preprocessed_local_values(&self) -> &[T] {
+        &self.preprocessed_local_values
+    }
+
+    fn get_preprocessed_next_values(&self) -> &[T] {
+        &self.preprocessed_next_values
+    }
+
+    fn get_periodic_values(&self) -> &[T] {
+        &self.periodic_values
+    }
+
+    fn get_row_values(&self, index: usize) -> &[T] {
+        &self.rows[index]
+    }
+
+    fn from_values_with_fixed(
+        lv: &[T],
+        nv: &[T],
+        preprocessed_lv: &[T],
+        preprocessed_nv: &[T],
+        periodic: &[T],
+        pis: &[U],
+    ) -> Self {
+        assert!(R >= 2, "The frame should hold the local and next rows");
         assert_eq!(lv.len(), Self::COLUMNS);
         assert_eq!(nv.len(), Self::COLUMNS);
+        assert_eq!(preprocessed_lv.len(), Self::PREPROCESSED_COLUMNS);
+        assert_eq!(preprocessed_nv.len(), Self::PREPROCESSED_COLUMNS);
+        assert_eq!(periodic.len(), Self::PERIODIC_COLUMNS);
         assert_eq!(pis.len(), Self::PUBLIC_INPUTS);
 
+        // The rows at the extra offsets are set by `with_extra_rows`.
+        let mut rows = [[T::default(); N]; R];
+        rows[0] = lv.try_into().unwrap();
+        rows[1] = nv.try_into().unwrap();
         Self {
-            local_values: lv.try_into().unwrap(),
-            next_values: nv.try_into().unwrap(),
+            rows,
             public_inputs: pis.try_into().unwrap(),
+            preprocessed_local_values: preprocessed_lv.try_into().unwrap(),
+            preprocessed_next_values: preprocessed_nv.try_into().unwrap(),
+            periodic_values: periodic.try_into().unwrap(),
+        }
+    }
+
+    fn with_extra_rows(mut self, extra_rows: &[Vec<T>]) -> Self {
+        assert_eq!(extra_rows.len() + 2, R);
+        for (row, values) in self.rows[2..].iter_mut().zip(extra_rows) {
+            *row = values.as_slice().try_into().unwrap();
         }
+        self
     }
 }
@@ -0,0 +1,216 @@
+//! Support for defining the constraints of a STARK once, over an [`ExpressionBuilder`], and
+//! deriving both `eval_packed_generic` and `eval_ext_circuit` from them.
+
+use alloc::vec::Vec;
+
+use plonky2::field::extension::{Extendable, FieldExtension};
+use plonky2::field::packed::PackedField;
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::expression::{ExpressionBuilder, PackedExpressionBuilder};
+
+use crate::constraint_consumer::{
+    ConstraintConsumer, ConstraintFilter, RecursiveConstraintConsumer,
+};
+use crate::evaluation_frame::StarkEvaluationFrame;
+use crate::stark::Stark;
+
+/// The values of an evaluation frame, as expressions of an [`ExpressionBuilder`].
+pub struct ExpressionFrame<'a, T> {
+    /// The rows at the offsets of [`Stark::row_offsets`].
+    rows: Vec<&'a [T]>,
+    preprocessed_local_values: &'a [T],
+    preprocessed_next_values: &'a [T],
+    periodic_values: &'a [T],
+    public_inputs: Vec<T>,
+}
+
+impl<'a, T: Copy + Default> ExpressionFrame<'a, T> {
+    /// Views the values of `frame`, with its public inputs mapped to expressions by `public_input`.
+    fn new<U: Copy + Default, S: StarkEvaluationFrame<T, U>>(
+        frame: &'a S,
+        num_rows: usize,
+        public_input: impl FnMut(U) -> T,
+    ) -> Self {
+        Self {
+            rows: (0..num_rows).map(|i| frame.get_row_values(i)).collect(),
+            preprocessed_local_values: frame.get_preprocessed_local_values(),
+            preprocessed_next_values: frame.get_preprocessed_next_values(),
+            periodic_values: frame.get_periodic_values(),
+            public_inputs: frame
+                .get_public_inputs()
+                .iter()
+                .copied()
+                .map(public_input)
+                .collect(),
+        }
+    }
+}
+
+impl<'a, T> ExpressionFrame<'a, T> {
+    pub fn get_local_values(&self) -> &[T] {
+        self.rows[0]
+    }
+
+    pub fn get_next_values(&self) -> &[T] {
+        self.rows[1]
+    }
+
+    /// Returns the values of the row at the `index`-th offset of [`Stark::row_offsets`].
+    pub fn get_row_values(&self, index: usize) -> &[T] {
+        self.rows[index]
+    }
+
+    pub fn get_preprocessed_local_values(&self) -> &[T] {
+        self.preprocessed_local_values
+    }
+
+    pub fn get_preprocessed_next_values(&self) -> &[T] {
+        self.preprocessed_next_values
+    }
+
+    pub fn get_periodic_values(&self) -> &[T] {
+        self.periodic_values
+    }
+
+    pub fn get_public_inputs(&self) -> &[T] {
+        &self.public_inputs
+    }
+}
+
+/// Collects the constraints of an [`ExpressionStark`] along with their filters, which are applied
+/// when passing them to a [`ConstraintConsumer`] or a [`RecursiveConstraintConsumer`].
+pub struct ExpressionConstraintConsumer<T> {
+    constraints: Vec<(ConstraintFilter, T)>,
+}
+
+impl<T> ExpressionConstraintConsumer<T> {
+    const fn new() -> Self {
+        Self {
+            constraints: Vec::new(),
+        }
+    }
+
+    /// Add one constraint valid on all rows except the last.
+    pub fn constraint_transition(&mut self, constraint: T) {
+        self.constraints
+            .push((ConstraintFilter::Transition, constraint));
+    }
+
+    /// Add one constraint on all rows.
+    pub fn constraint(&mut self, constraint: T) {
+        self.constraints.push((ConstraintFilter::None, constraint));
+    }
+
+    /// Add one constraint which only applies to the first row of the trace.
+    pub fn constraint_first_row(&mut self, constraint: T) {
+        self.constraints
+            .push((ConstraintFilter::FirstRow, constraint));
+    }
+
+    /// Add one constraint which only applies to the last row of the trace.
+    pub fn constraint_last_row(&mut self, constraint: T) {
+        self.constraints
+            .push((ConstraintFilter::LastRow, constraint));
+    }
+}
+
+/// A STARK whose constraints are defined once, over an [`ExpressionBuilder`].
+///
+/// Its `eval_packed_generic` and `eval_ext_circuit` are then derived from `eval_constraints` by
+/// invoking [`impl_expression_stark!`](crate::impl_expression_stark) in its `Stark` impl, which
+/// guarantees that they agree.
+pub trait ExpressionStark<F: RichField + Extendable<D>, const D: usize>: Stark<F, D> {
+    /// Evaluates the constraints of this STARK, passing them to `yield_constr`.
+    fn eval_constraints<B: ExpressionBuilder<F, D>>(
+        &self,
+        builder: &mut B,
+        vars: &ExpressionFrame<B::Expr>,
+        yield_constr: &mut ExpressionConstraintConsumer<B::Expr>,
+    );
+
+    fn eval_packed_from_constraints<FE, P, const D2: usize>(
+        &self,
+        vars: &Self::EvaluationFrame<FE, P, D2>,
+        yield_constr: &mut ConstraintConsumer<P>,
+    ) where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>,
+    {
+        let vars = ExpressionFrame::new(vars, self.row_offsets().len(), P::from);
+        let mut constraints = ExpressionConstraintConsumer::new();
+        self.eval_constraints(
+            &mut PackedExpressionBuilder::<P, D2>::new(),
+            &vars,
+            &mut constraints,
+        );
+        for (filter, constraint) in constraints.constraints {
+            match filter {
+                ConstraintFilter::None => yield_constr.constraint(constraint),
+                ConstraintFilter::Transition => yield_constr.constraint_transition(constraint),
+                ConstraintFilter::FirstRow => yield_constr.constraint_first_row(constraint),
+                ConstraintFilter::LastRow => yield_constr.constraint_last_row(constraint),
+            }
+        }
+    }
+
+    fn eval_ext_circuit_from_constraints(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: &Self::EvaluationFrameTarget,
+        yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+    ) {
+        let vars = ExpressionFrame::new(vars, self.row_offsets().len(), |t| t);
+        let mut constraints = ExpressionConstraintConsumer::new();
+        self.eval_constraints(builder, &vars, &mut constraints);
+        for (filter, constraint) in constraints.constraints {
+            match filter {
+                ConstraintFilter::None => yield_constr.constraint(builder, constraint),
+                ConstraintFilter::Transition => {
+                    yield_constr.constraint_transition(builder, constraint)
+                }
+                ConstraintFilter::FirstRow => {
+                    yield_constr.constraint_first_row(builder, constraint)
+                }
+                ConstraintFilter::LastRow => yield_constr.constraint_last_row(builder, constraint),
+            }
+        }
+    }
+}
+
+/// Implements `eval_packed_generic` and `eval_ext_circuit` for an [`ExpressionStark`], from its
+/// `eval_constraints`. This should be invoked in the STARK's `Stark` impl, where `F` and `D` are in
+/// scope, which then only defines the other methods of `Stark`.
+#[macro_export]
+macro_rules! impl_expression_stark {
+    () => {
+        fn eval_packed_generic<FE, P, const D2: usize>(
+            &self,
+            vars: &Self::EvaluationFrame<FE, P, D2>,
+            yield_constr: &mut $crate::constraint_consumer::ConstraintConsumer<P>,
+        ) where
+            FE: ::plonky2::field::extension::FieldExtension<D2, BaseField = F>,
+            P: ::plonky2::field::packed::PackedField<Scalar = FE>,
+        {
+            $crate::expression::ExpressionStark::eval_packed_from_constraints(
+                self,
+                vars,
+                yield_constr,
+            )
+        }
+
+        fn eval_ext_circuit(
+            &self,
+            builder: &mut ::plonky2::plonk::circuit_builder::CircuitBuilder<F, D>,
+            vars: &Self::EvaluationFrameTarget,
+            yield_constr: &mut $crate::constraint_consumer::RecursiveConstraintConsumer<F, D>,
+        ) {
+            $crate::expression::ExpressionStark::eval_ext_circuit_from_constraints(
+                self,
+                builder,
+                vars,
+                yield_constr,
+            )
+        }
+    };
+}
@@ -7,10 +7,11 @@ use plonky2::field::packed::PackedField;
 use plonky2::field::polynomial::PolynomialValues;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
-use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::expression::ExpressionBuilder;
 
-use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
-use crate::evaluation_frame::{StarkEvaluationFrame, StarkFrame};
+use crate::evaluation_frame::StarkFrame;
+use crate::expression::{ExpressionConstraintConsumer, ExpressionFrame, ExpressionStark};
+use crate::impl_expression_stark;
 use crate::permutation::PermutationPair;
 use crate::stark::Stark;
 use crate::util::trace_rows_to_poly_values;
@@ -70,65 +71,41 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for FibonacciStar
     type EvaluationFrameTarget =
         StarkFrame<ExtensionTarget<D>, ExtensionTarget<D>, COLUMNS, PUBLIC_INPUTS>;
 
-    fn eval_packed_generic<FE, P, const D2: usize>(
-        &self,
-        vars: &Self::EvaluationFrame<FE, P, D2>,
-        yield_constr: &mut ConstraintConsumer<P>,
-    ) where
-        FE: FieldExtension<D2, BaseField = F>,
-        P: PackedField<Scalar = FE>,
-    {
-        let local_values = vars.get_local_values();
-        let next_values = vars.get_next_values();
-        let public_inputs = vars.get_public_inputs();
+    impl_expression_stark!();
 
-        // Check public inputs.
-        yield_constr.constraint_first_row(local_values[0] - public_inputs[Self::PI_INDEX_X0]);
-        yield_constr.constraint_first_row(local_values[1] - public_inputs[Self::PI_INDEX_X1]);
-        yield_constr.constraint_last_row(local_values[1] - public_inputs[Self::PI_INDEX_RES]);
+    fn constraint_degree(&self) -> usize {
+        2
+    }
 
-        // x0' <- x1
-        yield_constr.constraint_transition(next_values[0] - local_values[1]);
-        // x1' <- x0 + x1
-        yield_constr.constraint_transition(next_values[1] - local_values[0] - local_values[1]);
+    fn permutation_pairs(&self) -> Vec<PermutationPair> {
+        vec![PermutationPair::singletons(2, 3)]
     }
+}
 
-    fn eval_ext_circuit(
+impl<F: RichField + Extendable<D>, const D: usize> ExpressionStark<F, D> for FibonacciStark<F, D> {
+    fn eval_constraints<B: ExpressionBuilder<F, D>>(
         &self,
-        builder: &mut CircuitBuilder<F, D>,
-        vars: &Self::EvaluationFrameTarget,
-        yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+        builder: &mut B,
+        vars: &ExpressionFrame<B::Expr>,
+        yield_constr: &mut ExpressionConstraintConsumer<B::Expr>,
     ) {
         let local_values = vars.get_local_values();
         let next_values = vars.get_next_values();
         let public_inputs = vars.get_public_inputs();
+
         // Check public inputs.
-        let pis_constraints = [
-            builder.sub_extension(local_values[0], public_inputs[Self::PI_INDEX_X0]),
-            builder.sub_extension(local_values[1], public_inputs[Self::PI_INDEX_X1]),
-            builder.sub_extension(local_values[1], public_inputs[Self::PI_INDEX_RES]),
-        ];
-        yield_constr.constraint_first_row(builder, pis_constraints[0]);
-        yield_constr.constraint_first_row(builder, pis_constraints[1]);
-        yield_constr.constraint_last_row(builder, pis_constraints[2]);
+        yield_constr
+            .constraint_first_row(builder.sub(local_values[0], public_inputs[Self::PI_INDEX_X0]));
+        yield_constr
+            .constraint_first_row(builder.sub(local_values[1], public_inputs[Self::PI_INDEX_X1]));
+        yield_constr
+            .constraint_last_row(builder.sub(local_values[1], public_inputs[Self::PI_INDEX_RES]));
 
         // x0' <- x1
-        let first_col_constraint = builder.sub_extension(next_values[0], local_values[1]);
-        yield_constr.constraint_transition(builder, first_col_constraint);
+        yield_constr.constraint_transition(builder.sub(next_values[0], local_values[1]));
         // x1' <- x0 + x1
-        let second_col_constraint = {
-            let tmp = builder.sub_extension(next_values[1], local_values[0]);
-            builder.sub_extension(tmp, local_values[1])
-        };
-        yield_constr.constraint_transition(builder, second_col_constraint);
-    }
-
-    fn constraint_degree(&self) -> usize {
-        2
-    }
-
-    fn permutation_pairs(&self) -> Vec<PermutationPair> {
-        vec![PermutationPair::singletons(2, 3)]
+        let sum = builder.add(local_values[0], local_values[1]);
+        yield_constr.constraint_transition(builder.sub(next_values[1], sum));
     }
 }
 
@@ -145,6 +122,7 @@ mod tests {
     use plonky2::util::timing::TimingTree;
 
     use crate::config::StarkConfig;
+    use crate::constraint_analysis::analyze_constraints;
     use crate::fibonacci_stark::FibonacciStark;
     use crate::proof::StarkProofWithPublicInputs;
     use crate::prover::prove;
@@ -195,6 +173,19 @@ mod tests {
         test_stark_low_degree(stark)
     }
 
+    #[test]
+    fn test_fibonacci_stark_analysis() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type S = FibonacciStark<F, D>;
+
+        let analysis = analyze_constraints(&S::new(1 << 5));
+        assert_eq!(analysis.constraints.len(), 5);
+        assert_eq!(analysis.required_degree, 2);
+        analysis.check()
+    }
+
     #[test]
     fn test_fibonacci_stark_circuit() -> Result<()> {
         const D: usize = 2;
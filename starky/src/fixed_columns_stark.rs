@@ -0,0 +1,235 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use plonky2::field::extension::{Extendable, FieldExtension};
+use plonky2::field::packed::PackedField;
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::ext_target::ExtensionTarget;
+use plonky2::plonk::expression::ExpressionBuilder;
+
+use crate::evaluation_frame::StarkFrame;
+use crate::expression::{ExpressionConstraintConsumer, ExpressionFrame, ExpressionStark};
+use crate::impl_expression_stark;
+use crate::stark::Stark;
+
+/// Toy STARK system used for testing preprocessed and periodic columns.
+/// Accumulates `x' <- x + c * k'`, where `k = offset + i` is a preprocessed column holding the row
+/// index `i` shifted by `offset`, and `c` is a periodic column repeating `PERIODIC_VALUES`.
+#[derive(Copy, Clone)]
+struct FixedColumnsStark<F: RichField + Extendable<D>, const D: usize> {
+    num_rows: usize,
+    offset: u64,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> FixedColumnsStark<F, D> {
+    // The first public input is the initial `x`.
+    const PI_INDEX_X0: usize = 0;
+    // The second public input is the final `x`.
+    const PI_INDEX_RES: usize = 1;
+
+    const PERIODIC_VALUES: [u64; 4] = [1, 2, 3, 4];
+
+    const fn new(num_rows: usize, offset: u64) -> Self {
+        Self {
+            num_rows,
+            offset,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn k(&self, i: usize) -> F {
+        F::from_canonical_u64(self.offset + i as u64)
+    }
+
+    fn c(i: usize) -> F {
+        F::from_canonical_u64(Self::PERIODIC_VALUES[i % Self::PERIODIC_VALUES.len()])
+    }
+
+    /// Generate the trace starting from `x0`, returning it along with the final value of `x`.
+    fn generate_trace(&self, x0: F) -> (Vec<PolynomialValues<F>>, F) {
+        let mut x = x0;
+        let mut column = Vec::with_capacity(self.num_rows);
+        for i in 0..self.num_rows {
+            column.push(x);
+            x += Self::c(i) * self.k(i + 1);
+        }
+        let res = column[self.num_rows - 1];
+        (vec![PolynomialValues::new(column)], res)
+    }
+}
+
+const COLUMNS: usize = 1;
+const PUBLIC_INPUTS: usize = 2;
+const PREPROCESSED_COLUMNS: usize = 1;
+const PERIODIC_COLUMNS: usize = 1;
+
+impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for FixedColumnsStark<F, D> {
+    type EvaluationFrame<FE, P, const D2: usize>
+        = StarkFrame<P, P::Scalar, COLUMNS, PUBLIC_INPUTS, PREPROCESSED_COLUMNS, PERIODIC_COLUMNS>
+    where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>;
+
+    type EvaluationFrameTarget = StarkFrame<
+        ExtensionTarget<D>,
+        ExtensionTarget<D>,
+        COLUMNS,
+        PUBLIC_INPUTS,
+        PREPROCESSED_COLUMNS,
+        PERIODIC_COLUMNS,
+    >;
+
+    impl_expression_stark!();
+
+    fn constraint_degree(&self) -> usize {
+        2
+    }
+
+    fn preprocessed_columns(&self, num_rows: usize) -> Vec<PolynomialValues<F>> {
+        vec![PolynomialValues::new(
+            (0..num_rows).map(|i| self.k(i)).collect(),
+        )]
+    }
+
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        vec![Self::PERIODIC_VALUES
+            .iter()
+            .map(|&c| F::from_canonical_u64(c))
+            .collect()]
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> ExpressionStark<F, D>
+    for FixedColumnsStark<F, D>
+{
+    fn eval_constraints<B: ExpressionBuilder<F, D>>(
+        &self,
+        builder: &mut B,
+        vars: &ExpressionFrame<B::Expr>,
+        yield_constr: &mut ExpressionConstraintConsumer<B::Expr>,
+    ) {
+        let x = vars.get_local_values()[0];
+        let next_x = vars.get_next_values()[0];
+        let next_k = vars.get_preprocessed_next_values()[0];
+        let c = vars.get_periodic_values()[0];
+        let public_inputs = vars.get_public_inputs();
+
+        // Check public inputs.
+        yield_constr.constraint_first_row(builder.sub(x, public_inputs[Self::PI_INDEX_X0]));
+        yield_constr.constraint_last_row(builder.sub(x, public_inputs[Self::PI_INDEX_RES]));
+
+        // x' <- x + c * k'
+        let diff = builder.sub(next_x, x);
+        yield_constr.constraint_transition(builder.mul_sub(c, next_k, diff));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2::field::types::Field;
+    use plonky2::iop::witness::PartialWitness;
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use plonky2::util::timing::TimingTree;
+
+    use crate::config::StarkConfig;
+    use crate::constraint_analysis::analyze_constraints;
+    use crate::fixed_columns_stark::FixedColumnsStark;
+    use crate::preprocessed::{StarkProvingKey, StarkVerifierKey};
+    use crate::prover::{prove, prove_with_key};
+    use crate::recursive_verifier::{
+        add_virtual_stark_proof_with_pis, set_stark_proof_with_pis_target,
+        verify_stark_proof_circuit_with_key,
+    };
+    use crate::stark_testing::{test_stark_circuit_constraints, test_stark_low_degree};
+    use crate::verifier::{verify_stark_proof, verify_stark_proof_with_key};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type S = FixedColumnsStark<F, D>;
+
+    const NUM_ROWS: usize = 1 << 5;
+
+    #[test]
+    fn test_fixed_columns_stark() -> Result<()> {
+        let config = StarkConfig::standard_fast_config();
+        let stark = S::new(NUM_ROWS, 7);
+        let (trace, res) = stark.generate_trace(F::ONE);
+        let public_inputs = [F::ONE, res];
+        let proof = prove::<F, C, S, D>(
+            stark,
+            &config,
+            trace,
+            &public_inputs,
+            &mut TimingTree::default(),
+        )?;
+        verify_stark_proof(stark, proof.clone(), &config)?;
+
+        // The preprocessed columns are bound by the verifier key.
+        let other_stark = S::new(NUM_ROWS, 8);
+        let other_key = StarkVerifierKey::new(&other_stark, &config, 5);
+        assert!(verify_stark_proof_with_key(other_stark, &other_key, proof, &config).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fixed_columns_stark_degree() -> Result<()> {
+        test_stark_low_degree(S::new(NUM_ROWS, 7))
+    }
+
+    #[test]
+    fn test_fixed_columns_stark_analysis() -> Result<()> {
+        let analysis = analyze_constraints(&S::new(NUM_ROWS, 7));
+        // The transition constraint is quadratic in the periodic and preprocessed columns.
+        assert_eq!(analysis.constraints[2].degree, Some(2));
+        assert_eq!(analysis.constraints[2].row_offsets, [0, 1]);
+        analysis.check()
+    }
+
+    #[test]
+    fn test_fixed_columns_stark_circuit() -> Result<()> {
+        test_stark_circuit_constraints::<F, C, S, D>(S::new(NUM_ROWS, 7))
+    }
+
+    #[test]
+    fn test_recursive_fixed_columns_stark_verifier() -> Result<()> {
+        let config = StarkConfig::standard_fast_config();
+        let stark = S::new(NUM_ROWS, 7);
+        let proving_key = StarkProvingKey::new(&stark, &config, 5, &mut TimingTree::default());
+        let verifier_key = &proving_key.verifier_key;
+
+        let (trace, res) = stark.generate_trace(F::ONE);
+        let public_inputs = [F::ONE, res];
+        let proof = prove_with_key::<F, C, S, D>(
+            stark,
+            &proving_key,
+            &config,
+            trace,
+            &public_inputs,
+            &mut TimingTree::default(),
+        )?;
+        verify_stark_proof_with_key(stark, verifier_key, proof.clone(), &config)?;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let mut pw = PartialWitness::new();
+        let pt = add_virtual_stark_proof_with_pis(&mut builder, stark, &config, 5);
+        set_stark_proof_with_pis_target(&mut pw, &pt, &proof);
+        verify_stark_proof_circuit_with_key::<F, C, S, D>(
+            &mut builder,
+            stark,
+            verifier_key,
+            pt,
+            &config,
+        );
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+}
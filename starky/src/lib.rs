@@ -5,19 +5,28 @@
 extern crate alloc;
 
 mod get_challenges;
+mod periodic;
 
 pub mod config;
+pub mod constraint_analysis;
 pub mod constraint_consumer;
 pub mod evaluation_frame;
+pub mod expression;
 pub mod permutation;
+pub mod preprocessed;
 pub mod proof;
 pub mod prover;
 pub mod recursive_verifier;
 pub mod stark;
 pub mod stark_testing;
+pub mod symbolic;
 pub mod util;
 pub mod vanishing_poly;
 pub mod verifier;
 
 #[cfg(test)]
 pub mod fibonacci_stark;
+#[cfg(test)]
+pub mod fixed_columns_stark;
+#[cfg(test)]
+pub mod window_stark;
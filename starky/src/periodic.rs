@@ -0,0 +1,138 @@
+//! Periodic columns, which repeat a list of values whose length is a power of two `p` dividing
+//! the trace length `n`.
+//!
+//! Such a column is the polynomial `Q(x^(n/p))`, where `Q` interpolates its values over the
+//! subgroup of order `p`, so the verifier can evaluate it in `O(p)` operations instead of having
+//! it committed.
+
+use alloc::vec::Vec;
+
+use plonky2::field::extension::{Extendable, FieldExtension};
+use plonky2::field::polynomial::{PolynomialCoeffs, PolynomialValues};
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::ext_target::ExtensionTarget;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::util::log2_strict;
+
+fn period_bits<F>(values: &[F], degree_bits: usize) -> usize {
+    let period_bits = log2_strict(values.len());
+    assert!(
+        period_bits <= degree_bits,
+        "The period of a periodic column is larger than the trace."
+    );
+    period_bits
+}
+
+/// The coefficients of `Q` for each periodic column, along with the log of its period.
+fn interpolants<F: Field>(
+    columns: &[Vec<F>],
+    degree_bits: usize,
+) -> Vec<(PolynomialCoeffs<F>, usize)> {
+    columns
+        .iter()
+        .map(|values| {
+            let period_bits = period_bits(values, degree_bits);
+            (PolynomialValues::new(values.clone()).ifft(), period_bits)
+        })
+        .collect()
+}
+
+/// The values of the periodic columns on the trace domain.
+pub(crate) fn periodic_column_values<F: Field>(
+    columns: &[Vec<F>],
+    degree_bits: usize,
+) -> Vec<PolynomialValues<F>> {
+    columns
+        .iter()
+        .map(|values| {
+            period_bits(values, degree_bits);
+            PolynomialValues::new(
+                values
+                    .iter()
+                    .copied()
+                    .cycle()
+                    .take(1 << degree_bits)
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+/// Evaluates the periodic columns at `x`.
+pub(crate) fn eval_periodic_columns<F: RichField + Extendable<D>, const D: usize>(
+    columns: &[Vec<F>],
+    x: F::Extension,
+    degree_bits: usize,
+) -> Vec<F::Extension> {
+    interpolants(columns, degree_bits)
+        .into_iter()
+        .map(|(interpolant, period_bits)| {
+            interpolant
+                .to_extension()
+                .eval(x.exp_power_of_2(degree_bits - period_bits))
+        })
+        .collect()
+}
+
+/// Evaluates the periodic columns at `x` in a circuit.
+pub(crate) fn eval_periodic_columns_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    columns: &[Vec<F>],
+    x: ExtensionTarget<D>,
+    degree_bits: usize,
+) -> Vec<ExtensionTarget<D>> {
+    interpolants(columns, degree_bits)
+        .into_iter()
+        .map(|(interpolant, period_bits)| {
+            let y = builder.exp_power_of_2_extension(x, degree_bits - period_bits);
+            interpolant
+                .coeffs
+                .iter()
+                .rev()
+                .fold(builder.zero_extension(), |acc, &c| {
+                    let c = builder.constant_extension(F::Extension::from_basefield(c));
+                    builder.mul_add_extension(acc, y, c)
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::field::types::{Field, Sample};
+
+    use super::*;
+
+    #[test]
+    fn test_eval_periodic_columns() {
+        type F = GoldilocksField;
+        const D: usize = 2;
+        let degree_bits = 5;
+
+        let columns = [
+            F::rand_vec(1),
+            F::rand_vec(4),
+            F::rand_vec(1 << degree_bits),
+        ];
+        let x = <F as Extendable<D>>::Extension::rand();
+        let expected = periodic_column_values(&columns, degree_bits)
+            .into_iter()
+            .map(|values| values.ifft().to_extension::<D>().eval(x))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            eval_periodic_columns::<F, D>(&columns, x, degree_bits),
+            expected
+        );
+
+        // On the trace domain, the columns repeat their values.
+        let g = F::primitive_root_of_unity(degree_bits);
+        let row = 13;
+        let x = g.exp_u64(row as u64).into();
+        let values = eval_periodic_columns::<F, D>(&columns, x, degree_bits);
+        for (column, value) in columns.iter().zip(values) {
+            assert_eq!(value, column[row % column.len()].into());
+        }
+    }
+}
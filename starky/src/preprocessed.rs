@@ -0,0 +1,87 @@
+//! Keys holding the commitment to the preprocessed columns of a STARK, which only depend on the
+//! trace length, so that they are committed once rather than in every proof.
+
+use plonky2::field::extension::Extendable;
+use plonky2::fri::oracle::PolynomialBatch;
+use plonky2::hash::hash_types::RichField;
+use plonky2::hash::merkle_tree::MerkleCap;
+use plonky2::plonk::config::GenericConfig;
+use plonky2::timed;
+use plonky2::util::timing::TimingTree;
+
+use crate::config::StarkConfig;
+use crate::stark::Stark;
+
+/// The data the prover needs about the preprocessed columns of a STARK, for traces of
+/// `2^degree_bits` rows.
+pub struct StarkProvingKey<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+{
+    /// The commitment to the preprocessed columns, if there are any.
+    pub preprocessed_commitment: Option<PolynomialBatch<F, C, D>>,
+    pub verifier_key: StarkVerifierKey<F, C, D>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    StarkProvingKey<F, C, D>
+{
+    pub fn new<S: Stark<F, D>>(
+        stark: &S,
+        config: &StarkConfig,
+        degree_bits: usize,
+        timing: &mut TimingTree,
+    ) -> Self {
+        let num_rows = 1 << degree_bits;
+        let preprocessed_commitment = stark.uses_preprocessed_columns().then(|| {
+            let columns = stark.preprocessed_columns(num_rows);
+            assert_eq!(columns.len(), S::PREPROCESSED_COLUMNS);
+            assert!(
+                columns.iter().all(|column| column.len() == num_rows),
+                "Preprocessed columns should have as many rows as the trace."
+            );
+            timed!(
+                timing,
+                "compute preprocessed commitment",
+                PolynomialBatch::from_values(
+                    columns,
+                    config.fri_config.rate_bits,
+                    false,
+                    config.fri_config.cap_height,
+                    timing,
+                    None,
+                )
+            )
+        });
+        let preprocessed_cap = preprocessed_commitment
+            .as_ref()
+            .map(|commitment| commitment.merkle_tree.cap.clone());
+
+        Self {
+            preprocessed_commitment,
+            verifier_key: StarkVerifierKey {
+                degree_bits,
+                preprocessed_cap,
+            },
+        }
+    }
+}
+
+/// The data the verifier needs about the preprocessed columns of a STARK, for traces of
+/// `2^degree_bits` rows.
+#[derive(Debug, Clone)]
+pub struct StarkVerifierKey<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+> {
+    pub degree_bits: usize,
+    /// Merkle cap of LDEs of the preprocessed columns, if there are any.
+    pub preprocessed_cap: Option<MerkleCap<F, C::Hasher>>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    StarkVerifierKey<F, C, D>
+{
+    pub fn new<S: Stark<F, D>>(stark: &S, config: &StarkConfig, degree_bits: usize) -> Self {
+        StarkProvingKey::new(stark, config, degree_bits, &mut TimingTree::default()).verifier_key
+    }
+}
@@ -1,4 +1,3 @@
-use alloc::vec;
 use alloc::vec::Vec;
 
 use itertools::Itertools;
@@ -19,6 +18,7 @@ use plonky2_maybe_rayon::*;
 
 use crate::config::StarkConfig;
 use crate::permutation::PermutationChallengeSet;
+use crate::stark::row_shift;
 
 #[derive(Debug, Clone)]
 pub struct StarkProof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
@@ -129,6 +129,10 @@ pub(crate) struct StarkProofChallengesTarget<const D: usize> {
 pub struct StarkOpeningSet<F: RichField + Extendable<D>, const D: usize> {
     pub local_values: Vec<F::Extension>,
     pub next_values: Vec<F::Extension>,
+    /// The trace values at each row offset after the first two of `Stark::row_offsets`.
+    pub extra_row_values: Vec<Vec<F::Extension>>,
+    pub preprocessed_local_values: Option<Vec<F::Extension>>,
+    pub preprocessed_next_values: Option<Vec<F::Extension>>,
     pub permutation_zs: Option<Vec<F::Extension>>,
     pub permutation_zs_next: Option<Vec<F::Extension>>,
     pub quotient_polys: Vec<F::Extension>,
@@ -138,7 +142,9 @@ impl<F: RichField + Extendable<D>, const D: usize> StarkOpeningSet<F, D> {
     pub fn new<C: GenericConfig<D, F = F>>(
         zeta: F::Extension,
         g: F,
+        extra_row_offsets: &[isize],
         trace_commitment: &PolynomialBatch<F, C, D>,
+        preprocessed_commitment: Option<&PolynomialBatch<F, C, D>>,
         permutation_zs_commitment: Option<&PolynomialBatch<F, C, D>>,
         quotient_commitment: &PolynomialBatch<F, C, D>,
     ) -> Self {
@@ -152,6 +158,15 @@ impl<F: RichField + Extendable<D>, const D: usize> StarkOpeningSet<F, D> {
         Self {
             local_values: eval_commitment(zeta, trace_commitment),
             next_values: eval_commitment(zeta_next, trace_commitment),
+            extra_row_values: extra_row_offsets
+                .iter()
+                .map(|&offset| {
+                    eval_commitment(zeta.scalar_mul(row_shift(g, offset)), trace_commitment)
+                })
+                .collect(),
+            preprocessed_local_values: preprocessed_commitment.map(|c| eval_commitment(zeta, c)),
+            preprocessed_next_values: preprocessed_commitment
+                .map(|c| eval_commitment(zeta_next, c)),
             permutation_zs: permutation_zs_commitment.map(|c| eval_commitment(zeta, c)),
             permutation_zs_next: permutation_zs_commitment.map(|c| eval_commitment(zeta_next, c)),
             quotient_polys: eval_commitment(zeta, quotient_commitment),
@@ -163,6 +178,7 @@ impl<F: RichField + Extendable<D>, const D: usize> StarkOpeningSet<F, D> {
             values: self
                 .local_values
                 .iter()
+                .chain(self.preprocessed_local_values.iter().flatten())
                 .chain(self.permutation_zs.iter().flatten())
                 .chain(&self.quotient_polys)
                 .copied()
@@ -172,12 +188,19 @@ impl<F: RichField + Extendable<D>, const D: usize> StarkOpeningSet<F, D> {
             values: self
                 .next_values
                 .iter()
+                .chain(self.preprocessed_next_values.iter().flatten())
                 .chain(self.permutation_zs_next.iter().flatten())
                 .copied()
                 .collect_vec(),
         };
+        let extra_row_batches = self.extra_row_values.iter().map(|values| FriOpeningBatch {
+            values: values.clone(),
+        });
         FriOpenings {
-            batches: vec![zeta_batch, zeta_next_batch],
+            batches: [zeta_batch, zeta_next_batch]
+                .into_iter()
+                .chain(extra_row_batches)
+                .collect(),
         }
     }
 }
@@ -185,6 +208,9 @@ impl<F: RichField + Extendable<D>, const D: usize> StarkOpeningSet<F, D> {
 pub struct StarkOpeningSetTarget<const D: usize> {
     pub local_values: Vec<ExtensionTarget<D>>,
     pub next_values: Vec<ExtensionTarget<D>>,
+    pub extra_row_values: Vec<Vec<ExtensionTarget<D>>>,
+    pub preprocessed_local_values: Option<Vec<ExtensionTarget<D>>>,
+    pub preprocessed_next_values: Option<Vec<ExtensionTarget<D>>>,
     pub permutation_zs: Option<Vec<ExtensionTarget<D>>>,
     pub permutation_zs_next: Option<Vec<ExtensionTarget<D>>>,
     pub quotient_polys: Vec<ExtensionTarget<D>>,
@@ -196,6 +222,7 @@ impl<const D: usize> StarkOpeningSetTarget<D> {
             values: self
                 .local_values
                 .iter()
+                .chain(self.preprocessed_local_values.iter().flatten())
                 .chain(self.permutation_zs.iter().flatten())
                 .chain(&self.quotient_polys)
                 .copied()
@@ -205,12 +232,22 @@ impl<const D: usize> StarkOpeningSetTarget<D> {
             values: self
                 .next_values
                 .iter()
+                .chain(self.preprocessed_next_values.iter().flatten())
                 .chain(self.permutation_zs_next.iter().flatten())
                 .copied()
                 .collect_vec(),
         };
+        let extra_row_batches = self
+            .extra_row_values
+            .iter()
+            .map(|values| FriOpeningBatchTarget {
+                values: values.clone(),
+            });
         FriOpeningsTarget {
-            batches: vec![zeta_batch, zeta_next_batch],
+            batches: [zeta_batch, zeta_next_batch]
+                .into_iter()
+                .chain(extra_row_batches)
+                .collect(),
         }
     }
 }
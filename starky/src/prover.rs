@@ -21,12 +21,14 @@ use plonky2_maybe_rayon::*;
 use crate::config::StarkConfig;
 use crate::constraint_consumer::ConstraintConsumer;
 use crate::evaluation_frame::StarkEvaluationFrame;
+use crate::periodic::periodic_column_values;
 use crate::permutation::{
     compute_permutation_z_polys, get_n_permutation_challenge_sets, PermutationChallengeSet,
     PermutationCheckVars,
 };
+use crate::preprocessed::StarkProvingKey;
 use crate::proof::{StarkOpeningSet, StarkProof, StarkProofWithPublicInputs};
-use crate::stark::Stark;
+use crate::stark::{row_index, Stark};
 use crate::vanishing_poly::eval_vanishing_poly;
 
 pub fn prove<F, C, S, const D: usize>(
@@ -36,6 +38,33 @@ pub fn prove<F, C, S, const D: usize>(
     public_inputs: &[F],
     timing: &mut TimingTree,
 ) -> Result<StarkProofWithPublicInputs<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    S: Stark<F, D>,
+{
+    let degree_bits = log2_strict(trace_poly_values[0].len());
+    let proving_key = StarkProvingKey::new(&stark, config, degree_bits, timing);
+    prove_with_key(
+        stark,
+        &proving_key,
+        config,
+        trace_poly_values,
+        public_inputs,
+        timing,
+    )
+}
+
+/// Like `prove`, but with the preprocessed columns already committed in `proving_key`, which can
+/// be reused for all proofs with the same trace length.
+pub fn prove_with_key<F, C, S, const D: usize>(
+    stark: S,
+    proving_key: &StarkProvingKey<F, C, D>,
+    config: &StarkConfig,
+    trace_poly_values: Vec<PolynomialValues<F>>,
+    public_inputs: &[F],
+    timing: &mut TimingTree,
+) -> Result<StarkProofWithPublicInputs<F, C, D>>
 where
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -43,6 +72,12 @@ where
 {
     let degree = trace_poly_values[0].len();
     let degree_bits = log2_strict(degree);
+    ensure!(
+        proving_key.verifier_key.degree_bits == degree_bits,
+        "The proving key is for another trace length."
+    );
+    stark.check_row_offsets(degree_bits)?;
+    let preprocessed_commitment = proving_key.preprocessed_commitment.as_ref();
     let fri_params = config.fri_params(degree_bits);
     let rate_bits = config.fri_config.rate_bits;
     let cap_height = config.fri_config.cap_height;
@@ -68,6 +103,9 @@ where
 
     let trace_cap = trace_commitment.merkle_tree.cap.clone();
     let mut challenger = Challenger::new();
+    if let Some(cap) = &proving_key.verifier_key.preprocessed_cap {
+        challenger.observe_cap(cap);
+    }
     challenger.observe_cap(&trace_cap);
 
     // Permutation arguments.
@@ -112,6 +150,7 @@ where
     let quotient_polys = compute_quotient_polys::<F, <F as Packable>::Packing, C, S, D>(
         &stark,
         &trace_commitment,
+        preprocessed_commitment,
         &permutation_zs_commitment_challenges,
         public_inputs,
         alphas,
@@ -155,13 +194,16 @@ where
     let openings = StarkOpeningSet::new(
         zeta,
         g,
+        &stark.extra_row_offsets(),
         &trace_commitment,
+        preprocessed_commitment,
         permutation_zs_commitment,
         &quotient_commitment,
     );
     challenger.observe_openings(&openings.to_fri_openings());
 
     let initial_merkle_trees = once(&trace_commitment)
+        .chain(preprocessed_commitment)
         .chain(permutation_zs_commitment)
         .chain(once(&quotient_commitment))
         .collect_vec();
@@ -196,6 +238,7 @@ where
 fn compute_quotient_polys<'a, F, P, C, S, const D: usize>(
     stark: &S,
     trace_commitment: &'a PolynomialBatch<F, C, D>,
+    preprocessed_commitment: Option<&'a PolynomialBatch<F, C, D>>,
     permutation_zs_commitment_challenges: &'a Option<(
         PolynomialBatch<F, C, D>,
         Vec<PermutationChallengeSet<F>>,
@@ -222,6 +265,7 @@ where
     let step = 1 << (rate_bits - quotient_degree_bits);
     // When opening the `Z`s polys at the "next" point, need to look at the point `next_step` steps away.
     let next_step = 1 << quotient_degree_bits;
+    let extra_row_offsets = stark.extra_row_offsets();
 
     // Evaluation of the first Lagrange polynomial on the LDE domain.
     let lagrange_first = PolynomialValues::selector(degree, 0).lde_onto_coset(quotient_degree_bits);
@@ -229,11 +273,20 @@ where
     let lagrange_last =
         PolynomialValues::selector(degree, degree - 1).lde_onto_coset(quotient_degree_bits);
 
+    // Evaluations of the periodic columns on the LDE domain.
+    let periodic_ldes = periodic_column_values(&stark.periodic_columns(), degree_bits)
+        .into_iter()
+        .map(|values| values.lde_onto_coset(quotient_degree_bits))
+        .collect::<Vec<_>>();
+
     let z_h_on_coset = ZeroPolyOnCoset::<F>::new(degree_bits, quotient_degree_bits);
 
     // Retrieve the LDE values at index `i`.
     let get_trace_values_packed =
         |i_start| -> Vec<P> { trace_commitment.get_lde_values_packed(i_start, step) };
+    let get_preprocessed_values_packed = |i_start| -> Vec<P> {
+        preprocessed_commitment.map_or_else(Vec::new, |c| c.get_lde_values_packed(i_start, step))
+    };
 
     // Last element of the subgroup.
     let last = F::primitive_root_of_unity(degree_bits).inverse();
@@ -256,7 +309,11 @@ where
             let x = *P::from_slice(&coset[i_range.clone()]);
             let z_last = x - last;
             let lagrange_basis_first = *P::from_slice(&lagrange_first.values[i_range.clone()]);
-            let lagrange_basis_last = *P::from_slice(&lagrange_last.values[i_range]);
+            let lagrange_basis_last = *P::from_slice(&lagrange_last.values[i_range.clone()]);
+            let periodic_values = periodic_ldes
+                .iter()
+                .map(|lde| *P::from_slice(&lde.values[i_range.clone()]))
+                .collect::<Vec<_>>();
 
             let mut consumer = ConstraintConsumer::new(
                 alphas.clone(),
@@ -264,10 +321,22 @@ where
                 lagrange_basis_first,
                 lagrange_basis_last,
             );
-            let vars = S::EvaluationFrame::from_values(
+            let vars = S::EvaluationFrame::from_values_with_fixed(
                 &get_trace_values_packed(i_start),
                 &get_trace_values_packed(i_next_start),
+                &get_preprocessed_values_packed(i_start),
+                &get_preprocessed_values_packed(i_next_start),
+                &periodic_values,
                 public_inputs,
+            )
+            .with_extra_rows(
+                &extra_row_offsets
+                    .iter()
+                    .map(|&offset| {
+                        let row = row_index(offset, degree);
+                        get_trace_values_packed((i_start + row * next_step) % size)
+                    })
+                    .collect::<Vec<_>>(),
             );
             let permutation_check_data = permutation_zs_commitment_challenges.as_ref().map(
                 |(permutation_zs_commitment, permutation_challenge_sets)| PermutationCheckVars {
@@ -6,7 +6,7 @@ use itertools::Itertools;
 use plonky2::field::extension::Extendable;
 use plonky2::field::types::Field;
 use plonky2::fri::witness_util::set_fri_proof_target;
-use plonky2::hash::hash_types::RichField;
+use plonky2::hash::hash_types::{MerkleCapTarget, RichField};
 use plonky2::iop::ext_target::ExtensionTarget;
 use plonky2::iop::witness::Witness;
 use plonky2::plonk::circuit_builder::CircuitBuilder;
@@ -17,7 +17,9 @@ use plonky2::with_context;
 use crate::config::StarkConfig;
 use crate::constraint_consumer::RecursiveConstraintConsumer;
 use crate::evaluation_frame::StarkEvaluationFrame;
+use crate::periodic::eval_periodic_columns_circuit;
 use crate::permutation::PermutationCheckDataTarget;
+use crate::preprocessed::StarkVerifierKey;
 use crate::proof::{
     StarkOpeningSetTarget, StarkProof, StarkProofChallengesTarget, StarkProofTarget,
     StarkProofWithPublicInputs, StarkProofWithPublicInputsTarget,
@@ -37,18 +39,59 @@ pub fn verify_stark_proof_circuit<
     inner_config: &StarkConfig,
 ) where
     C::Hasher: AlgebraicHasher<F>,
+{
+    let degree_bits = proof_with_pis.proof.recover_degree_bits(inner_config);
+    let verifier_key = StarkVerifierKey::<F, C, D>::new(&stark, inner_config, degree_bits);
+    verify_stark_proof_circuit_with_key(
+        builder,
+        stark,
+        &verifier_key,
+        proof_with_pis,
+        inner_config,
+    );
+}
+
+/// Like `verify_stark_proof_circuit`, but with the preprocessed columns already committed in
+/// `verifier_key`, whose cap is a constant of the circuit.
+pub fn verify_stark_proof_circuit_with_key<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    S: Stark<F, D>,
+    const D: usize,
+>(
+    builder: &mut CircuitBuilder<F, D>,
+    stark: S,
+    verifier_key: &StarkVerifierKey<F, C, D>,
+    proof_with_pis: StarkProofWithPublicInputsTarget<D>,
+    inner_config: &StarkConfig,
+) where
+    C::Hasher: AlgebraicHasher<F>,
 {
     assert_eq!(proof_with_pis.public_inputs.len(), S::PUBLIC_INPUTS);
     let degree_bits = proof_with_pis.proof.recover_degree_bits(inner_config);
+    assert_eq!(
+        degree_bits, verifier_key.degree_bits,
+        "The verifier key is for another trace length."
+    );
+    let preprocessed_cap = verifier_key
+        .preprocessed_cap
+        .as_ref()
+        .map(|cap| builder.constant_merkle_cap(cap));
     let challenges = with_context!(
         builder,
         "compute challenges",
-        proof_with_pis.get_challenges::<F, C, S>(builder, &stark, inner_config)
+        proof_with_pis.get_challenges::<F, C, S>(
+            builder,
+            &stark,
+            preprocessed_cap.as_ref(),
+            inner_config
+        )
     );
 
     verify_stark_proof_with_challenges_circuit::<F, C, S, D>(
         builder,
         stark,
+        preprocessed_cap,
         proof_with_pis,
         challenges,
         inner_config,
@@ -65,6 +108,7 @@ fn verify_stark_proof_with_challenges_circuit<
 >(
     builder: &mut CircuitBuilder<F, D>,
     stark: S,
+    preprocessed_cap: Option<MerkleCapTarget>,
     proof_with_pis: StarkProofWithPublicInputsTarget<D>,
     challenges: StarkProofChallengesTarget<D>,
     inner_config: &StarkConfig,
@@ -82,19 +126,32 @@ fn verify_stark_proof_with_challenges_circuit<
     let StarkOpeningSetTarget {
         local_values,
         next_values,
+        extra_row_values,
+        preprocessed_local_values,
+        preprocessed_next_values,
         permutation_zs,
         permutation_zs_next,
         quotient_polys,
     } = &proof.openings;
 
-    let vars = S::EvaluationFrameTarget::from_values(
+    let periodic_values = eval_periodic_columns_circuit(
+        builder,
+        &stark.periodic_columns(),
+        challenges.stark_zeta,
+        degree_bits,
+    );
+    let vars = S::EvaluationFrameTarget::from_values_with_fixed(
         local_values,
         next_values,
+        preprocessed_local_values.as_deref().unwrap_or_default(),
+        preprocessed_next_values.as_deref().unwrap_or_default(),
+        &periodic_values,
         &public_inputs
             .into_iter()
             .map(|t| builder.convert_to_ext(t))
             .collect::<Vec<_>>(),
-    );
+    )
+    .with_extra_rows(extra_row_values);
 
     let zeta_pow_deg = builder.exp_power_of_2_extension(challenges.stark_zeta, degree_bits);
     let z_h_zeta = builder.sub_extension(zeta_pow_deg, one);
@@ -146,6 +203,7 @@ fn verify_stark_proof_with_challenges_circuit<
     }
 
     let merkle_caps = once(proof.trace_cap)
+        .chain(preprocessed_cap)
         .chain(proof.permutation_zs_cap)
         .chain(once(proof.quotient_polys_cap))
         .collect_vec();
@@ -209,10 +267,18 @@ pub fn add_virtual_stark_proof<F: RichField + Extendable<D>, S: Stark<F, D>, con
     config: &StarkConfig,
     degree_bits: usize,
 ) -> StarkProofTarget<D> {
+    stark
+        .check_row_offsets(degree_bits)
+        .expect("Invalid row offsets");
     let fri_params = config.fri_params(degree_bits);
     let cap_height = fri_params.config.cap_height;
 
     let num_leaves_per_oracle = once(S::COLUMNS)
+        .chain(
+            stark
+                .uses_preprocessed_columns()
+                .then_some(S::PREPROCESSED_COLUMNS),
+        )
         .chain(
             stark
                 .uses_permutation_args()
@@ -243,6 +309,17 @@ fn add_stark_opening_set_target<F: RichField + Extendable<D>, S: Stark<F, D>, co
     StarkOpeningSetTarget {
         local_values: builder.add_virtual_extension_targets(S::COLUMNS),
         next_values: builder.add_virtual_extension_targets(S::COLUMNS),
+        extra_row_values: stark
+            .extra_row_offsets()
+            .iter()
+            .map(|_| builder.add_virtual_extension_targets(S::COLUMNS))
+            .collect(),
+        preprocessed_local_values: stark
+            .uses_preprocessed_columns()
+            .then(|| builder.add_virtual_extension_targets(S::PREPROCESSED_COLUMNS)),
+        preprocessed_next_values: stark
+            .uses_preprocessed_columns()
+            .then(|| builder.add_virtual_extension_targets(S::PREPROCESSED_COLUMNS)),
         permutation_zs: stark
             .uses_permutation_args()
             .then(|| builder.add_virtual_extension_targets(stark.num_permutation_batches(config))),
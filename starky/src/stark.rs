@@ -1,8 +1,11 @@
 use alloc::vec;
 use alloc::vec::Vec;
 
+use anyhow::{ensure, Result};
 use plonky2::field::extension::{Extendable, FieldExtension};
 use plonky2::field::packed::PackedField;
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::field::types::Field;
 use plonky2::fri::structure::{
     FriBatchInfo, FriBatchInfoTarget, FriInstanceInfo, FriInstanceInfoTarget, FriOracleInfo,
     FriPolynomialInfo,
@@ -22,6 +25,10 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
     /// The total number of columns in the trace.
     const COLUMNS: usize = Self::EvaluationFrameTarget::COLUMNS;
     const PUBLIC_INPUTS: usize = Self::EvaluationFrameTarget::PUBLIC_INPUTS;
+    /// The number of preprocessed columns, see `preprocessed_columns`.
+    const PREPROCESSED_COLUMNS: usize = Self::EvaluationFrameTarget::PREPROCESSED_COLUMNS;
+    /// The number of periodic columns, see `periodic_columns`.
+    const PERIODIC_COLUMNS: usize = Self::EvaluationFrameTarget::PERIODIC_COLUMNS;
 
     /// This is used to evaluate constraints natively.
     type EvaluationFrame<FE, P, const D2: usize>: StarkEvaluationFrame<P, FE>
@@ -102,6 +109,17 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
             blinding: false,
         });
 
+        let preprocessed_info = if self.uses_preprocessed_columns() {
+            let polys = FriPolynomialInfo::from_range(oracles.len(), 0..Self::PREPROCESSED_COLUMNS);
+            oracles.push(FriOracleInfo {
+                num_polys: Self::PREPROCESSED_COLUMNS,
+                blinding: false,
+            });
+            polys
+        } else {
+            vec![]
+        };
+
         let permutation_zs_info = if self.uses_permutation_args() {
             let num_z_polys = self.num_permutation_batches(config);
             let polys = FriPolynomialInfo::from_range(oracles.len(), 0..num_z_polys);
@@ -125,6 +143,7 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
             point: zeta,
             polynomials: [
                 trace_info.clone(),
+                preprocessed_info.clone(),
                 permutation_zs_info.clone(),
                 quotient_info,
             ]
@@ -132,9 +151,18 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
         };
         let zeta_next_batch = FriBatchInfo {
             point: zeta.scalar_mul(g),
-            polynomials: [trace_info, permutation_zs_info].concat(),
+            polynomials: [trace_info.clone(), preprocessed_info, permutation_zs_info].concat(),
         };
-        let batches = vec![zeta_batch, zeta_next_batch];
+        let mut batches = vec![zeta_batch, zeta_next_batch];
+        // Only the trace is opened at the extra rows.
+        batches.extend(
+            self.extra_row_offsets()
+                .into_iter()
+                .map(|offset| FriBatchInfo {
+                    point: zeta.scalar_mul(row_shift(g, offset)),
+                    polynomials: trace_info.clone(),
+                }),
+        );
 
         FriInstanceInfo { oracles, batches }
     }
@@ -155,6 +183,17 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
             blinding: false,
         });
 
+        let preprocessed_info = if self.uses_preprocessed_columns() {
+            let polys = FriPolynomialInfo::from_range(oracles.len(), 0..Self::PREPROCESSED_COLUMNS);
+            oracles.push(FriOracleInfo {
+                num_polys: Self::PREPROCESSED_COLUMNS,
+                blinding: false,
+            });
+            polys
+        } else {
+            vec![]
+        };
+
         let permutation_zs_info = if self.uses_permutation_args() {
             let num_z_polys = self.num_permutation_batches(config);
             let polys = FriPolynomialInfo::from_range(oracles.len(), 0..num_z_polys);
@@ -178,6 +217,7 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
             point: zeta,
             polynomials: [
                 trace_info.clone(),
+                preprocessed_info.clone(),
                 permutation_zs_info.clone(),
                 quotient_info,
             ]
@@ -186,13 +226,95 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
         let zeta_next = builder.mul_const_extension(g, zeta);
         let zeta_next_batch = FriBatchInfoTarget {
             point: zeta_next,
-            polynomials: [trace_info, permutation_zs_info].concat(),
+            polynomials: [trace_info.clone(), preprocessed_info, permutation_zs_info].concat(),
         };
-        let batches = vec![zeta_batch, zeta_next_batch];
+        let mut batches = vec![zeta_batch, zeta_next_batch];
+        // Only the trace is opened at the extra rows.
+        for offset in self.extra_row_offsets() {
+            let point = builder.mul_const_extension(row_shift(g, offset), zeta);
+            batches.push(FriBatchInfoTarget {
+                point,
+                polynomials: trace_info.clone(),
+            });
+        }
 
         FriInstanceInfoTarget { oracles, batches }
     }
 
+    /// The offsets of the trace rows that constraints are evaluated on, relative to the current row.
+    /// These must start with `0` and `1`, i.e. the local and next rows, and be distinct. Negative
+    /// offsets refer to previous rows, e.g. `-1` to row `i - 1`. The rows at the offsets after the
+    /// first two are accessed through [`StarkEvaluationFrame::get_row_values`], in the order they
+    /// are listed, so a STARK declaring more than two offsets needs an evaluation frame holding
+    /// that many rows, e.g. a [`StarkFrame`](crate::evaluation_frame::StarkFrame) with `R` set to
+    /// the number of offsets.
+    ///
+    /// The trace wraps around, so transition constraints reading a row past the last one, or
+    /// before the first one, see the rows at the other end again. Such constraints should be
+    /// disabled near the ends of the trace, e.g. with a preprocessed or periodic selector column.
+    /// Offsets must be smaller than the trace length in absolute value, and distinct modulo it,
+    /// see `check_row_offsets`.
+    fn row_offsets(&self) -> Vec<isize> {
+        vec![0, 1]
+    }
+
+    /// The offsets of `row_offsets` after the local and next rows, at which the trace is opened in
+    /// addition to `zeta` and `g * zeta`.
+    fn extra_row_offsets(&self) -> Vec<isize> {
+        let offsets = self.row_offsets();
+        assert!(
+            offsets.starts_with(&[0, 1]),
+            "Row offsets should start with the local and next rows"
+        );
+        assert!(
+            offsets
+                .iter()
+                .enumerate()
+                .all(|(i, offset)| !offsets[..i].contains(offset)),
+            "Row offsets should be distinct"
+        );
+        offsets[2..].to_vec()
+    }
+
+    /// Checks that `row_offsets` are valid for a trace of `2^degree_bits` rows, i.e. that they
+    /// are smaller than its length in absolute value and don't refer to the same row.
+    fn check_row_offsets(&self, degree_bits: usize) -> Result<()> {
+        let degree = 1 << degree_bits;
+        let mut rows = vec![0, 1];
+        for offset in self.extra_row_offsets() {
+            ensure!(
+                offset.unsigned_abs() < degree,
+                "Row offset {offset} isn't smaller than the trace length {degree}"
+            );
+            let row = row_index(offset, degree);
+            ensure!(
+                !rows.contains(&row),
+                "Row offset {offset} refers to the same row as another one in a trace of length \
+                 {degree}"
+            );
+            rows.push(row);
+        }
+        Ok(())
+    }
+
+    /// The values of the preprocessed columns for a trace of `num_rows` rows. These don't depend on
+    /// the witness, so they are committed once in the verifier key, see
+    /// [`StarkProvingKey`](crate::preprocessed::StarkProvingKey). Empty by default.
+    fn preprocessed_columns(&self, _num_rows: usize) -> Vec<PolynomialValues<F>> {
+        vec![]
+    }
+
+    /// The values of the periodic columns over one period each. Each period must be a power of two
+    /// no larger than the trace, which repeats the values. The verifier evaluates these columns
+    /// itself, in time linear in the period. Empty by default.
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        vec![]
+    }
+
+    fn uses_preprocessed_columns(&self) -> bool {
+        Self::PREPROCESSED_COLUMNS > 0
+    }
+
     /// Pairs of lists of columns that should be permutations of one another. A permutation argument
     /// will be used for each such pair. Empty by default.
     fn permutation_pairs(&self) -> Vec<PermutationPair> {
@@ -223,3 +345,18 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
         )
     }
 }
+
+/// The index of the row at `offset` from row `0` in a trace of `degree` rows, which wraps around.
+pub(crate) fn row_index(offset: isize, degree: usize) -> usize {
+    offset.rem_euclid(degree as isize) as usize
+}
+
+/// The power of the trace domain's generator `g` shifting a point by `offset` rows.
+pub(crate) fn row_shift<F: Field>(g: F, offset: isize) -> F {
+    let shift = g.exp_u64(offset.unsigned_abs() as u64);
+    if offset < 0 {
+        shift.inverse()
+    } else {
+        shift
+    }
+}
@@ -14,7 +14,7 @@ use plonky2::util::{log2_ceil, log2_strict, transpose};
 
 use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
 use crate::evaluation_frame::StarkEvaluationFrame;
-use crate::stark::Stark;
+use crate::stark::{row_index, Stark};
 
 const WITNESS_SIZE: usize = 1 << 5;
 
@@ -25,7 +25,12 @@ pub fn test_stark_low_degree<F: RichField + Extendable<D>, S: Stark<F, D>, const
 ) -> Result<()> {
     let rate_bits = log2_ceil(stark.constraint_degree() + 1);
 
-    let trace_ldes = random_low_degree_matrix::<F>(S::COLUMNS, rate_bits);
+    // The preprocessed and periodic columns are low-degree too, so we sample them along with the
+    // trace.
+    let trace_ldes = random_low_degree_matrix::<F>(
+        S::COLUMNS + S::PREPROCESSED_COLUMNS + S::PERIODIC_COLUMNS,
+        rate_bits,
+    );
     let size = trace_ldes.len();
     let public_inputs = F::rand_vec(S::PUBLIC_INPUTS);
 
@@ -36,12 +41,30 @@ pub fn test_stark_low_degree<F: RichField + Extendable<D>, S: Stark<F, D>, const
     let subgroup =
         F::cyclic_subgroup_known_order(F::primitive_root_of_unity(log2_strict(size)), size);
     let alpha = F::rand();
+    let extra_row_offsets = stark.extra_row_offsets();
     let constraint_evals = (0..size)
         .map(|i| {
-            let vars = S::EvaluationFrame::from_values(
-                &trace_ldes[i],
-                &trace_ldes[(i + (1 << rate_bits)) % size],
+            let (local_values, local_fixed) = trace_ldes[i].split_at(S::COLUMNS);
+            let (preprocessed_local_values, periodic_values) =
+                local_fixed.split_at(S::PREPROCESSED_COLUMNS);
+            let next_row = &trace_ldes[(i + (1 << rate_bits)) % size];
+            let (next_values, next_fixed) = next_row.split_at(S::COLUMNS);
+            let vars = S::EvaluationFrame::from_values_with_fixed(
+                local_values,
+                next_values,
+                preprocessed_local_values,
+                &next_fixed[..S::PREPROCESSED_COLUMNS],
+                periodic_values,
                 &public_inputs,
+            )
+            .with_extra_rows(
+                &extra_row_offsets
+                    .iter()
+                    .map(|&offset| {
+                        let row = row_index(offset, WITNESS_SIZE);
+                        trace_ldes[(i + (row << rate_bits)) % size][..S::COLUMNS].to_vec()
+                    })
+                    .collect::<Vec<_>>(),
             );
 
             let mut consumer = ConstraintConsumer::<F>::new(
@@ -80,10 +103,20 @@ pub fn test_stark_circuit_constraints<
     stark: S,
 ) -> Result<()> {
     // Compute native constraint evaluation on random values.
-    let vars = S::EvaluationFrame::from_values(
+    let vars = S::EvaluationFrame::from_values_with_fixed(
         &F::Extension::rand_vec(S::COLUMNS),
         &F::Extension::rand_vec(S::COLUMNS),
+        &F::Extension::rand_vec(S::PREPROCESSED_COLUMNS),
+        &F::Extension::rand_vec(S::PREPROCESSED_COLUMNS),
+        &F::Extension::rand_vec(S::PERIODIC_COLUMNS),
         &F::Extension::rand_vec(S::PUBLIC_INPUTS),
+    )
+    .with_extra_rows(
+        &stark
+            .extra_row_offsets()
+            .iter()
+            .map(|_| F::Extension::rand_vec(S::COLUMNS))
+            .collect::<Vec<_>>(),
     );
     let alphas = F::rand_vec(1);
     let z_last = F::Extension::rand();
@@ -110,6 +143,19 @@ pub fn test_stark_circuit_constraints<
     pw.set_extension_targets(&locals_t, vars.get_local_values());
     let nexts_t = builder.add_virtual_extension_targets(S::COLUMNS);
     pw.set_extension_targets(&nexts_t, vars.get_next_values());
+    let extra_rows_t = (2..stark.row_offsets().len())
+        .map(|index| {
+            let row_t = builder.add_virtual_extension_targets(S::COLUMNS);
+            pw.set_extension_targets(&row_t, vars.get_row_values(index));
+            row_t
+        })
+        .collect::<Vec<_>>();
+    let preprocessed_locals_t = builder.add_virtual_extension_targets(S::PREPROCESSED_COLUMNS);
+    pw.set_extension_targets(&preprocessed_locals_t, vars.get_preprocessed_local_values());
+    let preprocessed_nexts_t = builder.add_virtual_extension_targets(S::PREPROCESSED_COLUMNS);
+    pw.set_extension_targets(&preprocessed_nexts_t, vars.get_preprocessed_next_values());
+    let periodics_t = builder.add_virtual_extension_targets(S::PERIODIC_COLUMNS);
+    pw.set_extension_targets(&periodics_t, vars.get_periodic_values());
     let pis_t = builder.add_virtual_extension_targets(S::PUBLIC_INPUTS);
     pw.set_extension_targets(&pis_t, vars.get_public_inputs());
     let alphas_t = builder.add_virtual_targets(1);
@@ -121,7 +167,15 @@ pub fn test_stark_circuit_constraints<
     let lagrange_last_t = builder.add_virtual_extension_target();
     pw.set_extension_target(lagrange_last_t, lagrange_last);
 
-    let vars = S::EvaluationFrameTarget::from_values(&locals_t, &nexts_t, &pis_t);
+    let vars = S::EvaluationFrameTarget::from_values_with_fixed(
+        &locals_t,
+        &nexts_t,
+        &preprocessed_locals_t,
+        &preprocessed_nexts_t,
+        &periodics_t,
+        &pis_t,
+    )
+    .with_extra_rows(&extra_rows_t);
     let mut consumer = RecursiveConstraintConsumer::<F, D>::new(
         builder.zero_extension(),
         alphas_t,
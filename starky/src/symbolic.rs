@@ -0,0 +1,331 @@
+//! A symbolic stand-in for packed field elements, used to analyze constraints, see
+//! [`analyze_constraints`](crate::constraint_analysis::analyze_constraints).
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::{Debug, Formatter};
+use core::iter::{Product, Sum};
+use core::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::field::packed::PackedField;
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::field::types::Field;
+
+/// The log of [`SYMBOLIC_WIDTH`].
+const SYMBOLIC_WIDTH_BITS: usize = 5;
+
+/// The number of lanes of a [`SymbolicValue`].
+pub const SYMBOLIC_WIDTH: usize = 1 << SYMBOLIC_WIDTH_BITS;
+
+/// A polynomial in a formal variable `t`, of degree less than [`SYMBOLIC_WIDTH`], stored as its
+/// evaluations at the powers of a primitive `SYMBOLIC_WIDTH`-th root of unity, one per lane.
+///
+/// Evaluating constraints on values of the form `a + b t`, with random `a` and `b`, for the
+/// variables of interest, and on random constants for the others, yields polynomials whose degrees
+/// are, with overwhelming probability, the degrees of the constraints in the variables of interest.
+///
+/// The arithmetic is lane-wise, as for any [`PackedField`], so that constraints can be evaluated
+/// with the regular `eval_packed_generic`: multiplying evaluations multiplies the polynomials. A
+/// polynomial of degree `SYMBOLIC_WIDTH` or more can't be represented, and is reduced modulo
+/// `t^SYMBOLIC_WIDTH - 1`.
+#[derive(Copy, Clone)]
+#[repr(transparent)]
+pub struct SymbolicValue<F: Field>(pub [F; SYMBOLIC_WIDTH]);
+
+impl<F: Field> SymbolicValue<F> {
+    pub fn constant(c: F) -> Self {
+        Self([c; SYMBOLIC_WIDTH])
+    }
+
+    /// The polynomial `a + b t`.
+    pub fn linear(a: F, b: F) -> Self {
+        let points = F::cyclic_subgroup_known_order(
+            F::primitive_root_of_unity(SYMBOLIC_WIDTH_BITS),
+            SYMBOLIC_WIDTH,
+        );
+        let mut values = [a; SYMBOLIC_WIDTH];
+        for (v, t) in values.iter_mut().zip(points) {
+            *v += b * t;
+        }
+        Self(values)
+    }
+
+    /// The coefficients of the polynomial, in increasing degree order.
+    pub fn coeffs(&self) -> Vec<F> {
+        PolynomialValues::new(self.0.to_vec()).ifft().coeffs
+    }
+
+    /// The degree in `t`, or `None` for the zero polynomial.
+    pub fn degree(&self) -> Option<usize> {
+        self.coeffs().iter().rposition(|c| c.is_nonzero())
+    }
+}
+
+impl<F: Field> Add<Self> for SymbolicValue<F> {
+    type Output = Self;
+    fn add(mut self, rhs: Self) -> Self {
+        self += rhs;
+        self
+    }
+}
+impl<F: Field> Add<F> for SymbolicValue<F> {
+    type Output = Self;
+    fn add(mut self, rhs: F) -> Self {
+        self += rhs;
+        self
+    }
+}
+impl<F: Field> AddAssign<Self> for SymbolicValue<F> {
+    fn add_assign(&mut self, rhs: Self) {
+        for (l, r) in self.0.iter_mut().zip(rhs.0) {
+            *l += r;
+        }
+    }
+}
+impl<F: Field> AddAssign<F> for SymbolicValue<F> {
+    fn add_assign(&mut self, rhs: F) {
+        for l in &mut self.0 {
+            *l += rhs;
+        }
+    }
+}
+
+impl<F: Field> Debug for SymbolicValue<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let coeffs = self.coeffs();
+        let mut terms = coeffs
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.is_nonzero())
+            .peekable();
+        if terms.peek().is_none() {
+            return write!(f, "0");
+        }
+        for (i, (pow, c)) in terms.enumerate() {
+            if i > 0 {
+                write!(f, " + ")?;
+            }
+            match pow {
+                0 => write!(f, "{c}")?,
+                1 => write!(f, "{c}*t")?,
+                _ => write!(f, "{c}*t^{pow}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<F: Field> Default for SymbolicValue<F> {
+    fn default() -> Self {
+        Self::constant(F::ZERO)
+    }
+}
+
+impl<F: Field> Div<F> for SymbolicValue<F> {
+    type Output = Self;
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: F) -> Self {
+        self * rhs.inverse()
+    }
+}
+
+impl<F: Field> From<F> for SymbolicValue<F> {
+    fn from(c: F) -> Self {
+        Self::constant(c)
+    }
+}
+
+impl<F: Field> Mul<Self> for SymbolicValue<F> {
+    type Output = Self;
+    fn mul(mut self, rhs: Self) -> Self {
+        self *= rhs;
+        self
+    }
+}
+impl<F: Field> Mul<F> for SymbolicValue<F> {
+    type Output = Self;
+    fn mul(mut self, rhs: F) -> Self {
+        self *= rhs;
+        self
+    }
+}
+impl<F: Field> MulAssign<Self> for SymbolicValue<F> {
+    fn mul_assign(&mut self, rhs: Self) {
+        for (l, r) in self.0.iter_mut().zip(rhs.0) {
+            *l *= r;
+        }
+    }
+}
+impl<F: Field> MulAssign<F> for SymbolicValue<F> {
+    fn mul_assign(&mut self, rhs: F) {
+        for c in &mut self.0 {
+            *c *= rhs;
+        }
+    }
+}
+
+impl<F: Field> Neg for SymbolicValue<F> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self(self.0.map(|c| -c))
+    }
+}
+
+impl<F: Field> Product for SymbolicValue<F> {
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::constant(F::ONE), |acc, x| acc * x)
+    }
+}
+
+impl<F: Field> Sub<Self> for SymbolicValue<F> {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+impl<F: Field> Sub<F> for SymbolicValue<F> {
+    type Output = Self;
+    fn sub(self, rhs: F) -> Self {
+        self + -rhs
+    }
+}
+impl<F: Field> SubAssign<Self> for SymbolicValue<F> {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+impl<F: Field> SubAssign<F> for SymbolicValue<F> {
+    fn sub_assign(&mut self, rhs: F) {
+        *self = *self - rhs;
+    }
+}
+
+impl<F: Field> Sum for SymbolicValue<F> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |acc, x| acc + x)
+    }
+}
+
+unsafe impl<F: Field> PackedField for SymbolicValue<F>
+where
+    F: Add<Self, Output = Self> + Mul<Self, Output = Self> + Sub<Self, Output = Self>,
+{
+    type Scalar = F;
+
+    const WIDTH: usize = SYMBOLIC_WIDTH;
+    const ZEROS: Self = Self([F::ZERO; SYMBOLIC_WIDTH]);
+    const ONES: Self = Self([F::ONE; SYMBOLIC_WIDTH]);
+
+    fn from_slice(slice: &[Self::Scalar]) -> &Self {
+        assert_eq!(slice.len(), Self::WIDTH);
+        unsafe { &*slice.as_ptr().cast() }
+    }
+    fn from_slice_mut(slice: &mut [Self::Scalar]) -> &mut Self {
+        assert_eq!(slice.len(), Self::WIDTH);
+        unsafe { &mut *slice.as_mut_ptr().cast() }
+    }
+    fn as_slice(&self) -> &[Self::Scalar] {
+        &self.0[..]
+    }
+    fn as_slice_mut(&mut self) -> &mut [Self::Scalar] {
+        &mut self.0[..]
+    }
+
+    fn interleave(&self, other: Self, block_len: usize) -> (Self, Self) {
+        assert!(
+            block_len.is_power_of_two() && block_len <= Self::WIDTH,
+            "unsupported block length"
+        );
+        if block_len == Self::WIDTH {
+            return (*self, other);
+        }
+        let mut res = [Self::ZEROS; 2];
+        let blocks = self.0.chunks(block_len).zip(other.0.chunks(block_len));
+        for (i, (a, b)) in blocks.enumerate() {
+            let start = (i / 2) * 2 * block_len;
+            let res = &mut res[i % 2].0[start..start + 2 * block_len];
+            res[..block_len].copy_from_slice(a);
+            res[block_len..].copy_from_slice(b);
+        }
+        (res[0], res[1])
+    }
+}
+
+/// Implements the arithmetic with a scalar on the left, which can't be done generically.
+macro_rules! impl_scalar_ops {
+    ($field:ty) => {
+        impl Add<SymbolicValue<$field>> for $field {
+            type Output = SymbolicValue<$field>;
+            fn add(self, rhs: Self::Output) -> Self::Output {
+                rhs + self
+            }
+        }
+        impl Mul<SymbolicValue<$field>> for $field {
+            type Output = SymbolicValue<$field>;
+            fn mul(self, rhs: Self::Output) -> Self::Output {
+                rhs * self
+            }
+        }
+        impl Sub<SymbolicValue<$field>> for $field {
+            type Output = SymbolicValue<$field>;
+            fn sub(self, rhs: Self::Output) -> Self::Output {
+                -rhs + self
+            }
+        }
+    };
+}
+
+impl_scalar_ops!(GoldilocksField);
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::field::packed::PackedField;
+    use plonky2::field::types::{Field, Sample};
+
+    use crate::symbolic::{SymbolicValue, SYMBOLIC_WIDTH};
+
+    type F = GoldilocksField;
+
+    #[test]
+    fn test_symbolic_degree() {
+        let x = SymbolicValue::linear(F::rand(), F::rand());
+        let y = SymbolicValue::linear(F::rand(), F::rand());
+        assert_eq!((x * y * x + y).degree(), Some(3));
+        assert_eq!((F::TWO - x * y).degree(), Some(2));
+        // Cancelling terms don't count towards the degree.
+        assert_eq!((x * x - x * x + y).degree(), Some(1));
+        assert_eq!((x - x).degree(), None);
+    }
+
+    #[test]
+    fn test_symbolic_arithmetic_is_lane_wise() {
+        let x = SymbolicValue::linear(F::rand(), F::rand());
+        let y = SymbolicValue::linear(F::rand(), F::rand());
+        let c = F::rand();
+        let z = x * y - c * x + F::ONE;
+        for i in 0..SYMBOLIC_WIDTH {
+            assert_eq!(z.0[i], x.0[i] * y.0[i] - c * x.0[i] + F::ONE);
+        }
+        assert_eq!(z.coeffs().len(), SYMBOLIC_WIDTH);
+    }
+
+    #[test]
+    fn test_symbolic_interleave() {
+        let a = SymbolicValue(F::rand_array());
+        let b = SymbolicValue(F::rand_array());
+        let mut block_len = 1;
+        while block_len <= SYMBOLIC_WIDTH {
+            let (x, y) = a.interleave(b, block_len);
+            if block_len < SYMBOLIC_WIDTH {
+                // Blocks of `a` and `b` at the same position end up next to one another.
+                assert_eq!(x.0[block_len..2 * block_len], b.0[..block_len]);
+            }
+            let (res_a, res_b) = x.interleave(y, block_len);
+            assert_eq!(res_a.as_slice(), a.as_slice());
+            assert_eq!(res_b.as_slice(), b.as_slice());
+            block_len *= 2;
+        }
+    }
+}
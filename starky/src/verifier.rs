@@ -13,11 +13,16 @@ use plonky2::plonk::plonk_common::reduce_with_powers;
 use crate::config::StarkConfig;
 use crate::constraint_consumer::ConstraintConsumer;
 use crate::evaluation_frame::StarkEvaluationFrame;
+use crate::periodic::eval_periodic_columns;
 use crate::permutation::PermutationCheckVars;
+use crate::preprocessed::StarkVerifierKey;
 use crate::proof::{StarkOpeningSet, StarkProof, StarkProofChallenges, StarkProofWithPublicInputs};
 use crate::stark::Stark;
 use crate::vanishing_poly::eval_vanishing_poly;
 
+/// Verifies a proof of `stark`. If the stark has preprocessed columns, this commits to them on
+/// every call, which is as costly as a part of proving; callers verifying several proofs should
+/// build a `StarkVerifierKey` once and use `verify_stark_proof_with_key` instead.
 pub fn verify_stark_proof<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -27,11 +32,46 @@ pub fn verify_stark_proof<
     stark: S,
     proof_with_pis: StarkProofWithPublicInputs<F, C, D>,
     config: &StarkConfig,
+) -> Result<()> {
+    let degree_bits = proof_with_pis.proof.recover_degree_bits(config);
+    let verifier_key = StarkVerifierKey::new(&stark, config, degree_bits);
+    verify_stark_proof_with_key(stark, &verifier_key, proof_with_pis, config)
+}
+
+/// Like `verify_stark_proof`, but with the preprocessed columns already committed in
+/// `verifier_key`. Without preprocessed columns, this is no faster.
+pub fn verify_stark_proof_with_key<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    S: Stark<F, D>,
+    const D: usize,
+>(
+    stark: S,
+    verifier_key: &StarkVerifierKey<F, C, D>,
+    proof_with_pis: StarkProofWithPublicInputs<F, C, D>,
+    config: &StarkConfig,
 ) -> Result<()> {
     ensure!(proof_with_pis.public_inputs.len() == S::PUBLIC_INPUTS);
     let degree_bits = proof_with_pis.proof.recover_degree_bits(config);
-    let challenges = proof_with_pis.get_challenges(&stark, config, degree_bits);
-    verify_stark_proof_with_challenges(stark, proof_with_pis, challenges, degree_bits, config)
+    ensure!(
+        degree_bits == verifier_key.degree_bits,
+        "The verifier key is for another trace length."
+    );
+    stark.check_row_offsets(degree_bits)?;
+    let challenges = proof_with_pis.get_challenges(
+        &stark,
+        verifier_key.preprocessed_cap.as_ref(),
+        config,
+        degree_bits,
+    );
+    verify_stark_proof_with_challenges(
+        stark,
+        verifier_key,
+        proof_with_pis,
+        challenges,
+        degree_bits,
+        config,
+    )
 }
 
 pub(crate) fn verify_stark_proof_with_challenges<
@@ -41,12 +81,13 @@ pub(crate) fn verify_stark_proof_with_challenges<
     const D: usize,
 >(
     stark: S,
+    verifier_key: &StarkVerifierKey<F, C, D>,
     proof_with_pis: StarkProofWithPublicInputs<F, C, D>,
     challenges: StarkProofChallenges<F, D>,
     degree_bits: usize,
     config: &StarkConfig,
 ) -> Result<()> {
-    validate_proof_shape(&stark, &proof_with_pis, config)?;
+    validate_proof_shape(&stark, verifier_key, &proof_with_pis, config)?;
     check_permutation_options(&stark, &proof_with_pis, &challenges)?;
     let StarkProofWithPublicInputs {
         proof,
@@ -55,19 +96,31 @@ pub(crate) fn verify_stark_proof_with_challenges<
     let StarkOpeningSet {
         local_values,
         next_values,
+        extra_row_values,
+        preprocessed_local_values,
+        preprocessed_next_values,
         permutation_zs,
         permutation_zs_next,
         quotient_polys,
     } = &proof.openings;
-    let vars = S::EvaluationFrame::from_values(
+    let periodic_values = eval_periodic_columns(
+        &stark.periodic_columns(),
+        challenges.stark_zeta,
+        degree_bits,
+    );
+    let vars = S::EvaluationFrame::from_values_with_fixed(
         local_values,
         next_values,
+        preprocessed_local_values.as_deref().unwrap_or_default(),
+        preprocessed_next_values.as_deref().unwrap_or_default(),
+        &periodic_values,
         &public_inputs
             .iter()
             .copied()
             .map(F::Extension::from_basefield)
             .collect::<Vec<_>>(),
-    );
+    )
+    .with_extra_rows(extra_row_values);
     let (l_0, l_last) = eval_l_0_and_l_last(degree_bits, challenges.stark_zeta);
     let last = F::primitive_root_of_unity(degree_bits).inverse();
     let z_last = challenges.stark_zeta - last.into();
@@ -114,6 +167,7 @@ pub(crate) fn verify_stark_proof_with_challenges<
     }
 
     let merkle_caps = once(proof.trace_cap)
+        .chain(verifier_key.preprocessed_cap.clone())
         .chain(proof.permutation_zs_cap)
         .chain(once(proof.quotient_polys_cap))
         .collect_vec();
@@ -136,6 +190,7 @@ pub(crate) fn verify_stark_proof_with_challenges<
 
 fn validate_proof_shape<F, C, S, const D: usize>(
     stark: &S,
+    verifier_key: &StarkVerifierKey<F, C, D>,
     proof_with_pis: &StarkProofWithPublicInputs<F, C, D>,
     config: &StarkConfig,
 ) -> anyhow::Result<()>
@@ -163,6 +218,9 @@ where
     let StarkOpeningSet {
         local_values,
         next_values,
+        extra_row_values,
+        preprocessed_local_values,
+        preprocessed_next_values,
         permutation_zs,
         permutation_zs_next,
         quotient_polys,
@@ -179,8 +237,33 @@ where
 
     ensure!(local_values.len() == S::COLUMNS);
     ensure!(next_values.len() == S::COLUMNS);
+    ensure!(extra_row_values.len() == stark.extra_row_offsets().len());
+    ensure!(extra_row_values
+        .iter()
+        .all(|values| values.len() == S::COLUMNS));
     ensure!(quotient_polys.len() == stark.num_quotient_polys(config));
 
+    if stark.uses_preprocessed_columns() {
+        let preprocessed_cap = verifier_key
+            .preprocessed_cap
+            .as_ref()
+            .ok_or_else(|| anyhow!("Missing preprocessed cap"))?;
+        let preprocessed_local_values = preprocessed_local_values
+            .as_ref()
+            .ok_or_else(|| anyhow!("Missing preprocessed_local_values"))?;
+        let preprocessed_next_values = preprocessed_next_values
+            .as_ref()
+            .ok_or_else(|| anyhow!("Missing preprocessed_next_values"))?;
+
+        ensure!(preprocessed_cap.height() == cap_height);
+        ensure!(preprocessed_local_values.len() == S::PREPROCESSED_COLUMNS);
+        ensure!(preprocessed_next_values.len() == S::PREPROCESSED_COLUMNS);
+    } else {
+        ensure!(verifier_key.preprocessed_cap.is_none());
+        ensure!(preprocessed_local_values.is_none());
+        ensure!(preprocessed_next_values.is_none());
+    }
+
     if stark.uses_permutation_args() {
         let permutation_zs_cap = permutation_zs_cap
             .as_ref()
@@ -0,0 +1,330 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use plonky2::field::extension::{Extendable, FieldExtension};
+use plonky2::field::packed::PackedField;
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::ext_target::ExtensionTarget;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+
+use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
+use crate::evaluation_frame::{StarkEvaluationFrame, StarkFrame};
+use crate::stark::Stark;
+
+/// Toy STARK system used for testing evaluation frames wider than two rows, including previous
+/// rows. Computes `x_{i+3} = x_{i-1} + x_i` on a single column, opened at the row offsets
+/// `[0, 1, 3, -1]`. A preprocessed selector disables the constraint on the first row and the last 3
+/// rows, which would otherwise wrap around to the other end of the trace.
+#[derive(Copy, Clone)]
+struct WindowStark<F: RichField + Extendable<D>, const D: usize> {
+    num_rows: usize,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> WindowStark<F, D> {
+    // The first public input is `x_0`.
+    const PI_INDEX_X0: usize = 0;
+    // The second public input is `x_{n-1}`.
+    const PI_INDEX_RES: usize = 1;
+
+    const WINDOW: usize = 4;
+
+    const fn new(num_rows: usize) -> Self {
+        Self {
+            num_rows,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Generate the trace from `x_0`, with the next rows of the first window set to one.
+    fn generate_trace(&self, x0: F) -> (Vec<PolynomialValues<F>>, F) {
+        let mut column = vec![F::ONE; self.num_rows];
+        column[0] = x0;
+        for i in Self::WINDOW..self.num_rows {
+            column[i] = column[i - Self::WINDOW] + column[i - Self::WINDOW + 1];
+        }
+        let res = column[self.num_rows - 1];
+        (vec![PolynomialValues::new(column)], res)
+    }
+}
+
+const COLUMNS: usize = 1;
+const PUBLIC_INPUTS: usize = 2;
+const PREPROCESSED_COLUMNS: usize = 1;
+const ROWS: usize = 4;
+
+impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for WindowStark<F, D> {
+    type EvaluationFrame<FE, P, const D2: usize>
+        = StarkFrame<P, P::Scalar, COLUMNS, PUBLIC_INPUTS, PREPROCESSED_COLUMNS, 0, ROWS>
+    where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>;
+
+    type EvaluationFrameTarget = StarkFrame<
+        ExtensionTarget<D>,
+        ExtensionTarget<D>,
+        COLUMNS,
+        PUBLIC_INPUTS,
+        PREPROCESSED_COLUMNS,
+        0,
+        ROWS,
+    >;
+
+    fn eval_packed_generic<FE, P, const D2: usize>(
+        &self,
+        vars: &Self::EvaluationFrame<FE, P, D2>,
+        yield_constr: &mut ConstraintConsumer<P>,
+    ) where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>,
+    {
+        let x = vars.get_local_values()[0];
+        let window_x = vars.get_row_values(2)[0];
+        let prev_x = vars.get_row_values(3)[0];
+        let selector = vars.get_preprocessed_local_values()[0];
+        let public_inputs = vars.get_public_inputs();
+
+        // Check public inputs.
+        yield_constr.constraint_first_row(x - public_inputs[Self::PI_INDEX_X0]);
+        yield_constr.constraint_last_row(x - public_inputs[Self::PI_INDEX_RES]);
+
+        // x_{i+3} <- x_{i-1} + x_i
+        yield_constr.constraint(selector * (window_x - prev_x - x));
+    }
+
+    fn eval_ext_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: &Self::EvaluationFrameTarget,
+        yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+    ) {
+        let x = vars.get_local_values()[0];
+        let window_x = vars.get_row_values(2)[0];
+        let prev_x = vars.get_row_values(3)[0];
+        let selector = vars.get_preprocessed_local_values()[0];
+        let public_inputs = vars.get_public_inputs();
+
+        // Check public inputs.
+        let x0_constraint = builder.sub_extension(x, public_inputs[Self::PI_INDEX_X0]);
+        yield_constr.constraint_first_row(builder, x0_constraint);
+        let res_constraint = builder.sub_extension(x, public_inputs[Self::PI_INDEX_RES]);
+        yield_constr.constraint_last_row(builder, res_constraint);
+
+        // x_{i+3} <- x_{i-1} + x_i
+        let sum = builder.add_extension(prev_x, x);
+        let diff = builder.sub_extension(window_x, sum);
+        let window_constraint = builder.mul_extension(selector, diff);
+        yield_constr.constraint(builder, window_constraint);
+    }
+
+    fn constraint_degree(&self) -> usize {
+        2
+    }
+
+    fn row_offsets(&self) -> Vec<isize> {
+        vec![0, 1, Self::WINDOW as isize - 1, -1]
+    }
+
+    fn preprocessed_columns(&self, num_rows: usize) -> Vec<PolynomialValues<F>> {
+        let mut selector = vec![F::ONE; num_rows];
+        selector[0] = F::ZERO;
+        for s in &mut selector[num_rows - Self::WINDOW + 1..] {
+            *s = F::ZERO;
+        }
+        vec![PolynomialValues::new(selector)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2::field::extension::FieldExtension;
+    use plonky2::field::packed::PackedField;
+    use plonky2::field::polynomial::PolynomialValues;
+    use plonky2::field::types::Field;
+    use plonky2::iop::witness::PartialWitness;
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use plonky2::util::timing::TimingTree;
+
+    use crate::config::StarkConfig;
+    use crate::constraint_analysis::analyze_constraints;
+    use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
+    use crate::preprocessed::StarkProvingKey;
+    use crate::prover::{prove, prove_with_key};
+    use crate::recursive_verifier::{
+        add_virtual_stark_proof_with_pis, set_stark_proof_with_pis_target,
+        verify_stark_proof_circuit_with_key,
+    };
+    use crate::stark::Stark;
+    use crate::stark_testing::{test_stark_circuit_constraints, test_stark_low_degree};
+    use crate::verifier::{verify_stark_proof, verify_stark_proof_with_key};
+    use crate::window_stark::WindowStark;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type S = WindowStark<F, D>;
+
+    const NUM_ROWS: usize = 1 << 5;
+
+    #[test]
+    fn test_window_stark() -> Result<()> {
+        let config = StarkConfig::standard_fast_config();
+        let stark = S::new(NUM_ROWS);
+        let (trace, res) = stark.generate_trace(F::TWO);
+        let public_inputs = [F::TWO, res];
+        let proof = prove::<F, C, S, D>(
+            stark,
+            &config,
+            trace,
+            &public_inputs,
+            &mut TimingTree::default(),
+        )?;
+        assert_eq!(proof.proof.openings.extra_row_values.len(), 2);
+        verify_stark_proof(stark, proof.clone(), &config)?;
+
+        // The extra rows are bound by the FRI opening proof.
+        for i in 0..2 {
+            let mut bad_proof = proof.clone();
+            bad_proof.proof.openings.extra_row_values[i][0] += F::ONE.into();
+            assert!(verify_stark_proof(stark, bad_proof, &config).is_err());
+        }
+        Ok(())
+    }
+
+    /// A STARK opened at the given row offsets, and otherwise like `WindowStark`.
+    #[derive(Copy, Clone)]
+    struct OffsetsStark(S, &'static [isize]);
+
+    impl Stark<F, D> for OffsetsStark {
+        type EvaluationFrame<FE, P, const D2: usize>
+            = <S as Stark<F, D>>::EvaluationFrame<FE, P, D2>
+        where
+            FE: FieldExtension<D2, BaseField = F>,
+            P: PackedField<Scalar = FE>;
+
+        type EvaluationFrameTarget = <S as Stark<F, D>>::EvaluationFrameTarget;
+
+        fn eval_packed_generic<FE, P, const D2: usize>(
+            &self,
+            vars: &Self::EvaluationFrame<FE, P, D2>,
+            yield_constr: &mut ConstraintConsumer<P>,
+        ) where
+            FE: FieldExtension<D2, BaseField = F>,
+            P: PackedField<Scalar = FE>,
+        {
+            self.0.eval_packed_generic(vars, yield_constr)
+        }
+
+        fn eval_ext_circuit(
+            &self,
+            builder: &mut CircuitBuilder<F, D>,
+            vars: &Self::EvaluationFrameTarget,
+            yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+        ) {
+            self.0.eval_ext_circuit(builder, vars, yield_constr)
+        }
+
+        fn constraint_degree(&self) -> usize {
+            2
+        }
+
+        fn row_offsets(&self) -> Vec<isize> {
+            self.1.to_vec()
+        }
+
+        fn preprocessed_columns(&self, num_rows: usize) -> Vec<PolynomialValues<F>> {
+            self.0.preprocessed_columns(num_rows)
+        }
+    }
+
+    #[test]
+    fn test_row_offsets_are_checked() {
+        let check = |offsets| OffsetsStark(S::new(NUM_ROWS), offsets).check_row_offsets(5);
+        assert!(check(&[0, 1, 3, -1]).is_ok());
+        assert!(check(&[0, 1, 30, -30]).is_ok());
+        // Offsets must be smaller than the trace length,
+        assert!(check(&[0, 1, 32, -1]).is_err());
+        assert!(check(&[0, 1, 3, -32]).is_err());
+        // and refer to distinct rows.
+        assert!(check(&[0, 1, 31, -1]).is_err());
+        assert!(check(&[0, 1, 3, -31]).is_err());
+    }
+
+    #[test]
+    fn test_prove_rejects_offsets_past_the_trace() {
+        let config = StarkConfig::standard_fast_config();
+        let stark = OffsetsStark(S::new(NUM_ROWS), &[0, 1, 3, -(NUM_ROWS as isize)]);
+        let (trace, res) = S::new(NUM_ROWS).generate_trace(F::TWO);
+        let result = prove::<F, C, _, D>(
+            stark,
+            &config,
+            trace,
+            &[F::TWO, res],
+            &mut TimingTree::default(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_window_stark_degree() -> Result<()> {
+        test_stark_low_degree(S::new(NUM_ROWS))
+    }
+
+    #[test]
+    fn test_window_stark_analysis() {
+        let analysis = analyze_constraints(&S::new(NUM_ROWS));
+        assert_eq!(analysis.constraints[2].row_offsets, [-1, 0, 3]);
+        assert_eq!(analysis.required_degree, 2);
+        assert!(!analysis.is_degree_too_tight() && !analysis.is_degree_too_loose());
+        // The window constraint is disabled on the last rows by a selector rather than a
+        // transition filter, which the analysis can't tell.
+        assert_eq!(analysis.missing_transition_filters(), [2]);
+        assert!(analysis.check().is_err());
+    }
+
+    #[test]
+    fn test_window_stark_circuit() -> Result<()> {
+        test_stark_circuit_constraints::<F, C, S, D>(S::new(NUM_ROWS))
+    }
+
+    #[test]
+    fn test_recursive_window_stark_verifier() -> Result<()> {
+        let config = StarkConfig::standard_fast_config();
+        let stark = S::new(NUM_ROWS);
+        let proving_key = StarkProvingKey::new(&stark, &config, 5, &mut TimingTree::default());
+        let verifier_key = &proving_key.verifier_key;
+
+        let (trace, res) = stark.generate_trace(F::TWO);
+        let public_inputs = [F::TWO, res];
+        let proof = prove_with_key::<F, C, S, D>(
+            stark,
+            &proving_key,
+            &config,
+            trace,
+            &public_inputs,
+            &mut TimingTree::default(),
+        )?;
+        verify_stark_proof_with_key(stark, verifier_key, proof.clone(), &config)?;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let mut pw = PartialWitness::new();
+        let pt = add_virtual_stark_proof_with_pis(&mut builder, stark, &config, 5);
+        set_stark_proof_with_pis_target(&mut pw, &pt, &proof);
+        verify_stark_proof_circuit_with_key::<F, C, S, D>(
+            &mut builder,
+            stark,
+            verifier_key,
+            pt,
+            &config,
+        );
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+}
@@ -29,6 +29,29 @@ pub const fn log2_ceil(n: usize) -> usize {
     (usize::BITS - n.saturating_sub(1).leading_zeros()) as usize
 }
 
+/// Transposes a `2^lb_size` by `2^lb_size` matrix, stored in row-major order, in place.
+pub fn transpose_square_in_place<T>(arr: &mut [T], lb_size: usize) {
+    transpose_square_blocks_in_place(arr, lb_size, lb_size);
+}
+
+/// Transposes in place each of the square blocks of side `2^lb_rows` which make up a `2^lb_rows`
+/// by `2^lb_cols` matrix stored in row-major order, with `lb_rows <= lb_cols`.
+pub fn transpose_square_blocks_in_place<T>(arr: &mut [T], lb_rows: usize, lb_cols: usize) {
+    assert!(lb_rows <= lb_cols, "Expected no more rows than columns");
+    assert_eq!(
+        arr.len(),
+        1 << (lb_rows + lb_cols),
+        "Expected a matrix of 2^{lb_rows} by 2^{lb_cols}"
+    );
+    for block_start in (0..1 << lb_cols).step_by(1 << lb_rows) {
+        // SAFETY: The block's last entry is `((2^lb_rows - 1) << lb_cols) + 2^lb_rows - 1` past its
+        // start, which is at most `2^lb_cols - 2^lb_rows`, so it lies within the matrix.
+        unsafe {
+            transpose_in_place_square(&mut arr[block_start..], lb_cols, lb_rows, 0);
+        }
+    }
+}
+
 /// Computes `log_2(n)`, panicking if `n` is not a power of two.
 pub fn log2_strict(n: usize) -> usize {
     let res = n.trailing_zeros();